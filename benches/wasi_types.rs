@@ -0,0 +1,79 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wiggle::GuestMemory;
+use wiggle_test::{HostMemory, WasiCtx};
+
+// Only the types are benchmarked, so leave the module trait out.
+wiggle::from_witx!({
+    witx: ["tests/wasi.witx"],
+    ctx: WasiCtx,
+    exclude_modules: [wasi_snapshot_preview1],
+});
+
+fn filestat() -> types::Filestat {
+    types::Filestat {
+        dev: 1,
+        ino: 2,
+        filetype: types::Filetype::RegularFile,
+        nlink: 1,
+        size: 4096,
+        atim: 3,
+        mtim: 4,
+        ctim: 5,
+    }
+}
+
+fn structs(c: &mut Criterion) {
+    let host_memory = HostMemory::new();
+    let mut group = c.benchmark_group("wasi structs");
+    group.bench_function("filestat write", |b| {
+        let ptr = host_memory.ptr::<types::Filestat>(64);
+        b.iter(|| ptr.write(black_box(filestat())).expect("in bounds"))
+    });
+    group.bench_function("filestat read", |b| {
+        let ptr = host_memory.ptr::<types::Filestat>(64);
+        ptr.write(filestat()).expect("in bounds");
+        b.iter(|| black_box(ptr.read().expect("valid filestat")))
+    });
+    group.bench_function("fdstat read", |b| {
+        let ptr = host_memory.ptr::<types::Fdstat>(64);
+        ptr.write(types::Fdstat {
+            fs_filetype: types::Filetype::RegularFile,
+            fs_flags: types::Fdflags::APPEND,
+            fs_rights_base: types::Rights::FD_READ | types::Rights::FD_WRITE,
+            fs_rights_inheriting: types::Rights::FD_READ,
+        })
+        .expect("in bounds");
+        b.iter(|| black_box(ptr.read().expect("valid fdstat")))
+    });
+    group.finish();
+}
+
+fn iovecs(c: &mut Criterion) {
+    let host_memory = HostMemory::new();
+    let iovs = host_memory.ptr::<[types::Iovec]>((0, 16));
+    for (i, iov) in iovs.iter().enumerate() {
+        iov.expect("in bounds")
+            .write(types::Iovec {
+                buf: host_memory.ptr(256 + i as u64 * 128),
+                buf_len: 128,
+            })
+            .expect("in bounds");
+    }
+    let mut group = c.benchmark_group("wasi iovecs");
+    group.bench_function("read 16 iovecs", |b| {
+        b.iter(|| black_box(iovs.to_vec().expect("valid iovecs")))
+    });
+    group.bench_function("gather 16 iovecs", |b| {
+        b.iter(|| {
+            for iov in iovs.iter() {
+                let iov = iov.expect("in bounds").read().expect("valid iovec");
+                let buf = iov.buf.as_array(iov.buf_len.into());
+                black_box(buf.to_vec().expect("in bounds"));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, structs, iovecs);
+criterion_main!(benches);
@@ -0,0 +1,156 @@
+//! Builds functions which are both `async` and traced, and checks that the
+//! span of a call is only entered while its future is being polled.
+//!
+//! This needs a crate of its own, since generated functions with `tracing`
+//! refer to the `tracing` crate, which the root crate doesn't depend on.
+
+use wiggle::{GuestError, GuestErrorType};
+
+wiggle::from_witx!({
+    witx_literal: r#"
+        (typename $errno (enum u32 $ok $invalid_arg))
+        (module $traced
+          (@interface func (export "wait")
+            (param $rounds u32)
+            (result $error $errno)))
+    "#,
+    ctx: Host,
+    async: true,
+    tracing: true,
+});
+
+/// A host which checks which span it's called in.
+pub struct Host {
+    pub spans: std::sync::Arc<spans::Spans>,
+}
+
+impl<'a> GuestErrorType<'a> for types::Errno {
+    type Context = Host;
+    fn success() -> types::Errno {
+        types::Errno::Ok
+    }
+    fn from_error(_e: GuestError, _ctx: &Host) -> types::Errno {
+        types::Errno::InvalidArg
+    }
+}
+
+impl traced::Traced for Host {
+    async fn wait(&self, rounds: u32) -> Result<(), types::Errno> {
+        for _ in 0..rounds {
+            assert_eq!(self.spans.current(), Some("wiggle abi"));
+            spans::YieldNow(false).await;
+        }
+        assert_eq!(self.spans.current(), Some("wiggle abi"));
+        Ok(())
+    }
+}
+
+pub mod spans {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// The spans created, and the stack of those entered.
+    #[derive(Default)]
+    pub struct Spans {
+        next: AtomicU64,
+        names: Mutex<Vec<&'static str>>,
+        entered: Mutex<Vec<u64>>,
+    }
+
+    impl Spans {
+        /// The name of the innermost span entered, if any.
+        pub fn current(&self) -> Option<&'static str> {
+            let entered = self.entered.lock().unwrap();
+            let id = *entered.last()?;
+            Some(self.names.lock().unwrap()[id as usize - 1])
+        }
+    }
+
+    pub struct Recorder(pub Arc<Spans>);
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.0.next.fetch_add(1, Ordering::SeqCst) + 1;
+            self.0.names.lock().unwrap().push(span.metadata().name());
+            Id::from_u64(id)
+        }
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+        fn event(&self, _event: &Event<'_>) {}
+        fn enter(&self, span: &Id) {
+            self.0.entered.lock().unwrap().push(span.into_u64());
+        }
+        fn exit(&self, span: &Id) {
+            let mut entered = self.0.entered.lock().unwrap();
+            assert_eq!(
+                entered.pop(),
+                Some(span.into_u64()),
+                "spans exited in order"
+            );
+        }
+    }
+
+    /// Returns `Pending` once, like waiting on I/O.
+    pub struct YieldNow(pub bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::{traced, types, Host};
+        use std::task::{Wake, Waker};
+        use wiggle_test::HostMemory;
+
+        struct NoopWaker;
+
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        #[test]
+        fn span_is_only_entered_while_polled() {
+            let spans = Arc::new(Spans::default());
+            let host = Host {
+                spans: spans.clone(),
+            };
+            let host_memory = HostMemory::new();
+            let waker = Waker::from(Arc::new(NoopWaker));
+            let mut cx = Context::from_waker(&waker);
+
+            tracing::subscriber::with_default(Recorder(spans.clone()), || {
+                let mut call = Box::pin(traced::wait(&host, &host_memory, 2));
+                for _ in 0..2 {
+                    assert!(call.as_mut().poll(&mut cx).is_pending());
+                    // The call is suspended, so its span mustn't leak into
+                    // whatever the thread runs next.
+                    assert_eq!(spans.current(), None);
+                }
+                assert_eq!(
+                    call.as_mut().poll(&mut cx),
+                    Poll::Ready(types::Errno::Ok.into())
+                );
+                assert_eq!(spans.current(), None);
+            });
+        }
+    }
+}
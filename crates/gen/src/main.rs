@@ -0,0 +1,113 @@
+//! Prints the Rust code `wiggle::from_witx!` would expand to, for build
+//! systems which can't run the macro and for reviewing generated code, or a
+//! C header for non-Rust guests.
+//!
+//! ```text
+//! wiggle-gen --witx api.witx --ctx Ctx [--module m]... [--config "async: true"] [-o out.rs]
+//! wiggle-gen --witx api.witx --c-header api [-o api.h]
+//! ```
+
+use anyhow::{anyhow, bail, Result};
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "\
+usage: wiggle-gen --witx <path>... --ctx <type> [options]
+       wiggle-gen --witx <path>... --c-header <prefix> [options]
+
+options:
+    --witx <path>       a witx file making up the document; may be repeated
+    --ctx <type>        the ctx type generated functions are passed
+    --c-header <prefix> print a C header for guests rather than Rust code,
+                        naming its definitions `<prefix>_...`
+    --module <name>     only generate this module; may be repeated
+    --config <fields>   further `from_witx!` config fields, e.g. \"async: true\"
+    -o, --output <path> write the code to a file rather than stdout
+    -h, --help          print this message";
+
+#[derive(Debug, Default)]
+struct Args {
+    witx: Vec<String>,
+    ctx: Option<String>,
+    c_header: Option<String>,
+    modules: Vec<String>,
+    config: Vec<String>,
+    output: Option<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Args>> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("`{}` needs a value", arg))
+            };
+            match arg.as_str() {
+                "--witx" => parsed.witx.push(value()?),
+                "--ctx" => parsed.ctx = Some(value()?),
+                "--c-header" => parsed.c_header = Some(value()?),
+                "--module" => parsed.modules.push(value()?),
+                "--config" => parsed.config.push(value()?),
+                "-o" | "--output" => parsed.output = Some(value()?),
+                "-h" | "--help" => return Ok(None),
+                _ => bail!("unexpected argument `{}`", arg),
+            }
+        }
+        Ok(Some(parsed))
+    }
+
+    /// The `from_witx!` config the arguments describe.
+    fn config(&self) -> Result<wiggle_generate::Config> {
+        if self.witx.is_empty() {
+            bail!("at least one `--witx` is required");
+        }
+        // Headers are for guests, which have no ctx.
+        let ctx = match (&self.ctx, &self.c_header) {
+            (Some(ctx), _) => format!("ctx: {}", ctx),
+            (None, Some(_)) => "guest: true".to_string(),
+            (None, None) => bail!("`--ctx` is required"),
+        };
+        let witx = self
+            .witx
+            .iter()
+            .map(|p| format!("{:?}", p))
+            .collect::<Vec<_>>();
+        let mut fields = vec![format!("witx: [{}]", witx.join(", ")), ctx];
+        if !self.modules.is_empty() {
+            fields.push(format!("include_modules: [{}]", self.modules.join(", ")));
+        }
+        fields.extend(self.config.iter().cloned());
+        let text = format!("{{ {} }}", fields.join(", "));
+        text.parse()
+            .map_err(|e| anyhow!("invalid config `{}`: {}", text, e))
+    }
+}
+
+fn run() -> Result<()> {
+    let args = match Args::parse(env::args().skip(1))? {
+        Some(args) => args,
+        None => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+    };
+    let config = args.config()?;
+    let output = match &args.c_header {
+        Some(prefix) => wiggle_generate::generate_c_header(&config, prefix)?,
+        None => wiggle_generate::generate_to_string(&config)?,
+    };
+    match &args.output {
+        Some(path) => fs::write(path, output)?,
+        None => println!("{}", output),
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("wiggle-gen: {:#}\n\n{}", e, USAGE);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,78 @@
+use std::process::Command;
+
+fn wiggle_gen(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_wiggle-gen"))
+        .args(args)
+        .output()
+        .expect("run wiggle-gen")
+}
+
+#[test]
+fn prints_generated_code() {
+    let out = wiggle_gen(&["--witx", "../../tests/atoms.witx", "--ctx", "Ctx"]);
+    assert!(out.status.success());
+    let code = String::from_utf8(out.stdout).expect("utf-8 output");
+    assert!(code.contains("pub mod types"));
+    assert!(code.contains("pub trait Atoms"));
+}
+
+#[test]
+fn writes_selected_modules_to_a_file() {
+    let path = std::env::temp_dir().join("wiggle-gen-cli-test.rs");
+    let out = wiggle_gen(&[
+        "--witx",
+        "../../tests/shapes.witx",
+        "--witx",
+        "../../tests/paths.witx",
+        "--ctx",
+        "Ctx",
+        "--module",
+        "paths",
+        "--config",
+        "async: true",
+        "-o",
+        path.to_str().expect("utf-8 path"),
+    ]);
+    assert!(out.status.success());
+    let code = std::fs::read_to_string(&path).expect("read output");
+    std::fs::remove_file(&path).expect("remove output");
+    assert!(code.contains("pub trait Paths"));
+    assert!(!code.contains("pub trait Shapes"));
+    assert!(code.contains("async fn distance"));
+}
+
+#[test]
+fn prints_a_c_header() {
+    let out = wiggle_gen(&["--witx", "../../tests/atoms.witx", "--c-header", "atoms"]);
+    assert!(out.status.success());
+    let header = String::from_utf8(out.stdout).expect("utf-8 output");
+    assert!(header.contains("#ifndef ATOMS_H"));
+    assert!(header.contains("atoms_int_float_args("));
+}
+
+#[test]
+fn reports_bad_arguments() {
+    let out = wiggle_gen(&["--witx", "../../tests/atoms.witx"]);
+    assert!(!out.status.success());
+    let err = String::from_utf8(out.stderr).expect("utf-8 output");
+    assert!(err.contains("`--ctx` is required"), "{}", err);
+}
+
+#[test]
+fn reports_unsupported_shapes() {
+    let path = std::env::temp_dir().join("wiggle-gen-cli-unsupported.witx");
+    std::fs::write(
+        &path,
+        "(module $m (@interface func (export \"f\") (param $p (array u8))))",
+    )
+    .expect("write witx");
+    let witx = path.to_str().expect("utf-8 path");
+    let code = wiggle_gen(&["--witx", witx, "--ctx", "Ctx"]);
+    let header = wiggle_gen(&["--witx", witx, "--c-header", "m"]);
+    std::fs::remove_file(&path).expect("remove witx");
+    for out in [code, header] {
+        assert!(!out.status.success());
+        let err = String::from_utf8(out.stderr).expect("utf-8 output");
+        assert!(err.contains("`p` of function `f`"), "{}", err);
+    }
+}
@@ -0,0 +1,325 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use witx::{Document, InterfaceFunc, Module, Type, TypeRef};
+
+use crate::conversions::{Converter, Direction};
+use crate::module_trait::{trait_groups, TraitMethod};
+use crate::names::Names;
+
+/// Defines the `adapter` module, when `adapter` is configured, implementing
+/// the module traits of `doc` for the ctx in terms of the traits of the
+/// newer document the adapter is configured with.
+///
+/// Arguments are converted from the old types to the new ones, and results
+/// and errors back, through `From` impls between the two `types` modules,
+/// which are generated for every type that needs converting. Enum variants,
+/// flags and struct members are matched by name, integers out of range for
+/// their new type saturate, and floats are only converted losslessly.
+/// Pointers are passed on as they are, which needs the types they point to
+/// to be laid out the same in both documents. When `conversions` is
+/// configured too, the adapter uses its impls.
+pub fn define_adapter(doc: &Document, names: &Names) -> TokenStream {
+    let conf = match names.adapter() {
+        Some(conf) => conf,
+        None => return quote!(),
+    };
+    let span = conf.witx.span();
+    if names.generic_ctx() {
+        return quote_spanned!(span=> compile_error!("`adapter` needs a ctx type, not `ctx: generic`"););
+    }
+    let new_doc = match crate::witx_document(&conf.witx) {
+        Ok(doc) => doc,
+        Err(e) => {
+            let msg = format!("loading the adapter's witx: {}", e);
+            return quote_spanned!(span=> compile_error!(#msg););
+        }
+    };
+
+    let mut converter = Converter::new(names, &conf.to, &conf.renames);
+    if let Some(conversions) = names.conversions() {
+        if conversions.to.to_string() != conf.to.to_string() {
+            return quote_spanned!(span=> compile_error!("`adapter` and `conversions` need to convert to the same types"););
+        }
+        // The conversions the adapter needs may be defined by `conversions`
+        // already, and mustn't be defined twice.
+        converter.define_all(doc, &new_doc);
+        converter.conversions.clear();
+    }
+    // The adapter can't handle failed conversions.
+    converter.fallible = false;
+    let mut adapter = Adapter {
+        names,
+        converter,
+        span,
+    };
+    let mut impls = Vec::new();
+    for m in names.modules(doc) {
+        impls.push(match new_doc.module(&adapter.converter.rename(&m.name)) {
+            Some(new_m) => adapter.define_impls(&m, &new_m),
+            None => {
+                let msg = format!(
+                    "the adapter's witx has no module `{}`",
+                    adapter.converter.rename(&m.name).as_str()
+                );
+                quote_spanned!(span=> compile_error!(#msg);)
+            }
+        });
+    }
+    let ctx_type = names.ctx_type();
+    let conversions = adapter.converter.conversions;
+    let doc = format!(
+        "Implementations of the module traits in terms of those of `{}`.",
+        conf.to
+    );
+    quote! {
+        #[doc = #doc]
+        pub mod adapter {
+            use super::#ctx_type;
+            use super::types::*;
+
+            #(#conversions)*
+
+            #(#impls)*
+        }
+    }
+}
+
+struct Adapter<'a> {
+    names: &'a Names,
+    converter: Converter<'a>,
+    /// Where to report the conversions the adapter can't make.
+    span: Span,
+}
+
+impl<'a> Adapter<'a> {
+    /// The impls of the traits of `m` for the ctx, calling those of `new_m`.
+    fn define_impls(&mut self, m: &Module, new_m: &Module) -> TokenStream {
+        let names = self.names;
+        let ctx_type = names.ctx_type();
+        let modname = names.module(&m.name);
+        let to = self.converter.to;
+        let span = self.span;
+        let new_modname = names.module(&new_m.name);
+        let new_traitname = names.trait_name(&new_m.name);
+        let new_trait = quote!(#to::#new_modname::#new_traitname);
+        let mut impls = Vec::new();
+        for (traitname, methods) in trait_groups(names, m) {
+            let methods = methods
+                .iter()
+                .map(|method| {
+                    let signature = &method.signature;
+                    match self.define_method(method, new_m, &new_trait) {
+                        Ok(body) => quote!(#signature { #body }),
+                        Err(msg) => quote_spanned!(span=> #signature { compile_error!(#msg) }),
+                    }
+                })
+                .collect::<Vec<_>>();
+            impls.push(quote! {
+                #[allow(clippy::too_many_arguments)]
+                impl super::#modname::#traitname for #ctx_type {
+                    #(#methods)*
+                }
+            });
+        }
+        quote!(#(#impls)*)
+    }
+
+    /// The body of the method calling the new trait's method for `method`.
+    fn define_method(
+        &mut self,
+        method: &TraitMethod,
+        new_m: &Module,
+        new_trait: &TokenStream,
+    ) -> Result<TokenStream, String> {
+        let names = self.names;
+        let func = &method.func;
+        let new_func = new_m
+            .func(&self.converter.rename(&func.name))
+            .ok_or_else(|| {
+                format!(
+                    "the adapter's module `{}` has no function `{}`",
+                    new_m.name.as_str(),
+                    self.converter.rename(&func.name).as_str()
+                )
+            })?;
+        if func.params.len() != new_func.params.len()
+            || func.results.len() != new_func.results.len()
+        {
+            return Err(format!(
+                "`{}` has a different signature in the adapter's witx",
+                func.name.as_str()
+            ));
+        }
+
+        let mut args = Vec::new();
+        for (param, new_param) in func.params.iter().zip(&new_func.params) {
+            args.push(self.convert_arg(func, param, new_param)?);
+        }
+
+        let mut rets = Vec::new();
+        let mut converted_rets = Vec::new();
+        for (result, new_result) in func.results.iter().zip(&new_func.results).skip(1) {
+            let name = names.func_param(&result.name);
+            converted_rets.push(self.converter.convert(
+                quote!(#name),
+                &new_result.tref,
+                &result.tref,
+                Direction::ToOld,
+            )?);
+            rets.push(name);
+        }
+        let err = match func.results.first().zip(new_func.results.first()) {
+            Some((result, new_result)) => {
+                if let TypeRef::Name(nt) = &result.tref {
+                    if names.user_error(&nt.name).is_some() {
+                        return Err(format!(
+                            "`{}` returns a user error type, which the adapter can't convert to",
+                            func.name.as_str()
+                        ));
+                    }
+                }
+                self.converter.convert(
+                    quote!(e),
+                    &new_result.tref,
+                    &result.tref,
+                    Direction::ToOld,
+                )?
+            }
+            None => quote!(e),
+        };
+
+        let ctx_type = names.ctx_type();
+        let new_ident = names.func(&new_func.name);
+        let await_ = if names.is_async() {
+            quote!(.await)
+        } else {
+            quote!()
+        };
+        Ok(quote! {
+            match <#ctx_type as #new_trait>::#new_ident(self, #(#args),*)#await_ {
+                Ok((#(#rets),*)) => Ok((#(#converted_rets),*)),
+                Err(e) => Err(#err),
+            }
+        })
+    }
+
+    /// The argument passed to the new trait's method for `param`.
+    fn convert_arg(
+        &mut self,
+        func: &InterfaceFunc,
+        param: &witx::InterfaceFuncParam,
+        new_param: &witx::InterfaceFuncParam,
+    ) -> Result<TokenStream, String> {
+        let names = self.names;
+        if names.param_view(&param.tref).is_some() {
+            return Err(format!(
+                "`{}` takes a struct view, which the adapter can't convert",
+                func.name.as_str()
+            ));
+        }
+        let name = names.func_param(&param.name);
+        let ty = param.tref.type_();
+        match ty.passed_by() {
+            witx::TypePassedBy::Value(_) => self.converter.convert(
+                quote!(#name),
+                &param.tref,
+                &new_param.tref,
+                Direction::ToNew,
+            ),
+            witx::TypePassedBy::Pointer => {
+                let value = self.converter.convert(
+                    quote!((*#name).clone()),
+                    &param.tref,
+                    &new_param.tref,
+                    Direction::ToNew,
+                )?;
+                Ok(quote!(&#value))
+            }
+            witx::TypePassedBy::PointerLengthPair => {
+                if let Type::Builtin(witx::BuiltinType::String) = &*ty {
+                    if let Type::Builtin(witx::BuiltinType::String) = &*new_param.tref.type_() {
+                        return Ok(quote!(#name));
+                    }
+                }
+                let value = self.converter.convert(
+                    quote!(#name),
+                    &param.tref,
+                    &new_param.tref,
+                    Direction::ToNew,
+                )?;
+                Ok(quote!(&#value))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Config;
+
+    fn generate(witx: &str) -> String {
+        let config: Config = format!(
+            r#"{{
+                witx_literal: {:?},
+                ctx: Ctx,
+                adapter: {{
+                    witx: ["../../tests/adapter_preview1.witx"],
+                    to: crate::preview1,
+                    renames: {{ example_unstable: example_preview1 }},
+                }},
+            }}"#,
+            witx
+        )
+        .parse()
+        .expect("parse config");
+        let doc = witx::parse(witx).expect("parse witx");
+        define_adapter(&doc, &Names::new(&config)).to_string()
+    }
+
+    #[test]
+    fn converts_enums_by_name() {
+        let generated = generate(
+            r#"
+            (typename $errno (enum u16 $success $badf $inval))
+            (typename $fd (handle))
+            (typename $whence (enum u8 $cur $end $set))
+            (module $example_unstable
+              (@interface func (export "fd_seek")
+                (param $fd $fd)
+                (param $offset s64)
+                (param $whence $whence)
+                (result $error $errno)
+                (result $newoffset u64)))
+            "#,
+        );
+        assert!(generated.contains(
+            "super :: types :: Whence :: Cur => crate :: preview1 :: types :: Whence :: Cur"
+        ));
+        assert!(generated.contains("impl super :: example_unstable :: ExampleUnstable for Ctx"));
+    }
+
+    #[test]
+    fn reports_missing_counterparts() {
+        let generated = generate(
+            r#"
+            (typename $errno (enum u16 $success $badf))
+            (typename $fd (handle))
+            (typename $whence (enum u8 $cur $end $set))
+            (module $example_unstable
+              (@interface func (export "fd_sync")
+                (result $error $errno))
+              (@interface func (export "fd_seek")
+                (param $fd $fd)
+                (param $offset s64)
+                (param $whence $whence)
+                (result $error $errno)
+                (result $newoffset u64)))
+            "#,
+        );
+        assert!(
+            generated.contains("the adapter's module `example_preview1` has no function `fd_sync`")
+        );
+        assert!(generated.contains("variant `inval` of `errno` has no counterpart in `errno`"));
+    }
+}
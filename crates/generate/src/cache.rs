@@ -0,0 +1,124 @@
+use proc_macro2::Span;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+thread_local! {
+    // Documents hold `Rc`s, so the cache can't be shared between threads. The
+    // compiler expands all of a crate's macros on the same thread, which is
+    // where the reuse matters.
+    static DOCUMENTS: RefCell<HashMap<u64, witx::Document>> = RefCell::new(HashMap::new());
+}
+
+/// Loads the witx document made up of the files at `paths`, like
+/// `witx::load`, reusing the result of an earlier load of the same files.
+///
+/// Cache entries are keyed by a hash of the contents of every file making up
+/// the document, including those pulled in by `(use ...)` declarations, so
+/// editing any of them results in a fresh parse.
+pub fn load_document<P: AsRef<Path>>(paths: &[P]) -> Result<witx::Document, witx::WitxError> {
+    let key = match document_hash(paths) {
+        Some(key) => key,
+        // Let witx itself report whatever is wrong with the files.
+        None => return witx::load(paths),
+    };
+    if let Some(doc) = DOCUMENTS.with(|docs| docs.borrow().get(&key).cloned()) {
+        return Ok(doc);
+    }
+    let doc = witx::load(paths)?;
+    DOCUMENTS.with(|docs| docs.borrow_mut().insert(key, doc.clone()));
+    Ok(doc)
+}
+
+/// Parses the witx document `text` given by `witx_literal:`. Errors are
+/// reported at `span`, the string literal, with the line and column within it
+/// that witx complained about.
+pub fn parse_literal(text: &str, span: Span) -> syn::Result<witx::Document> {
+    witx::parse(text).map_err(|e| {
+        let report = e.report_with(&witx::MockFs::new(&[("-", text)]));
+        syn::Error::new(span, format!("invalid witx_literal: {}", report))
+    })
+}
+
+/// Hashes the paths and contents of all files making up a witx document, or
+/// returns `None` if any of them can't be read.
+fn document_hash<P: AsRef<Path>>(paths: &[P]) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    for (path, contents) in document_files(paths)? {
+        path.hash(&mut hasher);
+        contents.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+/// The canonical paths of all files making up a witx document, including
+/// those pulled in by `(use ...)` declarations, or `None` if any of them
+/// can't be read.
+pub fn document_paths<P: AsRef<Path>>(paths: &[P]) -> Option<Vec<PathBuf>> {
+    Some(
+        document_files(paths)?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect(),
+    )
+}
+
+/// The canonical paths and contents of all files making up a witx document.
+fn document_files<P: AsRef<Path>>(paths: &[P]) -> Option<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    for path in paths {
+        let path = path.as_ref();
+        // witx resolves `use` declarations relative to the directory of the
+        // top-level file, even when they appear in a used file.
+        let root = path.parent().unwrap_or_else(|| Path::new("."));
+        read_file(path, root, &mut seen, &mut files)?;
+    }
+    Some(files)
+}
+
+fn read_file(
+    path: &Path,
+    root: &Path,
+    seen: &mut HashSet<PathBuf>,
+    files: &mut Vec<(PathBuf, String)>,
+) -> Option<()> {
+    let path = path.canonicalize().ok()?;
+    if !seen.insert(path.clone()) {
+        return Some(());
+    }
+    let contents = fs::read_to_string(&path).ok()?;
+    let used = uses(&contents)
+        .map(|used| root.join(used))
+        .collect::<Vec<_>>();
+    files.push((path, contents));
+    for used in used {
+        read_file(&used, root, seen, files)?;
+    }
+    Some(())
+}
+
+/// Finds the file names of all `(use "...")` declarations in `contents`.
+pub(crate) fn uses(contents: &str) -> impl Iterator<Item = &str> {
+    contents.split("(use").skip(1).filter_map(|rest| {
+        let rest = rest.trim_start().strip_prefix('"')?;
+        rest.split('"').next()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn literal_errors_name_the_position() {
+        let text = "(module $m\n  (@interface func (export \"f\")\n    (param $x $missing)))";
+        let err = parse_literal(text, Span::call_site()).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.starts_with("invalid witx_literal:"), "{}", msg);
+        assert!(msg.contains("3 |     (param $x $missing)"), "{}", msg);
+    }
+}
@@ -1,38 +1,149 @@
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use proc_macro2::Span;
+use proc_macro2::{Span, TokenStream};
+use quote::ToTokens;
 use syn::{
     braced, bracketed,
+    ext::IdentExt,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
-    Error, Ident, LitStr, Result, Token,
+    Attribute, Error, Ident, LitBool, LitInt, LitStr, Path, Result, Token,
 };
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub witx: WitxConf,
-    pub ctx: CtxConf,
+    /// Required unless generating guest bindings, which have no ctx.
+    pub ctx: Option<CtxConf>,
+    pub policy: Option<PolicyConf>,
+    pub cancel: Option<CancelConf>,
+    pub limits: Option<LimitsConf>,
+    pub version: Option<VersionConf>,
+    pub memory64: Option<Memory64Conf>,
+    pub memory: Option<MemoryConf>,
+    pub nullable: Option<NullableConf>,
+    pub tracing: Option<TracingConf>,
+    pub instrument: Option<InstrumentConf>,
+    pub async_: Option<AsyncConf>,
+    pub multi_memory: Option<MultiMemoryConf>,
+    pub no_std: Option<NoStdConf>,
+    pub types: Option<TypesConf>,
+    pub include_functions: Option<FilterConf>,
+    pub exclude_functions: Option<FilterConf>,
+    pub include_modules: Option<FilterConf>,
+    pub exclude_modules: Option<FilterConf>,
+    pub derive: Option<DeriveConf>,
+    pub attributes: Option<AttributesConf>,
+    pub substitute: Option<SubstituteConf>,
+    pub naming: Option<NamingConf>,
+    pub serde: Option<SerdeConf>,
+    pub proptest: Option<ProptestConf>,
+    pub struct_views: Option<StructViewsConf>,
+    pub guest: Option<GuestConf>,
+    pub mock: Option<MockConf>,
+    pub not_supported: Option<NotSupportedConf>,
+    pub split_traits: Option<SplitTraitsConf>,
+    pub multi_value: Option<MultiValueConf>,
+    pub errors: Option<ErrorsConf>,
+    pub adapter: Option<AdapterConf>,
+    pub conversions: Option<ConversionsConf>,
 }
 
 #[derive(Debug, Clone)]
 pub enum ConfigField {
     Witx(WitxConf),
     Ctx(CtxConf),
+    Policy(PolicyConf),
+    Cancel(CancelConf),
+    Limits(LimitsConf),
+    Version(VersionConf),
+    Memory64(Memory64Conf),
+    Memory(MemoryConf),
+    Nullable(NullableConf),
+    Tracing(TracingConf),
+    Instrument(InstrumentConf),
+    Async(AsyncConf),
+    MultiMemory(MultiMemoryConf),
+    NoStd(NoStdConf),
+    Types(TypesConf),
+    IncludeFunctions(FilterConf),
+    ExcludeFunctions(FilterConf),
+    IncludeModules(FilterConf),
+    ExcludeModules(FilterConf),
+    Derive(DeriveConf),
+    Attributes(AttributesConf),
+    Substitute(SubstituteConf),
+    Naming(NamingConf),
+    Serde(SerdeConf),
+    Proptest(ProptestConf),
+    StructViews(StructViewsConf),
+    Guest(GuestConf),
+    Mock(MockConf),
+    NotSupported(NotSupportedConf),
+    SplitTraits(SplitTraitsConf),
+    MultiValue(MultiValueConf),
+    Errors(ErrorsConf),
+    Adapter(AdapterConf),
+    Conversions(ConversionsConf),
 }
 
 impl ConfigField {
     pub fn parse_pair(ident: &str, value: ParseStream, err_loc: Span) -> Result<Self> {
         match ident {
             "witx" => Ok(ConfigField::Witx(value.parse()?)),
+            "witx_literal" => {
+                let lit: LitStr = value.parse()?;
+                Ok(ConfigField::Witx(WitxConf::Literal {
+                    text: lit.value(),
+                    span: lit.span(),
+                }))
+            }
             "ctx" => Ok(ConfigField::Ctx(value.parse()?)),
-            _ => Err(Error::new(err_loc, "expected `witx` or `ctx`")),
+            "policy" => Ok(ConfigField::Policy(value.parse()?)),
+            "cancel" => Ok(ConfigField::Cancel(value.parse()?)),
+            "limits" => Ok(ConfigField::Limits(value.parse()?)),
+            "version" => Ok(ConfigField::Version(value.parse()?)),
+            "memory64" => Ok(ConfigField::Memory64(value.parse()?)),
+            "memory" => Ok(ConfigField::Memory(value.parse()?)),
+            "nullable" => Ok(ConfigField::Nullable(value.parse()?)),
+            "tracing" => Ok(ConfigField::Tracing(value.parse()?)),
+            "instrument" => Ok(ConfigField::Instrument(value.parse()?)),
+            "async" => Ok(ConfigField::Async(value.parse()?)),
+            "multi_memory" => Ok(ConfigField::MultiMemory(value.parse()?)),
+            "no_std" => Ok(ConfigField::NoStd(value.parse()?)),
+            "types" => Ok(ConfigField::Types(value.parse()?)),
+            "include_functions" => Ok(ConfigField::IncludeFunctions(value.parse()?)),
+            "exclude_functions" => Ok(ConfigField::ExcludeFunctions(value.parse()?)),
+            "include_modules" => Ok(ConfigField::IncludeModules(value.parse()?)),
+            "exclude_modules" => Ok(ConfigField::ExcludeModules(value.parse()?)),
+            "derive" => Ok(ConfigField::Derive(value.parse()?)),
+            "attributes" => Ok(ConfigField::Attributes(value.parse()?)),
+            "substitute" => Ok(ConfigField::Substitute(value.parse()?)),
+            "naming" => Ok(ConfigField::Naming(value.parse()?)),
+            "serde" => Ok(ConfigField::Serde(value.parse()?)),
+            "proptest" => Ok(ConfigField::Proptest(value.parse()?)),
+            "struct_views" => Ok(ConfigField::StructViews(value.parse()?)),
+            "guest" => Ok(ConfigField::Guest(value.parse()?)),
+            "mock" => Ok(ConfigField::Mock(value.parse()?)),
+            "not_supported" => Ok(ConfigField::NotSupported(value.parse()?)),
+            "split_traits" => Ok(ConfigField::SplitTraits(value.parse()?)),
+            "multi_value" => Ok(ConfigField::MultiValue(value.parse()?)),
+            "errors" => Ok(ConfigField::Errors(value.parse()?)),
+            "adapter" => Ok(ConfigField::Adapter(value.parse()?)),
+            "conversions" => Ok(ConfigField::Conversions(value.parse()?)),
+            _ => Err(Error::new(
+                err_loc,
+                "expected `witx`, `witx_literal`, `ctx`, `policy`, `cancel`, `limits`, `version`, `memory64`, `memory`, `nullable`, `tracing`, `instrument`, `async`, `multi_memory`, `no_std`, `types`, `include_functions`, `exclude_functions`, `include_modules`, `exclude_modules`, `derive`, `attributes`, `substitute`, `naming`, `serde`, `proptest`, `struct_views`, `guest`, `mock`, `not_supported`, `split_traits`, `multi_value`, `errors`, `adapter` or `conversions`",
+            )),
         }
     }
 }
 
 impl Parse for ConfigField {
     fn parse(input: ParseStream) -> Result<Self> {
-        let id: Ident = input.parse()?;
+        // `async` is a keyword, so isn't accepted as a plain `Ident`.
+        let id = input.call(Ident::parse_any)?;
         let _colon: Token![:] = input.parse()?;
         Self::parse_pair(id.to_string().as_ref(), input, id.span())
     }
@@ -42,23 +153,218 @@ impl Config {
     pub fn build(fields: impl Iterator<Item = ConfigField>, err_loc: Span) -> Result<Self> {
         let mut witx = None;
         let mut ctx = None;
+        let mut policy = None;
+        let mut cancel = None;
+        let mut limits = None;
+        let mut version = None;
+        let mut memory64 = None;
+        let mut memory = None;
+        let mut nullable = None;
+        let mut tracing = None;
+        let mut instrument = None;
+        let mut async_ = None;
+        let mut multi_memory = None;
+        let mut no_std = None;
+        let mut types = None;
+        let mut include_functions = None;
+        let mut exclude_functions = None;
+        let mut include_modules = None;
+        let mut exclude_modules = None;
+        let mut derive = None;
+        let mut attributes = None;
+        let mut substitute = None;
+        let mut naming = None;
+        let mut serde = None;
+        let mut proptest = None;
+        let mut struct_views = None;
+        let mut guest = None;
+        let mut mock = None;
+        let mut not_supported = None;
+        let mut split_traits = None;
+        let mut multi_value = None;
+        let mut errors = None;
+        let mut adapter = None;
+        let mut conversions = None;
         for f in fields {
             match f {
                 ConfigField::Witx(c) => {
+                    if witx.is_some() {
+                        return Err(Error::new(
+                            err_loc,
+                            "only one of `witx` and `witx_literal` may be given",
+                        ));
+                    }
                     witx = Some(c);
                 }
                 ConfigField::Ctx(c) => {
                     ctx = Some(c);
                 }
+                ConfigField::Policy(c) => {
+                    policy = Some(c);
+                }
+                ConfigField::Cancel(c) => {
+                    cancel = Some(c);
+                }
+                ConfigField::Limits(c) => {
+                    limits = Some(c);
+                }
+                ConfigField::Version(c) => {
+                    version = Some(c);
+                }
+                ConfigField::Memory64(c) => {
+                    memory64 = Some(c);
+                }
+                ConfigField::Memory(c) => {
+                    memory = Some(c);
+                }
+                ConfigField::Nullable(c) => {
+                    nullable = Some(c);
+                }
+                ConfigField::Tracing(c) => {
+                    tracing = Some(c);
+                }
+                ConfigField::Instrument(c) => {
+                    instrument = Some(c);
+                }
+                ConfigField::Async(c) => {
+                    async_ = Some(c);
+                }
+                ConfigField::MultiMemory(c) => {
+                    multi_memory = Some(c);
+                }
+                ConfigField::NoStd(c) => {
+                    no_std = Some(c);
+                }
+                ConfigField::Types(c) => {
+                    types = Some(c);
+                }
+                ConfigField::IncludeFunctions(c) => {
+                    include_functions = Some(c);
+                }
+                ConfigField::ExcludeFunctions(c) => {
+                    exclude_functions = Some(c);
+                }
+                ConfigField::IncludeModules(c) => {
+                    include_modules = Some(c);
+                }
+                ConfigField::ExcludeModules(c) => {
+                    exclude_modules = Some(c);
+                }
+                ConfigField::Derive(c) => {
+                    derive = Some(c);
+                }
+                ConfigField::Attributes(c) => {
+                    attributes = Some(c);
+                }
+                ConfigField::Substitute(c) => {
+                    substitute = Some(c);
+                }
+                ConfigField::Naming(c) => {
+                    naming = Some(c);
+                }
+                ConfigField::Serde(c) => {
+                    serde = Some(c);
+                }
+                ConfigField::Proptest(c) => {
+                    proptest = Some(c);
+                }
+                ConfigField::StructViews(c) => {
+                    struct_views = Some(c);
+                }
+                ConfigField::Guest(c) => {
+                    guest = Some(c);
+                }
+                ConfigField::Mock(c) => {
+                    mock = Some(c);
+                }
+                ConfigField::NotSupported(c) => {
+                    not_supported = Some(c);
+                }
+                ConfigField::SplitTraits(c) => {
+                    split_traits = Some(c);
+                }
+                ConfigField::MultiValue(c) => {
+                    multi_value = Some(c);
+                }
+                ConfigField::Errors(c) => {
+                    errors = Some(c);
+                }
+                ConfigField::Adapter(c) => {
+                    adapter = Some(c);
+                }
+                ConfigField::Conversions(c) => {
+                    conversions = Some(c);
+                }
             }
         }
+        if memory.is_some()
+            && multi_memory
+                .as_ref()
+                .is_some_and(|m: &MultiMemoryConf| m.enabled)
+        {
+            return Err(Error::new(
+                err_loc,
+                "`memory` can't be combined with `multi_memory`",
+            ));
+        }
+        if cancel.is_some() && no_std.as_ref().is_some_and(|n: &NoStdConf| n.enabled) {
+            return Err(Error::new(
+                err_loc,
+                "`cancel` needs the standard library, so can't be combined with `no_std`",
+            ));
+        }
+        if ctx.is_none() && !guest.as_ref().is_some_and(|g: &GuestConf| g.enabled) {
+            return Err(Error::new(err_loc, "`ctx` field required"));
+        }
+        if include_functions.is_some() && exclude_functions.is_some() {
+            return Err(Error::new(
+                err_loc,
+                "`include_functions` can't be combined with `exclude_functions`",
+            ));
+        }
+        if include_modules.is_some() && exclude_modules.is_some() {
+            return Err(Error::new(
+                err_loc,
+                "`include_modules` can't be combined with `exclude_modules`",
+            ));
+        }
         Ok(Config {
             witx: witx
                 .take()
-                .ok_or_else(|| Error::new(err_loc, "`witx` field required"))?,
-            ctx: ctx
-                .take()
-                .ok_or_else(|| Error::new(err_loc, "`ctx` field required"))?,
+                .ok_or_else(|| Error::new(err_loc, "`witx` or `witx_literal` field required"))?,
+            ctx,
+            policy,
+            cancel,
+            limits,
+            version,
+            memory64,
+            memory,
+            nullable,
+            tracing,
+            instrument,
+            async_,
+            multi_memory,
+            no_std,
+            types,
+            include_functions,
+            exclude_functions,
+            include_modules,
+            exclude_modules,
+            derive,
+            attributes,
+            substitute,
+            naming,
+            serde,
+            proptest,
+            struct_views,
+            guest,
+            mock,
+            not_supported,
+            split_traits,
+            multi_value,
+            errors,
+            adapter,
+            conversions,
         })
     }
 }
@@ -69,33 +375,138 @@ impl Parse for Config {
         let _lbrace = braced!(contents in input);
         let fields: Punctuated<ConfigField, Token![,]> =
             contents.parse_terminated(ConfigField::parse)?;
-        Ok(Config::build(fields.into_iter(), input.span())?)
+        Config::build(fields.into_iter(), input.span())
     }
 }
 
+impl FromStr for Config {
+    type Err = Error;
+
+    /// Parses a config written as `from_witx!` is given it, e.g.
+    /// `{ witx: ["api.witx"], ctx: Ctx }`.
+    fn from_str(s: &str) -> Result<Self> {
+        syn::parse_str(s)
+    }
+}
+
+/// Where the witx document comes from.
 #[derive(Debug, Clone)]
-pub struct WitxConf {
-    pub paths: Vec<PathBuf>,
+pub enum WitxConf {
+    /// `witx: [...]`: the files making up the document. `span` is that of the
+    /// list of paths, and `env_vars` are the environment variables
+    /// interpolated into them.
+    Paths {
+        paths: Vec<PathBuf>,
+        span: Span,
+        env_vars: Vec<String>,
+    },
+    /// `witx_literal: "..."`: the text of the document itself, which can't
+    /// `use` other files. `span` is that of the string literal.
+    Literal { text: String, span: Span },
+}
+
+impl WitxConf {
+    /// Where errors about the document as a whole are reported.
+    pub fn span(&self) -> Span {
+        match self {
+            WitxConf::Paths { span, .. } | WitxConf::Literal { span, .. } => *span,
+        }
+    }
 }
 
 impl Parse for WitxConf {
     fn parse(input: ParseStream) -> Result<Self> {
         let content;
-        let _ = bracketed!(content in input);
+        let bracket = bracketed!(content in input);
         let path_lits: Punctuated<LitStr, Token![,]> = content.parse_terminated(Parse::parse)?;
-        let paths: Vec<PathBuf> = path_lits
+        let mut env_vars = Vec::new();
+        let paths = path_lits
             .iter()
-            .map(|lit| PathBuf::from(lit.value()))
-            .collect();
-        Ok(WitxConf { paths })
+            .map(|lit| resolve_path(lit, &mut env_vars))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(WitxConf::Paths {
+            paths,
+            span: bracket.span,
+            env_vars,
+        })
+    }
+}
+
+/// Interpolates `$VAR` and `${VAR}` environment variables into the path
+/// `lit`, adding their names to `env_vars`, and resolves the result relative
+/// to `CARGO_MANIFEST_DIR` when it's set.
+///
+/// Paths used to be resolved relative to the compiler's working directory, so
+/// if nothing exists at the manifest-relative path but something does at the
+/// path as written, that's used instead.
+fn resolve_path(lit: &LitStr, env_vars: &mut Vec<String>) -> Result<PathBuf> {
+    let value = lit.value();
+    let mut path = String::new();
+    let mut rest = value.as_str();
+    while let Some(dollar) = rest.find('$') {
+        path.push_str(&rest[..dollar]);
+        rest = &rest[dollar + 1..];
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => return Err(Error::new(lit.span(), "unclosed `${` in witx path")),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        if name.is_empty() {
+            return Err(Error::new(
+                lit.span(),
+                "expected an environment variable name after `$` in witx path",
+            ));
+        }
+        let var = std::env::var(name).map_err(|_| {
+            Error::new(
+                lit.span(),
+                format!("environment variable `{}` isn't set", name),
+            )
+        })?;
+        path.push_str(&var);
+        env_vars.push(name.to_string());
+        rest = after;
+    }
+    path.push_str(rest);
+
+    let path = PathBuf::from(path);
+    match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) if path.is_relative() => {
+            let in_manifest_dir = PathBuf::from(dir).join(&path);
+            if !in_manifest_dir.exists() && path.exists() {
+                Ok(path)
+            } else {
+                Ok(in_manifest_dir)
+            }
+        }
+        _ => Ok(path),
     }
 }
 
+/// The ctx generated functions are passed: a type, or `generic` to have the
+/// functions be generic over any ctx implementing the module trait, so that a
+/// library can leave the choice of ctx to its users. Generic functions
+/// convert guest errors through the ctx's `wiggle::GuestErrorCtx` instead of
+/// `wiggle::GuestErrorType`, and have no `dispatch` function, `ABI_FUNCTIONS`
+/// table or engine glue.
 #[derive(Debug, Clone)]
 pub struct CtxConf {
     pub name: Ident,
 }
 
+impl CtxConf {
+    pub fn is_generic(&self) -> bool {
+        self.name == "generic"
+    }
+}
+
 impl Parse for CtxConf {
     fn parse(input: ParseStream) -> Result<Self> {
         Ok(CtxConf {
@@ -103,3 +514,818 @@ impl Parse for CtxConf {
         })
     }
 }
+
+/// Enables the runtime policy check in every generated function. The value
+/// names the error type that denied calls return, and the ctx must implement
+/// `wiggle::PolicyCtx` for it.
+#[derive(Debug, Clone)]
+pub struct PolicyConf {
+    pub error: Ident,
+}
+
+impl Parse for PolicyConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(PolicyConf {
+            error: input.parse()?,
+        })
+    }
+}
+
+/// Enables the cancellation check on entry to every generated function. The
+/// value names the error type cancelled calls return, and the ctx must
+/// implement `wiggle::CancelCtx` for it.
+#[derive(Debug, Clone)]
+pub struct CancelConf {
+    pub error: Ident,
+}
+
+impl Parse for CancelConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(CancelConf {
+            error: input.parse()?,
+        })
+    }
+}
+
+/// Limits on the guest data each generated function accesses: either
+/// `limits: ctx`, to use the limits returned by the ctx's `wiggle::LimitsCtx`
+/// implementation, or fixed limits such as
+/// `limits: { max_slice_len: 1024, max_string_len: 4096, max_call_bytes: 65536 }`
+/// where any of the fields can be left out.
+#[derive(Debug, Clone)]
+pub enum LimitsConf {
+    Ctx,
+    Fixed {
+        max_slice_len: Option<u32>,
+        max_string_len: Option<u32>,
+        max_call_bytes: Option<u64>,
+    },
+}
+
+impl Parse for LimitsConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input.peek(syn::token::Brace) {
+            let id: Ident = input.parse()?;
+            if id != "ctx" {
+                return Err(Error::new(id.span(), "expected `ctx` or `{ ... }`"));
+            }
+            return Ok(LimitsConf::Ctx);
+        }
+        let contents;
+        let _lbrace = braced!(contents in input);
+        let fields: Punctuated<(Ident, LitInt), Token![,]> =
+            contents.parse_terminated(|input| {
+                let id: Ident = input.parse()?;
+                let _colon: Token![:] = input.parse()?;
+                Ok((id, input.parse()?))
+            })?;
+        let mut max_slice_len = None;
+        let mut max_string_len = None;
+        let mut max_call_bytes = None;
+        for (id, value) in fields {
+            match id.to_string().as_str() {
+                "max_slice_len" => max_slice_len = Some(value.base10_parse()?),
+                "max_string_len" => max_string_len = Some(value.base10_parse()?),
+                "max_call_bytes" => max_call_bytes = Some(value.base10_parse()?),
+                _ => {
+                    return Err(Error::new(
+                        id.span(),
+                        "expected `max_slice_len`, `max_string_len` or `max_call_bytes`",
+                    ))
+                }
+            }
+        }
+        Ok(LimitsConf::Fixed {
+            max_slice_len,
+            max_string_len,
+            max_call_bytes,
+        })
+    }
+}
+
+/// The interface version reported to guests by the generated
+/// `wiggle_interface_info` functions.
+#[derive(Debug, Clone)]
+pub struct VersionConf {
+    pub version: u32,
+}
+
+impl Parse for VersionConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitInt = input.parse()?;
+        Ok(VersionConf {
+            version: lit.base10_parse()?,
+        })
+    }
+}
+
+/// Whether the guest memory is a 64-bit (memory64) memory, in which case
+/// generated functions take pointers, lengths and sizes as `i64`s rather than
+/// `i32`s.
+///
+/// witx only defines how types are laid out in 32-bit memories, so
+/// interfaces which store pointers in guest memory, such as structs with
+/// pointer members, can't be generated for memory64.
+#[derive(Debug, Clone)]
+pub struct Memory64Conf {
+    pub enabled: bool,
+}
+
+impl Parse for Memory64Conf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(Memory64Conf { enabled: lit.value })
+    }
+}
+
+/// The type of the guest memory generated functions are passed. Without it
+/// they take a `&dyn wiggle::GuestMemory`; with it, a reference to the named
+/// type, so that the accesses they make are monomorphized.
+#[derive(Debug, Clone)]
+pub struct MemoryConf {
+    pub name: Ident,
+}
+
+impl Parse for MemoryConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(MemoryConf {
+            name: input.parse()?,
+        })
+    }
+}
+
+/// Pointer members of structs which may be null, written as
+/// `nullable: [iovec.buf, ...]` with the witx names of the struct and member.
+/// These members are generated as `Option<GuestPtr<T>>`, with offset 0 read
+/// as `None`.
+#[derive(Debug, Clone)]
+pub struct NullableConf {
+    pub members: Vec<NullableMember>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NullableMember {
+    pub struct_name: Ident,
+    pub member: Ident,
+}
+
+impl Parse for NullableConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        let _ = bracketed!(content in input);
+        let members: Punctuated<NullableMember, Token![,]> =
+            content.parse_terminated(Parse::parse)?;
+        Ok(NullableConf {
+            members: members.into_iter().collect(),
+        })
+    }
+}
+
+impl Parse for NullableMember {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let struct_name = input.parse()?;
+        let _dot: Token![.] = input.parse()?;
+        Ok(NullableMember {
+            struct_name,
+            member: input.parse()?,
+        })
+    }
+}
+
+/// Whether generated functions emit `tracing` spans and events, written as
+/// `tracing: true`. Each call gets a span named after the function, with its
+/// arguments and result logged at the trace level. The crate invoking the
+/// macro must depend on `tracing`.
+#[derive(Debug, Clone)]
+pub struct TracingConf {
+    pub enabled: bool,
+}
+
+impl Parse for TracingConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(TracingConf { enabled: lit.value })
+    }
+}
+
+/// Whether generated functions call the ctx's instrumentation hook, written as
+/// `instrument: true`. The ctx must implement `wiggle::InstrumentationCtx`.
+#[derive(Debug, Clone)]
+pub struct InstrumentConf {
+    pub enabled: bool,
+}
+
+impl Parse for InstrumentConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(InstrumentConf { enabled: lit.value })
+    }
+}
+
+/// Whether the generated traits and functions are async, written as
+/// `async: true`. Trait methods become `async fn`s, and the generated
+/// functions await them.
+///
+/// The memory accessed by a call stays borrow-checked for as long as its
+/// future is alive, across every await point. The futures hold the call's
+/// borrow checker in a `RefCell`, so they aren't `Send`.
+#[derive(Debug, Clone)]
+pub struct AsyncConf {
+    pub enabled: bool,
+}
+
+impl Parse for AsyncConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(AsyncConf { enabled: lit.value })
+    }
+}
+
+/// Whether the generated functions take the memories of an instance using the
+/// multi-memory proposal, written as `multi_memory: true`. They then take a
+/// `&dyn wiggle::MemoryProvider` and the index of the memory the call's
+/// pointers are in, instead of a single memory.
+#[derive(Debug, Clone)]
+pub struct MultiMemoryConf {
+    pub enabled: bool,
+}
+
+impl Parse for MultiMemoryConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(MultiMemoryConf { enabled: lit.value })
+    }
+}
+
+/// Whether the generated code is for a `no_std` crate, written as
+/// `no_std: true`. It then names items through `core` and `alloc` instead of
+/// `std`, so the crate must declare `extern crate alloc`, and depend on
+/// wiggle without its default `std` feature.
+#[derive(Debug, Clone)]
+pub struct NoStdConf {
+    pub enabled: bool,
+}
+
+impl Parse for NoStdConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(NoStdConf { enabled: lit.value })
+    }
+}
+
+/// Whether the generated types implement serde's `Serialize` and
+/// `Deserialize`, written as `serde: true`. The crate must then enable
+/// wiggle's `serde` feature.
+///
+/// Enums are represented by the witx names of their variants, and flags by
+/// lists of the witx names of the flags that are set. Ints and handles are
+/// represented by their values, and structs by maps of the witx names of
+/// their members. Unions, and structs with members which can't be
+/// serialized, like pointers, strings and unions, are left out.
+#[derive(Debug, Clone)]
+pub struct SerdeConf {
+    pub enabled: bool,
+}
+
+impl Parse for SerdeConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(SerdeConf { enabled: lit.value })
+    }
+}
+
+/// Whether the generated types implement proptest's `Arbitrary`, written as
+/// `proptest: true`, so that strategies for them are `any::<T>()`. The crate
+/// must then depend on `proptest` itself.
+///
+/// Every value generated is valid: enums generate their variants, flags
+/// sets of their flags, and ints and handles any value. Structs generate
+/// their members, unless one can't be generated, like pointers, strings and
+/// unions, in which case the struct is left out, as are unions.
+#[derive(Debug, Clone)]
+pub struct ProptestConf {
+    pub enabled: bool,
+}
+
+impl Parse for ProptestConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(ProptestConf { enabled: lit.value })
+    }
+}
+
+/// Whether trait methods take views of the structs passed to them, written
+/// as `struct_views: true`. A `FooView` reads the members of a `Foo` in guest
+/// memory only when they're accessed, so functions taking big structs don't
+/// read every member up front. Methods take `&Foo`s otherwise.
+#[derive(Debug, Clone)]
+pub struct StructViewsConf {
+    pub enabled: bool,
+}
+
+impl Parse for StructViewsConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(StructViewsConf { enabled: lit.value })
+    }
+}
+
+/// Whether to generate bindings for a wasm guest calling the interface,
+/// rather than for the host implementing it, written as `guest: true`.
+///
+/// Each module then gets the raw `extern "C"` imports of its functions, and
+/// wrappers taking Rust types which pass them through linear memory. Types
+/// are generated with the witx layouts, as `#[repr(C)]` structs holding raw
+/// pointers. Host options, like the ctx, don't apply to guests.
+#[derive(Debug, Clone)]
+pub struct GuestConf {
+    pub enabled: bool,
+}
+
+impl Parse for GuestConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(GuestConf { enabled: lit.value })
+    }
+}
+
+/// Whether to generate a mock of each module trait, written as `mock: true`.
+///
+/// Each module then has a `mock` module with a `Mock{Trait}`, whose methods
+/// record their calls and return the error it was created with, for testing
+/// code calling the trait.
+#[derive(Debug, Clone)]
+pub struct MockConf {
+    pub enabled: bool,
+}
+
+impl Parse for MockConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(MockConf { enabled: lit.value })
+    }
+}
+
+/// The "not supported" variant of error enums, written as
+/// `not_supported: { errno: notsup }` with witx names. Trait methods
+/// returning one of these errors get a default implementation returning the
+/// variant, so that implementations only need to override the methods they
+/// support.
+#[derive(Debug, Clone)]
+pub struct NotSupportedConf {
+    pub errors: Vec<(String, Ident)>,
+}
+
+impl Parse for NotSupportedConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(NotSupportedConf {
+            errors: parse_name_map(input)?,
+        })
+    }
+}
+
+/// Splits module traits into smaller ones, written as
+/// `split_traits: { module: { Fs: [fd_read, fd_write], Clock: [clock_time_get] } }`
+/// with the witx names of modules and functions.
+///
+/// Each listed trait gets the methods of the listed functions, and those of
+/// the functions not listed go to a `{Trait}Other` trait. The module trait
+/// then has them all as supertraits, and is implemented for every type
+/// implementing them, so that they can be implemented separately.
+#[derive(Debug, Clone)]
+pub struct SplitTraitsConf {
+    pub modules: Vec<SplitModule>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SplitModule {
+    pub module: Ident,
+    pub traits: Vec<SplitTrait>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SplitTrait {
+    pub name: Ident,
+    pub funcs: Vec<Ident>,
+}
+
+impl Parse for SplitTraitsConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        let _ = braced!(content in input);
+        let mut modules = Vec::new();
+        while !content.is_empty() {
+            let module = content.call(Ident::parse_any)?;
+            let _: Token![:] = content.parse()?;
+            let traits_content;
+            let _ = braced!(traits_content in content);
+            let mut traits = Vec::new();
+            while !traits_content.is_empty() {
+                let name = traits_content.parse()?;
+                let _: Token![:] = traits_content.parse()?;
+                let funcs: FilterConf = traits_content.parse()?;
+                traits.push(SplitTrait {
+                    name,
+                    funcs: funcs.names,
+                });
+                if traits_content.is_empty() {
+                    break;
+                }
+                let _: Token![,] = traits_content.parse()?;
+            }
+            modules.push(SplitModule { module, traits });
+            if content.is_empty() {
+                break;
+            }
+            let _: Token![,] = content.parse()?;
+        }
+        Ok(SplitTraitsConf { modules })
+    }
+}
+
+/// Whether generated functions return their results as core values, for
+/// engines supporting wasm multi-value, written as `multi_value: true`.
+///
+/// Functions then return a tuple of the error followed by the other results,
+/// rather than taking pointers to write the other results to. Results which
+/// aren't passed by value, like structs, can't be returned this way.
+#[derive(Debug, Clone)]
+pub struct MultiValueConf {
+    pub enabled: bool,
+}
+
+impl Parse for MultiValueConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitBool = input.parse()?;
+        Ok(MultiValueConf { enabled: lit.value })
+    }
+}
+
+/// Re-uses the `types` module generated by another invocation, written as
+/// `types: path::to::types`, rather than generating one. The witx document
+/// must still declare the types, and they're generated with the other
+/// invocation's configuration.
+///
+/// This lets interfaces which `use` the same witx file of shared types be
+/// generated in separate invocations, or separate crates, without ending up
+/// with a distinct copy of each type per invocation.
+#[derive(Debug, Clone)]
+pub struct TypesConf {
+    pub path: TokenStream,
+}
+
+impl Parse for TypesConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let path: Path = input.parse()?;
+        Ok(TypesConf {
+            path: path.into_token_stream(),
+        })
+    }
+}
+
+/// The witx names of the functions or modules to generate, or not to
+/// generate, written as e.g. `include_functions: [fd_read, fd_write]`.
+/// Function names apply to the functions of that name in every module.
+///
+/// Filtered out functions are left out of the module trait and get no
+/// trampoline, but are still listed in the module's `INTERFACE`, as not
+/// implemented.
+#[derive(Debug, Clone)]
+pub struct FilterConf {
+    pub names: Vec<Ident>,
+}
+
+impl FilterConf {
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
+}
+
+impl Parse for FilterConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        let _ = bracketed!(content in input);
+        let names: Punctuated<Ident, Token![,]> = content.parse_terminated(Ident::parse_any)?;
+        Ok(FilterConf {
+            names: names.into_iter().collect(),
+        })
+    }
+}
+
+/// Extra derives for generated types, written as
+/// `derive: { _: [Default], errno: [serde::Serialize] }`. The list under `_`
+/// applies to every type which derives anything, and the others to the type
+/// with that witx name. Both are added to the derives wiggle generates
+/// anyway.
+#[derive(Debug, Clone)]
+pub struct DeriveConf {
+    pub types: Vec<PerTypeConf>,
+}
+
+impl Parse for DeriveConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let types = parse_per_type(input, |content| {
+            let paths: Punctuated<Path, Token![,]> = content.parse_terminated(Path::parse)?;
+            Ok(paths.iter().map(|p| p.into_token_stream()).collect())
+        })?;
+        Ok(DeriveConf { types })
+    }
+}
+
+/// Extra attributes for generated types, written as
+/// `attributes: { errno: [#[serde(rename_all = "snake_case")]] }`, with the
+/// same keys as `derive`. They're placed after the type's derives, so can
+/// be helper attributes for them.
+#[derive(Debug, Clone)]
+pub struct AttributesConf {
+    pub types: Vec<PerTypeConf>,
+}
+
+impl Parse for AttributesConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let types = parse_per_type(input, |content| {
+            let attrs = content.call(Attribute::parse_outer)?;
+            Ok(attrs.iter().map(|a| a.into_token_stream()).collect())
+        })?;
+        Ok(AttributesConf { types })
+    }
+}
+
+/// A list of items for the type with witx name `typename`, or for every type
+/// if it's `None`.
+#[derive(Debug, Clone)]
+pub struct PerTypeConf {
+    pub typename: Option<Ident>,
+    pub items: Vec<TokenStream>,
+}
+
+impl PerTypeConf {
+    pub fn applies_to(&self, name: &str) -> bool {
+        self.typename.as_ref().map_or(true, |t| t == name)
+    }
+}
+
+/// Parses `{ _: [...], typename: [...], ... }`, with `items` parsing the
+/// contents of each list.
+fn parse_per_type(
+    input: ParseStream,
+    items: fn(ParseStream) -> Result<Vec<TokenStream>>,
+) -> Result<Vec<PerTypeConf>> {
+    let content;
+    let _ = braced!(content in input);
+    let mut types = Vec::new();
+    while !content.is_empty() {
+        let typename = if content.peek(Token![_]) {
+            let _: Token![_] = content.parse()?;
+            None
+        } else {
+            Some(content.call(Ident::parse_any)?)
+        };
+        let _: Token![:] = content.parse()?;
+        let list;
+        let _ = bracketed!(list in content);
+        types.push(PerTypeConf {
+            typename,
+            items: items(&list)?,
+        });
+        if content.is_empty() {
+            break;
+        }
+        let _: Token![,] = content.parse()?;
+    }
+    Ok(types)
+}
+
+/// Rust types to use in place of witx types, written as
+/// `substitute: { timestamp: crate::time::Timestamp }`.
+///
+/// The witx type is still generated, as `{Name}Repr` (e.g.
+/// `TimestampRepr`), and `{Name}` becomes an alias of the substitute, in the
+/// generated `types` module, so it's best written as a `crate::` path. The
+/// substitute must be a type of the crate invoking the macro, implementing
+/// `From<{Name}Repr>`, with `{Name}Repr: From<substitute>`; `GuestType` is
+/// implemented for it through those conversions. Types with lifetimes, and
+/// the error types of functions, can't be substituted.
+#[derive(Debug, Clone)]
+pub struct SubstituteConf {
+    pub types: Vec<Substitution>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    pub typename: Ident,
+    pub with: TokenStream,
+}
+
+impl Parse for SubstituteConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        let _ = braced!(content in input);
+        let types: Punctuated<Substitution, Token![,]> = content.parse_terminated(Parse::parse)?;
+        Ok(SubstituteConf {
+            types: types.into_iter().collect(),
+        })
+    }
+}
+
+impl Parse for Substitution {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let typename = input.call(Ident::parse_any)?;
+        let _colon: Token![:] = input.parse()?;
+        let with: syn::Type = input.parse()?;
+        Ok(Substitution {
+            typename,
+            with: with.into_token_stream(),
+        })
+    }
+}
+
+/// Rust error types trait methods return in place of witx error types,
+/// written as `errors: { errno: crate::MyError }`.
+///
+/// Methods whose error result is of the witx type then return
+/// `Result<T, MyError>`, and the generated functions convert the error with
+/// `From<MyError>` for the witx type, which the crate invoking the macro must
+/// implement, along with `Debug` (and `Clone`, for mocks). Guest errors are
+/// still reported through the witx type's `GuestErrorType` implementation.
+#[derive(Debug, Clone)]
+pub struct ErrorsConf {
+    pub types: Vec<Substitution>,
+}
+
+impl Parse for ErrorsConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let substitutions: SubstituteConf = input.parse()?;
+        Ok(ErrorsConf {
+            types: substitutions.types,
+        })
+    }
+}
+
+/// Implements the module traits of this document in terms of those of a newer
+/// version of it, generated by another invocation, so one implementation can
+/// back both. Written as
+/// `adapter: { witx: ["new.witx"], to: crate::new, renames: { old: new } }`,
+/// where `to` is the path of the other invocation, and the optional `renames`
+/// maps the witx names of modules, functions, struct members, enum variants
+/// and flags to their names in the newer document.
+///
+/// The ctx implements the new traits, and the generated `adapter` module
+/// implements the old traits for it, converting the arguments and results
+/// between the old and new types.
+#[derive(Debug, Clone)]
+pub struct AdapterConf {
+    pub witx: WitxConf,
+    pub to: TokenStream,
+    pub renames: Vec<(String, Ident)>,
+}
+
+impl Parse for AdapterConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let contents;
+        let brace = braced!(contents in input);
+        let mut witx = None;
+        let mut to = None;
+        let mut renames = Vec::new();
+        while !contents.is_empty() {
+            let id: Ident = contents.parse()?;
+            let _colon: Token![:] = contents.parse()?;
+            match id.to_string().as_str() {
+                "witx" => witx = Some(contents.parse()?),
+                "to" => to = Some(contents.parse::<Path>()?.into_token_stream()),
+                "renames" => renames = parse_name_map(&contents)?,
+                _ => return Err(Error::new(id.span(), "expected `witx`, `to` or `renames`")),
+            }
+            if contents.is_empty() {
+                break;
+            }
+            let _: Token![,] = contents.parse()?;
+        }
+        Ok(AdapterConf {
+            witx: witx.ok_or_else(|| Error::new(brace.span, "`witx` field required"))?,
+            to: to.ok_or_else(|| Error::new(brace.span, "`to` field required"))?,
+            renames,
+        })
+    }
+}
+
+/// Conversions between the types of this document and those of the same
+/// names in another, generated by another invocation, written as
+/// `conversions: { witx: ["other.witx"], to: crate::other, renames: { old: new } }`
+/// like `adapter`.
+#[derive(Debug, Clone)]
+pub struct ConversionsConf {
+    pub witx: WitxConf,
+    pub to: TokenStream,
+    pub renames: Vec<(String, Ident)>,
+}
+
+impl Parse for ConversionsConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let other: AdapterConf = input.parse()?;
+        Ok(ConversionsConf {
+            witx: other.witx,
+            to: other.to,
+            renames: other.renames,
+        })
+    }
+}
+
+/// How witx names are converted to Rust identifiers, written as e.g.
+/// `naming: { types: preserve, functions: snake, rename: { "2big": TooBig } }`.
+///
+/// Each of `types` (including module traits), `variants` (of enums and
+/// unions), `flags` (including int constants) and `functions` takes one of
+/// `preserve`, `camel`, `snake` or `shouty`, and defaults to the usual Rust
+/// style for its kind. `rename` maps individual witx names to identifiers,
+/// and overrides the styles for those kinds. `variant_prefixes` gives the
+/// prefixes of the variants of particular enums and unions, so that e.g.
+/// `variant_prefixes: { errno: E }` generates `Errno::EBadf` rather than
+/// `Errno::Badf`; variants aren't prefixed otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct NamingConf {
+    pub types: Option<Case>,
+    pub variants: Option<Case>,
+    pub flags: Option<Case>,
+    pub functions: Option<Case>,
+    pub rename: Vec<(String, Ident)>,
+    pub variant_prefixes: Vec<(String, Ident)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    Preserve,
+    Camel,
+    Snake,
+    Shouty,
+}
+
+impl Parse for NamingConf {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        let _ = braced!(content in input);
+        let mut naming = NamingConf::default();
+        while !content.is_empty() {
+            let key: Ident = content.parse()?;
+            let _: Token![:] = content.parse()?;
+            match key.to_string().as_str() {
+                "types" => naming.types = Some(content.parse()?),
+                "variants" => naming.variants = Some(content.parse()?),
+                "flags" => naming.flags = Some(content.parse()?),
+                "functions" => naming.functions = Some(content.parse()?),
+                "rename" => naming.rename = parse_name_map(&content)?,
+                "variant_prefixes" => naming.variant_prefixes = parse_name_map(&content)?,
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        "expected `types`, `variants`, `flags`, `functions`, `rename` or \
+                         `variant_prefixes`",
+                    ))
+                }
+            }
+            if content.is_empty() {
+                break;
+            }
+            let _: Token![,] = content.parse()?;
+        }
+        Ok(naming)
+    }
+}
+
+/// Parses `{ name: Ident, "name": Ident, ... }`, where names are witx names,
+/// so may be written as strings when they aren't identifiers.
+fn parse_name_map(input: ParseStream) -> Result<Vec<(String, Ident)>> {
+    let content;
+    let _ = braced!(content in input);
+    let mut map = Vec::new();
+    while !content.is_empty() {
+        let from = if content.peek(LitStr) {
+            content.parse::<LitStr>()?.value()
+        } else {
+            content.call(Ident::parse_any)?.to_string()
+        };
+        let _: Token![:] = content.parse()?;
+        map.push((from, content.call(Ident::parse_any)?));
+        if content.is_empty() {
+            break;
+        }
+        let _: Token![,] = content.parse()?;
+    }
+    Ok(map)
+}
+
+impl Parse for Case {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let case: Ident = input.parse()?;
+        match case.to_string().as_str() {
+            "preserve" => Ok(Case::Preserve),
+            "camel" => Ok(Case::Camel),
+            "snake" => Ok(Case::Snake),
+            "shouty" => Ok(Case::Shouty),
+            _ => Err(Error::new(
+                case.span(),
+                "expected `preserve`, `camel`, `snake` or `shouty`",
+            )),
+        }
+    }
+}
@@ -0,0 +1,642 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, quote_spanned};
+use std::collections::HashMap;
+use witx::{Document, Id, NamedType, Type, TypeRef};
+
+use crate::lifetimes::LifetimeExt;
+use crate::names::Names;
+
+/// Defines the `conversions` module, when `conversions` is configured, with
+/// conversions between the types of `doc` and the types of the same names
+/// in the other document.
+///
+/// Enums, flags, handles and structs are converted, in both directions,
+/// matching variants, flags and members by name. Types with a counterpart
+/// for each of their values get a `From` impl, and enums and flags which
+/// don't, along with the structs containing them, get a `TryFrom` impl.
+/// Integers out of range for their new type saturate, and floats are only
+/// converted where that's lossless.
+pub fn define_conversions(doc: &Document, names: &Names) -> TokenStream {
+    let conf = match names.conversions() {
+        Some(conf) => conf,
+        None => return quote!(),
+    };
+    let other_doc = match crate::witx_document(&conf.witx) {
+        Ok(doc) => doc,
+        Err(e) => {
+            let msg = format!("loading the witx to convert to: {}", e);
+            let span = conf.witx.span();
+            return quote_spanned!(span=> compile_error!(#msg););
+        }
+    };
+    let mut converter = Converter::new(names, &conf.to, &conf.renames);
+    converter.define_all(doc, &other_doc);
+    let impls = converter.conversions;
+    let doc = format!(
+        "Conversions between the types of this module and those of `{}`.",
+        conf.to
+    );
+    quote! {
+        #[doc = #doc]
+        pub mod conversions {
+            #(#impls)*
+        }
+    }
+}
+
+/// Which way values are converted: from the types of this document to those
+/// of the other, or back.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Direction {
+    ToNew,
+    ToOld,
+}
+
+/// How values of one type are converted to another.
+#[derive(Clone)]
+enum Conversion {
+    From,
+    /// Some values have no counterpart, for the given reason.
+    TryFrom(String),
+}
+
+/// Defines conversions between the types of this document, the old one, and
+/// those of the new one, generated by the invocation at `to`.
+pub(crate) struct Converter<'a> {
+    names: &'a Names,
+    /// The path of the invocation generating the new document.
+    pub to: &'a TokenStream,
+    renames: &'a [(String, Ident)],
+    /// Whether `TryFrom` impls are defined for types which can't have a
+    /// `From` impl.
+    pub fallible: bool,
+    /// The conversions between pairs of types, or why there's none.
+    converted: HashMap<(Id, Id, Direction), Result<Conversion, String>>,
+    /// The impls defined so far.
+    pub conversions: Vec<TokenStream>,
+}
+
+impl<'a> Converter<'a> {
+    pub fn new(names: &'a Names, to: &'a TokenStream, renames: &'a [(String, Ident)]) -> Self {
+        Converter {
+            names,
+            to,
+            renames,
+            fallible: true,
+            converted: HashMap::new(),
+            conversions: Vec::new(),
+        }
+    }
+
+    /// The name of `id` in the new document.
+    pub fn rename(&self, id: &Id) -> Id {
+        self.renames
+            .iter()
+            .find(|(old, _)| old == id.as_str())
+            .map(|(_, new)| Id::new(new.to_string()))
+            .unwrap_or_else(|| id.clone())
+    }
+
+    /// Whether `from` and `to` are the names of the same thing in the two
+    /// documents, converting in `direction`.
+    fn corresponds(&self, from: &Id, to: &Id, direction: Direction) -> bool {
+        match direction {
+            Direction::ToNew => self.rename(from) == *to,
+            Direction::ToOld => self.rename(to) == *from,
+        }
+    }
+
+    /// The path of the `types` module converted from or to.
+    fn types(&self, direction: Direction, from: bool) -> TokenStream {
+        let to = self.to;
+        match (direction, from) {
+            (Direction::ToNew, true) | (Direction::ToOld, false) => quote!(super::types),
+            (Direction::ToNew, false) | (Direction::ToOld, true) => quote!(#to::types),
+        }
+    }
+
+    /// Defines the conversions, both ways, between each type of `old` and
+    /// the type of the same name in `new`, where they can be converted.
+    pub fn define_all(&mut self, old: &Document, new: &Document) {
+        for nt in old.typenames() {
+            if self.names.substitute(&nt.name).is_some() {
+                continue;
+            }
+            if let Some(new_nt) = new.typename(&self.rename(&nt.name)) {
+                let _ = self.conversion(&nt, &new_nt, Direction::ToNew);
+                let _ = self.conversion(&new_nt, &nt, Direction::ToOld);
+            }
+        }
+    }
+
+    /// Converts `expr` from type `from` to type `to`, with `From`.
+    pub fn convert(
+        &mut self,
+        expr: TokenStream,
+        from: &TypeRef,
+        to: &TypeRef,
+        direction: Direction,
+    ) -> Result<TokenStream, String> {
+        self.convert_value(expr, from, to, direction, false)
+    }
+
+    /// Converts `expr` from type `from` to type `to`, returning from a
+    /// `TryFrom` impl if it can't be.
+    fn try_convert(
+        &mut self,
+        expr: TokenStream,
+        from: &TypeRef,
+        to: &TypeRef,
+        direction: Direction,
+    ) -> Result<TokenStream, String> {
+        self.convert_value(expr, from, to, direction, true)
+    }
+
+    fn convert_value(
+        &mut self,
+        expr: TokenStream,
+        from: &TypeRef,
+        to: &TypeRef,
+        direction: Direction,
+        fallible: bool,
+    ) -> Result<TokenStream, String> {
+        let names = self.names;
+        match (&*from.type_(), &*to.type_()) {
+            (Type::Builtin(a), Type::Builtin(b)) if a == b => return Ok(expr),
+            (Type::Builtin(a), Type::Builtin(b))
+                if *a != witx::BuiltinType::String && *b != witx::BuiltinType::String =>
+            {
+                return convert_builtin(expr, *a, *b, names);
+            }
+            (Type::Pointer(a), Type::Pointer(b))
+            | (Type::ConstPointer(a), Type::ConstPointer(b))
+            | (Type::Array(a), Type::Array(b)) => {
+                if self.same_layout(a, b, direction) {
+                    return Ok(quote!(wiggle::GuestPtr::new(#expr.mem(), #expr.offset())));
+                }
+                return Err(format!(
+                    "pointers to `{}` can't be converted, since it's laid out differently in the other witx",
+                    type_name(a)
+                ));
+            }
+            _ => {}
+        }
+        match (from, to) {
+            (TypeRef::Name(from_nt), TypeRef::Name(to_nt)) => {
+                let to_types = self.types(direction, false);
+                let to_type = names.type_(&to_nt.name);
+                match self.conversion(from_nt, to_nt, direction)? {
+                    Conversion::From => Ok(quote!(#to_types::#to_type::from(#expr))),
+                    Conversion::TryFrom(_) if fallible => {
+                        let core = names.core();
+                        Ok(
+                            quote!(<#to_types::#to_type as #core::convert::TryFrom<_>>::try_from(#expr)?),
+                        )
+                    }
+                    Conversion::TryFrom(reason) => Err(reason),
+                }
+            }
+            _ => Err(format!(
+                "`{}` can't be converted to `{}`",
+                type_name(from),
+                type_name(to)
+            )),
+        }
+    }
+
+    /// How `from` is converted to `to`, defining the impl if it hasn't been
+    /// defined yet.
+    fn conversion(
+        &mut self,
+        from: &NamedType,
+        to: &NamedType,
+        direction: Direction,
+    ) -> Result<Conversion, String> {
+        let key = (from.name.clone(), to.name.clone(), direction);
+        if let Some(conversion) = self.converted.get(&key) {
+            return conversion.clone();
+        }
+        let names = self.names;
+        let core = names.core();
+        let from_types = self.types(direction, true);
+        let to_types = self.types(direction, false);
+        let from_type = names.type_(&from.name);
+        let to_type = names.type_(&to.name);
+        let lifetime = if from.tref.needs_lifetime() {
+            quote!(<'a>)
+        } else {
+            quote!()
+        };
+        let conversion = match self.define_from(from, to, direction) {
+            Ok(body) => {
+                self.conversions.push(quote! {
+                    impl #lifetime From<#from_types::#from_type #lifetime> for #to_types::#to_type #lifetime {
+                        fn from(v: #from_types::#from_type #lifetime) -> Self {
+                            #body
+                        }
+                    }
+                });
+                Ok(Conversion::From)
+            }
+            Err(reason) if self.fallible => self.define_try_from(from, to, direction).map(|body| {
+                self.conversions.push(quote! {
+                    impl #lifetime #core::convert::TryFrom<#from_types::#from_type #lifetime> for #to_types::#to_type #lifetime {
+                        type Error = wiggle::ConversionError;
+                        fn try_from(v: #from_types::#from_type #lifetime) -> Result<Self, wiggle::ConversionError> {
+                            #body
+                        }
+                    }
+                });
+                Conversion::TryFrom(reason)
+            }),
+            Err(reason) => Err(reason),
+        };
+        self.converted.insert(key, conversion.clone());
+        conversion
+    }
+
+    /// The body of the `From` impl converting `from` to `to`, if every value
+    /// has a counterpart.
+    fn define_from(
+        &mut self,
+        from: &NamedType,
+        to: &NamedType,
+        direction: Direction,
+    ) -> Result<TokenStream, String> {
+        let names = self.names;
+        let from_types = self.types(direction, true);
+        let to_types = self.types(direction, false);
+        let from_type = names.type_(&from.name);
+        let to_type = names.type_(&to.name);
+        let no_counterpart = |what: &str, id: &Id| {
+            format!(
+                "{} `{}` of `{}` has no counterpart in `{}`",
+                what,
+                id.as_str(),
+                from.name.as_str(),
+                to.name.as_str()
+            )
+        };
+
+        Ok(match (&*from.type_(), &*to.type_()) {
+            (Type::Enum(a), Type::Enum(b)) => {
+                let mut arms = Vec::new();
+                for v in &a.variants {
+                    let w = b
+                        .variants
+                        .iter()
+                        .find(|w| self.corresponds(&v.name, &w.name, direction))
+                        .ok_or_else(|| no_counterpart("variant", &v.name))?;
+                    let v = names.enum_variant(&from.name, &v.name);
+                    let w = names.enum_variant(&to.name, &w.name);
+                    arms.push(quote!(#from_types::#from_type::#v => #to_types::#to_type::#w));
+                }
+                quote!(match v { #(#arms,)* })
+            }
+            (Type::Flags(a), Type::Flags(b)) => {
+                let mut inserts = Vec::new();
+                for f in &a.flags {
+                    let g = b
+                        .flags
+                        .iter()
+                        .find(|g| self.corresponds(&f.name, &g.name, direction))
+                        .ok_or_else(|| no_counterpart("flag", &f.name))?;
+                    let f = names.flag_member(&f.name);
+                    let g = names.flag_member(&g.name);
+                    inserts.push(quote! {
+                        if v.contains(&#from_types::#from_type::#f) {
+                            flags.insert(#to_types::#to_type::#g);
+                        }
+                    });
+                }
+                quote! {
+                    let mut flags = #to_types::#to_type::EMPTY_FLAGS;
+                    #(#inserts)*
+                    flags
+                }
+            }
+            (Type::Handle(_), Type::Handle(_)) => {
+                quote!(#to_types::#to_type::from(u32::from(v)))
+            }
+            (Type::Struct(a), Type::Struct(b)) => {
+                let members = self.struct_members(from, a, to, b, direction, false)?;
+                quote!(#to_types::#to_type { #(#members),* })
+            }
+            _ => {
+                return Err(format!(
+                    "`{}` can't be converted to `{}`",
+                    from.name.as_str(),
+                    to.name.as_str()
+                ))
+            }
+        })
+    }
+
+    /// The body of the `TryFrom` impl converting `from` to `to`, which fails
+    /// for the values without a counterpart.
+    fn define_try_from(
+        &mut self,
+        from: &NamedType,
+        to: &NamedType,
+        direction: Direction,
+    ) -> Result<TokenStream, String> {
+        let names = self.names;
+        let from_types = self.types(direction, true);
+        let to_types = self.types(direction, false);
+        let from_type = names.type_(&from.name);
+        let to_type = names.type_(&to.name);
+        let from_name = from.name.as_str();
+        let to_name = to.name.as_str();
+        let err = quote!(wiggle::ConversionError { from: #from_name, to: #to_name });
+
+        Ok(match (&*from.type_(), &*to.type_()) {
+            (Type::Enum(a), Type::Enum(b)) => {
+                let arms = a.variants.iter().map(|v| {
+                    let w = b
+                        .variants
+                        .iter()
+                        .find(|w| self.corresponds(&v.name, &w.name, direction));
+                    let v = names.enum_variant(&from.name, &v.name);
+                    match w {
+                        Some(w) => {
+                            let w = names.enum_variant(&to.name, &w.name);
+                            quote!(#from_types::#from_type::#v => Ok(#to_types::#to_type::#w))
+                        }
+                        None => quote!(#from_types::#from_type::#v => Err(#err)),
+                    }
+                });
+                quote!(match v { #(#arms,)* })
+            }
+            (Type::Flags(a), Type::Flags(b)) => {
+                let inserts = a.flags.iter().map(|f| {
+                    let g = b
+                        .flags
+                        .iter()
+                        .find(|g| self.corresponds(&f.name, &g.name, direction));
+                    let f = names.flag_member(&f.name);
+                    match g {
+                        Some(g) => {
+                            let g = names.flag_member(&g.name);
+                            quote! {
+                                if v.contains(&#from_types::#from_type::#f) {
+                                    flags.insert(#to_types::#to_type::#g);
+                                }
+                            }
+                        }
+                        None => quote! {
+                            if v.contains(&#from_types::#from_type::#f) {
+                                return Err(#err);
+                            }
+                        },
+                    }
+                });
+                quote! {
+                    let mut flags = #to_types::#to_type::EMPTY_FLAGS;
+                    #(#inserts)*
+                    Ok(flags)
+                }
+            }
+            (Type::Struct(a), Type::Struct(b)) => {
+                let members = self.struct_members(from, a, to, b, direction, true)?;
+                quote!(Ok(#to_types::#to_type { #(#members),* }))
+            }
+            _ => {
+                return Err(format!(
+                    "`{}` can't be converted to `{}`",
+                    from.name.as_str(),
+                    to.name.as_str()
+                ))
+            }
+        })
+    }
+
+    /// The members of the struct `to` converted from those of `from`.
+    fn struct_members(
+        &mut self,
+        from: &NamedType,
+        a: &witx::StructDatatype,
+        to: &NamedType,
+        b: &witx::StructDatatype,
+        direction: Direction,
+        fallible: bool,
+    ) -> Result<Vec<TokenStream>, String> {
+        let names = self.names;
+        let mut members = Vec::new();
+        for n in &b.members {
+            let m = a
+                .members
+                .iter()
+                .find(|m| self.corresponds(&m.name, &n.name, direction))
+                .ok_or_else(|| {
+                    format!(
+                        "member `{}` of `{}` has no counterpart in `{}`",
+                        n.name.as_str(),
+                        to.name.as_str(),
+                        from.name.as_str()
+                    )
+                })?;
+            let (old_struct, old_member) = match direction {
+                Direction::ToNew => (&from.name, &m.name),
+                Direction::ToOld => (&to.name, &n.name),
+            };
+            if names.is_nullable(old_struct, old_member) {
+                return Err(format!(
+                    "the nullable member `{}` of `{}` can't be converted",
+                    old_member.as_str(),
+                    old_struct.as_str()
+                ));
+            }
+            let member = names.struct_member(&m.name);
+            let value = if fallible {
+                self.try_convert(quote!(v.#member), &m.tref, &n.tref, direction)?
+            } else {
+                self.convert(quote!(v.#member), &m.tref, &n.tref, direction)?
+            };
+            let n = names.struct_member(&n.name);
+            members.push(quote!(#n: #value));
+        }
+        Ok(members)
+    }
+
+    /// Whether values of types `a` and `b` are laid out the same in guest
+    /// memory, with the same meanings, so pointers to one can be passed as
+    /// pointers to the other.
+    fn same_layout(&self, a: &TypeRef, b: &TypeRef, direction: Direction) -> bool {
+        let names_match = |a: &[&Id], b: &[&Id]| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| self.corresponds(a, b, direction))
+        };
+        match (&*a.type_(), &*b.type_()) {
+            (Type::Builtin(a), Type::Builtin(b)) => a == b,
+            (Type::Enum(a), Type::Enum(b)) => {
+                a.repr == b.repr
+                    && names_match(
+                        &a.variants.iter().map(|v| &v.name).collect::<Vec<_>>(),
+                        &b.variants.iter().map(|v| &v.name).collect::<Vec<_>>(),
+                    )
+            }
+            (Type::Flags(a), Type::Flags(b)) => {
+                a.repr == b.repr
+                    && names_match(
+                        &a.flags.iter().map(|f| &f.name).collect::<Vec<_>>(),
+                        &b.flags.iter().map(|f| &f.name).collect::<Vec<_>>(),
+                    )
+            }
+            (Type::Int(a), Type::Int(b)) => a.repr == b.repr,
+            (Type::Handle(_), Type::Handle(_)) => true,
+            (Type::Struct(a), Type::Struct(b)) => {
+                names_match(
+                    &a.members.iter().map(|m| &m.name).collect::<Vec<_>>(),
+                    &b.members.iter().map(|m| &m.name).collect::<Vec<_>>(),
+                ) && a
+                    .members
+                    .iter()
+                    .zip(&b.members)
+                    .all(|(m, n)| self.same_layout(&m.tref, &n.tref, direction))
+            }
+            (Type::Union(a), Type::Union(b)) => {
+                self.same_layout(
+                    &TypeRef::Name(a.tag.clone()),
+                    &TypeRef::Name(b.tag.clone()),
+                    direction,
+                ) && names_match(
+                    &a.variants.iter().map(|v| &v.name).collect::<Vec<_>>(),
+                    &b.variants.iter().map(|v| &v.name).collect::<Vec<_>>(),
+                ) && a
+                    .variants
+                    .iter()
+                    .zip(&b.variants)
+                    .all(|(v, w)| match (&v.tref, &w.tref) {
+                        (Some(a), Some(b)) => self.same_layout(a, b, direction),
+                        (None, None) => true,
+                        _ => false,
+                    })
+            }
+            (Type::Pointer(a), Type::Pointer(b))
+            | (Type::ConstPointer(a), Type::ConstPointer(b))
+            | (Type::Array(a), Type::Array(b)) => self.same_layout(a, b, direction),
+            _ => false,
+        }
+    }
+}
+
+/// The witx name of `tref`, for error messages.
+fn type_name(tref: &TypeRef) -> String {
+    match tref {
+        TypeRef::Name(nt) => nt.name.as_str().to_string(),
+        TypeRef::Value(ty) => ty.kind().to_string(),
+    }
+}
+
+/// Converts `expr` between two numeric builtins. Integers saturate, to the
+/// new type's `MIN` when they're below its range and to its `MAX` when they're
+/// above it. Floats only widen, and integers only become floats which hold
+/// each of their values.
+fn convert_builtin(
+    expr: TokenStream,
+    from: witx::BuiltinType,
+    to: witx::BuiltinType,
+    names: &Names,
+) -> Result<TokenStream, String> {
+    let core = names.core();
+    let ty = names.builtin_type(to, quote!('_));
+    let lossless = match (numeric(from), numeric(to)) {
+        (Numeric::Int { signed, .. }, Numeric::Int { .. }) => {
+            if int_fits(from, to) {
+                return Ok(quote!(<#ty as #core::convert::From<_>>::from(#expr)));
+            }
+            let below = if signed {
+                quote!(Err(_) if v < 0 => <#ty>::MIN,)
+            } else {
+                quote!()
+            };
+            return Ok(quote! {
+                {
+                    let v = #expr;
+                    match <#ty as #core::convert::TryFrom<_>>::try_from(v) {
+                        Ok(v) => v,
+                        #below
+                        Err(_) => <#ty>::MAX,
+                    }
+                }
+            });
+        }
+        (Numeric::Int { signed, bits }, Numeric::Float { mantissa }) => {
+            bits.map_or(false, |bits| bits - signed as u32 <= mantissa)
+        }
+        (Numeric::Float { mantissa: a }, Numeric::Float { mantissa: b }) => a <= b,
+        (Numeric::Float { .. }, Numeric::Int { .. }) => false,
+    };
+    if lossless {
+        Ok(quote!(<#ty as #core::convert::From<_>>::from(#expr)))
+    } else {
+        Err(format!(
+            "`{}` can't be converted to `{}` without losing precision",
+            names.builtin_type(from, quote!('_)),
+            ty,
+        ))
+    }
+}
+
+/// Returns whether every value of the integer `from` is a value of `to`.
+fn int_fits(from: witx::BuiltinType, to: witx::BuiltinType) -> bool {
+    match (numeric(from), numeric(to)) {
+        (
+            Numeric::Int {
+                signed: a,
+                bits: Some(a_bits),
+            },
+            Numeric::Int {
+                signed: b,
+                bits: Some(b_bits),
+            },
+        ) => (a == b && a_bits <= b_bits) || (!a && b && a_bits < b_bits),
+        // `usize` only implements `From` the integers of 16 bits or fewer.
+        (
+            Numeric::Int {
+                signed: false,
+                bits: Some(bits),
+            },
+            Numeric::Int { bits: None, .. },
+        ) => bits <= 16,
+        _ => false,
+    }
+}
+
+enum Numeric {
+    /// `bits` is `None` for `usize`, whose width depends on the target.
+    Int {
+        signed: bool,
+        bits: Option<u32>,
+    },
+    Float {
+        mantissa: u32,
+    },
+}
+
+fn numeric(ty: witx::BuiltinType) -> Numeric {
+    use witx::BuiltinType::*;
+    let int = |signed, bits| Numeric::Int {
+        signed,
+        bits: Some(bits),
+    };
+    match ty {
+        U8 | Char8 => int(false, 8),
+        U16 => int(false, 16),
+        U32 => int(false, 32),
+        U64 => int(false, 64),
+        S8 => int(true, 8),
+        S16 => int(true, 16),
+        S32 => int(true, 32),
+        S64 => int(true, 64),
+        USize => Numeric::Int {
+            signed: false,
+            bits: None,
+        },
+        F32 => Numeric::Float { mantissa: 24 },
+        F64 => Numeric::Float { mantissa: 53 },
+        String => unreachable!("strings aren't numeric"),
+    }
+}
@@ -0,0 +1,118 @@
+use proc_macro2::TokenStream;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::config::{Config, WitxConf};
+use crate::locations::display_path;
+
+/// Formats generated `code` with rustfmt, if it can be run. Otherwise the
+/// code is left as `TokenStream`'s `Display` writes it, on one long line.
+pub fn pretty(code: &str) -> String {
+    let rustfmt = Command::new("rustfmt")
+        .args(["--edition", "2018", "--emit", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut rustfmt = match rustfmt {
+        Ok(rustfmt) => rustfmt,
+        Err(_) => return code.to_string(),
+    };
+    // rustfmt only starts writing once it has read all of its input, so
+    // there's no need for a separate writer thread.
+    let written = rustfmt
+        .stdin
+        .take()
+        .map(|mut stdin| stdin.write_all(code.as_bytes()))
+        .unwrap_or(Ok(()));
+    match rustfmt.wait_with_output() {
+        Ok(out) if written.is_ok() && out.status.success() => {
+            String::from_utf8(out.stdout).unwrap_or_else(|_| code.to_string())
+        }
+        _ => code.to_string(),
+    }
+}
+
+/// Appends the `code` generated for `config` to the file at `path`, after a
+/// header naming the witx documents it was generated from. The file is
+/// truncated first if `truncate` is set.
+pub fn write_debug_file(
+    path: &Path,
+    config: &Config,
+    code: &TokenStream,
+    truncate: bool,
+) -> io::Result<()> {
+    let sources = match &config.witx {
+        WitxConf::Paths { paths, .. } => paths
+            .iter()
+            .map(|p| display_path(p))
+            .collect::<Vec<_>>()
+            .join(", "),
+        WitxConf::Literal { .. } => "witx_literal".to_string(),
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(!truncate)
+        .truncate(truncate)
+        .open(path)?;
+    writeln!(file, "// from_witx! expansion for {}", sources)?;
+    writeln!(file, "{}", pretty(&code.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn appends_expansions() {
+        let config: Config = r#"{ witx: ["a.witx", "b.witx"], ctx: Ctx }"#
+            .parse()
+            .expect("parse config");
+        let path = std::env::temp_dir().join("wiggle-generate-debug-file.rs");
+        write_debug_file(
+            &path,
+            &config,
+            &quote!(
+                pub mod a {}
+            ),
+            true,
+        )
+        .expect("write");
+        write_debug_file(
+            &path,
+            &config,
+            &quote!(
+                pub mod b {}
+            ),
+            false,
+        )
+        .expect("write");
+        let dump = std::fs::read_to_string(&path).expect("read");
+        std::fs::remove_file(&path).expect("remove");
+        assert_eq!(
+            dump.matches("// from_witx! expansion for a.witx, b.witx\n")
+                .count(),
+            2
+        );
+        assert!(dump.contains("pub mod a"));
+        assert!(dump.contains("pub mod b"));
+
+        write_debug_file(
+            &path,
+            &config,
+            &quote!(
+                pub mod c {}
+            ),
+            true,
+        )
+        .expect("write");
+        let dump = std::fs::read_to_string(&path).expect("read");
+        std::fs::remove_file(&path).expect("remove");
+        assert!(!dump.contains("pub mod a"));
+        assert!(dump.contains("pub mod c"));
+    }
+}
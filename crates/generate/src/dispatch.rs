@@ -0,0 +1,122 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use witx::{AtomType, Module};
+
+use crate::funcs::core_signature;
+use crate::names::Names;
+
+/// Defines the `dispatch` function of a module, which calls the generated
+/// function of a given name with core values, for interpreters resolving
+/// imports at runtime.
+///
+/// As for the engine glue, nothing is generated for async interfaces, for
+/// interfaces taking multiple memories, or for a generic ctx.
+pub fn define_dispatch(names: &Names, m: &Module) -> TokenStream {
+    if names.is_async() || names.multi_memory() || names.generic_ctx() {
+        return quote!();
+    }
+    let ctx_type = names.ctx_type();
+    let memory_type = names.memory_type();
+    let alloc = names.alloc();
+
+    let arms = names.funcs(m).map(|func| {
+        let ident = names.func(&func.name);
+        let funcname = func.name.as_str();
+        let (core_args, core_rets) = core_signature(names, &func);
+        let expected = core_args.len();
+        let args = core_args.iter().enumerate().map(|(index, arg)| {
+            let getter = match names.core_arg_atom(arg) {
+                AtomType::I32 => format_ident!("i32"),
+                AtomType::I64 => format_ident!("i64"),
+                AtomType::F32 => format_ident!("f32"),
+                AtomType::F64 => format_ident!("f64"),
+            };
+            quote! {
+                args[#index].#getter().ok_or(wiggle::DispatchError::ArgType {
+                    func: #funcname,
+                    index: #index,
+                })?
+            }
+        });
+        let call = quote!(#ident(ctx, memory, #(#args),*));
+        let results = match core_rets.len() {
+            0 => quote! {{
+                #call;
+                #alloc::vec::Vec::new()
+            }},
+            1 => quote!(#alloc::vec![wiggle::Value::from(#call)]),
+            n => {
+                let rets = (0..n).map(syn::Index::from);
+                quote! {{
+                    let rets = #call;
+                    #alloc::vec![#(wiggle::Value::from(rets.#rets)),*]
+                }}
+            }
+        };
+        quote! {
+            #funcname => {
+                if args.len() != #expected {
+                    return Err(wiggle::DispatchError::ArgCount {
+                        func: #funcname,
+                        expected: #expected,
+                        got: args.len(),
+                    });
+                }
+                Ok(#results)
+            }
+        }
+    });
+
+    quote! {
+        /// Calls the function named `name` with the core values `args`,
+        /// returning its core results.
+        pub fn dispatch(
+            name: &str,
+            ctx: &#ctx_type,
+            memory: &#memory_type,
+            args: &[wiggle::Value],
+        ) -> Result<#alloc::vec::Vec<wiggle::Value>, wiggle::DispatchError> {
+            match name {
+                #(#arms)*
+                _ => Err(wiggle::DispatchError::UnknownFunction(name.into())),
+            }
+        }
+    }
+}
+
+/// Defines the `ABI_FUNCTIONS` table of a module, listing its generated
+/// functions with their core signatures. Nothing is generated for async
+/// interfaces, whose functions return futures, or for a generic ctx, whose
+/// functions have no single address.
+pub fn define_abi_functions(names: &Names, m: &Module) -> TokenStream {
+    if names.is_async() || names.generic_ctx() {
+        return quote!();
+    }
+    let value_type = |atom| match atom {
+        AtomType::I32 => quote!(wiggle::ValueType::I32),
+        AtomType::I64 => quote!(wiggle::ValueType::I64),
+        AtomType::F32 => quote!(wiggle::ValueType::F32),
+        AtomType::F64 => quote!(wiggle::ValueType::F64),
+    };
+    let funcs = names.funcs(m).map(|func| {
+        let ident = names.func(&func.name);
+        let funcname = func.name.as_str();
+        let (core_args, core_rets) = core_signature(names, &func);
+        let params = core_args
+            .iter()
+            .map(|arg| value_type(names.core_arg_atom(arg)));
+        let results = core_rets.iter().map(|atom| value_type(*atom));
+        quote! {
+            wiggle::AbiFunc {
+                name: #funcname,
+                func: #ident as *const (),
+                params: &[#(#params),*],
+                results: &[#(#results),*],
+            }
+        }
+    });
+    quote! {
+        /// Every generated function of this module, with its core signature.
+        pub const ABI_FUNCTIONS: &[wiggle::AbiFunc] = &[#(#funcs),*];
+    }
+}
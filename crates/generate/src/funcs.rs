@@ -1,58 +1,154 @@
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
 
+use crate::config::LimitsConf;
 use crate::lifetimes::anon_lifetime;
 use crate::names::Names;
+use crate::unsupported;
 
-pub fn define_func(names: &Names, func: &witx::InterfaceFunc) -> TokenStream {
+pub fn define_func(
+    names: &Names,
+    module: &witx::Module,
+    func: &witx::InterfaceFunc,
+) -> TokenStream {
+    let modulename = module.name.as_str();
     let funcname = func.name.as_str();
 
     let ident = names.func(&func.name);
     let ctx_type = names.ctx_type();
-    let coretype = func.core_type();
+    let alloc = names.alloc();
+    let (core_args, core_rets) = core_signature(names, func);
+    let multi_value = multi_value_returns(names, func);
+
+    // `generate` reports these for the whole document up front, but the
+    // function may be defined on its own too.
+    let unsupported = unsupported::unsupported_func(func, true);
+    if !unsupported.is_empty() {
+        let span = names.witx_span();
+        return quote_spanned!(span=> #(compile_error!(#unsupported);)*);
+    }
+
+    if multi_value {
+        let in_memory = func
+            .results
+            .iter()
+            .skip(1)
+            .find(|r| result_atom(names, &r.tref).is_none());
+        if let Some(result) = in_memory {
+            let msg = format!(
+                "`{}` returns `{}` in guest memory, which can't be returned as a multi-value result",
+                funcname,
+                result.name.as_str(),
+            );
+            return quote!(compile_error!(#msg););
+        }
+    }
 
-    let params = coretype.args.iter().map(|arg| {
+    if names.memory64() {
+        // Arguments are only stored in memory when they're passed by pointer,
+        // but results always are.
+        let in_memory = func
+            .params
+            .iter()
+            .find(|p| match &*p.tref.type_() {
+                witx::Type::Pointer(pointee) | witx::Type::ConstPointer(pointee) => {
+                    depends_on_pointer_width(pointee)
+                }
+                witx::Type::Array(elem) => depends_on_pointer_width(elem),
+                witx::Type::Struct(_) | witx::Type::Union(_) => depends_on_pointer_width(&p.tref),
+                _ => false,
+            })
+            .or_else(|| {
+                func.results
+                    .iter()
+                    .skip(1)
+                    .filter(|_| !multi_value)
+                    .find(|r| depends_on_pointer_width(&r.tref))
+            });
+        if let Some(param) = in_memory {
+            let msg = format!(
+                "`{}` stores pointers or sizes in guest memory through `{}`, which have no memory64 layout",
+                funcname,
+                param.name.as_str(),
+            );
+            return quote!(compile_error!(#msg););
+        }
+    }
+
+    let params = core_args.iter().map(|arg| {
         let name = names.func_core_arg(arg);
-        let atom = names.atom_type(arg.repr());
+        let atom = names.atom_type(names.core_arg_atom(arg));
         quote!(#name : #atom)
     });
 
-    let abi_args = quote!(
-            ctx: &#ctx_type, memory: &dyn wiggle_runtime::GuestMemory,
+    let memory_type = names.memory_type();
+    let abi_args = if names.multi_memory() {
+        quote!(
+            ctx: &#ctx_type, memories: &dyn wiggle::MemoryProvider, memory_index: u32,
             #(#params),*
-    );
-    let abi_ret = if let Some(ret) = &coretype.ret {
-        match ret.signifies {
-            witx::CoreParamSignifies::Value(atom) => names.atom_type(atom),
-            _ => unreachable!("ret should always be passed by value"),
-        }
+        )
+    } else {
+        quote!(
+            ctx: &#ctx_type, memory: &#memory_type,
+            #(#params),*
+        )
+    };
+    let abi_ret = if !core_rets.is_empty() {
+        core_rets_type(names, &core_rets)
     } else if func.noreturn {
         // Ideally we would return `quote!(!)` here, but, we'd have to change
         // the error handling logic in all the marshalling code to never return,
         // and instead provide some other way to bail to the context...
         // noreturn func
-        unimplemented!("noreturn funcs not supported yet!")
+        unreachable!("noreturn funcs are reported as unsupported")
     } else {
         quote!(())
     };
 
-    let err_type = coretype.ret.map(|ret| ret.param.tref);
-    let err_val = err_type
-        .clone()
-        .map(|_res| quote!(#abi_ret::from(e)))
-        .unwrap_or_else(|| quote!(()));
+    // Returns the error `e` from the function, with zeroes for the other
+    // results when they're returned as well.
+    let ret_error = |e: TokenStream| -> TokenStream {
+        let err_atom = names.atom_type(core_rets[0]);
+        if multi_value {
+            let zeroes = core_rets[1..].iter().map(|atom| match atom {
+                witx::AtomType::I32 | witx::AtomType::I64 => quote!(0),
+                witx::AtomType::F32 | witx::AtomType::F64 => quote!(0.0),
+            });
+            quote!((#err_atom::from(#e), #(#zeroes),*))
+        } else {
+            quote!(#err_atom::from(#e))
+        }
+    };
+
+    let err_type = func.results.first().map(|err| err.tref.clone());
+    let err_val = match &err_type {
+        // Errors of a user type are converted to the witx type first.
+        Some(witx::TypeRef::Name(nt)) if names.user_error(&nt.name).is_some() => {
+            let err_typename = names.type_(&nt.name);
+            ret_error(quote!(#err_typename::from(e)))
+        }
+        Some(_) => ret_error(quote!(e)),
+        None => quote!(()),
+    };
 
-    let error_handling = |location: &str| -> TokenStream {
+    let error_handling = |location: &str, param: &witx::Id| -> TokenStream {
+        let witx = names.param_location(&func.name, param);
+        let param = param.as_str();
         if let Some(tref) = &err_type {
-            let abi_ret = match tref.type_().passed_by() {
-                witx::TypePassedBy::Value(atom) => names.atom_type(atom),
-                _ => unreachable!("err should always be passed by value"),
+            let err_typename = names.type_ref(tref, anon_lifetime());
+            let from_error = if names.generic_ctx() {
+                quote!(wiggle::GuestErrorCtx::<#err_typename>::guest_error(ctx, e, call))
+            } else {
+                quote!(wiggle::GuestErrorType::from_call_error(e, ctx, call))
             };
-            let err_typename = names.type_ref(&tref, anon_lifetime());
+            let trace = trace(names, quote!(guest_error = %e));
+            let ret = ret_error(quote!(err));
             quote! {
-                let e = wiggle_runtime::GuestError::InFunc { funcname: #funcname, location: #location, err: Box::new(e.into()) };
-                let err: #err_typename = wiggle_runtime::GuestErrorType::from_error(e, ctx);
-                return #abi_ret::from(err);
+                let e = wiggle::GuestError::InFunc { funcname: #funcname, location: #location, witx: #witx, err: #alloc::boxed::Box::new(e.into()) };
+                #trace
+                let call = wiggle::CallContext { module: #modulename, function: #funcname, param: Some(#param) };
+                let err: #err_typename = #from_error;
+                return #ret;
             }
         } else {
             quote! {
@@ -61,19 +157,69 @@ pub fn define_func(names: &Names, func: &witx::InterfaceFunc) -> TokenStream {
         }
     };
 
+    let call_memory = call_memory(names);
+    let call_memory = if names.multi_memory() {
+        let unknown_memory = error_handling("memory_index", &witx::Id::new("memory_index"));
+        quote! {
+            let memory = match memories.memory(memory_index) {
+                Some(memory) => memory,
+                None => {
+                    let e = wiggle::GuestError::UnknownMemory(memory_index);
+                    #unknown_memory
+                }
+            };
+            let memory = #call_memory.with_memory_index(memory_index);
+        }
+    } else {
+        quote!(let memory = #call_memory;)
+    };
+    let cancel_check = cancel_check(names, func, &err_type, &ret_error);
+    let policy_check = policy_check(names, func, &err_type, &ret_error);
+
     let marshal_args = func
         .params
         .iter()
-        .map(|p| marshal_arg(names, p, error_handling(p.name.as_str())));
+        .map(|p| marshal_arg(names, p, error_handling(p.name.as_str(), &p.name)));
     let trait_args = func.params.iter().map(|param| {
         let name = names.func_param(&param.name);
+        if names.param_view(&param.tref).is_some() {
+            return quote!(#name);
+        }
         match param.tref.type_().passed_by() {
             witx::TypePassedBy::Value { .. } => quote!(#name),
-            witx::TypePassedBy::Pointer { .. } => quote!(&#name),
-            witx::TypePassedBy::PointerLengthPair { .. } => quote!(&#name),
+            witx::TypePassedBy::Pointer => quote!(&#name),
+            witx::TypePassedBy::PointerLengthPair => quote!(&#name),
         }
     });
 
+    let (trace_span, trace_args) = if names.tracing() {
+        let args = func.params.iter().map(|param| {
+            let name = names.func_param(&param.name);
+            quote!(#name = ?#name)
+        });
+        let trace_args = if func.params.is_empty() {
+            quote!()
+        } else {
+            trace(names, quote!(#(#args),*))
+        };
+        // An entered span would stay entered across the await of an async
+        // call, leaking into whatever else runs on the thread while it's
+        // pending, so async calls instrument the future instead.
+        let span = if names.is_async() {
+            quote! {
+                let _span = ::tracing::span!(::tracing::Level::TRACE, "wiggle abi", function = #funcname);
+            }
+        } else {
+            quote! {
+                let _span = ::tracing::span!(::tracing::Level::TRACE, "wiggle abi", function = #funcname);
+                let _enter = _span.enter();
+            }
+        };
+        (span, trace_args)
+    } else {
+        (quote!(), quote!())
+    };
+
     let (trait_rets, trait_bindings) = if func.results.len() < 2 {
         (quote!({}), quote!(_))
     } else {
@@ -92,45 +238,421 @@ pub fn define_func(names: &Names, func: &witx::InterfaceFunc) -> TokenStream {
         .results
         .iter()
         .skip(1)
-        .map(|result| marshal_result(names, result, &error_handling));
+        .filter(|_| !multi_value)
+        .map(|result| marshal_result(names, result, error_handling));
+    let trace_result = if func.results.len() < 2 {
+        trace(names, quote!("ok"))
+    } else {
+        trace(names, quote!(result = ?#trait_rets))
+    };
+    let trace_error = trace(names, quote!(error = ?e));
+    let (instrument_before, instrument_ok, instrument_err) = if names.instrument() {
+        let args = func.params.iter().map(|param| {
+            let argname = param.name.as_str();
+            let name = names.func_param(&param.name);
+            quote!((#argname, #alloc::format!("{:?}", #name)))
+        });
+        (
+            quote! {
+                let instrumentation = wiggle::InstrumentationCtx::instrumentation(ctx);
+                if let Some(hook) = instrumentation {
+                    hook.before_call(#funcname, &[#(#args),*]);
+                }
+            },
+            quote! {
+                if let Some(hook) = instrumentation {
+                    hook.after_call(#funcname, Ok(&#alloc::format!("{:?}", #trait_rets)));
+                }
+            },
+            quote! {
+                if let Some(hook) = instrumentation {
+                    hook.after_call(#funcname, Err(&#alloc::format!("{:?}", e)));
+                }
+            },
+        )
+    } else {
+        (quote!(), quote!(), quote!())
+    };
     let marshal_rets_pre = marshal_rets.clone().map(|(pre, _post)| pre);
     let marshal_rets_post = marshal_rets.map(|(_pre, post)| post);
 
     let success = if let Some(ref err_type) = err_type {
-        let err_typename = names.type_ref(&err_type, anon_lifetime());
+        let err_typename = names.type_ref(err_type, anon_lifetime());
+        let err_atom = names.atom_type(core_rets[0]);
+        let results = func
+            .results
+            .iter()
+            .skip(1)
+            .zip(&core_rets[1..])
+            .map(|(result, atom)| {
+                let name = names.func_param(&result.name);
+                let atom = names.atom_type(*atom);
+                match &*result.tref.type_() {
+                    witx::Type::Builtin(_) => quote!(#name as #atom),
+                    _ => quote!(#atom::from(#name)),
+                }
+            });
+        let ret = if multi_value {
+            quote!((#err_atom::from(success), #(#results),*))
+        } else {
+            quote!(#err_atom::from(success))
+        };
+        let success = if names.generic_ctx() {
+            quote!(wiggle::GuestErrorCtx::<#err_typename>::success(ctx))
+        } else {
+            quote!(wiggle::GuestErrorType::success())
+        };
         quote! {
-            let success:#err_typename = wiggle_runtime::GuestErrorType::success();
-            #abi_ret::from(success)
+            let success:#err_typename = #success;
+            #ret
         }
     } else {
         quote!()
     };
 
-    quote!(pub fn #ident(#abi_args) -> #abi_ret {
+    let (generics, where_clause) = if names.generic_ctx() {
+        ctx_bounds(names, module, &err_type)
+    } else {
+        (quote!(), quote!())
+    };
+
+    let call = quote!(ctx.#ident(#(#trait_args),*));
+    let (asyncness, call) = if !names.is_async() {
+        (quote!(), call)
+    } else if names.tracing() {
+        (
+            quote!(async),
+            quote!(::tracing::Instrument::instrument(#call, _span.clone()).await),
+        )
+    } else {
+        (quote!(async), quote!(#call.await))
+    };
+
+    let returns_doc = match func.results.len() {
+        0 => "",
+        1 => " and returns its error code",
+        _ if multi_value => " and returns its error code, followed by the other results",
+        _ => {
+            " and returns its error code, after writing the other results through the \
+             pointers passed last"
+        }
+    };
+    let doc = format!(
+        "The `{}` import, taking the core wasm values it's called with. Reads the \
+         arguments from guest memory, calls the trait method `{}` on the ctx{}.",
+        funcname, ident, returns_doc
+    );
+
+    quote!(#[doc = #doc]
+    #[allow(clippy::too_many_arguments)]
+    pub #asyncness fn #ident #generics(#abi_args) -> #abi_ret #where_clause {
+        #trace_span
+        #call_memory
+        let memory = &memory;
+        #cancel_check
+        #policy_check
         #(#marshal_args)*
+        #trace_args
         #(#marshal_rets_pre)*
-        let #trait_bindings  = match ctx.#ident(#(#trait_args),*) {
-            Ok(#trait_bindings) => #trait_rets,
-            Err(e) => { return #err_val; },
+        #instrument_before
+        let #trait_bindings  = match #call {
+            Ok(#trait_bindings) => {
+                #trace_result
+                #instrument_ok
+                #trait_rets
+            }
+            Err(e) => {
+                #trace_error
+                #instrument_err
+                return #err_val;
+            },
         };
         #(#marshal_rets_post)*
         #success
     })
 }
 
+/// The type parameter of a generated function with `ctx: generic`, and the
+/// bounds on it: the ctx implements the module trait, converts errors of type
+/// `err_type`, and implements the traits of the other options taking the ctx.
+fn ctx_bounds(
+    names: &Names,
+    module: &witx::Module,
+    err_type: &Option<witx::TypeRef>,
+) -> (TokenStream, TokenStream) {
+    let ctx_type = names.ctx_type();
+    let traitname = names.trait_name(&module.name);
+    let mut bounds = vec![quote!(#traitname)];
+    if let Some(tref) = err_type {
+        let err_typename = names.type_ref(tref, anon_lifetime());
+        bounds.push(quote!(wiggle::GuestErrorCtx<#err_typename>));
+    }
+    if let Some(err) = names.cancel_error_type() {
+        bounds.push(quote!(wiggle::CancelCtx<#err>));
+    }
+    if let Some(err) = names.policy_error_type() {
+        bounds.push(quote!(wiggle::PolicyCtx<#err>));
+    }
+    if names.instrument() {
+        bounds.push(quote!(wiggle::InstrumentationCtx));
+    }
+    if let Some(LimitsConf::Ctx) = names.limits() {
+        bounds.push(quote!(wiggle::LimitsCtx));
+    }
+    (
+        quote!(<#ctx_type>),
+        quote!(where #ctx_type: #(#bounds)+* + ?Sized),
+    )
+}
+
+/// The core params of the generated function for `func`, and the core
+/// values it returns: only the error, unless `multi_value` has it return the
+/// other results too, instead of taking pointers to write them to.
+pub(crate) fn core_signature(
+    names: &Names,
+    func: &witx::InterfaceFunc,
+) -> (Vec<witx::CoreParamType>, Vec<witx::AtomType>) {
+    let coretype = func.core_type();
+    let mut args = coretype.args;
+    let mut rets = coretype
+        .ret
+        .iter()
+        .map(|ret| match ret.signifies {
+            witx::CoreParamSignifies::Value(atom) => atom,
+            _ => unreachable!("ret should always be passed by value"),
+        })
+        .collect::<Vec<_>>();
+    if multi_value_returns(names, func) {
+        // The pointers to write results to come after the params.
+        args.truncate(args.len() - (func.results.len() - 1));
+        rets.extend(
+            func.results
+                .iter()
+                .skip(1)
+                .filter_map(|r| result_atom(names, &r.tref)),
+        );
+    }
+    (args, rets)
+}
+
+/// The type returning the core values `rets`.
+pub(crate) fn core_rets_type(names: &Names, rets: &[witx::AtomType]) -> TokenStream {
+    let atoms = rets.iter().map(|atom| names.atom_type(*atom));
+    match rets.len() {
+        1 => names.atom_type(rets[0]),
+        _ => quote!((#(#atoms),*)),
+    }
+}
+
+/// Whether `func` returns results other than its error as core values.
+fn multi_value_returns(names: &Names, func: &witx::InterfaceFunc) -> bool {
+    names.multi_value() && func.results.len() > 1
+}
+
+/// The core type a result of type `tref` is returned as with `multi_value`,
+/// if it can be.
+fn result_atom(names: &Names, tref: &witx::TypeRef) -> Option<witx::AtomType> {
+    match &*tref.type_() {
+        witx::Type::Builtin(witx::BuiltinType::USize) => Some(names.pointer_atom()),
+        witx::Type::Pointer(_) | witx::Type::ConstPointer(_) => None,
+        ty => match ty.passed_by() {
+            witx::TypePassedBy::Value(atom) => Some(atom),
+            _ => None,
+        },
+    }
+}
+
+/// A trace-level `tracing` event with `fields`, if tracing is enabled.
+fn trace(names: &Names, fields: TokenStream) -> TokenStream {
+    if !names.tracing() {
+        quote!()
+    } else if names.is_async() {
+        // The span of an async call is only entered while its future is
+        // polled, so events outside of it enter it themselves.
+        quote!(_span.in_scope(|| ::tracing::trace!(#fields));)
+    } else {
+        quote!(::tracing::trace!(#fields);)
+    }
+}
+
+/// Whether values of type `tref` hold pointers or sizes, whose layout in
+/// memory depends on the width of guest pointers.
+pub(crate) fn depends_on_pointer_width(tref: &witx::TypeRef) -> bool {
+    match &*tref.type_() {
+        witx::Type::Pointer(_)
+        | witx::Type::ConstPointer(_)
+        | witx::Type::Array(_)
+        | witx::Type::Builtin(witx::BuiltinType::String)
+        | witx::Type::Builtin(witx::BuiltinType::USize) => true,
+        witx::Type::Struct(s) => s.members.iter().any(|m| depends_on_pointer_width(&m.tref)),
+        witx::Type::Union(u) => u
+            .variants
+            .iter()
+            .any(|v| v.tref.as_ref().is_some_and(depends_on_pointer_width)),
+        _ => false,
+    }
+}
+
+fn call_memory(names: &Names) -> TokenStream {
+    fn option<T: quote::ToTokens>(v: &Option<T>) -> TokenStream {
+        match v {
+            Some(v) => quote!(Some(#v)),
+            None => quote!(None),
+        }
+    }
+    match names.limits() {
+        None => quote!(wiggle::CallMemory::new(memory)),
+        Some(LimitsConf::Ctx) => {
+            quote!(wiggle::CallMemory::with_limits(
+                memory,
+                wiggle::LimitsCtx::limits(ctx)
+            ))
+        }
+        Some(LimitsConf::Fixed {
+            max_slice_len,
+            max_string_len,
+            max_call_bytes,
+        }) => {
+            let max_slice_len = option(max_slice_len);
+            let max_string_len = option(max_string_len);
+            let max_call_bytes = option(max_call_bytes);
+            quote!(wiggle::CallMemory::with_limits(
+                memory,
+                wiggle::GuestLimits {
+                    max_slice_len: #max_slice_len,
+                    max_string_len: #max_string_len,
+                    max_call_bytes: #max_call_bytes,
+                },
+            ))
+        }
+    }
+}
+
+fn cancel_check(
+    names: &Names,
+    func: &witx::InterfaceFunc,
+    err_type: &Option<witx::TypeRef>,
+    ret_error: &dyn Fn(TokenStream) -> TokenStream,
+) -> TokenStream {
+    let cancel_err = match names.cancel_error_type() {
+        Some(err) => err,
+        None => return quote!(),
+    };
+    let funcname = func.name.as_str();
+    let trap = quote!(panic!("call to `{}` cancelled", #funcname));
+    let cancelled = if err_type.is_some() {
+        let ret = ret_error(quote!(e));
+        quote! {
+            match wiggle::CancelCtx::<#cancel_err>::cancelled_error(ctx) {
+                Some(e) => return #ret,
+                None => #trap,
+            }
+        }
+    } else {
+        trap
+    };
+    quote! {
+        if let Some(token) = wiggle::CancelCtx::<#cancel_err>::cancel_token(ctx) {
+            if token.is_cancelled() {
+                #cancelled
+            }
+        }
+    }
+}
+
+fn policy_check(
+    names: &Names,
+    func: &witx::InterfaceFunc,
+    err_type: &Option<witx::TypeRef>,
+    ret_error: &dyn Fn(TokenStream) -> TokenStream,
+) -> TokenStream {
+    let alloc = names.alloc();
+    let policy_err = match names.policy_error_type() {
+        Some(err) => err,
+        None => return quote!(),
+    };
+    let funcname = func.name.as_str();
+
+    // Arguments are collected from the core values the guest passed in,
+    // before marshalling, so that only strings need to touch memory.
+    let args = func.params.iter().filter_map(|param| {
+        let argname = param.name.as_str();
+        let name = names.func_param(&param.name);
+        let unsigned = |atom| match atom {
+            witx::AtomType::I32 => Some(quote!(wiggle::PolicyArg::Unsigned(#name as u32 as u64))),
+            witx::AtomType::I64 => Some(quote!(wiggle::PolicyArg::Unsigned(#name as u64))),
+            witx::AtomType::F32 | witx::AtomType::F64 => None,
+        };
+        let arg = match (&*param.tref.type_(), param.tref.type_().passed_by()) {
+            (witx::Type::Builtin(witx::BuiltinType::String), _) => {
+                let ptr = names.guest_offset(&names.func_ptr_binding(&param.name));
+                let len = names.guest_offset(&names.func_len_binding(&param.name));
+                return Some(quote! {
+                    if let Some(arg) = wiggle::PolicyArg::from_guest_str(
+                        &wiggle::GuestPtr::<str>::new(memory, (#ptr, #len)),
+                    ) {
+                        args.push((#argname, arg));
+                    }
+                });
+            }
+            (
+                witx::Type::Builtin(witx::BuiltinType::S8)
+                | witx::Type::Builtin(witx::BuiltinType::S16)
+                | witx::Type::Builtin(witx::BuiltinType::S32)
+                | witx::Type::Builtin(witx::BuiltinType::S64),
+                _,
+            ) => Some(quote!(wiggle::PolicyArg::Signed(#name as i64))),
+            (witx::Type::Pointer(_), _) | (witx::Type::ConstPointer(_), _) => None,
+            (witx::Type::Builtin(witx::BuiltinType::USize), _) => unsigned(names.pointer_atom()),
+            (_, witx::TypePassedBy::Value(atom)) => unsigned(atom),
+            _ => None,
+        }?;
+        Some(quote!(args.push((#argname, #arg));))
+    });
+
+    let deny = if err_type.is_some() {
+        let ret = ret_error(quote!(e));
+        quote!(wiggle::PolicyDecision::Deny(e) => return #ret,)
+    } else {
+        quote!(wiggle::PolicyDecision::Deny(_) => panic!("policy denied call to `{}`", #funcname),)
+    };
+
+    quote! {
+        if let Some(policy) = wiggle::PolicyCtx::<#policy_err>::policy(ctx) {
+            #[allow(unused_mut)]
+            let mut args: #alloc::vec::Vec<(&str, wiggle::PolicyArg)> = #alloc::vec::Vec::new();
+            #(#args)*
+            match policy.check(&wiggle::PolicyCall { function: #funcname, args: &args }) {
+                wiggle::PolicyDecision::Allow => {}
+                #deny
+                wiggle::PolicyDecision::Trap => panic!("policy trapped call to `{}`", #funcname),
+            }
+        }
+    }
+}
+
 fn marshal_arg(
     names: &Names,
     param: &witx::InterfaceFuncParam,
     error_handling: TokenStream,
 ) -> TokenStream {
     let tref = &param.tref;
-    let interface_typename = names.type_ref(&tref, anon_lifetime());
+    // Substituted types are converted to from their witx representation.
+    let substituted = names.substituted_name(tref);
+    let interface_typename = match &substituted {
+        Some(name) => {
+            let repr = names.substitute_repr(name);
+            quote!(#repr)
+        }
+        None => names.type_ref(tref, anon_lifetime()),
+    };
+    let core = names.core();
 
     let try_into_conversion = {
         let name = names.func_param(&param.name);
         quote! {
             let #name: #interface_typename = {
-                use ::std::convert::TryInto;
+                use #core::convert::TryInto;
                 match #name.try_into() {
                     Ok(a) => a,
                     Err(e) => {
@@ -143,10 +665,10 @@ fn marshal_arg(
 
     let read_conversion = {
         let pointee_type = names.type_ref(tref, anon_lifetime());
-        let arg_name = names.func_ptr_binding(&param.name);
+        let ptr = names.guest_offset(&names.func_ptr_binding(&param.name));
         let name = names.func_param(&param.name);
         quote! {
-            let #name = match wiggle_runtime::GuestPtr::<#pointee_type>::new(memory, #arg_name as u32).read() {
+            let #name = match wiggle::GuestPtr::<#pointee_type, _>::new(memory, #ptr).read() {
                 Ok(r) => r,
                 Err(e) => {
                     #error_handling
@@ -155,7 +677,7 @@ fn marshal_arg(
         }
     };
 
-    match &*tref.type_() {
+    let conversion = match &*tref.type_() {
         witx::Type::Enum(_e) => try_into_conversion,
         witx::Type::Flags(_f) => try_into_conversion,
         witx::Type::Int(_i) => try_into_conversion,
@@ -188,37 +710,63 @@ fn marshal_arg(
             }
             witx::BuiltinType::String => {
                 let lifetime = anon_lifetime();
-                let ptr_name = names.func_ptr_binding(&param.name);
-                let len_name = names.func_len_binding(&param.name);
+                let ptr = names.guest_offset(&names.func_ptr_binding(&param.name));
+                let len = names.guest_offset(&names.func_len_binding(&param.name));
                 let name = names.func_param(&param.name);
                 quote! {
-                    let #name = wiggle_runtime::GuestPtr::<#lifetime, str>::new(memory, (#ptr_name as u32, #len_name as u32));
+                    let #name = wiggle::GuestPtr::<#lifetime, str>::new(memory, (#ptr, #len));
                 }
             }
         },
         witx::Type::Pointer(pointee) | witx::Type::ConstPointer(pointee) => {
             let pointee_type = names.type_ref(pointee, anon_lifetime());
             let name = names.func_param(&param.name);
+            let ptr = names.guest_offset(&name);
             quote! {
-                let #name = wiggle_runtime::GuestPtr::<#pointee_type>::new(memory, #name as u32);
+                let #name = wiggle::GuestPtr::<#pointee_type>::new(memory, #ptr);
             }
         }
-        witx::Type::Struct(_) => read_conversion,
+        witx::Type::Struct(_) => match names.param_view(tref) {
+            Some(view) => {
+                let pointee_type = names.type_ref(tref, anon_lifetime());
+                let ptr = names.guest_offset(&names.func_ptr_binding(&param.name));
+                let name = names.func_param(&param.name);
+                quote! {
+                    let #name = match #view::new(wiggle::GuestPtr::<#pointee_type>::new(memory, #ptr)) {
+                        Ok(view) => view,
+                        Err(e) => {
+                            #error_handling
+                        }
+                    };
+                }
+            }
+            None => read_conversion,
+        },
         witx::Type::Array(arr) => {
             let pointee_type = names.type_ref(arr, anon_lifetime());
-            let ptr_name = names.func_ptr_binding(&param.name);
-            let len_name = names.func_len_binding(&param.name);
+            let ptr = names.guest_offset(&names.func_ptr_binding(&param.name));
+            let len = names.guest_offset(&names.func_len_binding(&param.name));
             let name = names.func_param(&param.name);
             quote! {
-                let #name = wiggle_runtime::GuestPtr::<[#pointee_type]>::new(memory, (#ptr_name as u32, #len_name as u32));
+                let #name = wiggle::GuestPtr::<[#pointee_type]>::new(memory, (#ptr, #len));
             }
         }
         witx::Type::Union(_u) => read_conversion,
         witx::Type::Handle(_h) => {
             let name = names.func_param(&param.name);
-            let handle_type = names.type_ref(tref, anon_lifetime());
-            quote!( let #name = #handle_type::from(#name); )
+            quote!( let #name = #interface_typename::from(#name); )
+        }
+    };
+    match &substituted {
+        Some(substituted) => {
+            let name = names.func_param(&param.name);
+            let ident = names.type_(substituted);
+            quote! {
+                #conversion
+                let #name = <#ident>::from(#name);
+            }
         }
+        None => conversion,
     }
 }
 
@@ -228,7 +776,7 @@ fn marshal_result<F>(
     error_handling: F,
 ) -> (TokenStream, TokenStream)
 where
-    F: Fn(&str) -> TokenStream,
+    F: Fn(&str, &witx::Id) -> TokenStream,
 {
     let tref = &result.tref;
 
@@ -236,9 +784,13 @@ where
         let pointee_type = names.type_ref(tref, anon_lifetime());
         // core type is given func_ptr_binding name.
         let ptr_name = names.func_ptr_binding(&result.name);
-        let ptr_err_handling = error_handling(&format!("{}:result_ptr_mut", result.name.as_str()));
+        let ptr = names.guest_offset(&ptr_name);
+        let ptr_err_handling = error_handling(
+            &format!("{}:result_ptr_mut", result.name.as_str()),
+            &result.name,
+        );
         let pre = quote! {
-            let #ptr_name = wiggle_runtime::GuestPtr::<#pointee_type>::new(memory, #ptr_name as u32);
+            let #ptr_name = wiggle::GuestPtr::<#pointee_type, _>::new(memory, #ptr);
         };
         // trait binding returns func_param name.
         let val_name = names.func_param(&result.name);
@@ -251,13 +803,78 @@ where
     };
 
     match &*tref.type_() {
-        witx::Type::Builtin(b) => match b {
-            witx::BuiltinType::String => unimplemented!("string result types"),
-            _ => write_val_to_ptr,
-        },
-        witx::Type::Pointer { .. } | witx::Type::ConstPointer { .. } | witx::Type::Array { .. } => {
-            unimplemented!("pointer/array result types")
+        witx::Type::Builtin(witx::BuiltinType::String)
+        | witx::Type::Pointer { .. }
+        | witx::Type::ConstPointer { .. }
+        | witx::Type::Array { .. } => {
+            unreachable!("string, pointer and array results are reported as unsupported")
         }
         _ => write_val_to_ptr,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Config;
+
+    const WITX: &str = r#"
+        (typename $errno (enum u16 $success $badf))
+        (typename $fd (handle))
+        (module $example
+          (@interface func (export "fd_close")
+            (param $fd $fd)
+            (param $how $errno)
+            (result $error $errno)))
+    "#;
+
+    fn generate(config: &str) -> String {
+        let config: Config = syn::parse_str(config).expect("parse config");
+        let doc = witx::parse(WITX).expect("parse witx");
+        let module = doc.modules().next().expect("module");
+        let func = module.funcs().next().expect("func");
+        define_func(&Names::new(&config), &module, &func).to_string()
+    }
+
+    #[test]
+    fn tracing() {
+        let traced = generate("{ witx: [], ctx: Ctx, tracing: true }");
+        assert!(traced.contains(
+            ":: tracing :: span ! (:: tracing :: Level :: TRACE , \"wiggle abi\" , function = \"fd_close\")"
+        ));
+        assert!(traced.contains(":: tracing :: trace ! (fd = ? fd , how = ? how)"));
+        assert!(traced.contains(":: tracing :: trace ! (error = ? e)"));
+        assert!(traced.contains(":: tracing :: trace ! (guest_error = % e)"));
+
+        let untraced = generate("{ witx: [], ctx: Ctx, tracing: false }");
+        assert!(!untraced.contains("tracing"));
+    }
+
+    #[test]
+    fn multi_value_rejects_results_in_memory() {
+        let config: Config =
+            syn::parse_str("{ witx: [], ctx: Ctx, multi_value: true }").expect("parse config");
+        let doc = witx::parse(
+            r#"
+            (typename $errno (enum u16 $success $badf))
+            (typename $pair (struct (field $a u32) (field $b u32)))
+            (module $example
+              (@interface func (export "get_pair")
+                (result $error $errno)
+                (result $pair $pair)))
+            "#,
+        )
+        .expect("parse witx");
+        let module = doc.modules().next().expect("module");
+        let func = module.funcs().next().expect("func");
+        let generated = define_func(&Names::new(&config), &module, &func).to_string();
+        assert!(generated.contains("can't be returned as a multi-value result"));
+    }
+
+    #[test]
+    fn no_std() {
+        let generated = generate("{ witx: [], ctx: Ctx, no_std: true }");
+        assert!(generated.contains(":: core :: convert :: TryInto"));
+        assert!(!generated.contains(":: std ::"));
+    }
+}
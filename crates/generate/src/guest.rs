@@ -0,0 +1,559 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use witx::{BuiltinType, Layout, Type, TypePassedBy, TypeRef};
+
+use crate::funcs::depends_on_pointer_width;
+use crate::names::Names;
+
+/// Generates the bindings for a wasm guest calling the interfaces in `doc`,
+/// when `guest` is enabled.
+///
+/// The `types` module mirrors the host's, but with the witx layouts: structs
+/// are `#[repr(C)]`, hold raw pointers, and unions are a tag followed by a
+/// Rust union of the variants' contents. Every module has a `raw` module of
+/// `extern "C"` imports taking core wasm values, and a wrapper per function
+/// taking Rust types, returning the values of the remaining results for a
+/// zero error code and `Err(HostError)` otherwise. Codes the guest's witx
+/// doesn't know are returned as `HostError::Unknown`, rather than panicking.
+pub fn define_guest(doc: &witx::Document, names: &Names) -> TokenStream {
+    let errors = check_guest_supported(doc, names);
+    if !errors.is_empty() {
+        return quote!(#(#errors)*);
+    }
+
+    let core = names.core();
+    let types = doc.typenames().map(|nt| define_guest_type(names, &nt));
+    let modules = names.modules(doc).map(|m| define_guest_module(names, &m));
+    quote! {
+        pub mod types {
+            #(#types)*
+        }
+
+        /// An error code returned by the host.
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, #core::hash::Hash)]
+        pub enum HostError<E> {
+            /// A variant of the function's error enum.
+            Known(E),
+            /// A code the error enum has no variant for, such as one added
+            /// by a newer host.
+            Unknown(u64),
+        }
+
+        #(#modules)*
+    }
+}
+
+/// Reports the parts of `doc` guest bindings can't represent, beyond those
+/// host bindings can't either.
+fn check_guest_supported(doc: &witx::Document, names: &Names) -> Vec<TokenStream> {
+    let mut errors = Vec::new();
+    if names.memory64() {
+        errors.push("guest bindings are only generated for wasm32".to_string());
+    }
+    if names.multi_value() {
+        errors.push("guest bindings can't import functions returning multiple values".to_string());
+    }
+    for nt in doc.typenames() {
+        let ty = nt.type_();
+        let members = match &*ty {
+            Type::Struct(s) => s.members.iter().map(|m| (&m.name, Some(&m.tref))).collect(),
+            Type::Union(u) => u
+                .variants
+                .iter()
+                .map(|v| (&v.name, v.tref.as_ref()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for (member, tref) in members {
+            let kind = match tref.map(|t| t.type_()).as_deref() {
+                Some(Type::Builtin(BuiltinType::String)) => "a string",
+                Some(Type::Array(_)) => "an array",
+                _ => continue,
+            };
+            errors.push(format!(
+                "member `{}` of `{}` is {}, which guest bindings can't lay out",
+                member.as_str(),
+                nt.name.as_str(),
+                kind
+            ));
+        }
+    }
+    for module in names.modules(doc) {
+        for func in names.funcs(&module) {
+            if let Some(err) = func.results.first() {
+                if !matches!(&*err.tref.type_(), Type::Enum(_)) {
+                    errors.push(format!(
+                        "the error result of function `{}` isn't an enum, which guest bindings \
+                         need to tell errors apart from success",
+                        func.name.as_str()
+                    ));
+                }
+            }
+        }
+    }
+
+    let span = names.witx_span();
+    errors
+        .into_iter()
+        .map(|msg| quote_spanned!(span=> compile_error!(#msg);))
+        .collect()
+}
+
+/// The guest type for `tref`, where strings and arrays are only named, as
+/// the pointees of references.
+fn guest_type_ref(names: &Names, tref: &TypeRef) -> TokenStream {
+    match tref {
+        TypeRef::Name(nt) => match &*nt.type_() {
+            Type::Builtin(BuiltinType::String) | Type::Array(_) => guest_type(names, &nt.tref),
+            _ => names.type_(&nt.name),
+        },
+        TypeRef::Value(_) => guest_type(names, tref),
+    }
+}
+
+fn guest_type(names: &Names, tref: &TypeRef) -> TokenStream {
+    match &*tref.type_() {
+        Type::Builtin(b) => guest_builtin(*b),
+        Type::Pointer(p) => {
+            let pointee = guest_type_ref(names, p);
+            quote!(*mut #pointee)
+        }
+        Type::ConstPointer(p) => {
+            let pointee = guest_type_ref(names, p);
+            quote!(*const #pointee)
+        }
+        Type::Array(elem) => {
+            let elem = guest_type_ref(names, elem);
+            quote!([#elem])
+        }
+        _ => match tref {
+            TypeRef::Name(nt) => names.type_(&nt.name),
+            TypeRef::Value(_) => unreachable!("anonymous types are reported as unsupported"),
+        },
+    }
+}
+
+fn guest_builtin(b: BuiltinType) -> TokenStream {
+    match b {
+        BuiltinType::String => quote!(str),
+        BuiltinType::U8 | BuiltinType::Char8 => quote!(u8),
+        BuiltinType::U16 => quote!(u16),
+        BuiltinType::U32 => quote!(u32),
+        BuiltinType::U64 => quote!(u64),
+        BuiltinType::S8 => quote!(i8),
+        BuiltinType::S16 => quote!(i16),
+        BuiltinType::S32 => quote!(i32),
+        BuiltinType::S64 => quote!(i64),
+        BuiltinType::F32 => quote!(f32),
+        BuiltinType::F64 => quote!(f64),
+        BuiltinType::USize => quote!(usize),
+    }
+}
+
+fn int_repr(repr: witx::IntRepr) -> TokenStream {
+    match repr {
+        witx::IntRepr::U8 => quote!(u8),
+        witx::IntRepr::U16 => quote!(u16),
+        witx::IntRepr::U32 => quote!(u32),
+        witx::IntRepr::U64 => quote!(u64),
+    }
+}
+
+/// Whether passing `tref` to the host passes raw pointers it dereferences,
+/// so that the wrappers taking it must be `unsafe`.
+fn contains_pointer(tref: &TypeRef) -> bool {
+    match &*tref.type_() {
+        Type::Pointer(_) | Type::ConstPointer(_) => true,
+        Type::Array(elem) => contains_pointer(elem),
+        Type::Struct(s) => s.members.iter().any(|m| contains_pointer(&m.tref)),
+        Type::Union(u) => u
+            .variants
+            .iter()
+            .any(|v| v.tref.as_ref().is_some_and(contains_pointer)),
+        _ => false,
+    }
+}
+
+/// Whether `tref` contains a union, so can't derive `Debug`.
+fn contains_union(tref: &TypeRef) -> bool {
+    match &*tref.type_() {
+        Type::Union(u) => u.variants.iter().any(|v| v.tref.is_some()),
+        Type::Struct(s) => s.members.iter().any(|m| contains_union(&m.tref)),
+        _ => false,
+    }
+}
+
+fn define_guest_type(names: &Names, nt: &witx::NamedType) -> TokenStream {
+    let ident = names.type_(&nt.name);
+    let docs = names.docs(&nt.docs);
+    let core = names.core();
+    let ty = match &nt.tref {
+        TypeRef::Name(_) => {
+            return match &*nt.type_() {
+                Type::Builtin(BuiltinType::String) | Type::Array(_) => {
+                    let to = guest_type(names, &nt.tref);
+                    quote!(#docs pub type #ident<'a> = &'a #to;)
+                }
+                _ => {
+                    let to = guest_type_ref(names, &nt.tref);
+                    quote!(#docs pub type #ident = #to;)
+                }
+            };
+        }
+        TypeRef::Value(ty) => ty,
+    };
+    match &**ty {
+        Type::Enum(e) => {
+            let repr = int_repr(e.repr);
+            let variants = e
+                .variants
+                .iter()
+                .map(|v| names.enum_variant(&nt.name, &v.name))
+                .collect::<Vec<_>>();
+            let variant_docs = e.variants.iter().map(|v| names.docs(&v.docs));
+            let values = (0..e.variants.len())
+                .map(Literal::usize_unsuffixed)
+                .collect::<Vec<_>>();
+            quote! {
+                #docs
+                #[repr(#repr)]
+                #[derive(Copy, Clone, Debug, PartialEq, Eq, #core::hash::Hash)]
+                pub enum #ident {
+                    #(#variant_docs #variants = #values),*
+                }
+
+                impl #ident {
+                    /// The variant with the value `raw`, if there is one.
+                    pub fn from_raw(raw: #repr) -> Option<#ident> {
+                        match raw {
+                            #(#values => Some(#ident::#variants),)*
+                            _ => None,
+                        }
+                    }
+                }
+            }
+        }
+        Type::Flags(f) => {
+            let repr = int_repr(f.repr);
+            let flags = f.flags.iter().enumerate().map(|(i, flag)| {
+                let name = names.flag_member(&flag.name);
+                let flag_docs = names.docs(&flag.docs);
+                let value = Literal::u128_unsuffixed(1 << i);
+                quote!(#flag_docs pub const #name: #ident = #ident(#value);)
+            });
+            let all = Literal::u128_unsuffixed((1u128 << f.flags.len()) - 1);
+            quote! {
+                #docs
+                #[repr(transparent)]
+                #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, #core::hash::Hash)]
+                pub struct #ident(pub #repr);
+
+                impl #ident {
+                    #(#flags)*
+                    pub const EMPTY_FLAGS: #ident = #ident(0);
+                    pub const ALL_FLAGS: #ident = #ident(#all);
+
+                    pub fn contains(&self, other: #ident) -> bool {
+                        self.0 & other.0 == other.0
+                    }
+                }
+
+                impl #core::ops::BitOr for #ident {
+                    type Output = #ident;
+                    fn bitor(self, rhs: #ident) -> #ident {
+                        #ident(self.0 | rhs.0)
+                    }
+                }
+
+                impl #core::ops::BitAnd for #ident {
+                    type Output = #ident;
+                    fn bitand(self, rhs: #ident) -> #ident {
+                        #ident(self.0 & rhs.0)
+                    }
+                }
+            }
+        }
+        Type::Int(i) => {
+            let repr = int_repr(i.repr);
+            let consts = i.consts.iter().map(|c| {
+                let name = names.int_member(&c.name);
+                let const_docs = names.docs(&c.docs);
+                let value = Literal::u64_unsuffixed(c.value);
+                quote!(#const_docs pub const #name: #ident = #ident(#value);)
+            });
+            quote! {
+                #docs
+                #[repr(transparent)]
+                #[derive(Copy, Clone, Debug, PartialEq, Eq, #core::hash::Hash)]
+                pub struct #ident(pub #repr);
+
+                impl #ident {
+                    #(#consts)*
+                }
+            }
+        }
+        Type::Handle(_) => quote! {
+            #docs
+            #[repr(transparent)]
+            #[derive(Copy, Clone, Debug, PartialEq, Eq, #core::hash::Hash)]
+            pub struct #ident(pub u32);
+        },
+        Type::Struct(s) => {
+            let members = s.members.iter().map(|m| {
+                let member = names.struct_member(&m.name);
+                let member_docs = names.docs(&m.docs);
+                let type_ = guest_type_ref(names, &m.tref);
+                quote!(#member_docs pub #member: #type_)
+            });
+            let debug = if contains_union(&nt.tref) {
+                quote!()
+            } else {
+                quote!(, Debug)
+            };
+            let offsets = s.member_layout().into_iter().map(|ml| {
+                let member = names.struct_member(&ml.member.name);
+                let offset = ml.offset;
+                quote!(assert!(#core::mem::offset_of!(#ident, #member) == #offset);)
+            });
+            let assertions = layout_assertions(names, nt, quote!(#(#offsets)*));
+            quote! {
+                #docs
+                #[repr(C)]
+                #[derive(Copy, Clone #debug)]
+                pub struct #ident {
+                    #(#members),*
+                }
+
+                #assertions
+            }
+        }
+        Type::Union(u) => {
+            let tag = names.type_(&u.tag.name);
+            let fields = u
+                .variants
+                .iter()
+                .filter_map(|v| {
+                    let tref = v.tref.as_ref()?;
+                    let field = names.struct_member(&v.name);
+                    let field_docs = names.docs(&v.docs);
+                    let type_ = guest_type_ref(names, tref);
+                    Some(quote!(#field_docs pub #field: #type_))
+                })
+                .collect::<Vec<_>>();
+            let contents_offset = u.union_layout().contents_offset;
+            if fields.is_empty() {
+                let assertions = layout_assertions(names, nt, quote!());
+                return quote! {
+                    #docs
+                    #[repr(C)]
+                    #[derive(Copy, Clone, Debug)]
+                    pub struct #ident {
+                        pub tag: #tag,
+                    }
+
+                    #assertions
+                };
+            }
+            let contents = format_ident!("{}Contents", ident.to_string());
+            let assertions = layout_assertions(
+                names,
+                nt,
+                quote!(assert!(#core::mem::offset_of!(#ident, contents) == #contents_offset);),
+            );
+            quote! {
+                #docs
+                #[repr(C)]
+                #[derive(Copy, Clone)]
+                pub struct #ident {
+                    pub tag: #tag,
+                    pub contents: #contents,
+                }
+
+                /// The contents of every variant, of which `tag` says which is set.
+                #[repr(C)]
+                #[derive(Copy, Clone)]
+                pub union #contents {
+                    #(#fields),*
+                }
+
+                #assertions
+            }
+        }
+        Type::Builtin(BuiltinType::String) | Type::Array(_) => {
+            let to = guest_type(names, &nt.tref);
+            quote!(#docs pub type #ident<'a> = &'a #to;)
+        }
+        Type::Builtin(_) | Type::Pointer(_) | Type::ConstPointer(_) => {
+            let to = guest_type(names, &nt.tref);
+            quote!(#docs pub type #ident = #to;)
+        }
+    }
+}
+
+/// Asserts at compile time that the type `nt` has its witx layout, along with
+/// the `extra` assertions. Types with pointers or sizes only have it on
+/// 32-bit targets.
+fn layout_assertions(names: &Names, nt: &witx::NamedType, extra: TokenStream) -> TokenStream {
+    let ident = names.type_(&nt.name);
+    let core = names.core();
+    let layout = nt.mem_size_align();
+    let (size, align) = (layout.size, layout.align);
+    let cfg = if depends_on_pointer_width(&nt.tref) {
+        quote!(#[cfg(target_pointer_width = "32")])
+    } else {
+        quote!()
+    };
+    quote! {
+        #cfg
+        const _: () = {
+            assert!(#core::mem::size_of::<#ident>() == #size);
+            assert!(#core::mem::align_of::<#ident>() == #align);
+            #extra
+        };
+    }
+}
+
+fn define_guest_module(names: &Names, module: &witx::Module) -> TokenStream {
+    let modname = names.module(&module.name);
+    let module_str = module.name.as_str();
+    let docs = names.docs(&module.docs);
+    let funcs = names.funcs(module).collect::<Vec<_>>();
+    let imports = funcs.iter().map(|f| define_import(names, f));
+    let wrappers = funcs.iter().map(|f| define_wrapper(names, f));
+    quote! {
+        #docs
+        pub mod #modname {
+            #[allow(unused_imports)]
+            use super::types::*;
+
+            /// The functions imported from the host, taking and returning
+            /// core wasm values.
+            pub mod raw {
+                #[link(wasm_import_module = #module_str)]
+                extern "C" {
+                    #(#imports)*
+                }
+            }
+
+            #(#wrappers)*
+        }
+    }
+}
+
+fn define_import(names: &Names, func: &witx::InterfaceFunc) -> TokenStream {
+    let ident = names.func(&func.name);
+    let funcname = func.name.as_str();
+    let coretype = func.core_type();
+    let params = coretype.args.iter().map(|arg| {
+        let name = names.func_core_arg(arg);
+        let atom = names.atom_type(names.core_arg_atom(arg));
+        quote!(#name: #atom)
+    });
+    let ret = match &coretype.ret {
+        Some(ret) => {
+            let atom = names.atom_type(names.core_arg_atom(ret));
+            quote!(-> #atom)
+        }
+        None => quote!(),
+    };
+    quote! {
+        #[link_name = #funcname]
+        pub fn #ident(#(#params),*) #ret;
+    }
+}
+
+fn define_wrapper(names: &Names, func: &witx::InterfaceFunc) -> TokenStream {
+    let ident = names.func(&func.name);
+    let docs = names.docs(&func.docs);
+    let ptr_atom = names.atom_type(names.pointer_atom());
+
+    let coretype = func.core_type();
+    let mut params = Vec::new();
+    let mut args = Vec::new();
+    for param in &func.params {
+        let name = names.func_param(&param.name);
+        let tref = &param.tref;
+        match tref.type_().passed_by() {
+            TypePassedBy::Value(_) => {
+                let arg = coretype
+                    .args
+                    .iter()
+                    .find(|arg| arg.param.name == param.name)
+                    .expect("every param has a core arg");
+                let atom = names.atom_type(names.core_arg_atom(arg));
+                let type_ = guest_type_ref(names, tref);
+                params.push(quote!(#name: #type_));
+                args.push(match &*tref.type_() {
+                    Type::Flags(_) | Type::Int(_) | Type::Handle(_) => quote!(#name.0 as #atom),
+                    _ => quote!(#name as #atom),
+                });
+            }
+            TypePassedBy::Pointer => {
+                let type_ = guest_type_ref(names, tref);
+                params.push(quote!(#name: &#type_));
+                args.push(quote!(#name as *const #type_ as #ptr_atom));
+            }
+            TypePassedBy::PointerLengthPair => {
+                let type_ = guest_type(names, tref);
+                params.push(quote!(#name: &#type_));
+                args.push(quote!(#name.as_ptr() as #ptr_atom));
+                args.push(quote!(#name.len() as #ptr_atom));
+            }
+        }
+    }
+
+    let mut outs = Vec::new();
+    let mut out_types = Vec::new();
+    for result in func.results.iter().skip(1) {
+        let name = names.func_param(&result.name);
+        out_types.push(guest_type_ref(names, &result.tref));
+        args.push(quote!(#name.as_mut_ptr() as #ptr_atom));
+        outs.push(name);
+    }
+    let out_decls = outs
+        .iter()
+        .zip(&out_types)
+        .map(|(name, type_)| quote!(let mut #name = ::core::mem::MaybeUninit::<#type_>::uninit();));
+
+    let unsafety = if func.params.iter().any(|p| contains_pointer(&p.tref)) {
+        quote!(unsafe)
+    } else {
+        quote!()
+    };
+
+    let (ret, body) = match func.results.first() {
+        None => (quote!(()), quote!(unsafe { raw::#ident(#(#args),*) })),
+        Some(err) => {
+            let err_type = guest_type_ref(names, &err.tref);
+            let err_repr = match &*err.tref.type_() {
+                Type::Enum(e) => int_repr(e.repr),
+                _ => unreachable!("non-enum errors are reported as unsupported"),
+            };
+            (
+                quote!(Result<(#(#out_types),*), super::HostError<#err_type>>),
+                quote! {
+                    #(#out_decls)*
+                    let err = unsafe { raw::#ident(#(#args),*) };
+                    match err {
+                        0 => Ok(unsafe { (#(#outs.assume_init()),*) }),
+                        _ => {
+                            let raw = err as #err_repr;
+                            Err(match #err_type::from_raw(raw) {
+                                Some(e) => super::HostError::Known(e),
+                                None => super::HostError::Unknown(raw as u64),
+                            })
+                        }
+                    }
+                },
+            )
+        }
+    };
+
+    quote! {
+        #docs
+        #[allow(clippy::unused_unit)]
+        pub #unsafety fn #ident(#(#params),*) -> #ret {
+            #body
+        }
+    }
+}
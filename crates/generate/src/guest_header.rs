@@ -0,0 +1,415 @@
+use std::fmt::Write;
+
+use heck::{ShoutySnakeCase, SnakeCase};
+use witx::{BuiltinType, Document, IntRepr, Layout, Type, TypePassedBy, TypeRef};
+
+use crate::unsupported;
+
+/// Renders a C header for guest code calling the interfaces in `doc`.
+///
+/// Every type gets a definition matching its witx layout, named
+/// `{prefix}_{name}_t`, with constants named `{PREFIX}_{TYPE}_{NAME}`. Every
+/// function gets a prototype named `{prefix}_{name}` carrying the wasm import
+/// attributes for its module, with the error result (if any) as the return
+/// value and the remaining results as out-pointers.
+///
+/// The layouts are only asserted to match for wasm32, where pointers and
+/// `size_t` are 4 bytes.
+pub fn guest_c_header(doc: &Document, prefix: &str) -> String {
+    let h = Header {
+        prefix: prefix.to_snake_case(),
+    };
+    let guard = format!("{}_H", h.prefix.to_shouty_snake_case());
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        "/* This file is generated by wiggle from witx. Do not edit. */"
+    )
+    .unwrap();
+
+    // Parts of the document which can't be declared fail the build of
+    // anything including the header instead.
+    let funcs = doc
+        .modules()
+        .flat_map(|m| m.funcs().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let unsupported = unsupported::unsupported_shapes(doc, &funcs, false);
+    if !unsupported.is_empty() {
+        for msg in unsupported {
+            writeln!(out, "#error {:?}", msg).unwrap();
+        }
+        return out;
+    }
+    writeln!(out, "#ifndef {}", guard).unwrap();
+    writeln!(out, "#define {}", guard).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#include <stddef.h>").unwrap();
+    writeln!(out, "#include <stdint.h>").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#ifdef __cplusplus").unwrap();
+    writeln!(
+        out,
+        "#define {}_STATIC_ASSERT static_assert",
+        h.shouty_prefix()
+    )
+    .unwrap();
+    writeln!(out, "#define {}_ALIGNOF alignof", h.shouty_prefix()).unwrap();
+    writeln!(out, "#define {}_NORETURN [[noreturn]]", h.shouty_prefix()).unwrap();
+    writeln!(out, "extern \"C\" {{").unwrap();
+    writeln!(out, "#else").unwrap();
+    writeln!(
+        out,
+        "#define {}_STATIC_ASSERT _Static_assert",
+        h.shouty_prefix()
+    )
+    .unwrap();
+    writeln!(out, "#define {}_ALIGNOF _Alignof", h.shouty_prefix()).unwrap();
+    writeln!(out, "#define {}_NORETURN _Noreturn", h.shouty_prefix()).unwrap();
+    writeln!(out, "#endif").unwrap();
+
+    for nt in doc.typenames() {
+        writeln!(out).unwrap();
+        h.docs(&mut out, &nt.docs, "");
+        h.typename(&mut out, &nt);
+    }
+
+    for module in doc.modules() {
+        for func in module.funcs() {
+            writeln!(out).unwrap();
+            h.docs(&mut out, &func.docs, "");
+            h.func(&mut out, module.name.as_str(), &func);
+        }
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "#ifdef __cplusplus").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out, "#endif").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#endif").unwrap();
+    out
+}
+
+struct Header {
+    prefix: String,
+}
+
+impl Header {
+    fn shouty_prefix(&self) -> String {
+        self.prefix.to_shouty_snake_case()
+    }
+
+    fn type_name(&self, name: &witx::Id) -> String {
+        format!("{}_{}_t", self.prefix, name.as_str().to_snake_case())
+    }
+
+    fn constant(&self, typename: &witx::Id, name: &witx::Id) -> String {
+        format!(
+            "{}_{}_{}",
+            self.shouty_prefix(),
+            typename.as_str().to_shouty_snake_case(),
+            name.as_str().to_shouty_snake_case()
+        )
+    }
+
+    fn docs(&self, out: &mut String, docs: &str, indent: &str) {
+        let docs = docs.trim();
+        if docs.is_empty() {
+            return;
+        }
+        writeln!(out, "{}/**", indent).unwrap();
+        for line in docs.lines() {
+            writeln!(out, "{} * {}", indent, line.trim_end()).unwrap();
+        }
+        writeln!(out, "{} */", indent).unwrap();
+    }
+
+    fn typename(&self, out: &mut String, nt: &witx::NamedType) {
+        let name = self.type_name(&nt.name);
+        match &*nt.type_() {
+            Type::Enum(e) => {
+                writeln!(out, "typedef {} {};", int_repr(e.repr), name).unwrap();
+                for (i, variant) in e.variants.iter().enumerate() {
+                    writeln!(out).unwrap();
+                    self.docs(out, &variant.docs, "");
+                    let constant = self.constant(&nt.name, &variant.name);
+                    writeln!(out, "#define {} (({}){})", constant, name, i).unwrap();
+                }
+            }
+            Type::Int(i) => {
+                writeln!(out, "typedef {} {};", int_repr(i.repr), name).unwrap();
+                for c in i.consts.iter() {
+                    writeln!(out).unwrap();
+                    self.docs(out, &c.docs, "");
+                    let constant = self.constant(&nt.name, &c.name);
+                    writeln!(out, "#define {} (({}){}ull)", constant, name, c.value).unwrap();
+                }
+            }
+            Type::Flags(f) => {
+                writeln!(out, "typedef {} {};", int_repr(f.repr), name).unwrap();
+                for (i, flag) in f.flags.iter().enumerate() {
+                    writeln!(out).unwrap();
+                    self.docs(out, &flag.docs, "");
+                    let constant = self.constant(&nt.name, &flag.name);
+                    writeln!(out, "#define {} (({})(1ull << {}))", constant, name, i).unwrap();
+                }
+            }
+            Type::Handle(_) => {
+                writeln!(out, "typedef uint32_t {};", name).unwrap();
+            }
+            Type::Builtin(_) | Type::Pointer(_) | Type::ConstPointer(_) => {
+                let tref = &nt.tref;
+                writeln!(out, "typedef {};", self.declarator(tref, &name)).unwrap();
+            }
+            Type::Struct(s) => {
+                writeln!(out, "typedef struct {} {{", name).unwrap();
+                for m in s.members.iter() {
+                    self.docs(out, &m.docs, "    ");
+                    let member = m.name.as_str().to_snake_case();
+                    writeln!(out, "    {};", self.declarator(&m.tref, &member)).unwrap();
+                }
+                writeln!(out, "}} {};", name).unwrap();
+                self.assert_layout(out, &name, &nt.tref);
+                for m in s.member_layout() {
+                    let member = m.member.name.as_str().to_snake_case();
+                    self.assert_offset(out, &name, &member, m.offset);
+                }
+            }
+            Type::Union(u) => {
+                let tag = self.type_name(&u.tag.name);
+                let payloads = u
+                    .variants
+                    .iter()
+                    .filter_map(|v| v.tref.as_ref().map(|tref| (v, tref)))
+                    .collect::<Vec<_>>();
+                let contents = format!("{}_{}_u_t", self.prefix, nt.name.as_str().to_snake_case());
+                if !payloads.is_empty() {
+                    writeln!(out, "typedef union {} {{", contents).unwrap();
+                    for (v, tref) in payloads.iter() {
+                        self.docs(out, &v.docs, "    ");
+                        let member = v.name.as_str().to_snake_case();
+                        writeln!(out, "    {};", self.declarator(tref, &member)).unwrap();
+                    }
+                    writeln!(out, "}} {};", contents).unwrap();
+                }
+                writeln!(out, "typedef struct {} {{", name).unwrap();
+                writeln!(out, "    {} tag;", tag).unwrap();
+                if !payloads.is_empty() {
+                    writeln!(out, "    {} u;", contents).unwrap();
+                }
+                writeln!(out, "}} {};", name).unwrap();
+                self.assert_layout(out, &name, &nt.tref);
+                if !payloads.is_empty() {
+                    let offset = u.union_layout().contents_offset;
+                    self.assert_offset(out, &name, "u", offset);
+                }
+            }
+            // Arrays are only ever passed as a pointer and length pair, so
+            // there's nothing to declare for them.
+            Type::Array(_) => {
+                writeln!(
+                    out,
+                    "/* {} is an array; see the functions taking it. */",
+                    name
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    fn assert_layout(&self, out: &mut String, name: &str, tref: &TypeRef) {
+        let layout = tref.mem_size_align();
+        let prefix = self.shouty_prefix();
+        writeln!(
+            out,
+            "{}_STATIC_ASSERT(sizeof({}) == {}, \"witx calculated size\");",
+            prefix, name, layout.size
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "{}_STATIC_ASSERT({}_ALIGNOF({}) == {}, \"witx calculated align\");",
+            prefix, prefix, name, layout.align
+        )
+        .unwrap();
+    }
+
+    fn assert_offset(&self, out: &mut String, name: &str, member: &str, offset: usize) {
+        writeln!(
+            out,
+            "{}_STATIC_ASSERT(offsetof({}, {}) == {}, \"witx calculated offset\");",
+            self.shouty_prefix(),
+            name,
+            member,
+            offset
+        )
+        .unwrap();
+    }
+
+    /// Declares `name` as having the type `tref`.
+    fn declarator(&self, tref: &TypeRef, name: &str) -> String {
+        match tref {
+            TypeRef::Name(nt) => format!("{} {}", self.type_name(&nt.name), name),
+            TypeRef::Value(ty) => match &**ty {
+                Type::Builtin(BuiltinType::String) => format!("const char *{}", name),
+                Type::Builtin(b) => format!("{} {}", builtin(*b), name),
+                Type::Pointer(pointee) => self.declarator(pointee, &format!("*{}", name)),
+                Type::ConstPointer(pointee) => {
+                    format!("const {}", self.declarator(pointee, &format!("*{}", name)))
+                }
+                _ => unreachable!("anonymous types are reported as unsupported"),
+            },
+        }
+    }
+
+    fn func(&self, out: &mut String, module: &str, func: &witx::InterfaceFunc) {
+        let mut params = Vec::new();
+        for param in func.params.iter() {
+            let name = param.name.as_str().to_snake_case();
+            match param.tref.type_().passed_by() {
+                TypePassedBy::Value(_) => params.push(self.declarator(&param.tref, &name)),
+                TypePassedBy::Pointer => params.push(format!(
+                    "const {}",
+                    self.declarator(&param.tref, &format!("*{}", name))
+                )),
+                TypePassedBy::PointerLengthPair => {
+                    let ptr = match &*param.tref.type_() {
+                        Type::Array(elem) => {
+                            format!("const {}", self.declarator(elem, &format!("*{}", name)))
+                        }
+                        _ => format!("const char *{}", name),
+                    };
+                    params.push(ptr);
+                    params.push(format!("size_t {}_len", name));
+                }
+            }
+        }
+        for result in func.results.iter().skip(1) {
+            let name = result.name.as_str().to_snake_case();
+            params.push(self.declarator(&result.tref, &format!("*{}", name)));
+        }
+        let params = if params.is_empty() {
+            "void".to_owned()
+        } else {
+            params.join(",\n    ")
+        };
+
+        let ret = match func.results.first() {
+            Some(err) => self.declarator(&err.tref, "").trim_end().to_owned(),
+            None if func.noreturn => format!("{}_NORETURN void", self.shouty_prefix()),
+            None => "void".to_owned(),
+        };
+        let name = format!("{}_{}", self.prefix, func.name.as_str().to_snake_case());
+        writeln!(out, "{} {}(", ret, name).unwrap();
+        writeln!(out, "    {}", params).unwrap();
+        writeln!(
+            out,
+            ") __attribute__((__import_module__(\"{}\"), __import_name__(\"{}\")));",
+            module,
+            func.name.as_str()
+        )
+        .unwrap();
+    }
+}
+
+fn int_repr(repr: IntRepr) -> &'static str {
+    match repr {
+        IntRepr::U8 => "uint8_t",
+        IntRepr::U16 => "uint16_t",
+        IntRepr::U32 => "uint32_t",
+        IntRepr::U64 => "uint64_t",
+    }
+}
+
+fn builtin(b: BuiltinType) -> &'static str {
+    match b {
+        BuiltinType::String => "const char *",
+        BuiltinType::Char8 => "char",
+        BuiltinType::USize => "size_t",
+        BuiltinType::U8 => "uint8_t",
+        BuiltinType::U16 => "uint16_t",
+        BuiltinType::U32 => "uint32_t",
+        BuiltinType::U64 => "uint64_t",
+        BuiltinType::S8 => "int8_t",
+        BuiltinType::S16 => "int16_t",
+        BuiltinType::S32 => "int32_t",
+        BuiltinType::S64 => "int64_t",
+        BuiltinType::F32 => "float",
+        BuiltinType::F64 => "double",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const WITX: &str = r#"
+        (typename $errno (enum u16 $success $badf))
+        (typename $size u32)
+        (typename $fd (handle))
+        (typename $iovec
+          (struct
+            (field $buf (@witx pointer u8))
+            (field $buf_len $size)))
+        (typename $iovec_array (array $iovec))
+        (module $example
+          ;;; Read from a file descriptor.
+          (@interface func (export "fd_read")
+            (param $fd $fd)
+            (param $iovs $iovec_array)
+            (result $error $errno)
+            (result $nread $size)))
+    "#;
+
+    #[test]
+    fn renders_types_and_prototypes() {
+        let doc = witx::parse(WITX).expect("parse witx");
+        let header = guest_c_header(&doc, "example");
+        assert!(header.contains("#ifndef EXAMPLE_H"));
+        assert!(header.contains("typedef uint16_t example_errno_t;"));
+        assert!(header.contains("#define EXAMPLE_ERRNO_BADF ((example_errno_t)1)"));
+        assert!(header.contains("typedef uint32_t example_size_t;"));
+        assert!(
+            header.contains("    uint8_t *buf;\n    example_size_t buf_len;\n} example_iovec_t;")
+        );
+        assert!(header.contains("sizeof(example_iovec_t) == 8"));
+        assert!(header.contains("offsetof(example_iovec_t, buf_len) == 4"));
+        assert!(header.contains(
+            "example_errno_t example_fd_read(\n    example_fd_t fd,\n    \
+             const example_iovec_t *iovs,\n    size_t iovs_len,\n    example_size_t *nread\n) \
+             __attribute__((__import_module__(\"example\"), __import_name__(\"fd_read\")));"
+        ));
+        assert!(header.contains(" * Read from a file descriptor."));
+    }
+
+    #[test]
+    fn noreturn_for_c_and_cpp() {
+        let doc = witx::parse(
+            r#"
+            (module $example
+              (@interface func (export "proc_exit")
+                (param $rval u32)
+                (@witx noreturn)))
+            "#,
+        )
+        .expect("parse witx");
+        let header = guest_c_header(&doc, "example");
+        assert!(header.contains("#define EXAMPLE_NORETURN [[noreturn]]\nextern \"C\" {"));
+        assert!(header.contains("#define EXAMPLE_NORETURN _Noreturn\n#endif"));
+        assert!(header.contains("EXAMPLE_NORETURN void example_proc_exit("));
+    }
+
+    #[test]
+    fn reports_unsupported_shapes() {
+        let doc = witx::parse(
+            r#"
+            (typename $s (struct (field $a (array u8))))
+            "#,
+        )
+        .expect("parse witx");
+        let header = guest_c_header(&doc, "example");
+        assert!(header.contains("#error \"member `a` of struct `s`"));
+        assert!(!header.contains("typedef"));
+    }
+}
@@ -0,0 +1,45 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::names::Names;
+use witx::Module;
+
+/// Defines the `INTERFACE` description of a module, and the
+/// `wiggle_interface_info` function answering guest queries about it.
+pub fn define_introspection(names: &Names, m: &Module) -> TokenStream {
+    let name = m.name.as_str();
+    let version = names.interface_version();
+    let functions = m.funcs().map(|f| f.name.as_str().to_owned());
+    // Every function generated is implemented; those filtered out aren't.
+    let implemented = m.funcs().map(|f| names.func_selected(&f.name));
+    let memory_type = names.memory_type();
+    let pointer = names.atom_type(names.pointer_atom());
+    let version_offset = names.guest_offset(&format_ident!("version_ptr"));
+    let bits_offset = names.guest_offset(&format_ident!("bits_ptr"));
+    let bits_len = names.guest_offset(&format_ident!("bits_len"));
+
+    quote! {
+        pub const INTERFACE: wiggle::InterfaceInfo = wiggle::InterfaceInfo {
+            name: #name,
+            version: #version,
+            functions: &[#(#functions),*],
+            implemented: &[#(#implemented),*],
+        };
+
+        /// Writes the interface version to `version_ptr`, and the bitset of
+        /// implemented functions to the `bits_len` bytes at `bits_ptr`.
+        /// Returns the size of the whole bitset, or -1 if the pointers are
+        /// invalid.
+        pub fn wiggle_interface_info(
+            memory: &#memory_type,
+            version_ptr: #pointer,
+            bits_ptr: #pointer,
+            bits_len: #pointer,
+        ) -> i32 {
+            match INTERFACE.answer(memory, #version_offset, #bits_offset, #bits_len) {
+                Ok(len) => len as i32,
+                Err(_) => -1,
+            }
+        }
+    }
+}
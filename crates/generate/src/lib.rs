@@ -1,44 +1,514 @@
+mod adapter;
+mod cache;
 pub mod config;
+mod conversions;
+mod debug;
+mod dispatch;
 mod funcs;
+mod guest;
+mod guest_header;
+mod introspect;
 mod lifetimes;
+mod locations;
+mod lucet;
+mod metadata;
+mod mock;
 mod module_trait;
 mod names;
 mod types;
+mod unsupported;
+mod wasmtime;
 
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
+use std::fs;
+use std::path::Path;
 
+pub use adapter::define_adapter;
+pub use cache::{document_paths, load_document, parse_literal};
 pub use config::Config;
+use config::WitxConf;
+pub use conversions::define_conversions;
+pub use debug::{pretty, write_debug_file};
+pub use dispatch::{define_abi_functions, define_dispatch};
 pub use funcs::define_func;
+pub use guest_header::guest_c_header;
+pub use introspect::define_introspection;
+pub use lucet::define_lucet_hostcalls;
+pub use metadata::define_metadata;
+pub use mock::define_module_mock;
 pub use module_trait::define_module_trait;
 pub use names::Names;
-pub use types::define_datatype;
+pub use types::{define_datatype, define_substitute};
+pub use wasmtime::define_add_to_linker;
 
 pub fn generate(doc: &witx::Document, config: &Config) -> TokenStream {
     let names = Names::new(config); // TODO parse the names from the invocation of the macro, or from a file?
 
-    let types = doc.typenames().map(|t| define_datatype(&names, &t));
+    // Generating code for unsupported parts of the document would panic, so
+    // only report them.
+    let unsupported = unsupported::check_supported(doc, &names);
+    if !unsupported.is_empty() {
+        return quote!(#(#unsupported)*);
+    }
+    if names.guest() {
+        return guest::define_guest(doc, &names);
+    }
 
-    let modules = doc.modules().map(|module| {
+    let types = doc.typenames().map(|t| match names.substitute(&t.name) {
+        Some(with) => define_substitute(&names, &t, with),
+        None => define_datatype(&names, &t),
+    });
+    let nullable_errors = check_nullable(doc, &names);
+
+    let filter_errors = check_filters(doc, &names);
+    let attribute_errors = check_type_attributes(doc, &names);
+    let substitute_errors = check_substitutions(doc, &names);
+    let not_supported_errors = check_not_supported(doc, &names);
+    let split_errors = check_split_traits(doc, &names);
+    let user_error_errors = check_user_errors(doc, &names);
+
+    let modules = names.modules(doc).map(|module| {
         let modname = names.module(&module.name);
-        let fs = module.funcs().map(|f| define_func(&names, &f));
+        let fs = names
+            .funcs(&module)
+            .map(|f| define_func(&names, &module, &f));
         let modtrait = define_module_trait(&names, &module);
+        let mock = define_module_mock(&names, &module);
+        let introspection = define_introspection(&names, &module);
+        let dispatch = define_dispatch(&names, &module);
+        let abi_functions = define_abi_functions(&names, &module);
+        // Embedders opt into engine glue through this crate's `wasmtime` and
+        // `lucet` features, since the generated code needs them to depend on
+        // the engine.
+        let add_to_linker = if cfg!(feature = "wasmtime") {
+            define_add_to_linker(&names, &module)
+        } else {
+            quote!()
+        };
+        let lucet_hostcalls = if cfg!(feature = "lucet") {
+            define_lucet_hostcalls(&names, &module)
+        } else {
+            quote!()
+        };
         let ctx_type = names.ctx_type();
+        let memory_type = names
+            .memory_type_name()
+            .filter(|m| *m != ctx_type)
+            .map(|m| quote!(use super::#m;));
+        let ctx_type = if names.generic_ctx() {
+            quote!()
+        } else {
+            quote!(use super::#ctx_type;)
+        };
         quote!(
             pub mod #modname {
-                use super::#ctx_type;
+                #ctx_type
+                #memory_type
                 use super::types::*;
                 #(#fs)*
 
                 #modtrait
+
+                #mock
+
+                #introspection
+
+                #dispatch
+
+                #abi_functions
+
+                #add_to_linker
+
+                #lucet_hostcalls
             }
         )
     });
 
+    let types = match names.types_path() {
+        Some(path) => quote!(pub use #path as types;),
+        None => quote!(
+            pub mod types {
+                #(#types)*
+            }
+        ),
+    };
+
+    let metadata = define_metadata(doc, &names);
+    let conversions = define_conversions(doc, &names);
+    let adapter = define_adapter(doc, &names);
+
     quote!(
-        pub mod types {
-            #(#types)*
-        }
+        #(#nullable_errors)*
+        #(#filter_errors)*
+        #(#attribute_errors)*
+        #(#substitute_errors)*
+        #(#not_supported_errors)*
+        #(#split_errors)*
+        #(#user_error_errors)*
+        #types
+        #metadata
         #(#modules)*
+        #conversions
+        #adapter
     )
 }
+
+/// Reports the types named in the `errors` config which aren't in the
+/// document.
+fn check_user_errors(doc: &witx::Document, names: &Names) -> Vec<TokenStream> {
+    names
+        .user_errors()
+        .filter(|e| {
+            doc.typename(&witx::Id::new(e.typename.to_string()))
+                .is_none()
+        })
+        .map(|e| {
+            let msg = format!("no type named `{}` in the witx document", e.typename);
+            quote_spanned!(e.typename.span()=> compile_error!(#msg);)
+        })
+        .collect()
+}
+
+/// Reports the modules and functions named by `split_traits` which aren't
+/// in the document, and the functions listed more than once.
+fn check_split_traits(doc: &witx::Document, names: &Names) -> Vec<TokenStream> {
+    let mut errors = Vec::new();
+    for split in names.split_modules() {
+        let module = match doc.module(&witx::Id::new(split.module.to_string())) {
+            Some(module) => module,
+            None => {
+                let msg = format!("no module named `{}` in the witx document", split.module);
+                errors.push(quote_spanned!(split.module.span()=> compile_error!(#msg);));
+                continue;
+            }
+        };
+        let mut seen = Vec::new();
+        for func in split.traits.iter().flat_map(|t| &t.funcs) {
+            let msg = if module.func(&witx::Id::new(func.to_string())).is_none() {
+                format!("no function named `{}` in `{}`", func, split.module)
+            } else if seen.contains(&func) {
+                format!("`{}` is listed in more than one trait", func)
+            } else {
+                seen.push(func);
+                continue;
+            };
+            errors.push(quote_spanned!(func.span()=> compile_error!(#msg);));
+        }
+    }
+    errors
+}
+
+/// Reports the `not_supported` variants which aren't variants of an enum
+/// in the document.
+fn check_not_supported(doc: &witx::Document, names: &Names) -> Vec<TokenStream> {
+    names
+        .not_supported_variants()
+        .filter_map(|(typename, variant)| {
+            let nt = match doc.typename(&witx::Id::new(typename)) {
+                Some(nt) => nt,
+                None => {
+                    let msg = format!("no type named `{}` in the witx document", typename);
+                    return Some(quote_spanned!(variant.span()=> compile_error!(#msg);));
+                }
+            };
+            if names.user_error(&nt.name).is_some() {
+                let msg = format!(
+                    "`{}` is mapped to a user error type by `errors`, so has no default",
+                    typename
+                );
+                return Some(quote_spanned!(variant.span()=> compile_error!(#msg);));
+            }
+            let msg = match &*nt.type_() {
+                witx::Type::Enum(e) => {
+                    if e.variants.iter().any(|v| *variant == v.name.as_str()) {
+                        return None;
+                    }
+                    format!("`{}` has no variant named `{}`", typename, variant)
+                }
+                _ => format!("`{}` isn't an enum", typename),
+            };
+            Some(quote_spanned!(variant.span()=> compile_error!(#msg);))
+        })
+        .collect()
+}
+
+/// Reports the types named in the `derive` and `attributes` configs which
+/// aren't in the document, or are generated as type aliases.
+fn check_type_attributes(doc: &witx::Document, names: &Names) -> Vec<TokenStream> {
+    names
+        .attributed_types()
+        .filter_map(|name| {
+            let msg = match doc.typename(&witx::Id::new(name.to_string())) {
+                None => format!("no type named `{}` in the witx document", name),
+                Some(nt) => match &nt.tref {
+                    witx::TypeRef::Value(ty)
+                        if !matches!(
+                            &**ty,
+                            witx::Type::Builtin(_)
+                                | witx::Type::Pointer(_)
+                                | witx::Type::ConstPointer(_)
+                                | witx::Type::Array(_)
+                        ) =>
+                    {
+                        return None
+                    }
+                    _ => format!(
+                        "`{}` is generated as a type alias, so can't have derives or attributes",
+                        name
+                    ),
+                },
+            };
+            Some(quote_spanned!(name.span()=> compile_error!(#msg);))
+        })
+        .collect()
+}
+
+/// Reports the substituted types which aren't in the document, or can't be
+/// substituted.
+fn check_substitutions(doc: &witx::Document, names: &Names) -> Vec<TokenStream> {
+    use lifetimes::LifetimeExt;
+    names
+        .substitutions()
+        .filter_map(|s| {
+            let id = witx::Id::new(s.typename.to_string());
+            let is_error_type = || {
+                doc.modules().any(|m| {
+                    m.funcs().any(|f| {
+                        f.results
+                            .first()
+                            .and_then(|r| names.substituted_name(&r.tref))
+                            .is_some_and(|n| n == id)
+                    })
+                })
+            };
+            let msg = match doc.typename(&id) {
+                None => format!("no type named `{}` in the witx document", s.typename),
+                Some(nt) if nt.tref.needs_lifetime() => {
+                    format!("`{}` has a lifetime, so can't be substituted", s.typename)
+                }
+                Some(_) if is_error_type() => format!(
+                    "`{}` is the error type of a function, so can't be substituted",
+                    s.typename
+                ),
+                Some(_) => return None,
+            };
+            Some(quote_spanned!(s.typename.span()=> compile_error!(#msg);))
+        })
+        .collect()
+}
+
+/// Generates the code `from_witx!` would expand to for `config`, so that
+/// build scripts and code generators can write it out. The code is formatted
+/// with rustfmt when it's available.
+///
+/// The config is usually parsed from the same text the macro is given, e.g.
+/// `"{ witx: [\"api.witx\"], ctx: Ctx }".parse()`, and witx paths are
+/// relative to the current directory.
+///
+/// Documents with parts that can't be generated are an error, rather than
+/// code which fails to compile.
+pub fn generate_to_string(config: &Config) -> anyhow::Result<String> {
+    let doc = config_document(config)?;
+    check_unsupported(unsupported::unsupported_in_bindings(
+        &doc,
+        &Names::new(config),
+    ))?;
+    Ok(pretty(&generate(&doc, config).to_string()))
+}
+
+/// Like `generate_to_string`, writing the code to the file at `path`, which
+/// can then be `include!`d.
+pub fn generate_to_file(config: &Config, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    fs::write(path, generate_to_string(config)?)?;
+    Ok(())
+}
+
+/// Renders a C header for guests of the document `config` names, with the
+/// same layouts as the Rust bindings; see `guest_c_header` for how `prefix`
+/// names its definitions.
+pub fn generate_c_header(config: &Config, prefix: &str) -> anyhow::Result<String> {
+    let doc = config_document(config)?;
+    let funcs = doc
+        .modules()
+        .flat_map(|m| m.funcs().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    check_unsupported(unsupported::unsupported_shapes(&doc, &funcs, false))?;
+    Ok(guest_c_header(&doc, prefix))
+}
+
+/// Fails with every reason in `unsupported` that code can't be generated.
+fn check_unsupported(unsupported: Vec<String>) -> anyhow::Result<()> {
+    if unsupported.is_empty() {
+        return Ok(());
+    }
+    anyhow::bail!("{}", unsupported.join("\n"))
+}
+
+/// Loads the document named by `config`'s `witx` or `witx_literal` field.
+fn config_document(config: &Config) -> anyhow::Result<witx::Document> {
+    witx_document(&config.witx)
+}
+
+/// Loads or parses the witx document `witx` configures.
+pub(crate) fn witx_document(witx: &WitxConf) -> anyhow::Result<witx::Document> {
+    match witx {
+        WitxConf::Paths { paths, .. } => {
+            load_document(paths).map_err(|e| anyhow::anyhow!("{}", e.report()))
+        }
+        WitxConf::Literal { text, span } => Ok(parse_literal(text, *span)?),
+    }
+}
+
+/// Reports the `nullable` members which aren't pointer members of a struct
+/// in the document.
+fn check_nullable(doc: &witx::Document, names: &Names) -> Vec<TokenStream> {
+    names
+        .nullable_members()
+        .filter(|n| {
+            let struct_name = witx::Id::new(n.struct_name.to_string());
+            let member_name = witx::Id::new(n.member.to_string());
+            let is_pointer_member = doc.typename(&struct_name).is_some_and(|t| match &t.tref {
+                witx::TypeRef::Value(ty) => match &**ty {
+                    witx::Type::Struct(s) => s.members.iter().any(|m| {
+                        m.name == member_name
+                            && matches!(
+                                &m.tref,
+                                witx::TypeRef::Value(ty) if matches!(
+                                    &**ty,
+                                    witx::Type::Pointer(_) | witx::Type::ConstPointer(_)
+                                )
+                            )
+                    }),
+                    _ => false,
+                },
+                witx::TypeRef::Name(_) => false,
+            });
+            !is_pointer_member
+        })
+        .map(|n| {
+            let msg = format!(
+                "`{}.{}` is not a pointer member of a struct",
+                n.struct_name, n.member
+            );
+            quote_spanned!(n.member.span()=> compile_error!(#msg);)
+        })
+        .collect()
+}
+
+/// Reports the names given to the function and module filters which don't
+/// name a function or module in the document.
+fn check_filters(doc: &witx::Document, names: &Names) -> Vec<TokenStream> {
+    names
+        .filtered_names()
+        .filter(|(name, is_func)| {
+            let id = witx::Id::new(name.to_string());
+            if *is_func {
+                !doc.modules().any(|m| m.func(&id).is_some())
+            } else {
+                doc.module(&id).is_none()
+            }
+        })
+        .map(|(name, is_func)| {
+            let kind = if is_func { "function" } else { "module" };
+            let msg = format!("no {} named `{}` in the witx document", kind, name);
+            quote_spanned!(name.span()=> compile_error!(#msg);)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generates_to_a_file() {
+        let config: Config = r#"{ witx: ["../../tests/atoms.witx"], ctx: Ctx }"#
+            .parse()
+            .expect("parse config");
+        let path = std::env::temp_dir().join("wiggle-generate-atoms.rs");
+        generate_to_file(&config, &path).expect("generate");
+        let code = fs::read_to_string(&path).expect("read generated code");
+        fs::remove_file(&path).expect("remove generated code");
+        assert_eq!(code, generate_to_string(&config).expect("generate"));
+        assert!(code.contains("pub mod types"));
+        assert!(code.contains("pub trait Atoms"));
+        assert!(code.contains("pub fn double_int_return_float"));
+    }
+
+    #[test]
+    fn generates_a_c_header() {
+        let config: Config = r#"{ witx: ["../../tests/atoms.witx"], ctx: Ctx }"#
+            .parse()
+            .expect("parse config");
+        let header = generate_c_header(&config, "atoms").expect("generate");
+        assert!(header.contains("#ifndef ATOMS_H"));
+        assert!(header.contains("atoms_double_int_return_float("));
+    }
+
+    #[test]
+    fn generates_docs() {
+        let config: Config = r##"{
+            witx_literal: r#"
+                ;;; Error codes.
+                (typename $errno
+                  (enum u16
+                    ;;; No error occurred.
+                    $success
+                    ;;; Bad file descriptor.
+                    $badf))
+                ;;; Closing files.
+                (module $files
+                  ;;; Close a file descriptor.
+                  (@interface func (export "close")
+                    ;;; The file descriptor to close.
+                    (param $fd u32)
+                    (result $error $errno)))
+            "#,
+            ctx: Ctx,
+        }"##
+        .parse()
+        .expect("parse config");
+        let code = generate_to_string(&config).expect("generate");
+        for docs in [
+            "Error codes.",
+            "No error occurred.",
+            "Bad file descriptor.",
+            "Closing files.",
+            "Close a file descriptor.\\n\\n# Parameters\\n\\n* `fd`: The file descriptor to close.",
+        ] {
+            assert!(code.contains(docs), "missing docs {:?} in:\n{}", docs, code);
+        }
+    }
+
+    #[test]
+    fn reports_unsupported_shapes() {
+        let config: Config = r##"{
+            witx_literal: r#"
+                (module $m
+                  (@interface func (export "f") (param $p (array u8)))
+                  (@interface func (export "exit") (@witx noreturn)))
+            "#,
+            ctx: Ctx,
+        }"##
+        .parse()
+        .expect("parse config");
+        let err = generate_to_string(&config).unwrap_err().to_string();
+        assert!(err.contains("`p` of function `f`"), "{}", err);
+        assert!(err.contains("function `exit` doesn't return"), "{}", err);
+
+        // Headers can declare functions which don't return.
+        let err = generate_c_header(&config, "m").unwrap_err().to_string();
+        assert!(err.contains("`p` of function `f`"), "{}", err);
+        assert!(!err.contains("exit"), "{}", err);
+    }
+
+    #[test]
+    fn reports_witx_errors() {
+        let config: Config = "{ witx: [\"missing.witx\"], ctx: Ctx }"
+            .parse()
+            .expect("parse config");
+        let err = generate_to_string(&config).unwrap_err();
+        assert!(err.to_string().contains("missing.witx"), "{}", err);
+    }
+}
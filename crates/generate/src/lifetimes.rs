@@ -51,10 +51,7 @@ impl LifetimeExt for witx::BuiltinType {
         !self.needs_lifetime()
     }
     fn needs_lifetime(&self) -> bool {
-        match self {
-            witx::BuiltinType::String => true,
-            _ => false,
-        }
+        matches!(self, witx::BuiltinType::String)
     }
 }
 
@@ -0,0 +1,186 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cache::uses;
+
+/// The source text of a witx document, for finding where things in it are
+/// declared.
+///
+/// witx doesn't keep track of source locations once a document has been
+/// validated, so declarations are found by searching the text instead. The
+/// search only needs to be good enough for error messages: anything it can't
+/// find just has no location.
+#[derive(Debug, Clone, Default)]
+pub struct WitxSources {
+    files: Vec<(String, String)>,
+}
+
+impl WitxSources {
+    /// Reads the files at `paths`, and any files they `use`. Files which
+    /// can't be read are skipped.
+    pub fn load(paths: &[PathBuf]) -> WitxSources {
+        let mut sources = WitxSources::default();
+        for path in paths {
+            let root = path.parent().unwrap_or_else(|| Path::new("."));
+            sources.read(path, root);
+        }
+        sources
+    }
+
+    /// The text of a `witx_literal:` document.
+    pub fn literal(contents: &str) -> WitxSources {
+        WitxSources {
+            files: vec![("witx_literal".to_string(), contents.to_string())],
+        }
+    }
+
+    fn read(&mut self, path: &Path, root: &Path) {
+        let display = display_path(path);
+        if self.files.iter().any(|(p, _)| *p == display) {
+            return;
+        }
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return,
+        };
+        let used = uses(&contents)
+            .map(|used| root.join(used))
+            .collect::<Vec<_>>();
+        self.files.push((display, contents));
+        for used in used {
+            self.read(&used, root);
+        }
+    }
+
+    /// Finds the declaration of the parameter or result `param` of the
+    /// function exported as `func`.
+    pub fn func_param(&self, func: &str, param: &str) -> Option<WitxLocation> {
+        let export = format!("(export \"{}\")", func);
+        self.find(&export, "(@interface func", &["(param", "(result"], param)
+    }
+
+    /// Finds the declaration of the field `member` of the struct `typename`.
+    pub fn struct_member(&self, typename: &str, member: &str) -> Option<WitxLocation> {
+        let typename = format!("(typename ${}", typename);
+        self.find(&typename, "(typename", &["(field"], member)
+    }
+
+    /// Finds `$name` declared by one of `keywords` after `start` and before
+    /// the next `end`.
+    fn find(&self, start: &str, end: &str, keywords: &[&str], name: &str) -> Option<WitxLocation> {
+        let name = format!("${}", name);
+        self.files.iter().find_map(|(path, contents)| {
+            let begin = find_token(contents, start, 0)? + start.len();
+            let scope_end = contents[begin..]
+                .find(end)
+                .map(|i| begin + i)
+                .unwrap_or(contents.len());
+            let mut from = begin;
+            while let Some(pos) = find_token(&contents[..scope_end], &name, from) {
+                let before = contents[..pos].trim_end();
+                if keywords.iter().any(|k| before.ends_with(k)) {
+                    return Some(WitxLocation::new(path, contents, pos));
+                }
+                from = pos + name.len();
+            }
+            None
+        })
+    }
+}
+
+/// Shows `path` relative to the crate being compiled, which paths are
+/// resolved relative to, when it's in it.
+pub(crate) fn display_path(path: &Path) -> String {
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+    manifest_dir
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Finds `token` in `s` at or after `from`, where it isn't just the start of a
+/// longer identifier.
+fn find_token(s: &str, token: &str, mut from: usize) -> Option<usize> {
+    loop {
+        let pos = from + s[from..].find(token)?;
+        let next = s[pos + token.len()..].chars().next();
+        if !next.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            return Some(pos);
+        }
+        from = pos + token.len();
+    }
+}
+
+/// A position in a witx file. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WitxLocation {
+    pub path: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl WitxLocation {
+    fn new(path: &str, contents: &str, pos: usize) -> WitxLocation {
+        let before = &contents[..pos];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        WitxLocation {
+            path: path.to_string(),
+            line: before.matches('\n').count() as u32 + 1,
+            column: before[line_start..].chars().count() as u32 + 1,
+        }
+    }
+}
+
+impl ToTokens for WitxLocation {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let WitxLocation { path, line, column } = self;
+        tokens.extend(quote! {
+            wiggle::WitxLocation { path: #path, line: #line, column: #column }
+        });
+    }
+}
+
+/// Converts an optional location to an `Option<wiggle::WitxLocation>`
+/// expression.
+pub fn location_tokens(location: Option<WitxLocation>) -> TokenStream {
+    match location {
+        Some(location) => quote!(Some(#location)),
+        None => quote!(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sources(contents: &str) -> WitxSources {
+        WitxSources {
+            files: vec![("test.witx".to_string(), contents.to_string())],
+        }
+    }
+
+    #[test]
+    fn finds_params_and_fields() {
+        let sources = sources(
+            "(typename $pair\n  (struct\n    (field $first s32)\n    (field $first_too s32)))\n\
+             (module $m\n  (@interface func (export \"f\")\n    (param $first s32)\n    \
+             (result $error $errno)))\n",
+        );
+        let loc = |line, column| {
+            Some(WitxLocation {
+                path: "test.witx".to_string(),
+                line,
+                column,
+            })
+        };
+        assert_eq!(sources.struct_member("pair", "first"), loc(3, 12));
+        assert_eq!(sources.struct_member("pair", "first_too"), loc(4, 12));
+        assert_eq!(sources.func_param("f", "first"), loc(7, 12));
+        assert_eq!(sources.func_param("f", "error"), loc(8, 13));
+        assert_eq!(sources.func_param("g", "first"), None);
+        assert_eq!(sources.struct_member("pair", "second"), None);
+    }
+}
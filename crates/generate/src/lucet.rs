@@ -0,0 +1,101 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::names::Names;
+use witx::Module;
+
+/// Defines a lucet hostcall for every function of `m`, which runs the
+/// generated function against the instance's heap and embedder ctx.
+///
+/// Hostcalls are exported unmangled as `{module}_{function}`, for lucet's
+/// bindings to map the guest's imports onto. The ctx is fetched with
+/// `Vmctx::get_embed_ctx`, so the ctx type must be the type the instance was
+/// created with. The generated code refers to `lucet_runtime` directly, so
+/// the crate invoking the macro must depend on it.
+///
+/// As for wasmtime, nothing is generated for async interfaces, or for
+/// interfaces taking a named memory type or multiple memories, or for a
+/// generic ctx. Nor is it with `multi_value`, since hostcalls return a single
+/// value.
+pub fn define_lucet_hostcalls(names: &Names, m: &Module) -> TokenStream {
+    if names.is_async()
+        || names.multi_memory()
+        || names.memory_type_name().is_some()
+        || names.multi_value()
+        || names.generic_ctx()
+    {
+        return quote!();
+    }
+    let ctx_type = names.ctx_type();
+
+    let hostcalls = names.funcs(m).map(|func| {
+        let ident = names.func(&func.name);
+        let export = format_ident!("{}_{}", names.module(&m.name), ident);
+        let coretype = func.core_type();
+        let (params, args): (Vec<_>, Vec<_>) = coretype
+            .args
+            .iter()
+            .map(|arg| {
+                let name = names.func_core_arg(arg);
+                let atom = names.atom_type(names.core_arg_atom(arg));
+                (quote!(#name: #atom), quote!(#name))
+            })
+            .unzip();
+        let ret = match &coretype.ret {
+            Some(ret) => match ret.signifies {
+                witx::CoreParamSignifies::Value(atom) => names.atom_type(atom),
+                _ => unreachable!("ret should always be passed by value"),
+            },
+            None => quote!(()),
+        };
+        quote! {
+            #[lucet_runtime::lucet_hostcall]
+            #[no_mangle]
+            #[allow(clippy::too_many_arguments)]
+            pub fn #export(vmctx: &mut lucet_runtime::vmctx::Vmctx, #(#params),*) -> #ret {
+                let mut heap = vmctx.heap_mut();
+                let memory = wiggle::SliceMemory::new(&mut *heap);
+                let ctx = vmctx.get_embed_ctx::<#ctx_type>();
+                #ident(&ctx, &memory, #(#args),*)
+            }
+        }
+    });
+
+    quote!(#(#hostcalls)*)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Config;
+
+    const WITX: &str = r#"
+        (typename $errno (enum u16 $success $badf))
+        (typename $fd (handle))
+        (module $example
+          (@interface func (export "fd_close")
+            (param $fd $fd)
+            (result $error $errno)))
+    "#;
+
+    fn generate(config: &str) -> String {
+        let config: Config = syn::parse_str(config).expect("parse config");
+        let doc = witx::parse(WITX).expect("parse witx");
+        let module = doc.modules().next().expect("module");
+        define_lucet_hostcalls(&Names::new(&config), &module).to_string()
+    }
+
+    #[test]
+    fn defines_hostcalls() {
+        let hostcalls = generate("{ witx: [], ctx: Ctx }");
+        assert!(hostcalls.contains(
+            "# [lucet_runtime :: lucet_hostcall] # [no_mangle] \
+             # [allow (clippy :: too_many_arguments)] pub fn example_fd_close \
+             (vmctx : & mut lucet_runtime :: vmctx :: Vmctx , fd : i32) -> i32"
+        ));
+        assert!(hostcalls.contains("vmctx . get_embed_ctx :: < Ctx > ()"));
+        assert!(hostcalls.contains("fd_close (& ctx , & memory , fd)"));
+
+        assert_eq!(generate("{ witx: [], ctx: Ctx, async: true }"), "");
+    }
+}
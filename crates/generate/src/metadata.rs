@@ -0,0 +1,181 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use witx::{BuiltinType, Layout, Type, TypeRef};
+
+use crate::names::Names;
+
+/// Defines the `metadata` module, describing the generated functions and
+/// the types of the document for tools which enumerate them at runtime.
+pub fn define_metadata(doc: &witx::Document, names: &Names) -> TokenStream {
+    let funcs = names.modules(doc).flat_map(|m| {
+        let module = m.name.as_str().to_owned();
+        names
+            .funcs(&m)
+            .map(|f| {
+                let name = f.name.as_str();
+                let params = f.params.iter().map(param_metadata);
+                let results = f.results.iter().map(param_metadata);
+                quote! {
+                    wiggle::FuncMetadata {
+                        module: #module,
+                        name: #name,
+                        params: &[#(#params),*],
+                        results: &[#(#results),*],
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    });
+    let types = doc.typenames().map(|nt| type_metadata(&nt));
+
+    quote! {
+        /// Describes the functions and types of the interface.
+        pub mod metadata {
+            /// Every generated function, in witx order.
+            pub const FUNCTIONS: &[wiggle::FuncMetadata] = &[#(#funcs),*];
+
+            /// Every named type of the witx document, in witx order.
+            pub const TYPES: &[wiggle::TypeMetadata] = &[#(#types),*];
+
+            /// The function named `name` in the module named `module`.
+            pub fn function(module: &str, name: &str) -> Option<&'static wiggle::FuncMetadata> {
+                FUNCTIONS.iter().find(|f| f.module == module && f.name == name)
+            }
+
+            /// The type named `name`.
+            pub fn type_(name: &str) -> Option<&'static wiggle::TypeMetadata> {
+                TYPES.iter().find(|t| t.name == name)
+            }
+        }
+    }
+}
+
+fn param_metadata(param: &witx::InterfaceFuncParam) -> TokenStream {
+    let name = param.name.as_str();
+    let type_ = type_desc(&param.tref);
+    quote!(wiggle::ParamMetadata { name: #name, type_: #type_ })
+}
+
+fn type_desc(tref: &TypeRef) -> TokenStream {
+    match tref {
+        TypeRef::Name(nt) => {
+            let name = nt.name.as_str();
+            quote!(wiggle::TypeDesc::Named(#name))
+        }
+        TypeRef::Value(ty) => match &**ty {
+            Type::Builtin(b) => {
+                let name = builtin_name(*b);
+                quote!(wiggle::TypeDesc::Builtin(#name))
+            }
+            Type::Pointer(to) => {
+                let to = type_desc(to);
+                quote!(wiggle::TypeDesc::Pointer(&#to))
+            }
+            Type::ConstPointer(to) => {
+                let to = type_desc(to);
+                quote!(wiggle::TypeDesc::ConstPointer(&#to))
+            }
+            Type::Array(elem) => {
+                let elem = type_desc(elem);
+                quote!(wiggle::TypeDesc::Array(&#elem))
+            }
+            _ => unreachable!("anonymous types are reported as unsupported"),
+        },
+    }
+}
+
+fn builtin_name(b: BuiltinType) -> &'static str {
+    match b {
+        BuiltinType::String => "string",
+        BuiltinType::Char8 => "char8",
+        BuiltinType::USize => "usize",
+        BuiltinType::U8 => "u8",
+        BuiltinType::U16 => "u16",
+        BuiltinType::U32 => "u32",
+        BuiltinType::U64 => "u64",
+        BuiltinType::S8 => "s8",
+        BuiltinType::S16 => "s16",
+        BuiltinType::S32 => "s32",
+        BuiltinType::S64 => "s64",
+        BuiltinType::F32 => "f32",
+        BuiltinType::F64 => "f64",
+    }
+}
+
+fn int_repr_name(repr: witx::IntRepr) -> &'static str {
+    match repr {
+        witx::IntRepr::U8 => "u8",
+        witx::IntRepr::U16 => "u16",
+        witx::IntRepr::U32 => "u32",
+        witx::IntRepr::U64 => "u64",
+    }
+}
+
+fn type_metadata(nt: &witx::NamedType) -> TokenStream {
+    let name = nt.name.as_str();
+    let layout = nt.mem_size_align();
+    let (size, align) = (layout.size, layout.align);
+    let kind = match &nt.tref {
+        TypeRef::Name(_) => {
+            let to = type_desc(&nt.tref);
+            quote!(wiggle::TypeKind::Alias(#to))
+        }
+        TypeRef::Value(ty) => match &**ty {
+            Type::Enum(e) => {
+                let repr = int_repr_name(e.repr);
+                let variants = e.variants.iter().map(|v| v.name.as_str());
+                quote!(wiggle::TypeKind::Enum { repr: #repr, variants: &[#(#variants),*] })
+            }
+            Type::Flags(f) => {
+                let repr = int_repr_name(f.repr);
+                let flags = f.flags.iter().map(|f| f.name.as_str());
+                quote!(wiggle::TypeKind::Flags { repr: #repr, flags: &[#(#flags),*] })
+            }
+            Type::Int(i) => {
+                let repr = int_repr_name(i.repr);
+                let consts = i.consts.iter().map(|c| {
+                    let name = c.name.as_str();
+                    let value = c.value;
+                    quote!((#name, #value))
+                });
+                quote!(wiggle::TypeKind::Int { repr: #repr, consts: &[#(#consts),*] })
+            }
+            Type::Struct(s) => {
+                let members = s.member_layout().into_iter().map(|ml| {
+                    let name = ml.member.name.as_str();
+                    let type_ = type_desc(&ml.member.tref);
+                    let offset = ml.offset;
+                    quote!((wiggle::ParamMetadata { name: #name, type_: #type_ }, #offset))
+                });
+                quote!(wiggle::TypeKind::Struct { members: &[#(#members),*] })
+            }
+            Type::Union(u) => {
+                let tag = u.tag.name.as_str();
+                let variants = u.variants.iter().map(|v| {
+                    let name = v.name.as_str();
+                    match &v.tref {
+                        Some(tref) => {
+                            let type_ = type_desc(tref);
+                            quote!((#name, Some(#type_)))
+                        }
+                        None => quote!((#name, None)),
+                    }
+                });
+                quote!(wiggle::TypeKind::Union { tag: #tag, variants: &[#(#variants),*] })
+            }
+            Type::Handle(_) => quote!(wiggle::TypeKind::Handle),
+            Type::Builtin(_) | Type::Pointer(_) | Type::ConstPointer(_) | Type::Array(_) => {
+                let to = type_desc(&nt.tref);
+                quote!(wiggle::TypeKind::Alias(#to))
+            }
+        },
+    };
+    quote! {
+        wiggle::TypeMetadata {
+            name: #name,
+            size: #size,
+            align: #align,
+            kind: #kind,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use witx::{Module, TypeRef};
+
+use crate::lifetimes::anon_lifetime;
+use crate::module_trait::{trait_groups, TraitMethod};
+use crate::names::Names;
+
+/// Defines the `mock` module of `m`, when `mock` is enabled, with a mock
+/// implementing the module trait.
+///
+/// Every method of the mock records its call, with the `Debug` output of
+/// each argument, and returns the mock's value for its error type. There's
+/// one such value per error type the functions return, set on creation.
+pub fn define_module_mock(names: &Names, m: &Module) -> TokenStream {
+    if !names.mock() {
+        return quote!();
+    }
+    let traitname = names.trait_name(&m.name);
+    let mockname = format_ident!("Mock{}", traitname);
+    let core = names.core();
+    let alloc = names.alloc();
+
+    // The error fields of the mock, one per distinct error type.
+    let mut errors = Vec::new();
+    let impls = trait_groups(names, m)
+        .into_iter()
+        .map(|(traitname, methods)| {
+            let methods = methods
+                .iter()
+                .map(|method| mock_method(names, method, &mut errors))
+                .collect::<Vec<_>>();
+            quote! {
+                #[allow(clippy::too_many_arguments)]
+                impl #traitname for #mockname {
+                    #(#methods)*
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let (fields, types): (Vec<_>, Vec<_>) = errors.into_iter().unzip();
+
+    let doc = format!(
+        "A mock of [`{}`], which records its calls and fails them all.",
+        traitname
+    );
+    quote! {
+        /// A mock of the module trait, for testing code calling it.
+        pub mod mock {
+            use super::*;
+
+            #[doc = #doc]
+            pub struct #mockname {
+                #(
+                    /// The error returned by the methods with this error type.
+                    pub #fields: #types,
+                )*
+                calls: #core::cell::RefCell<#alloc::vec::Vec<wiggle::MockCall>>,
+            }
+
+            #[allow(clippy::new_without_default)]
+            impl #mockname {
+                /// A mock returning the given errors.
+                pub fn new(#(#fields: #types),*) -> Self {
+                    #mockname {
+                        #(#fields,)*
+                        calls: #core::cell::RefCell::new(#alloc::vec::Vec::new()),
+                    }
+                }
+
+                /// The calls made so far, in order.
+                pub fn calls(&self) -> #alloc::vec::Vec<wiggle::MockCall> {
+                    self.calls.borrow().clone()
+                }
+            }
+
+            #(#impls)*
+        }
+    }
+}
+
+/// The mock's implementation of `method`, adding the field for its error
+/// type to `errors` if it isn't there yet.
+fn mock_method(
+    names: &Names,
+    method: &TraitMethod,
+    errors: &mut Vec<(Ident, TokenStream)>,
+) -> TokenStream {
+    let alloc = names.alloc();
+    let err = match method.func.results.first().map(|r| &r.tref) {
+        Some(tref) => {
+            let (field, user_error) = match tref {
+                TypeRef::Name(nt) => (
+                    names.struct_member(&nt.name),
+                    names.user_error(&nt.name).is_some(),
+                ),
+                TypeRef::Value(_) => (format_ident!("error"), false),
+            };
+            if !errors.iter().any(|(f, _)| *f == field) {
+                errors.push((field.clone(), names.error_type(tref, anon_lifetime())));
+            }
+            // Witx error types are `Copy`, but user errors only need to be
+            // `Clone`.
+            if user_error {
+                quote!(self.#field.clone())
+            } else {
+                quote!(self.#field)
+            }
+        }
+        None => quote!(()),
+    };
+    let funcname = method.func.name.as_str();
+    let params = &method.params;
+    let signature = &method.signature;
+    quote! {
+        #signature {
+            self.calls.borrow_mut().push(wiggle::MockCall {
+                func: #funcname,
+                args: #alloc::vec![#(#alloc::format!("{:?}", #params)),*],
+            });
+            Err(#err)
+        }
+    }
+}
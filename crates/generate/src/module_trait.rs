@@ -1,57 +1,226 @@
-use proc_macro2::TokenStream;
-use quote::quote;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use std::rc::Rc;
 
 use crate::lifetimes::{anon_lifetime, LifetimeExt};
 use crate::names::Names;
-use witx::Module;
+use witx::{InterfaceFunc, Module};
+
+/// A method of a module trait.
+pub(crate) struct TraitMethod {
+    /// The witx function the method is for.
+    pub func: Rc<InterfaceFunc>,
+    pub ident: Ident,
+    pub params: Vec<Ident>,
+    pub signature: TokenStream,
+}
+
+/// The methods of the trait for `m`, one per generated function.
+pub(crate) fn trait_methods(names: &Names, m: &Module) -> Vec<TraitMethod> {
+    let asyncness = if names.is_async() {
+        quote!(async)
+    } else {
+        quote!()
+    };
+    names
+        .funcs(m)
+        .map(|f| {
+            // Check if we're returning an entity anotated with a lifetime,
+            // in which case, we'll need to annotate the function itself, and
+            // hence will need an explicit lifetime (rather than anonymous)
+            let (lifetime, is_anonymous) = if f
+                .params
+                .iter()
+                .chain(&f.results)
+                .any(|ret| ret.tref.needs_lifetime())
+            {
+                (quote!('a), false)
+            } else {
+                (anon_lifetime(), true)
+            };
+            let funcname = names.func(&f.name);
+            let args = f.params.iter().map(|arg| {
+                let arg_name = names.func_param(&arg.name);
+                let arg_typename = names.type_ref(&arg.tref, lifetime.clone());
+                if let Some(view) = names.param_view(&arg.tref) {
+                    return quote!(#arg_name: #view<#lifetime>);
+                }
+                let arg_type = match arg.tref.type_().passed_by() {
+                    witx::TypePassedBy::Value { .. } => quote!(#arg_typename),
+                    witx::TypePassedBy::Pointer => quote!(&#arg_typename),
+                    witx::TypePassedBy::PointerLengthPair => quote!(&#arg_typename),
+                };
+                quote!(#arg_name: #arg_type)
+            });
+            let rets = f
+                .results
+                .iter()
+                .skip(1)
+                .map(|ret| names.type_ref(&ret.tref, lifetime.clone()));
+            let err = f
+                .results
+                .first()
+                .map(|err_result| names.error_type(&err_result.tref, lifetime.clone()))
+                .unwrap_or(quote!(()));
+
+            let signature = if is_anonymous {
+                quote!(#asyncness fn #funcname(&self, #(#args),*) -> Result<(#(#rets),*), #err>)
+            } else {
+                quote!(#asyncness fn #funcname<#lifetime>(&self, #(#args),*) -> Result<(#(#rets),*), #err>)
+            };
+            TraitMethod {
+                params: f.params.iter().map(|arg| names.func_param(&arg.name)).collect(),
+                ident: funcname,
+                signature,
+                func: f,
+            }
+        })
+        .collect()
+}
+
+/// The methods of the trait for `m` grouped by the trait they're in, which
+/// is the module trait itself unless `split_traits` splits it.
+pub(crate) fn trait_groups(names: &Names, m: &Module) -> Vec<(Ident, Vec<TraitMethod>)> {
+    let traitname = names.trait_name(&m.name);
+    let mut methods = trait_methods(names, m);
+    let split = match names.split_traits(&m.name) {
+        Some(split) => split,
+        None => return vec![(traitname, methods)],
+    };
+    let mut groups = split
+        .traits
+        .iter()
+        .map(|t| {
+            let (listed, rest) = methods
+                .drain(..)
+                .partition(|method| t.funcs.iter().any(|f| f == method.func.name.as_str()));
+            methods = rest;
+            (t.name.clone(), listed)
+        })
+        .collect::<Vec<_>>();
+    if !methods.is_empty() {
+        groups.push((format_ident!("{}Other", traitname), methods));
+    }
+    groups
+}
 
 pub fn define_module_trait(names: &Names, m: &Module) -> TokenStream {
     let traitname = names.trait_name(&m.name);
-    let traitmethods = m.funcs().map(|f| {
-        // Check if we're returning an entity anotated with a lifetime,
-        // in which case, we'll need to annotate the function itself, and
-        // hence will need an explicit lifetime (rather than anonymous)
-        let (lifetime, is_anonymous) = if f
-            .params
-            .iter()
-            .chain(&f.results)
-            .any(|ret| ret.tref.needs_lifetime())
-        {
-            (quote!('a), false)
-        } else {
-            (anon_lifetime(), true)
-        };
-        let funcname = names.func(&f.name);
-        let args = f.params.iter().map(|arg| {
-            let arg_name = names.func_param(&arg.name);
-            let arg_typename = names.type_ref(&arg.tref, lifetime.clone());
-            let arg_type = match arg.tref.type_().passed_by() {
-                witx::TypePassedBy::Value { .. } => quote!(#arg_typename),
-                witx::TypePassedBy::Pointer { .. } => quote!(&#arg_typename),
-                witx::TypePassedBy::PointerLengthPair { .. } => quote!(&#arg_typename),
-            };
-            quote!(#arg_name: #arg_type)
+    let alloc = names.alloc();
+    let await_ = if names.is_async() {
+        quote!(.await)
+    } else {
+        quote!()
+    };
+    let trait_docs = names.docs(&m.docs);
+    // The trait only has lifetime parameters on its methods, so it is object
+    // safe. Forwarding it through `Box` lets a `Box<dyn Trait>` be used as the
+    // ctx, and implementations be swapped out at runtime.
+    // Async traits can't be made into objects, and their futures can't be
+    // required to be `Send` either: that's left to the embedder.
+    let allow_async = if names.is_async() {
+        quote!(#[allow(async_fn_in_trait)])
+    } else {
+        quote!()
+    };
+    let groups = trait_groups(names, m);
+    let traits = groups.iter().map(|(name, methods)| {
+        let traitmethods = methods.iter().map(|method| {
+            let docs = func_docs(names, &method.func);
+            let signature = &method.signature;
+            match not_supported(names, &method.func) {
+                Some(err) => quote! {
+                    #docs
+                    #[allow(unused_variables)]
+                    #signature {
+                        Err(#err)
+                    }
+                },
+                None => quote!(#docs #signature;),
+            }
+        });
+        let forwarding_methods = methods.iter().map(|method| {
+            let TraitMethod {
+                ident,
+                params,
+                signature,
+                ..
+            } = method;
+            quote!(#signature { (**self).#ident(#(#params),*)#await_ })
         });
-        let rets = f
-            .results
-            .iter()
-            .skip(1)
-            .map(|ret| names.type_ref(&ret.tref, lifetime.clone()));
-        let err = f
-            .results
-            .get(0)
-            .map(|err_result| names.type_ref(&err_result.tref, lifetime.clone()))
-            .unwrap_or(quote!(()));
-
-        if is_anonymous {
-            quote!(fn #funcname(&self, #(#args),*) -> Result<(#(#rets),*), #err>;)
+        let docs = if *name == traitname {
+            trait_docs.clone()
         } else {
-            quote!(fn #funcname<#lifetime>(&self, #(#args),*) -> Result<(#(#rets),*), #err>;)
+            let doc = format!("Part of [`{}`].", traitname);
+            quote!(#[doc = #doc])
+        };
+        quote! {
+            #[allow(clippy::too_many_arguments)]
+            #allow_async
+            #docs
+            pub trait #name {
+                #(#traitmethods)*
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            impl<T: #name + ?Sized> #name for #alloc::boxed::Box<T> {
+                #(#forwarding_methods)*
+            }
         }
     });
+    if groups.len() == 1 && groups[0].0 == traitname {
+        return quote!(#(#traits)*);
+    }
+
+    // The module trait combines the traits it's split into.
+    let parts = groups.iter().map(|(name, _)| name).collect::<Vec<_>>();
     quote! {
-        pub trait #traitname {
-            #(#traitmethods)*
+        #(#traits)*
+
+        #trait_docs
+        pub trait #traitname: #(#parts)+* {}
+
+        impl<T: #(#parts)+* + ?Sized> #traitname for T {}
+    }
+}
+
+/// The "not supported" error `f` returns by default, if its error type has
+/// one configured.
+fn not_supported(names: &Names, f: &InterfaceFunc) -> Option<TokenStream> {
+    let nt = match &f.results.first()?.tref {
+        witx::TypeRef::Name(nt) => nt,
+        witx::TypeRef::Value(_) => return None,
+    };
+    let variant = names.not_supported(&nt.name)?;
+    let typename = names.type_(&nt.name);
+    let variant = names.enum_variant(&nt.name, &variant);
+    Some(quote!(#typename::#variant))
+}
+
+/// The docs of the trait method for `f`: those of the function, followed by
+/// a list of those of its parameters, since parameters can't have their own.
+fn func_docs(names: &Names, f: &witx::InterfaceFunc) -> TokenStream {
+    let mut docs = f.docs.trim_end().to_string();
+    let params = f
+        .params
+        .iter()
+        .filter(|p| !p.docs.trim().is_empty())
+        .map(|p| {
+            let name = names.func_param(&p.name).to_string();
+            let param_docs = p.docs.lines().map(str::trim).collect::<Vec<_>>();
+            format!(
+                "* `{}`: {}",
+                name.trim_start_matches("r#"),
+                param_docs.join("\n  ")
+            )
+        })
+        .collect::<Vec<_>>();
+    if !params.is_empty() {
+        if !docs.is_empty() {
+            docs.push_str("\n\n");
         }
+        docs.push_str("# Parameters\n\n");
+        docs.push_str(&params.join("\n"));
     }
+    names.docs(&docs)
 }
@@ -1,32 +1,413 @@
 use heck::{CamelCase, ShoutySnakeCase, SnakeCase};
-use proc_macro2::{Ident, TokenStream};
-use quote::{format_ident, quote};
-use witx::{AtomType, BuiltinType, Id, TypeRef};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use std::rc::Rc;
+use witx::{AtomType, BuiltinType, Id, InterfaceFunc, Module, TypeRef};
 
+use crate::config::{
+    AdapterConf, Case, ConversionsConf, FilterConf, LimitsConf, NamingConf, NullableMember,
+    PerTypeConf, SplitModule, Substitution, WitxConf,
+};
 use crate::lifetimes::LifetimeExt;
+use crate::locations::{location_tokens, WitxSources};
 use crate::Config;
 
 #[derive(Debug, Clone)]
 pub struct Names {
     config: Config,
+    sources: WitxSources,
 }
 
 impl Names {
     pub fn new(config: &Config) -> Names {
         Names {
             config: config.clone(),
+            sources: match &config.witx {
+                WitxConf::Paths { paths, .. } => WitxSources::load(paths),
+                WitxConf::Literal { text, .. } => WitxSources::literal(text),
+            },
         }
     }
+    /// Where errors about the witx document are reported.
+    pub fn witx_span(&self) -> Span {
+        self.config.witx.span()
+    }
+    /// The ctx type, which is the type parameter of generated functions
+    /// with `ctx: generic`.
     pub fn ctx_type(&self) -> Ident {
-        self.config.ctx.name.clone()
+        let ctx = self
+            .config
+            .ctx
+            .as_ref()
+            .expect("the ctx is required for host bindings");
+        if ctx.is_generic() {
+            format_ident!("Ctx")
+        } else {
+            ctx.name.clone()
+        }
+    }
+    pub fn generic_ctx(&self) -> bool {
+        self.config.ctx.as_ref().is_some_and(|c| c.is_generic())
+    }
+    /// The witx name of the "not supported" variant of the error enum
+    /// `typename`, if it has one configured.
+    pub fn not_supported(&self, typename: &Id) -> Option<Id> {
+        let errors = &self.config.not_supported.as_ref()?.errors;
+        errors
+            .iter()
+            .find(|(t, _)| t == typename.as_str())
+            .map(|(_, variant)| Id::new(variant.to_string()))
+    }
+    /// The configured "not supported" variants, as written.
+    pub fn not_supported_variants(&self) -> impl Iterator<Item = (&str, &Ident)> {
+        self.config
+            .not_supported
+            .iter()
+            .flat_map(|n| n.errors.iter().map(|(t, v)| (t.as_str(), v)))
+    }
+    /// How the trait of the module `module` is split, if it is.
+    pub fn split_traits(&self, module: &Id) -> Option<&SplitModule> {
+        self.config
+            .split_traits
+            .as_ref()?
+            .modules
+            .iter()
+            .find(|m| m.module == module.as_str())
+    }
+    pub fn split_modules(&self) -> impl Iterator<Item = &SplitModule> {
+        self.config
+            .split_traits
+            .iter()
+            .flat_map(|s| s.modules.iter())
+    }
+    pub fn adapter(&self) -> Option<&AdapterConf> {
+        self.config.adapter.as_ref()
+    }
+    pub fn conversions(&self) -> Option<&ConversionsConf> {
+        self.config.conversions.as_ref()
+    }
+    pub fn mock(&self) -> bool {
+        self.config.mock.as_ref().is_some_and(|m| m.enabled)
+    }
+    pub fn guest(&self) -> bool {
+        self.config.guest.as_ref().is_some_and(|g| g.enabled)
+    }
+    pub fn policy_error_type(&self) -> Option<Ident> {
+        self.config.policy.as_ref().map(|p| p.error.clone())
+    }
+    pub fn cancel_error_type(&self) -> Option<Ident> {
+        self.config.cancel.as_ref().map(|c| c.error.clone())
+    }
+    pub fn limits(&self) -> Option<&LimitsConf> {
+        self.config.limits.as_ref()
+    }
+    pub fn interface_version(&self) -> u32 {
+        self.config.version.as_ref().map(|v| v.version).unwrap_or(0)
+    }
+    /// Where the parameter or result `param` of `func` is declared, as an
+    /// `Option<wiggle::WitxLocation>` expression.
+    pub fn param_location(&self, func: &Id, param: &Id) -> TokenStream {
+        location_tokens(self.sources.func_param(func.as_str(), param.as_str()))
+    }
+    /// Where the member `member` of `struct_name` is declared, as an
+    /// `Option<wiggle::WitxLocation>` expression.
+    pub fn member_location(&self, struct_name: &Id, member: &Id) -> TokenStream {
+        location_tokens(
+            self.sources
+                .struct_member(struct_name.as_str(), member.as_str()),
+        )
+    }
+    /// The named memory type, if generated functions are monomorphized over
+    /// one.
+    pub fn memory_type_name(&self) -> Option<Ident> {
+        self.config.memory.as_ref().map(|m| m.name.clone())
+    }
+    /// The type of memory generated functions are passed a reference to.
+    pub fn memory_type(&self) -> TokenStream {
+        match self.memory_type_name() {
+            Some(name) => quote!(#name),
+            None => quote!(dyn wiggle::GuestMemory),
+        }
+    }
+    /// The struct members configured to be nullable pointers.
+    pub fn nullable_members(&self) -> impl Iterator<Item = &NullableMember> {
+        self.config.nullable.iter().flat_map(|n| n.members.iter())
+    }
+    pub fn is_nullable(&self, struct_name: &Id, member: &Id) -> bool {
+        self.nullable_members()
+            .any(|n| n.struct_name == struct_name.as_str() && n.member == member.as_str())
+    }
+    /// Whether the module `name` is generated, given the `include_modules`
+    /// and `exclude_modules` filters.
+    pub fn module_selected(&self, name: &Id) -> bool {
+        selected(
+            &self.config.include_modules,
+            &self.config.exclude_modules,
+            name,
+        )
+    }
+    /// Whether functions named `name` are generated, given the
+    /// `include_functions` and `exclude_functions` filters.
+    pub fn func_selected(&self, name: &Id) -> bool {
+        selected(
+            &self.config.include_functions,
+            &self.config.exclude_functions,
+            name,
+        )
+    }
+    /// The modules of `doc` which are generated.
+    pub fn modules<'a>(&'a self, doc: &'a witx::Document) -> impl Iterator<Item = Rc<Module>> + 'a {
+        doc.modules().filter(move |m| self.module_selected(&m.name))
+    }
+    /// The functions of `m` which are generated.
+    pub fn funcs<'a>(&'a self, m: &'a Module) -> impl Iterator<Item = Rc<InterfaceFunc>> + 'a {
+        m.funcs().filter(move |f| self.func_selected(&f.name))
+    }
+    /// All names given to the function and module filters, paired with
+    /// whether they name functions.
+    pub fn filtered_names(&self) -> impl Iterator<Item = (&Ident, bool)> {
+        let c = &self.config;
+        let funcs = filter_names(&c.include_functions)
+            .chain(filter_names(&c.exclude_functions))
+            .map(|n| (n, true));
+        let modules = filter_names(&c.include_modules)
+            .chain(filter_names(&c.exclude_modules))
+            .map(|n| (n, false));
+        funcs.chain(modules)
+    }
+    /// The `derive` and `attributes` configured for the type `name`, as
+    /// attributes to place after its generated derives.
+    pub fn type_attributes(&self, name: &Id) -> TokenStream {
+        let applying = |types: Option<&Vec<PerTypeConf>>| {
+            types
+                .into_iter()
+                .flatten()
+                .filter(|t| t.applies_to(name.as_str()))
+                .flat_map(|t| t.items.iter().cloned())
+                .collect::<Vec<_>>()
+        };
+        let derives = applying(self.config.derive.as_ref().map(|d| &d.types));
+        let attrs = applying(self.config.attributes.as_ref().map(|a| &a.types));
+        let derive = if derives.is_empty() {
+            quote!()
+        } else {
+            quote!(#[derive(#(#derives),*)])
+        };
+        quote!(#derive #(#attrs)*)
+    }
+    /// The types named in the `derive` and `attributes` configs.
+    pub fn attributed_types(&self) -> impl Iterator<Item = &Ident> {
+        let derive = self.config.derive.iter().flat_map(|d| &d.types);
+        let attributes = self.config.attributes.iter().flat_map(|a| &a.types);
+        derive.chain(attributes).filter_map(|t| t.typename.as_ref())
+    }
+    /// The Rust type substituted for the witx type `name`, if any.
+    pub fn substitute(&self, name: &Id) -> Option<TokenStream> {
+        self.substitutions()
+            .find(|s| s.typename == name.as_str())
+            .map(|s| s.with.clone())
+    }
+    /// The Rust error type trait methods return for the witx error type
+    /// `name`, if `errors` maps it to one.
+    pub fn user_error(&self, name: &Id) -> Option<TokenStream> {
+        self.user_errors()
+            .find(|s| s.typename == name.as_str())
+            .map(|s| s.with.clone())
+    }
+    pub fn user_errors(&self) -> impl Iterator<Item = &Substitution> {
+        self.config.errors.iter().flat_map(|e| e.types.iter())
+    }
+    /// The error type of trait methods returning errors of type `tref`.
+    pub fn error_type(&self, tref: &TypeRef, lifetime: TokenStream) -> TokenStream {
+        match tref {
+            TypeRef::Name(nt) => self
+                .user_error(&nt.name)
+                .unwrap_or_else(|| self.type_ref(tref, lifetime)),
+            TypeRef::Value(_) => self.type_ref(tref, lifetime),
+        }
+    }
+    pub fn substitutions(&self) -> impl Iterator<Item = &Substitution> {
+        self.config.substitute.iter().flat_map(|s| s.types.iter())
+    }
+    /// The name the witx definition of the substituted type `name` is
+    /// generated under.
+    pub fn substitute_repr(&self, name: &Id) -> Ident {
+        let repr = Id::new(format!("{}_repr", name.as_str()));
+        self.ident(&repr, self.case(|n| n.types), Case::Camel)
+    }
+    /// The substituted type `tref` refers to, possibly through aliases.
+    pub fn substituted_name(&self, tref: &TypeRef) -> Option<Id> {
+        let mut tref = tref;
+        while let TypeRef::Name(nt) = tref {
+            if self.substitute(&nt.name).is_some() {
+                return Some(nt.name.clone());
+            }
+            tref = &nt.tref;
+        }
+        None
+    }
+    /// Whether a substituted type is part of `tref`'s representation, in
+    /// which case it isn't transparent, whatever witx says.
+    pub fn contains_substitute(&self, tref: &TypeRef) -> bool {
+        match tref {
+            TypeRef::Name(nt) => {
+                self.substitute(&nt.name).is_some() || self.contains_substitute(&nt.tref)
+            }
+            TypeRef::Value(ty) => match &**ty {
+                witx::Type::Struct(s) => {
+                    s.members.iter().any(|m| self.contains_substitute(&m.tref))
+                }
+                _ => false,
+            },
+        }
+    }
+    pub fn serde(&self) -> bool {
+        self.config.serde.as_ref().is_some_and(|s| s.enabled)
+    }
+    pub fn proptest(&self) -> bool {
+        self.config.proptest.as_ref().is_some_and(|p| p.enabled)
+    }
+    /// Whether `tref` is plain data, which gets serde and proptest
+    /// implementations when those are enabled: it has no pointers, strings or
+    /// unions, nor substituted types.
+    pub fn is_plain_data(&self, tref: &TypeRef) -> bool {
+        if let TypeRef::Name(nt) = tref {
+            if self.substitute(&nt.name).is_some() {
+                return false;
+            }
+        }
+        match &*tref.type_() {
+            witx::Type::Enum(_)
+            | witx::Type::Int(_)
+            | witx::Type::Flags(_)
+            | witx::Type::Handle(_) => true,
+            witx::Type::Builtin(b) => *b != BuiltinType::String,
+            witx::Type::Struct(s) => s.members.iter().all(|m| self.is_plain_data(&m.tref)),
+            witx::Type::Union(_)
+            | witx::Type::Pointer(_)
+            | witx::Type::ConstPointer(_)
+            | witx::Type::Array(_) => false,
+        }
+    }
+    pub fn is_async(&self) -> bool {
+        self.config.async_.as_ref().is_some_and(|a| a.enabled)
+    }
+    pub fn no_std(&self) -> bool {
+        self.config.no_std.as_ref().is_some_and(|n| n.enabled)
+    }
+    /// The crate generated code names `core` items through: `::core` for
+    /// `no_std` crates, and `::std` otherwise.
+    pub fn core(&self) -> TokenStream {
+        if self.no_std() {
+            quote!(::core)
+        } else {
+            quote!(::std)
+        }
+    }
+    /// Likewise, the crate generated code names `alloc` items through.
+    pub fn alloc(&self) -> TokenStream {
+        if self.no_std() {
+            quote!(::alloc)
+        } else {
+            quote!(::std)
+        }
+    }
+    /// The path of the `types` module generated by another invocation, if
+    /// this one re-uses it.
+    pub fn types_path(&self) -> Option<TokenStream> {
+        self.config.types.as_ref().map(|t| t.path.clone())
+    }
+    pub fn multi_memory(&self) -> bool {
+        self.config.multi_memory.as_ref().is_some_and(|m| m.enabled)
+    }
+    pub fn instrument(&self) -> bool {
+        self.config.instrument.as_ref().is_some_and(|i| i.enabled)
+    }
+    pub fn tracing(&self) -> bool {
+        self.config.tracing.as_ref().is_some_and(|t| t.enabled)
+    }
+    pub fn multi_value(&self) -> bool {
+        self.config.multi_value.as_ref().is_some_and(|m| m.enabled)
+    }
+    pub fn memory64(&self) -> bool {
+        self.config.memory64.as_ref().is_some_and(|m| m.enabled)
+    }
+    /// The core type of guest pointers, lengths and sizes.
+    pub fn pointer_atom(&self) -> AtomType {
+        if self.memory64() {
+            AtomType::I64
+        } else {
+            AtomType::I32
+        }
+    }
+    /// The core type a function takes `arg` as, which for pointers, lengths
+    /// and sizes depends on the width of the guest memory.
+    pub fn core_arg_atom(&self, arg: &witx::CoreParamType) -> AtomType {
+        let pointer_sized = match arg.signifies {
+            witx::CoreParamSignifies::PointerTo | witx::CoreParamSignifies::LengthOf => true,
+            witx::CoreParamSignifies::Value(_) => matches!(
+                &*arg.param.tref.type_(),
+                witx::Type::Pointer(_)
+                    | witx::Type::ConstPointer(_)
+                    | witx::Type::Builtin(BuiltinType::USize)
+            ),
+        };
+        if pointer_sized {
+            self.pointer_atom()
+        } else {
+            arg.repr()
+        }
+    }
+    /// Converts `value`, a core pointer or length argument, to a guest offset.
+    pub fn guest_offset(&self, value: &Ident) -> TokenStream {
+        if self.memory64() {
+            quote!(#value as u64)
+        } else {
+            quote!(#value as u32 as u64)
+        }
+    }
+    /// The `#[doc]` attribute for witx documentation, if there is any.
+    pub fn docs(&self, docs: &str) -> TokenStream {
+        let docs = docs.trim_end();
+        if docs.is_empty() {
+            quote!()
+        } else {
+            quote!(#[doc = #docs])
+        }
     }
     pub fn type_(&self, id: &Id) -> TokenStream {
-        let ident = format_ident!("{}", id.as_str().to_camel_case());
+        let ident = self.ident(id, self.case(|n| n.types), Case::Camel);
         quote!(#ident)
     }
+    /// The view of the struct `id`, reading its members from guest memory.
+    pub fn struct_view(&self, id: &Id) -> TokenStream {
+        let ident = format_ident!("{}View", self.type_(id).to_string());
+        quote!(#ident)
+    }
+    pub fn struct_views(&self) -> bool {
+        self.config.struct_views.as_ref().is_some_and(|s| s.enabled)
+    }
+    /// The view passed to trait methods for parameters of type `tref`, when
+    /// `struct_views` is enabled and `tref` is a struct.
+    pub fn param_view(&self, tref: &TypeRef) -> Option<TokenStream> {
+        if !self.struct_views() || self.contains_substitute(tref) {
+            return None;
+        }
+        let mut nt = match tref {
+            TypeRef::Name(nt) => nt,
+            TypeRef::Value(_) => return None,
+        };
+        while let TypeRef::Name(to) = &nt.tref {
+            nt = to;
+        }
+        match &*nt.type_() {
+            witx::Type::Struct(_) => Some(self.struct_view(&nt.name)),
+            _ => None,
+        }
+    }
     pub fn builtin_type(&self, b: BuiltinType, lifetime: TokenStream) -> TokenStream {
         match b {
-            BuiltinType::String => quote!(wiggle_runtime::GuestPtr<#lifetime, str>),
+            BuiltinType::String => quote!(wiggle::GuestPtr<#lifetime, str>),
             BuiltinType::U8 => quote!(u8),
             BuiltinType::U16 => quote!(u16),
             BuiltinType::U32 => quote!(u32),
@@ -63,31 +444,49 @@ impl Names {
             TypeRef::Value(ty) => match &**ty {
                 witx::Type::Builtin(builtin) => self.builtin_type(*builtin, lifetime.clone()),
                 witx::Type::Pointer(pointee) | witx::Type::ConstPointer(pointee) => {
-                    let pointee_type = self.type_ref(&pointee, lifetime.clone());
-                    quote!(wiggle_runtime::GuestPtr<#lifetime, #pointee_type>)
+                    let pointee_type = self.type_ref(pointee, lifetime.clone());
+                    quote!(wiggle::GuestPtr<#lifetime, #pointee_type>)
+                }
+                // Reported as unsupported by `generate`, but this may be
+                // called for other parts of a document too.
+                _ => {
+                    let span = self.witx_span();
+                    quote_spanned!(span=> compile_error!("anonymous types aren't supported; give it a typename"))
                 }
-                _ => unimplemented!("anonymous type ref"),
             },
         }
     }
 
-    pub fn enum_variant(&self, id: &Id) -> Ident {
+    /// The variant `id` of the enum or union `typename`.
+    pub fn enum_variant(&self, typename: &Id, id: &Id) -> Ident {
+        let case = self.case(|n| n.variants);
+        if self.rename(id).is_some() {
+            return self.ident(id, case, Case::Camel);
+        }
         // FIXME this is a hack - just a proof of concept.
-        if id.as_str().starts_with('2') {
+        let variant = if case.is_none() && id.as_str().starts_with('2') {
             format_ident!("TooBig")
-        } else if id.as_str() == "type" {
-            format_ident!("Type")
         } else {
-            format_ident!("{}", id.as_str().to_camel_case())
+            self.ident(id, case, Case::Camel)
+        };
+        let prefix = self.config.naming.as_ref().and_then(|n| {
+            n.variant_prefixes
+                .iter()
+                .find(|(t, _)| t == typename.as_str())
+                .map(|(_, prefix)| prefix)
+        });
+        match prefix {
+            Some(prefix) => format_ident!("{}{}", prefix, variant),
+            None => variant,
         }
     }
 
     pub fn flag_member(&self, id: &Id) -> Ident {
-        format_ident!("{}", id.as_str().to_shouty_snake_case())
+        self.ident(id, self.case(|n| n.flags), Case::Shouty)
     }
 
     pub fn int_member(&self, id: &Id) -> Ident {
-        format_ident!("{}", id.as_str().to_shouty_snake_case())
+        self.ident(id, self.case(|n| n.flags), Case::Shouty)
     }
 
     pub fn struct_member(&self, id: &Id) -> Ident {
@@ -104,11 +503,49 @@ impl Names {
     }
 
     pub fn trait_name(&self, id: &Id) -> Ident {
-        format_ident!("{}", id.as_str().to_camel_case())
+        self.ident(id, self.case(|n| n.types), Case::Camel)
     }
 
     pub fn func(&self, id: &Id) -> Ident {
-        format_ident!("{}", id.as_str().to_snake_case())
+        self.ident(id, self.case(|n| n.functions), Case::Snake)
+    }
+
+    /// The configured case for a kind of name, picked out of the `naming`
+    /// config by `kind`.
+    fn case(&self, kind: impl Fn(&NamingConf) -> Option<Case>) -> Option<Case> {
+        self.config.naming.as_ref().and_then(kind)
+    }
+
+    /// The identifier the witx name `id` is renamed to, if any.
+    fn rename(&self, id: &Id) -> Option<&Ident> {
+        let naming = self.config.naming.as_ref()?;
+        naming
+            .rename
+            .iter()
+            .find(|(from, _)| from == id.as_str())
+            .map(|(_, to)| to)
+    }
+
+    /// Converts the witx name `id` to an identifier: its rename if it has
+    /// one, and otherwise `id` in `case`, or `default`.
+    fn ident(&self, id: &Id, case: Option<Case>, default: Case) -> Ident {
+        if let Some(renamed) = self.rename(id) {
+            return renamed.clone();
+        }
+        let name = id.as_str();
+        let name = match case.unwrap_or(default) {
+            Case::Preserve => name.to_string(),
+            Case::Camel => name.to_camel_case(),
+            Case::Snake => name.to_snake_case(),
+            Case::Shouty => name.to_shouty_snake_case(),
+        };
+        // witx names can start with digits, and be keywords.
+        let name = if name.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("_{}", name)
+        } else {
+            name
+        };
+        syn::parse_str::<Ident>(&name).unwrap_or_else(|_| Ident::new_raw(&name, Span::call_site()))
     }
 
     pub fn func_param(&self, id: &Id) -> Ident {
@@ -138,3 +575,12 @@ impl Names {
         format_ident!("{}_len", id.as_str().to_snake_case())
     }
 }
+
+fn selected(include: &Option<FilterConf>, exclude: &Option<FilterConf>, name: &Id) -> bool {
+    include.as_ref().map_or(true, |i| i.contains(name.as_str()))
+        && !exclude.as_ref().is_some_and(|e| e.contains(name.as_str()))
+}
+
+fn filter_names(filter: &Option<FilterConf>) -> impl Iterator<Item = &Ident> {
+    filter.iter().flat_map(|f| f.names.iter())
+}
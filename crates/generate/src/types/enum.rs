@@ -1,63 +1,127 @@
-use super::{atom_token, int_repr_tokens};
+use super::{
+    atom_token, define_arbitrary, int_repr_tokens, serde_derive, serde_rename, transparent_cfg,
+};
 use crate::names::Names;
 
 use proc_macro2::TokenStream;
 use quote::quote;
-
-pub(super) fn define_enum(names: &Names, name: &witx::Id, e: &witx::EnumDatatype) -> TokenStream {
-    let ident = names.type_(&name);
+use witx::Layout;
+
+pub(super) fn define_enum(
+    names: &Names,
+    name: &witx::Id,
+    docs: TokenStream,
+    e: &witx::EnumDatatype,
+) -> TokenStream {
+    let ident = names.type_(name);
+    let type_attributes = names.type_attributes(name);
+    let serde_derive = serde_derive(names, false);
+    let core = names.core();
 
     let repr = int_repr_tokens(e.repr);
+    let transparent_cfg = transparent_cfg(e.repr.mem_size_align().size);
     let abi_repr = atom_token(match e.repr {
         witx::IntRepr::U8 | witx::IntRepr::U16 | witx::IntRepr::U32 => witx::AtomType::I32,
         witx::IntRepr::U64 => witx::AtomType::I64,
     });
 
+    let num_variants = e.variants.len();
     let mut variant_names = vec![];
+    let mut variant_renames = vec![];
+    let mut variant_docs = vec![];
     let mut tryfrom_repr_cases = vec![];
     let mut to_repr_cases = vec![];
     let mut to_display = vec![];
+    let mut witx_names = vec![];
 
     for (n, variant) in e.variants.iter().enumerate() {
-        let variant_name = names.enum_variant(&variant.name);
+        let variant_name = names.enum_variant(name, &variant.name);
         let docs = variant.docs.trim();
         let ident_str = ident.to_string();
         let variant_str = variant_name.to_string();
         tryfrom_repr_cases.push(quote!(#n => Ok(#ident::#variant_name)));
         to_repr_cases.push(quote!(#ident::#variant_name => #n as #repr));
-        to_display.push(quote!(#ident::#variant_name => format!("{} ({}::{}({}))", #docs, #ident_str, #variant_str, #repr::from(*self))));
+        to_display.push(quote!(#ident::#variant_name => write!(f, "{} ({}::{}({}))", #docs, #ident_str, #variant_str, #repr::from(*self))));
         variant_names.push(variant_name);
+        variant_renames.push(serde_rename(names, &variant.name));
+        variant_docs.push(names.docs(&variant.docs));
+        witx_names.push(variant.name.as_str());
     }
 
+    let alloc = names.alloc();
+    let arbitrary = define_arbitrary(
+        names,
+        &ident,
+        quote!(::proptest::sample::select(#ident::ALL)),
+    );
+
     quote! {
+        #docs
         #[repr(#repr)]
-        #[derive(Copy, Clone, Debug, ::std::hash::Hash, Eq, PartialEq)]
+        #[derive(Copy, Clone, Debug, #core::hash::Hash, Eq, PartialEq)]
+        #serde_derive
+        #type_attributes
         pub enum #ident {
-            #(#variant_names),*
+            #(#variant_docs #variant_renames #variant_names),*
+        }
+
+        impl #ident {
+            /// Every variant, in the order of their values.
+            pub const ALL: &'static [#ident] = &[#(#ident::#variant_names),*];
+            pub const VARIANT_COUNT: usize = #num_variants;
+
+            /// Iterates over every variant, in the order of their values.
+            pub fn iter() -> impl Iterator<Item = #ident> {
+                #ident::ALL.iter().copied()
+            }
+
+            /// The witx name of the variant.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#ident::#variant_names => #witx_names,)*
+                }
+            }
+
+            /// The variant with the witx name `name`, if there is one.
+            pub fn from_name(name: &str) -> Option<#ident> {
+                match name {
+                    #(#witx_names => Some(#ident::#variant_names),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl #core::str::FromStr for #ident {
+            type Err = wiggle::ParseNameError;
+            fn from_str(s: &str) -> Result<#ident, wiggle::ParseNameError> {
+                #ident::from_name(s).ok_or_else(|| wiggle::ParseNameError {
+                    typename: stringify!(#ident),
+                    name: #alloc::string::String::from(s),
+                })
+            }
         }
 
-        impl ::std::fmt::Display for #ident {
-            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                let to_str = match self {
+        impl #core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut #core::fmt::Formatter<'_>) -> #core::fmt::Result {
+                match self {
                     #(#to_display,)*
-                };
-                write!(f, "{}", to_str)
+                }
             }
         }
 
-        impl ::std::convert::TryFrom<#repr> for #ident {
-            type Error = wiggle_runtime::GuestError;
-            fn try_from(value: #repr) -> Result<#ident, wiggle_runtime::GuestError> {
+        impl #core::convert::TryFrom<#repr> for #ident {
+            type Error = wiggle::GuestError;
+            fn try_from(value: #repr) -> Result<#ident, wiggle::GuestError> {
                 match value as usize {
                     #(#tryfrom_repr_cases),*,
-                    _ => Err(wiggle_runtime::GuestError::InvalidEnumValue(stringify!(#ident))),
+                    _ => Err(wiggle::GuestError::InvalidEnumValue(stringify!(#ident))),
                 }
             }
         }
 
-        impl ::std::convert::TryFrom<#abi_repr> for #ident {
-            type Error = wiggle_runtime::GuestError;
-            fn try_from(value: #abi_repr) -> Result<#ident, wiggle_runtime::GuestError> {
+        impl #core::convert::TryFrom<#abi_repr> for #ident {
+            type Error = wiggle::GuestError;
+            fn try_from(value: #abi_repr) -> Result<#ident, wiggle::GuestError> {
                 #ident::try_from(value as #repr)
             }
         }
@@ -76,7 +140,7 @@ pub(super) fn define_enum(names: &Names, name: &witx::Id, e: &witx::EnumDatatype
             }
         }
 
-        impl<'a> wiggle_runtime::GuestType<'a> for #ident {
+        impl<'a> wiggle::GuestType<'a> for #ident {
             fn guest_size() -> u32 {
                 #repr::guest_size()
             }
@@ -85,29 +149,60 @@ pub(super) fn define_enum(names: &Names, name: &witx::Id, e: &witx::EnumDatatype
                 #repr::guest_align()
             }
 
-            fn read(location: &wiggle_runtime::GuestPtr<#ident>) -> Result<#ident, wiggle_runtime::GuestError> {
+            fn read<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, #ident, M>) -> Result<#ident, wiggle::GuestError> {
                 use std::convert::TryFrom;
                 let reprval = #repr::read(&location.cast())?;
                 let value = #ident::try_from(reprval)?;
                 Ok(value)
             }
 
-            fn write(location: &wiggle_runtime::GuestPtr<'_, #ident>, val: Self)
-                -> Result<(), wiggle_runtime::GuestError>
+            fn write<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, #ident, M>, val: Self)
+                -> Result<(), wiggle::GuestError>
             {
                 #repr::write(&location.cast(), #repr::from(val))
             }
+
+            #transparent_cfg
+
+            #[inline]
+            fn read_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>) -> Result<Vec<Self>, wiggle::GuestError> {
+                wiggle::read_transparent_slice(location)
+            }
+
+            #transparent_cfg
+
+            #[inline]
+            fn write_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>, vals: &[Self]) -> Result<(), wiggle::GuestError> {
+                wiggle::write_transparent_slice(location, vals)
+            }
         }
 
-        unsafe impl <'a> wiggle_runtime::GuestTypeTransparent<'a> for #ident {
+        #transparent_cfg
+
+        unsafe impl <'a> wiggle::GuestTypeTransparent<'a> for #ident {
             #[inline]
-            fn validate(location: *mut #ident) -> Result<(), wiggle_runtime::GuestError> {
+            fn validate(location: *mut #ident) -> Result<(), wiggle::GuestError> {
                 use std::convert::TryFrom;
                 // Validate value in memory using #ident::try_from(reprval)
                 let reprval = unsafe { (location as *mut #repr).read() };
                 let _val = #ident::try_from(reprval)?;
                 Ok(())
             }
+
+            #[inline]
+            fn validate_slice(location: *mut #ident, len: u64) -> Result<(), wiggle::GuestError> {
+                // Variants are numbered contiguously from 0, so the whole
+                // array is valid iff its largest value is a valid variant.
+                let reprvals = unsafe { #core::slice::from_raw_parts(location as *const #repr, len as usize) };
+                match reprvals.iter().max() {
+                    Some(max) if *max as usize >= #num_variants => {
+                        Err(wiggle::GuestError::InvalidEnumValue(stringify!(#ident)))
+                    }
+                    _ => Ok(()),
+                }
+            }
         }
+
+        #arbitrary
     }
 }
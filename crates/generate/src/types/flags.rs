@@ -1,36 +1,69 @@
-use super::{atom_token, int_repr_tokens};
+use super::{atom_token, define_arbitrary, int_repr_tokens, transparent_cfg};
 use crate::names::Names;
 
 use proc_macro2::{Literal, TokenStream};
 use quote::quote;
 use std::convert::TryFrom;
+use witx::Layout;
 
-pub(super) fn define_flags(names: &Names, name: &witx::Id, f: &witx::FlagsDatatype) -> TokenStream {
-    let ident = names.type_(&name);
+pub(super) fn define_flags(
+    names: &Names,
+    name: &witx::Id,
+    docs: TokenStream,
+    f: &witx::FlagsDatatype,
+) -> TokenStream {
+    let ident = names.type_(name);
+    let type_attributes = names.type_attributes(name);
+    let core = names.core();
     let repr = int_repr_tokens(f.repr);
+    let transparent_cfg = transparent_cfg(f.repr.mem_size_align().size);
     let abi_repr = atom_token(match f.repr {
         witx::IntRepr::U8 | witx::IntRepr::U16 | witx::IntRepr::U32 => witx::AtomType::I32,
         witx::IntRepr::U64 => witx::AtomType::I64,
     });
 
     let mut flag_constructors = vec![];
+    let mut flag_names = vec![];
+    let mut flag_parses = vec![];
     let mut all_values = 0;
     for (i, f) in f.flags.iter().enumerate() {
         let name = names.flag_member(&f.name);
+        let name_str = name.to_string();
+        flag_names.push(quote!((#name_str, #ident::#name)));
+        let witx_str = f.name.as_str();
+        if witx_str == name_str {
+            flag_parses.push(quote!(#name_str => #ident::#name));
+        } else {
+            flag_parses.push(quote!(#witx_str | #name_str => #ident::#name));
+        }
         let value = 1u128
             .checked_shl(u32::try_from(i).expect("flag value overflow"))
             .expect("flag value overflow");
         let value_token = Literal::u128_unsuffixed(value);
-        flag_constructors.push(quote!(pub const #name: #ident = #ident(#value_token)));
+        let flag_docs = names.docs(&f.docs);
+        flag_constructors.push(quote!(#flag_docs pub const #name: #ident = #ident(#value_token)));
         all_values += value;
     }
     let all_values_token = Literal::u128_unsuffixed(all_values);
 
-    let ident_str = ident.to_string();
+    let serde = if names.serde() {
+        define_flags_serde(names, name, f)
+    } else {
+        quote!()
+    };
+
+    let alloc = names.alloc();
+    let arbitrary = define_arbitrary(
+        names,
+        &ident,
+        quote!(::proptest::num::#repr::ANY.prop_map(|v| #ident(v & #ident::ALL_FLAGS.0))),
+    );
 
     quote! {
+        #docs
         #[repr(transparent)]
-        #[derive(Copy, Clone, Debug, ::std::hash::Hash, Eq, PartialEq)]
+        #[derive(Copy, Clone, Debug, #core::hash::Hash, Eq, PartialEq)]
+        #type_attributes
         pub struct #ident(#repr);
 
         impl #ident {
@@ -41,74 +74,157 @@ pub(super) fn define_flags(names: &Names, name: &witx::Id, f: &witx::FlagsDataty
             pub fn contains(&self, other: &#ident) -> bool {
                 !*self & *other == Self::EMPTY_FLAGS
             }
+
+            /// Whether any flag of `other` is set in `self`.
+            pub fn intersects(&self, other: &#ident) -> bool {
+                *self & *other != Self::EMPTY_FLAGS
+            }
+
+            /// Sets the flags of `other`.
+            pub fn insert(&mut self, other: #ident) {
+                *self |= other;
+            }
+
+            /// Clears the flags of `other`.
+            pub fn remove(&mut self, other: #ident) {
+                *self &= !other;
+            }
+
+            /// Flips the flags of `other`.
+            pub fn toggle(&mut self, other: #ident) {
+                *self ^= other;
+            }
+
+            pub const fn bits(&self) -> #repr {
+                self.0
+            }
+
+            /// The flags with the value `bits`, if it has no bits set which
+            /// aren't flags. Like `TryFrom`, but without an error.
+            pub fn from_bits(bits: #repr) -> Option<#ident> {
+                if bits & !Self::ALL_FLAGS.0 == 0 {
+                    Some(#ident(bits))
+                } else {
+                    None
+                }
+            }
+
+            /// The name and value of every flag, in the order of their bits.
+            pub const fn flag_names() -> &'static [(&'static str, #ident)] {
+                &[#(#flag_names),*]
+            }
+
+            /// The name and value of every flag set in `self`.
+            pub fn iter(&self) -> impl Iterator<Item = (&'static str, #ident)> {
+                let flags = *self;
+                Self::flag_names()
+                    .iter()
+                    .copied()
+                    .filter(move |(_, flag)| flags.contains(flag))
+            }
+        }
+
+        /// Prints the names of the flags set, like `READ|WRITE`, followed by
+        /// any bits set which aren't flags in hex, or `EMPTY_FLAGS`.
+        impl #core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut #core::fmt::Formatter<'_>) -> #core::fmt::Result {
+                let mut sep = "";
+                for (name, _) in self.iter() {
+                    write!(f, "{}{}", sep, name)?;
+                    sep = "|";
+                }
+                let unknown = self.0 & !#ident::ALL_FLAGS.0;
+                if unknown != 0 {
+                    write!(f, "{}{:#x}", sep, unknown)?;
+                } else if sep.is_empty() {
+                    f.write_str("EMPTY_FLAGS")?;
+                }
+                Ok(())
+            }
         }
 
-        impl ::std::fmt::Display for #ident {
-            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                write!(f, "{}({:#b})", #ident_str, self.0)
+        /// Parses the witx or Rust names of flags separated by `|` or `,`,
+        /// like `fd_read|fd_write`, or the output of `Display`.
+        impl #core::str::FromStr for #ident {
+            type Err = wiggle::ParseNameError;
+            fn from_str(s: &str) -> Result<#ident, wiggle::ParseNameError> {
+                let mut flags = #ident::EMPTY_FLAGS;
+                for name in s.split(&['|', ','][..]) {
+                    flags |= match name.trim() {
+                        "" | "EMPTY_FLAGS" => #ident::EMPTY_FLAGS,
+                        #(#flag_parses,)*
+                        name => {
+                            return Err(wiggle::ParseNameError {
+                                typename: stringify!(#ident),
+                                name: #alloc::string::String::from(name),
+                            })
+                        }
+                    };
+                }
+                Ok(flags)
             }
         }
 
-        impl ::std::ops::BitAnd for #ident {
+        impl #core::ops::BitAnd for #ident {
             type Output = Self;
             fn bitand(self, rhs: Self) -> Self::Output {
                 #ident(self.0 & rhs.0)
             }
         }
 
-        impl ::std::ops::BitAndAssign for #ident {
+        impl #core::ops::BitAndAssign for #ident {
             fn bitand_assign(&mut self, rhs: Self) {
                 *self = *self & rhs
             }
         }
 
-        impl ::std::ops::BitOr for #ident {
+        impl #core::ops::BitOr for #ident {
             type Output = Self;
             fn bitor(self, rhs: Self) -> Self::Output {
                 #ident(self.0 | rhs.0)
             }
         }
 
-        impl ::std::ops::BitOrAssign for #ident {
+        impl #core::ops::BitOrAssign for #ident {
             fn bitor_assign(&mut self, rhs: Self) {
                 *self = *self | rhs
             }
         }
 
-        impl ::std::ops::BitXor for #ident {
+        impl #core::ops::BitXor for #ident {
             type Output = Self;
             fn bitxor(self, rhs: Self) -> Self::Output {
                 #ident(self.0 ^ rhs.0)
             }
         }
 
-        impl ::std::ops::BitXorAssign for #ident {
+        impl #core::ops::BitXorAssign for #ident {
             fn bitxor_assign(&mut self, rhs: Self) {
                 *self = *self ^ rhs
             }
         }
 
-        impl ::std::ops::Not for #ident {
+        impl #core::ops::Not for #ident {
             type Output = Self;
             fn not(self) -> Self::Output {
                 #ident(!self.0)
             }
         }
 
-        impl ::std::convert::TryFrom<#repr> for #ident {
-            type Error = wiggle_runtime::GuestError;
-            fn try_from(value: #repr) -> Result<Self, wiggle_runtime::GuestError> {
+        impl #core::convert::TryFrom<#repr> for #ident {
+            type Error = wiggle::GuestError;
+            fn try_from(value: #repr) -> Result<Self, wiggle::GuestError> {
                 if #repr::from(!#ident::ALL_FLAGS) & value != 0 {
-                    Err(wiggle_runtime::GuestError::InvalidFlagValue(stringify!(#ident)))
+                    Err(wiggle::GuestError::InvalidFlagValue(stringify!(#ident)))
                 } else {
                     Ok(#ident(value))
                 }
             }
         }
 
-        impl ::std::convert::TryFrom<#abi_repr> for #ident {
-            type Error = wiggle_runtime::GuestError;
-            fn try_from(value: #abi_repr) -> Result<#ident, wiggle_runtime::GuestError> {
+        impl #core::convert::TryFrom<#abi_repr> for #ident {
+            type Error = wiggle::GuestError;
+            fn try_from(value: #abi_repr) -> Result<#ident, wiggle::GuestError> {
                 #ident::try_from(value as #repr)
             }
         }
@@ -125,7 +241,7 @@ pub(super) fn define_flags(names: &Names, name: &witx::Id, f: &witx::FlagsDataty
             }
         }
 
-        impl<'a> wiggle_runtime::GuestType<'a> for #ident {
+        impl<'a> wiggle::GuestType<'a> for #ident {
             fn guest_size() -> u32 {
                 #repr::guest_size()
             }
@@ -134,28 +250,119 @@ pub(super) fn define_flags(names: &Names, name: &witx::Id, f: &witx::FlagsDataty
                 #repr::guest_align()
             }
 
-            fn read(location: &wiggle_runtime::GuestPtr<#ident>) -> Result<#ident, wiggle_runtime::GuestError> {
+            fn read<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, #ident, M>) -> Result<#ident, wiggle::GuestError> {
                 use std::convert::TryFrom;
                 let reprval = #repr::read(&location.cast())?;
                 let value = #ident::try_from(reprval)?;
                 Ok(value)
             }
 
-            fn write(location: &wiggle_runtime::GuestPtr<'_, #ident>, val: Self) -> Result<(), wiggle_runtime::GuestError> {
+            fn write<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, #ident, M>, val: Self) -> Result<(), wiggle::GuestError> {
                 let val: #repr = #repr::from(val);
                 #repr::write(&location.cast(), val)
             }
+
+            #transparent_cfg
+
+            #[inline]
+            fn read_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>) -> Result<Vec<Self>, wiggle::GuestError> {
+                wiggle::read_transparent_slice(location)
+            }
+
+            #transparent_cfg
+
+            #[inline]
+            fn write_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>, vals: &[Self]) -> Result<(), wiggle::GuestError> {
+                wiggle::write_transparent_slice(location, vals)
+            }
         }
-        unsafe impl <'a> wiggle_runtime::GuestTypeTransparent<'a> for #ident {
+        #transparent_cfg
+        unsafe impl <'a> wiggle::GuestTypeTransparent<'a> for #ident {
             #[inline]
-            fn validate(location: *mut #ident) -> Result<(), wiggle_runtime::GuestError> {
+            fn validate(location: *mut #ident) -> Result<(), wiggle::GuestError> {
                 use std::convert::TryFrom;
                 // Validate value in memory using #ident::try_from(reprval)
                 let reprval = unsafe { (location as *mut #repr).read() };
                 let _val = #ident::try_from(reprval)?;
                 Ok(())
             }
+
+            #[inline]
+            fn validate_slice(location: *mut #ident, len: u64) -> Result<(), wiggle::GuestError> {
+                use std::convert::TryFrom;
+                // Fold every value together first: the array is valid iff no
+                // value has a bit set outside of `ALL_FLAGS`.
+                let reprvals = unsafe { #core::slice::from_raw_parts(location as *const #repr, len as usize) };
+                let allbits = reprvals.iter().fold(0 as #repr, |acc, v| acc | *v);
+                let _val = #ident::try_from(allbits)?;
+                Ok(())
+            }
+        }
+
+        #serde
+        #arbitrary
+    }
+}
+
+/// Represents flags in serde by the list of the witx names of the flags set.
+fn define_flags_serde(names: &Names, name: &witx::Id, f: &witx::FlagsDatatype) -> TokenStream {
+    let ident = names.type_(name);
+    let core = names.core();
+    let alloc = names.alloc();
+    let flags = f
+        .flags
+        .iter()
+        .map(|f| names.flag_member(&f.name))
+        .collect::<Vec<_>>();
+    let flag_strs = f.flags.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+    let expecting = format!("a list of {} flags", name.as_str());
+
+    quote! {
+        impl wiggle::serde::Serialize for #ident {
+            fn serialize<S: wiggle::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use wiggle::serde::ser::SerializeSeq;
+                let set = [#((#ident::#flags, #flag_strs)),*];
+                let len = set.iter().filter(|(flag, _)| self.contains(flag)).count();
+                let mut seq = serializer.serialize_seq(Some(len))?;
+                for (flag, name) in set.iter() {
+                    if self.contains(flag) {
+                        seq.serialize_element(name)?;
+                    }
+                }
+                seq.end()
+            }
         }
 
+        impl<'de> wiggle::serde::Deserialize<'de> for #ident {
+            fn deserialize<D: wiggle::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor;
+
+                impl<'de> wiggle::serde::de::Visitor<'de> for Visitor {
+                    type Value = #ident;
+
+                    fn expecting(&self, f: &mut #core::fmt::Formatter<'_>) -> #core::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_seq<A: wiggle::serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<#ident, A::Error> {
+                        let mut flags = #ident::EMPTY_FLAGS;
+                        while let Some(name) = seq.next_element::<#alloc::string::String>()? {
+                            flags |= match name.as_str() {
+                                #(#flag_strs => #ident::#flags,)*
+                                _ => {
+                                    return Err(wiggle::serde::de::Error::unknown_variant(
+                                        &name,
+                                        &[#(#flag_strs),*],
+                                    ))
+                                }
+                            };
+                        }
+                        Ok(flags)
+                    }
+                }
+
+                deserializer.deserialize_seq(Visitor)
+            }
+        }
     }
 }
@@ -1,3 +1,4 @@
+use super::{define_arbitrary, serde_derive, transparent_cfg};
 use crate::names::Names;
 
 use proc_macro2::TokenStream;
@@ -7,16 +8,36 @@ use witx::Layout;
 pub(super) fn define_handle(
     names: &Names,
     name: &witx::Id,
+    docs: TokenStream,
     h: &witx::HandleDatatype,
 ) -> TokenStream {
     let ident = names.type_(name);
+    let type_attributes = names.type_attributes(name);
+    let serde_derive = serde_derive(names, true);
+    let core = names.core();
     let size = h.mem_size_align().size as u32;
-    let align = h.mem_size_align().align as usize;
+    let align = h.mem_size_align().align;
+    let transparent_cfg = transparent_cfg(size as usize);
+    let arbitrary = define_arbitrary(
+        names,
+        &ident,
+        quote!(::proptest::num::u32::ANY.prop_map(#ident)),
+    );
     quote! {
+        #docs
         #[repr(transparent)]
-        #[derive(Copy, Clone, Debug, ::std::hash::Hash, Eq, PartialEq)]
+        #[derive(Copy, Clone, Debug, #core::hash::Hash, Eq, PartialEq)]
+        #serde_derive
+        #type_attributes
         pub struct #ident(u32);
 
+        impl #ident {
+            /// The size of the handle in guest memory.
+            pub const GUEST_SIZE: u32 = #size;
+            /// The alignment of the handle in guest memory.
+            pub const GUEST_ALIGN: usize = #align;
+        }
+
         impl From<#ident> for u32 {
             fn from(e: #ident) -> u32 {
                 e.0
@@ -40,13 +61,13 @@ pub(super) fn define_handle(
             }
         }
 
-        impl ::std::fmt::Display for #ident {
-            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        impl #core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut #core::fmt::Formatter<'_>) -> #core::fmt::Result {
                 write!(f, "{}({})", stringify!(#ident), self.0)
             }
         }
 
-        impl<'a> wiggle_runtime::GuestType<'a> for #ident {
+        impl<'a> wiggle::GuestType<'a> for #ident {
             fn guest_size() -> u32 {
                 #size
             }
@@ -55,23 +76,45 @@ pub(super) fn define_handle(
                 #align
             }
 
-            fn read(location: &wiggle_runtime::GuestPtr<'a, #ident>) -> Result<#ident, wiggle_runtime::GuestError> {
+            fn read<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, #ident, M>) -> Result<#ident, wiggle::GuestError> {
                 Ok(#ident(u32::read(&location.cast())?))
             }
 
-            fn write(location: &wiggle_runtime::GuestPtr<'_, Self>, val: Self) -> Result<(), wiggle_runtime::GuestError> {
+            fn write<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, Self, M>, val: Self) -> Result<(), wiggle::GuestError> {
                 u32::write(&location.cast(), val.0)
             }
+
+            #transparent_cfg
+
+            #[inline]
+            fn read_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>) -> Result<Vec<Self>, wiggle::GuestError> {
+                wiggle::read_transparent_slice(location)
+            }
+
+            #transparent_cfg
+
+            #[inline]
+            fn write_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>, vals: &[Self]) -> Result<(), wiggle::GuestError> {
+                wiggle::write_transparent_slice(location, vals)
+            }
         }
 
-        unsafe impl<'a> wiggle_runtime::GuestTypeTransparent<'a> for #ident {
+        #transparent_cfg
+
+        unsafe impl<'a> wiggle::GuestTypeTransparent<'a> for #ident {
             #[inline]
-            fn validate(_location: *mut #ident) -> Result<(), wiggle_runtime::GuestError> {
+            fn validate(_location: *mut #ident) -> Result<(), wiggle::GuestError> {
                 // All bit patterns accepted
                 Ok(())
             }
-        }
 
+            #[inline]
+            fn validate_slice(_location: *mut #ident, _len: u64) -> Result<(), wiggle::GuestError> {
+                // All bit patterns accepted
+                Ok(())
+            }
+        }
 
+        #arbitrary
     }
 }
@@ -1,12 +1,22 @@
-use super::{atom_token, int_repr_tokens};
+use super::{atom_token, define_arbitrary, int_repr_tokens, serde_derive, transparent_cfg};
 use crate::names::Names;
 
 use proc_macro2::TokenStream;
 use quote::quote;
-
-pub(super) fn define_int(names: &Names, name: &witx::Id, i: &witx::IntDatatype) -> TokenStream {
-    let ident = names.type_(&name);
+use witx::Layout;
+
+pub(super) fn define_int(
+    names: &Names,
+    name: &witx::Id,
+    docs: TokenStream,
+    i: &witx::IntDatatype,
+) -> TokenStream {
+    let ident = names.type_(name);
+    let type_attributes = names.type_attributes(name);
+    let serde_derive = serde_derive(names, true);
+    let core = names.core();
     let repr = int_repr_tokens(i.repr);
+    let transparent_cfg = transparent_cfg(i.repr.mem_size_align().size);
     let abi_repr = atom_token(match i.repr {
         witx::IntRepr::U8 | witx::IntRepr::U16 | witx::IntRepr::U32 => witx::AtomType::I32,
         witx::IntRepr::U64 => witx::AtomType::I64,
@@ -17,35 +27,45 @@ pub(super) fn define_int(names: &Names, name: &witx::Id, i: &witx::IntDatatype)
         .map(|r#const| {
             let const_ident = names.int_member(&r#const.name);
             let value = r#const.value;
-            quote!(pub const #const_ident: #ident = #ident(#value))
+            let const_docs = names.docs(&r#const.docs);
+            quote!(#const_docs pub const #const_ident: #ident = #ident(#value))
         })
         .collect::<Vec<_>>();
 
+    let arbitrary = define_arbitrary(
+        names,
+        &ident,
+        quote!(::proptest::num::#repr::ANY.prop_map(#ident)),
+    );
+
     quote! {
+        #docs
         #[repr(transparent)]
-        #[derive(Copy, Clone, Debug, ::std::hash::Hash, Eq, PartialEq)]
+        #[derive(Copy, Clone, Debug, #core::hash::Hash, Eq, PartialEq)]
+        #serde_derive
+        #type_attributes
         pub struct #ident(#repr);
 
         impl #ident {
             #(#consts;)*
         }
 
-        impl ::std::fmt::Display for #ident {
-            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        impl #core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut #core::fmt::Formatter<'_>) -> #core::fmt::Result {
                 write!(f, "{:?}", self)
             }
         }
 
-        impl ::std::convert::TryFrom<#repr> for #ident {
-            type Error = wiggle_runtime::GuestError;
-            fn try_from(value: #repr) -> Result<Self, wiggle_runtime::GuestError> {
+        impl #core::convert::TryFrom<#repr> for #ident {
+            type Error = wiggle::GuestError;
+            fn try_from(value: #repr) -> Result<Self, wiggle::GuestError> {
                 Ok(#ident(value))
             }
         }
 
-        impl ::std::convert::TryFrom<#abi_repr> for #ident {
-            type Error = wiggle_runtime::GuestError;
-            fn try_from(value: #abi_repr) -> Result<#ident, wiggle_runtime::GuestError> {
+        impl #core::convert::TryFrom<#abi_repr> for #ident {
+            type Error = wiggle::GuestError;
+            fn try_from(value: #abi_repr) -> Result<#ident, wiggle::GuestError> {
                 #ident::try_from(value as #repr)
             }
         }
@@ -62,7 +82,7 @@ pub(super) fn define_int(names: &Names, name: &witx::Id, i: &witx::IntDatatype)
             }
         }
 
-        impl<'a> wiggle_runtime::GuestType<'a> for #ident {
+        impl<'a> wiggle::GuestType<'a> for #ident {
             fn guest_size() -> u32 {
                 #repr::guest_size()
             }
@@ -71,23 +91,46 @@ pub(super) fn define_int(names: &Names, name: &witx::Id, i: &witx::IntDatatype)
                 #repr::guest_align()
             }
 
-            fn read(location: &wiggle_runtime::GuestPtr<'a, #ident>) -> Result<#ident, wiggle_runtime::GuestError> {
+            fn read<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, #ident, M>) -> Result<#ident, wiggle::GuestError> {
                 Ok(#ident(#repr::read(&location.cast())?))
 
             }
 
-            fn write(location: &wiggle_runtime::GuestPtr<'_, #ident>, val: Self) -> Result<(), wiggle_runtime::GuestError> {
+            fn write<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, #ident, M>, val: Self) -> Result<(), wiggle::GuestError> {
                 #repr::write(&location.cast(), val.0)
             }
+
+            #transparent_cfg
+
+            #[inline]
+            fn read_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>) -> Result<Vec<Self>, wiggle::GuestError> {
+                wiggle::read_transparent_slice(location)
+            }
+
+            #transparent_cfg
+
+            #[inline]
+            fn write_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>, vals: &[Self]) -> Result<(), wiggle::GuestError> {
+                wiggle::write_transparent_slice(location, vals)
+            }
         }
 
-        unsafe impl<'a> wiggle_runtime::GuestTypeTransparent<'a> for #ident {
+        #transparent_cfg
+
+        unsafe impl<'a> wiggle::GuestTypeTransparent<'a> for #ident {
+            #[inline]
+            fn validate(_location: *mut #ident) -> Result<(), wiggle::GuestError> {
+                // All bit patterns accepted
+                Ok(())
+            }
+
             #[inline]
-            fn validate(_location: *mut #ident) -> Result<(), wiggle_runtime::GuestError> {
+            fn validate_slice(_location: *mut #ident, _len: u64) -> Result<(), wiggle::GuestError> {
                 // All bit patterns accepted
                 Ok(())
             }
         }
 
+        #arbitrary
     }
 }
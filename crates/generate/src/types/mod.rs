@@ -7,68 +7,136 @@ mod union;
 
 use crate::lifetimes::LifetimeExt;
 use crate::names::Names;
+use crate::unsupported;
 
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
 
 pub fn define_datatype(names: &Names, namedtype: &witx::NamedType) -> TokenStream {
+    // As in `define_func`, for types defined on their own.
+    let unsupported = unsupported::unsupported_type(namedtype);
+    if !unsupported.is_empty() {
+        let span = names.witx_span();
+        return quote_spanned!(span=> #(compile_error!(#unsupported);)*);
+    }
+    let docs = names.docs(&namedtype.docs);
     match &namedtype.tref {
-        witx::TypeRef::Name(alias_to) => define_alias(names, &namedtype.name, &alias_to),
+        witx::TypeRef::Name(alias_to) => define_alias(names, &namedtype.name, docs, alias_to),
         witx::TypeRef::Value(v) => match &**v {
-            witx::Type::Enum(e) => r#enum::define_enum(names, &namedtype.name, &e),
-            witx::Type::Int(i) => int::define_int(names, &namedtype.name, &i),
-            witx::Type::Flags(f) => flags::define_flags(names, &namedtype.name, &f),
-            witx::Type::Struct(s) => r#struct::define_struct(names, &namedtype.name, &s),
-            witx::Type::Union(u) => union::define_union(names, &namedtype.name, &u),
-            witx::Type::Handle(h) => handle::define_handle(names, &namedtype.name, &h),
-            witx::Type::Builtin(b) => define_builtin(names, &namedtype.name, *b),
+            witx::Type::Enum(e) => r#enum::define_enum(names, &namedtype.name, docs, e),
+            witx::Type::Int(i) => int::define_int(names, &namedtype.name, docs, i),
+            witx::Type::Flags(f) => flags::define_flags(names, &namedtype.name, docs, f),
+            witx::Type::Struct(s) => r#struct::define_struct(names, &namedtype.name, docs, s),
+            witx::Type::Union(u) => union::define_union(names, &namedtype.name, docs, u),
+            witx::Type::Handle(h) => handle::define_handle(names, &namedtype.name, docs, h),
+            witx::Type::Builtin(b) => define_builtin(names, &namedtype.name, docs, *b),
             witx::Type::Pointer(p) => {
-                define_witx_pointer(names, &namedtype.name, quote!(wiggle_runtime::GuestPtr), p)
+                define_witx_pointer(names, &namedtype.name, docs, quote!(wiggle::GuestPtr), p)
             }
             witx::Type::ConstPointer(p) => {
-                define_witx_pointer(names, &namedtype.name, quote!(wiggle_runtime::GuestPtr), p)
+                define_witx_pointer(names, &namedtype.name, docs, quote!(wiggle::GuestPtr), p)
             }
-            witx::Type::Array(arr) => define_witx_array(names, &namedtype.name, &arr),
+            witx::Type::Array(arr) => define_witx_array(names, &namedtype.name, docs, arr),
         },
     }
 }
 
-fn define_alias(names: &Names, name: &witx::Id, to: &witx::NamedType) -> TokenStream {
+/// Defines the witx type `namedtype` under its `Repr` name, makes its own
+/// name an alias of the Rust type `with` substituted for it, and implements
+/// `GuestType` for `with` through the `Repr`.
+pub fn define_substitute(
+    names: &Names,
+    namedtype: &witx::NamedType,
+    with: TokenStream,
+) -> TokenStream {
+    let ident = names.type_(&namedtype.name);
+    let repr = names.substitute_repr(&namedtype.name);
+    let repr_type = witx::NamedType {
+        name: witx::Id::new(format!("{}_repr", namedtype.name.as_str())),
+        tref: namedtype.tref.clone(),
+        docs: namedtype.docs.clone(),
+    };
+    let repr_def = define_datatype(names, &repr_type);
+    let docs = names.docs(&namedtype.docs);
+    quote! {
+        #repr_def
+
+        #docs
+        pub type #ident = #with;
+
+        impl<'a> wiggle::GuestType<'a> for #with {
+            fn guest_size() -> u32 {
+                <#repr as wiggle::GuestType>::guest_size()
+            }
+
+            fn guest_align() -> usize {
+                <#repr as wiggle::GuestType>::guest_align()
+            }
+
+            fn read<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, Self, M>) -> Result<Self, wiggle::GuestError> {
+                let repr = <#repr as wiggle::GuestType>::read(&location.cast())?;
+                Ok(<#with>::from(repr))
+            }
+
+            fn write<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, Self, M>, val: Self) -> Result<(), wiggle::GuestError> {
+                <#repr as wiggle::GuestType>::write(&location.cast(), #repr::from(val))
+            }
+        }
+    }
+}
+
+fn define_alias(
+    names: &Names,
+    name: &witx::Id,
+    docs: TokenStream,
+    to: &witx::NamedType,
+) -> TokenStream {
     let ident = names.type_(name);
     let rhs = names.type_(&to.name);
     if to.tref.needs_lifetime() {
-        quote!(pub type #ident<'a> = #rhs<'a>;)
+        quote!(#docs pub type #ident<'a> = #rhs<'a>;)
     } else {
-        quote!(pub type #ident = #rhs;)
+        quote!(#docs pub type #ident = #rhs;)
     }
 }
 
-fn define_builtin(names: &Names, name: &witx::Id, builtin: witx::BuiltinType) -> TokenStream {
+fn define_builtin(
+    names: &Names,
+    name: &witx::Id,
+    docs: TokenStream,
+    builtin: witx::BuiltinType,
+) -> TokenStream {
     let ident = names.type_(name);
     let built = names.builtin_type(builtin, quote!('a));
     if builtin.needs_lifetime() {
-        quote!(pub type #ident<'a> = #built;)
+        quote!(#docs pub type #ident<'a> = #built;)
     } else {
-        quote!(pub type #ident = #built;)
+        quote!(#docs pub type #ident = #built;)
     }
 }
 
 fn define_witx_pointer(
     names: &Names,
     name: &witx::Id,
+    docs: TokenStream,
     pointer_type: TokenStream,
     pointee: &witx::TypeRef,
 ) -> TokenStream {
     let ident = names.type_(name);
     let pointee_type = names.type_ref(pointee, quote!('a));
 
-    quote!(pub type #ident<'a> = #pointer_type<'a, #pointee_type>;)
+    quote!(#docs pub type #ident<'a> = #pointer_type<'a, #pointee_type>;)
 }
 
-fn define_witx_array(names: &Names, name: &witx::Id, arr_raw: &witx::TypeRef) -> TokenStream {
+fn define_witx_array(
+    names: &Names,
+    name: &witx::Id,
+    docs: TokenStream,
+    arr_raw: &witx::TypeRef,
+) -> TokenStream {
     let ident = names.type_(name);
     let pointee_type = names.type_ref(arr_raw, quote!('a));
-    quote!(pub type #ident<'a> = wiggle_runtime::GuestPtr<'a, [#pointee_type]>;)
+    quote!(#docs pub type #ident<'a> = wiggle::GuestPtr<'a, [#pointee_type]>;)
 }
 
 fn int_repr_tokens(int_repr: witx::IntRepr) -> TokenStream {
@@ -80,6 +148,65 @@ fn int_repr_tokens(int_repr: witx::IntRepr) -> TokenStream {
     }
 }
 
+/// Guest memory is little-endian, so types wider than a byte only have the
+/// same representation in it as on the host, and can only be transparent, on
+/// little-endian hosts.
+fn transparent_cfg(size: usize) -> TokenStream {
+    if size > 1 {
+        quote!(#[cfg(target_endian = "little")])
+    } else {
+        quote!()
+    }
+}
+
+/// The serde derives of a type, when `serde` is enabled. A `transparent` type
+/// is represented by its only field.
+fn serde_derive(names: &Names, transparent: bool) -> TokenStream {
+    if !names.serde() {
+        return quote!();
+    }
+    let transparent = if transparent {
+        quote!(#[serde(transparent)])
+    } else {
+        quote!()
+    };
+    quote! {
+        #[derive(wiggle::serde::Serialize, wiggle::serde::Deserialize)]
+        #[serde(crate = "wiggle::serde")]
+        #transparent
+    }
+}
+
+/// Represents an enum variant or struct member by its witx name, when `serde`
+/// is enabled.
+fn serde_rename(names: &Names, id: &witx::Id) -> TokenStream {
+    if names.serde() {
+        let name = id.as_str();
+        quote!(#[serde(rename = #name)])
+    } else {
+        quote!()
+    }
+}
+
+/// Implements proptest's `Arbitrary` for `ident` with `strategy`, when
+/// `proptest` is enabled.
+fn define_arbitrary(names: &Names, ident: &TokenStream, strategy: TokenStream) -> TokenStream {
+    if !names.proptest() {
+        return quote!();
+    }
+    quote! {
+        impl ::proptest::arbitrary::Arbitrary for #ident {
+            type Parameters = ();
+            type Strategy = ::proptest::strategy::BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: ()) -> Self::Strategy {
+                use ::proptest::strategy::Strategy;
+                #strategy.boxed()
+            }
+        }
+    }
+}
+
 fn atom_token(atom: witx::AtomType) -> TokenStream {
     match atom {
         witx::AtomType::I32 => quote!(i32),
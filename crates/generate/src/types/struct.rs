@@ -1,70 +1,102 @@
+use super::{define_arbitrary, serde_derive, serde_rename, transparent_cfg};
 use crate::lifetimes::{anon_lifetime, LifetimeExt};
 use crate::names::Names;
 
+use heck::ShoutySnakeCase;
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use witx::Layout;
 
 pub(super) fn define_struct(
     names: &Names,
     name: &witx::Id,
+    docs: TokenStream,
     s: &witx::StructDatatype,
 ) -> TokenStream {
     let ident = names.type_(name);
+    let type_attributes = names.type_attributes(name);
     let size = s.mem_size_align().size as u32;
-    let align = s.mem_size_align().align as usize;
+    let align = s.mem_size_align().align;
+
+    let plain_data = !s.needs_lifetime() && s.members.iter().all(|m| names.is_plain_data(&m.tref));
+    let serializable = names.serde() && plain_data;
+    let serde_derive = if serializable {
+        serde_derive(names, false)
+    } else {
+        quote!()
+    };
 
     let member_names = s.members.iter().map(|m| names.struct_member(&m.name));
-    let member_decls = s.members.iter().map(|m| {
-        let name = names.struct_member(&m.name);
-        let type_ = match &m.tref {
+    let member_types = s
+        .members
+        .iter()
+        .map(|m| match &m.tref {
             witx::TypeRef::Name(nt) => names.type_(&nt.name),
             witx::TypeRef::Value(ty) => match &**ty {
                 witx::Type::Builtin(builtin) => names.builtin_type(*builtin, quote!('a)),
                 witx::Type::Pointer(pointee) | witx::Type::ConstPointer(pointee) => {
-                    let pointee_type = names.type_ref(&pointee, quote!('a));
-                    quote!(wiggle_runtime::GuestPtr<'a, #pointee_type>)
+                    let pointee_type = names.type_ref(pointee, quote!('a));
+                    if names.is_nullable(name, &m.name) {
+                        quote!(Option<wiggle::GuestPtr<'a, #pointee_type>>)
+                    } else {
+                        quote!(wiggle::GuestPtr<'a, #pointee_type>)
+                    }
                 }
-                _ => unimplemented!("other anonymous struct members"),
+                _ => names.type_ref(&m.tref, quote!('a)),
             },
+        })
+        .collect::<Vec<_>>();
+    let member_decls = s.members.iter().zip(&member_types).map(|(m, type_)| {
+        let member = names.struct_member(&m.name);
+        let serde_rename = if serializable {
+            serde_rename(names, &m.name)
+        } else {
+            quote!()
         };
-        quote!(pub #name: #type_)
+        let member_docs = names.docs(&m.docs);
+        quote!(#member_docs #serde_rename pub #member: #type_)
     });
 
+    let alloc = names.alloc();
     let member_reads = s.member_layout().into_iter().map(|ml| {
-        let name = names.struct_member(&ml.member.name);
-        let offset = ml.offset as u32;
+        let member = names.struct_member(&ml.member.name);
+        let offset = ml.offset as u64;
         let location = quote!(location.cast::<u8>().add(#offset)?.cast());
-        match &ml.member.tref {
-            witx::TypeRef::Name(nt) => {
-                let type_ = names.type_(&nt.name);
-                quote! {
-                    let #name = <#type_ as wiggle_runtime::GuestType>::read(&#location)?;
-                }
-            }
+        let type_ = match &ml.member.tref {
+            witx::TypeRef::Name(nt) => names.type_(&nt.name),
             witx::TypeRef::Value(ty) => match &**ty {
-                witx::Type::Builtin(builtin) => {
-                    let type_ = names.builtin_type(*builtin, anon_lifetime());
-                    quote! {
-                    let #name = <#type_ as wiggle_runtime::GuestType>::read(&#location)?;
-                    }
-                }
+                witx::Type::Builtin(builtin) => names.builtin_type(*builtin, anon_lifetime()),
                 witx::Type::Pointer(pointee) | witx::Type::ConstPointer(pointee) => {
-                    let pointee_type = names.type_ref(&pointee, anon_lifetime());
-                    quote! {
-                        let #name = <wiggle_runtime::GuestPtr::<#pointee_type> as wiggle_runtime::GuestType>::read(&#location)?;
+                    let pointee_type = names.type_ref(pointee, anon_lifetime());
+                    if names.is_nullable(name, &ml.member.name) {
+                        quote!(Option<wiggle::GuestPtr::<#pointee_type>>)
+                    } else {
+                        quote!(wiggle::GuestPtr::<#pointee_type>)
                     }
                 }
-                _ => unimplemented!("other anonymous struct members"),
+                _ => names.type_ref(&ml.member.tref, anon_lifetime()),
             },
+        };
+        let typename = name.as_str();
+        let field = ml.member.name.as_str();
+        let witx = names.member_location(name, &ml.member.name);
+        quote! {
+            let #member = <#type_ as wiggle::GuestType>::read(&#location).map_err(|e| {
+                wiggle::GuestError::InDataField {
+                    typename: #alloc::string::String::from(#typename),
+                    field: #alloc::string::String::from(#field),
+                    witx: #witx,
+                    err: #alloc::boxed::Box::new(e),
+                }
+            })?;
         }
     });
 
     let member_writes = s.member_layout().into_iter().map(|ml| {
         let name = names.struct_member(&ml.member.name);
-        let offset = ml.offset as u32;
+        let offset = ml.offset as u64;
         quote! {
-            wiggle_runtime::GuestType::write(
+            wiggle::GuestType::write(
                 &location.cast::<u8>().add(#offset)?.cast(),
                 val.#name,
             )?;
@@ -77,7 +109,29 @@ pub(super) fn define_struct(
         (quote!(), quote!(, Copy, PartialEq))
     };
 
-    let transparent = if s.is_transparent() {
+    let offsets = s.member_layout().into_iter().map(|ml| {
+        let offset_name =
+            format_ident!("OFFSET_{}", ml.member.name.as_str().to_shouty_snake_case());
+        let offset = ml.offset as u32;
+        quote!(pub const #offset_name: u32 = #offset;)
+    });
+    let layout = quote! {
+        impl #struct_lifetime #ident #struct_lifetime {
+            /// The size of the struct in guest memory.
+            pub const GUEST_SIZE: u32 = #size;
+            /// The alignment of the struct in guest memory.
+            pub const GUEST_ALIGN: usize = #align;
+            #(#offsets)*
+        }
+    };
+
+    let builder = define_builder(names, name, s, &member_types, &struct_lifetime);
+    let view = define_view(names, name, s, &member_types, &struct_lifetime);
+
+    let is_transparent =
+        s.is_transparent() && !s.members.iter().any(|m| names.contains_substitute(&m.tref));
+    let (transparent, slice_fns, fast_read) = if is_transparent {
+        let transparent_cfg = transparent_cfg(size as usize);
         let member_validate = s.member_layout().into_iter().map(|ml| {
             let offset = ml.offset;
             let typename = names.type_ref(&ml.member.tref, anon_lifetime());
@@ -90,26 +144,117 @@ pub(super) fn define_struct(
             }
         });
 
-        quote! {
-            unsafe impl<'a> wiggle_runtime::GuestTypeTransparent<'a> for #ident {
+        let transparent = quote! {
+            #transparent_cfg
+            unsafe impl<'a> wiggle::GuestTypeTransparent<'a> for #ident {
                 #[inline]
-                fn validate(location: *mut #ident) -> Result<(), wiggle_runtime::GuestError> {
+                fn validate(location: *mut #ident) -> Result<(), wiggle::GuestError> {
                     #(#member_validate)*
                     Ok(())
                 }
             }
+        };
+        // Float members are only canonicalized when read and written one by
+        // one, so memories canonicalizing NaNs don't get the bulk copy.
+        let slice_fns = quote! {
+            #transparent_cfg
+            #[inline]
+            fn read_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>) -> Result<Vec<Self>, wiggle::GuestError> {
+                if location.mem().canonicalize_nans() {
+                    return location.iter().map(|elem| elem?.read()).collect();
+                }
+                wiggle::read_transparent_slice(location)
+            }
+        };
+        // Copying a struct with padding out of Rust memory would read its
+        // uninitialized padding bytes, so those are written member by member
+        // with the default `write_slice`.
+        let write_slice = if has_padding(s) {
+            quote!()
+        } else {
+            quote! {
+                #transparent_cfg
+                #[inline]
+                fn write_slice<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, [Self], M>, vals: &[Self]) -> Result<(), wiggle::GuestError> {
+                    if location.mem().canonicalize_nans() {
+                        assert_eq!(
+                            location.len(),
+                            vals.len() as u64,
+                            "source slice length does not match the guest slice length"
+                        );
+                        for (elem, val) in location.iter().zip(vals) {
+                            elem?.write(val.clone())?;
+                        }
+                        return Ok(());
+                    }
+                    wiggle::write_transparent_slice(location, vals)
+                }
+            }
+        };
+        let slice_fns = quote!(#slice_fns #write_slice);
+        // Scalar-heavy structs are bounds checked once as a whole, rather
+        // than once per member. Only if that fails are the members read one
+        // by one, to find the one at fault.
+        let fast_read = quote! {
+            #transparent_cfg
+            {
+                if let Some(val) = wiggle::read_transparent(location) {
+                    return Ok(val);
+                }
+            }
+        };
+        (transparent, slice_fns, fast_read)
+    } else {
+        (quote!(), quote!(), quote!())
+    };
+
+    let arbitrary = if plain_data {
+        // Generates the members as nested pairs, so there's no limit on their
+        // number like there is for the tuple strategies.
+        let mut strategy = quote!(::proptest::strategy::Just(()));
+        let mut pattern = quote!(());
+        for m in s.members.iter().rev() {
+            let member = names.struct_member(&m.name);
+            let type_ = names.type_ref(&m.tref, anon_lifetime());
+            strategy = quote!((::proptest::arbitrary::any::<#type_>(), #strategy));
+            pattern = quote!((#member, #pattern));
+        }
+        let member_names = s.members.iter().map(|m| names.struct_member(&m.name));
+        define_arbitrary(
+            names,
+            &ident,
+            quote!(#strategy.prop_map(|#pattern| #ident { #(#member_names),* })),
+        )
+    } else {
+        quote!()
+    };
+
+    let guest_buffer = if is_guest_buffer(names, name, s) {
+        let buf = names.struct_member(&s.members[0].name);
+        let buf_len = names.struct_member(&s.members[1].name);
+        quote! {
+            impl<'a> wiggle::AsGuestBuffer<'a> for #ident<'a> {
+                fn as_guest_buffer(&self) -> wiggle::GuestPtr<'a, [u8]> {
+                    self.#buf.as_array(self.#buf_len as u64)
+                }
+            }
         }
     } else {
         quote!()
     };
 
     quote! {
+        #guest_buffer
+
+        #docs
         #[derive(Clone, Debug #extra_derive)]
+        #serde_derive
+        #type_attributes
         pub struct #ident #struct_lifetime {
             #(#member_decls),*
         }
 
-        impl<'a> wiggle_runtime::GuestType<'a> for #ident #struct_lifetime {
+        impl<'a> wiggle::GuestType<'a> for #ident #struct_lifetime {
             fn guest_size() -> u32 {
                 #size
             }
@@ -118,17 +263,194 @@ pub(super) fn define_struct(
                 #align
             }
 
-            fn read(location: &wiggle_runtime::GuestPtr<'a, Self>) -> Result<Self, wiggle_runtime::GuestError> {
+            fn read<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, Self, M>) -> Result<Self, wiggle::GuestError> {
+                #fast_read
                 #(#member_reads)*
                 Ok(#ident { #(#member_names),* })
             }
 
-            fn write(location: &wiggle_runtime::GuestPtr<'_, Self>, val: Self) -> Result<(), wiggle_runtime::GuestError> {
+            fn write<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, Self, M>, val: Self) -> Result<(), wiggle::GuestError> {
                 #(#member_writes)*
                 Ok(())
             }
+
+            #slice_fns
         }
 
+        #layout
         #transparent
+        #arbitrary
+        #builder
+        #view
+    }
+}
+
+/// Whether a struct's members, or those of any struct nested in it, leave
+/// gaps for alignment.
+fn has_padding(s: &witx::StructDatatype) -> bool {
+    let members_size: usize = s.members.iter().map(|m| m.tref.mem_size_align().size).sum();
+    members_size != s.mem_size_align().size
+        || s.members.iter().any(|m| match &*m.tref.type_() {
+            witx::Type::Struct(s) => has_padding(s),
+            _ => false,
+        })
+}
+
+/// Defines `{Name}View`, wrapping a pointer to the struct in guest memory,
+/// with an accessor per member returning a pointer to it.
+fn define_view(
+    names: &Names,
+    name: &witx::Id,
+    s: &witx::StructDatatype,
+    member_types: &[TokenStream],
+    struct_lifetime: &TokenStream,
+) -> TokenStream {
+    let ident = names.type_(name);
+    let view = names.struct_view(name);
+    let doc = format!(
+        "A `{}` in guest memory, whose members are only read when they're accessed.",
+        ident
+    );
+    let accessors = s
+        .member_layout()
+        .into_iter()
+        .zip(member_types)
+        .map(|(ml, type_)| {
+            let member = names.struct_member(&ml.member.name);
+            let offset = ml.offset as u64;
+            quote! {
+                pub fn #member(&self) -> wiggle::GuestPtr<'a, #type_> {
+                    wiggle::GuestPtr::new(self.ptr.mem(), self.ptr.offset() + #offset)
+                }
+            }
+        });
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Copy, Debug)]
+        pub struct #view<'a> {
+            ptr: wiggle::GuestPtr<'a, #ident #struct_lifetime>,
+        }
+
+        impl<'a> #view<'a> {
+            /// Fails if the struct would extend past the end of the address
+            /// space, so that none of its members do.
+            pub fn new(ptr: wiggle::GuestPtr<'a, #ident #struct_lifetime>) -> Result<Self, wiggle::GuestError> {
+                match ptr.offset().checked_add(#ident::GUEST_SIZE as u64) {
+                    Some(_) => Ok(#view { ptr }),
+                    None => Err(wiggle::GuestError::PtrOverflow),
+                }
+            }
+
+            pub fn ptr(&self) -> wiggle::GuestPtr<'a, #ident #struct_lifetime> {
+                self.ptr
+            }
+
+            /// Reads every member.
+            pub fn read(&self) -> Result<#ident #struct_lifetime, wiggle::GuestError> {
+                self.ptr.read()
+            }
+
+            #(#accessors)*
+        }
+    }
+}
+
+/// Defines `{Name}Builder`, with a setter per member, and `Name::builder`.
+/// Members which are numbers or flags default to zero or no flags, while
+/// `build` fails unless the others are set.
+fn define_builder(
+    names: &Names,
+    name: &witx::Id,
+    s: &witx::StructDatatype,
+    member_types: &[TokenStream],
+    struct_lifetime: &TokenStream,
+) -> TokenStream {
+    let ident = names.type_(name);
+    let builder = format_ident!("{}Builder", ident.to_string());
+    let typename = ident.to_string();
+
+    let mut fields = vec![];
+    let mut setters = vec![];
+    let mut builds = vec![];
+    for (m, type_) in s.members.iter().zip(member_types) {
+        let member = names.struct_member(&m.name);
+        let member_str = member.to_string();
+        let member_str = member_str.trim_start_matches("r#");
+        fields.push(quote!(#member: Option<#type_>));
+        setters.push(quote! {
+            pub fn #member(mut self, #member: #type_) -> Self {
+                self.#member = Some(#member);
+                self
+            }
+        });
+        let default = if names.contains_substitute(&m.tref) {
+            None
+        } else {
+            match &*m.tref.type_() {
+                witx::Type::Builtin(b) if *b != witx::BuiltinType::String => {
+                    Some(quote!(self.#member.unwrap_or_default()))
+                }
+                witx::Type::Flags(_) => Some(quote!(self.#member.unwrap_or(<#type_>::EMPTY_FLAGS))),
+                _ => None,
+            }
+        };
+        builds.push(match default {
+            Some(default) => quote!(#member: #default),
+            None => quote! {
+                #member: self.#member.ok_or(wiggle::MissingFieldError {
+                    typename: #typename,
+                    field: #member_str,
+                })?
+            },
+        });
+    }
+
+    quote! {
+        #[derive(Clone, Debug, Default)]
+        pub struct #builder #struct_lifetime {
+            #(#fields),*
+        }
+
+        impl #struct_lifetime #builder #struct_lifetime {
+            #(#setters)*
+
+            pub fn build(self) -> Result<#ident #struct_lifetime, wiggle::MissingFieldError> {
+                Ok(#ident {
+                    #(#builds),*
+                })
+            }
+        }
+
+        impl #struct_lifetime #ident #struct_lifetime {
+            pub fn builder() -> #builder #struct_lifetime {
+                #builder::default()
+            }
+        }
+    }
+}
+
+/// Whether `s` describes a byte buffer like WASI's `iovec`: a pointer to `u8`
+/// followed by its length.
+fn is_guest_buffer(names: &Names, name: &witx::Id, s: &witx::StructDatatype) -> bool {
+    match s.members.as_slice() {
+        [buf, buf_len] => {
+            let buf_is_bytes = match &buf.tref {
+                witx::TypeRef::Value(ty) => match &**ty {
+                    witx::Type::Pointer(pointee) | witx::Type::ConstPointer(pointee) => matches!(
+                        &*pointee.type_(),
+                        witx::Type::Builtin(witx::BuiltinType::U8)
+                    ),
+                    _ => false,
+                },
+                witx::TypeRef::Name(_) => false,
+            };
+            let buf_len_is_len = matches!(
+                &*buf_len.tref.type_(),
+                witx::Type::Builtin(witx::BuiltinType::U32 | witx::BuiltinType::USize)
+            );
+            buf_is_bytes && buf_len_is_len && !names.is_nullable(name, &buf.name)
+        }
+        _ => false,
     }
 }
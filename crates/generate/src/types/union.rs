@@ -1,39 +1,48 @@
 use crate::lifetimes::LifetimeExt;
 use crate::names::Names;
 
+use heck::SnakeCase;
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use witx::Layout;
 
-pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDatatype) -> TokenStream {
+pub(super) fn define_union(
+    names: &Names,
+    name: &witx::Id,
+    docs: TokenStream,
+    u: &witx::UnionDatatype,
+) -> TokenStream {
     let ident = names.type_(name);
+    let type_attributes = names.type_attributes(name);
     let size = u.mem_size_align().size as u32;
-    let align = u.mem_size_align().align as usize;
+    let align = u.mem_size_align().align;
     let ulayout = u.union_layout();
-    let contents_offset = ulayout.contents_offset as u32;
+    let contents_offset = ulayout.contents_offset as u64;
+    let contents_offset_u32 = ulayout.contents_offset as u32;
 
     let lifetime = quote!('a);
 
     let variants = u.variants.iter().map(|v| {
-        let var_name = names.enum_variant(&v.name);
+        let var_name = names.enum_variant(name, &v.name);
+        let var_docs = names.docs(&v.docs);
         if let Some(tref) = &v.tref {
-            let var_type = names.type_ref(&tref, lifetime.clone());
-            quote!(#var_name(#var_type))
+            let var_type = names.type_ref(tref, lifetime.clone());
+            quote!(#var_docs #var_name(#var_type))
         } else {
-            quote!(#var_name)
+            quote!(#var_docs #var_name)
         }
     });
 
     let tagname = names.type_(&u.tag.name);
 
     let read_variant = u.variants.iter().map(|v| {
-        let variantname = names.enum_variant(&v.name);
+        let variantname = names.enum_variant(name, &v.name);
         if let Some(tref) = &v.tref {
             let varianttype = names.type_ref(tref, lifetime.clone());
             quote! {
                 #tagname::#variantname => {
                     let variant_ptr = location.cast::<u8>().add(#contents_offset)?;
-                    let variant_val = <#varianttype as wiggle_runtime::GuestType>::read(&variant_ptr.cast())?;
+                    let variant_val = <#varianttype as wiggle::GuestType>::read(&variant_ptr.cast())?;
                     Ok(#ident::#variantname(variant_val))
                 }
             }
@@ -43,7 +52,7 @@ pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDataty
     });
 
     let write_variant = u.variants.iter().map(|v| {
-        let variantname = names.enum_variant(&v.name);
+        let variantname = names.enum_variant(name, &v.name);
         let write_tag = quote! {
             location.cast().write(#tagname::#variantname)?;
         };
@@ -53,7 +62,7 @@ pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDataty
                 #ident::#variantname(contents) => {
                     #write_tag
                     let variant_ptr = location.cast::<u8>().add(#contents_offset)?;
-                    <#varianttype as wiggle_runtime::GuestType>::write(&variant_ptr.cast(), contents)?;
+                    <#varianttype as wiggle::GuestType>::write(&variant_ptr.cast(), contents)?;
                 }
             }
         } else {
@@ -65,6 +74,32 @@ pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDataty
         }
     });
 
+    let to_tag = u.variants.iter().map(|v| {
+        let variantname = names.enum_variant(name, &v.name);
+        if v.tref.is_some() {
+            quote!(#ident::#variantname(_) => #tagname::#variantname)
+        } else {
+            quote!(#ident::#variantname => #tagname::#variantname)
+        }
+    });
+
+    let accessors = u.variants.iter().filter_map(|v| {
+        let tref = v.tref.as_ref()?;
+        let variantname = names.enum_variant(name, &v.name);
+        let varianttype = names.type_ref(tref, lifetime.clone());
+        let accessor = format_ident!("as_{}", v.name.as_str().to_snake_case());
+        Some(quote! {
+            /// The contents of the variant, if `self` is that variant.
+            pub fn #accessor(&self) -> Option<&#varianttype> {
+                match self {
+                    #ident::#variantname(contents) => Some(contents),
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                }
+            }
+        })
+    });
+
     let (enum_lifetime, extra_derive) = if u.needs_lifetime() {
         (quote!(<'a>), quote!())
     } else {
@@ -72,12 +107,32 @@ pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDataty
     };
 
     quote! {
+        #docs
         #[derive(Clone, Debug #extra_derive)]
+        #type_attributes
         pub enum #ident #enum_lifetime {
             #(#variants),*
         }
 
-        impl<'a> wiggle_runtime::GuestType<'a> for #ident #enum_lifetime {
+        impl #enum_lifetime #ident #enum_lifetime {
+            /// The size of the union in guest memory.
+            pub const GUEST_SIZE: u32 = #size;
+            /// The alignment of the union in guest memory.
+            pub const GUEST_ALIGN: usize = #align;
+            /// The offset of the contents of every variant, after the tag.
+            pub const OFFSET_CONTENTS: u32 = #contents_offset_u32;
+
+            /// The tag of the variant.
+            pub fn tag(&self) -> #tagname {
+                match self {
+                    #(#to_tag,)*
+                }
+            }
+
+            #(#accessors)*
+        }
+
+        impl<'a> wiggle::GuestType<'a> for #ident #enum_lifetime {
             fn guest_size() -> u32 {
                 #size
             }
@@ -86,8 +141,8 @@ pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDataty
                 #align
             }
 
-            fn read(location: &wiggle_runtime::GuestPtr<'a, Self>)
-                -> Result<Self, wiggle_runtime::GuestError>
+            fn read<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'a, Self, M>)
+                -> Result<Self, wiggle::GuestError>
             {
                 let tag = location.cast().read()?;
                 match tag {
@@ -96,8 +151,8 @@ pub(super) fn define_union(names: &Names, name: &witx::Id, u: &witx::UnionDataty
 
             }
 
-            fn write(location: &wiggle_runtime::GuestPtr<'_, Self>, val: Self)
-                -> Result<(), wiggle_runtime::GuestError>
+            fn write<M: ?Sized + wiggle::GuestMemory>(location: &wiggle::GuestPtr<'_, Self, M>, val: Self)
+                -> Result<(), wiggle::GuestError>
             {
                 match val {
                     #(#write_variant)*
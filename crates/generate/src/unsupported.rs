@@ -0,0 +1,244 @@
+use proc_macro2::TokenStream;
+use quote::quote_spanned;
+use std::rc::Rc;
+use witx::{Document, InterfaceFunc, Layout, NamedType, Type, TypeRef};
+
+use crate::names::Names;
+
+/// Reports the parts of `doc` that code can't be generated for, as
+/// `compile_error!`s at the `witx` config. Generating code for them would
+/// otherwise panic part-way through.
+pub fn check_supported(doc: &Document, names: &Names) -> Vec<TokenStream> {
+    let span = names.witx_span();
+    unsupported_in_bindings(doc, names)
+        .into_iter()
+        .map(|msg| quote_spanned!(span=> compile_error!(#msg);))
+        .collect()
+}
+
+/// Describes the parts of `doc` that Rust bindings can't be generated for,
+/// among the modules and functions `names` selects.
+pub(crate) fn unsupported_in_bindings(doc: &Document, names: &Names) -> Vec<String> {
+    let funcs = names
+        .modules(doc)
+        .flat_map(|m| names.funcs(&m).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    unsupported_shapes(doc, &funcs, true)
+}
+
+/// Describes the types of `doc` and the `funcs` that code can't be generated
+/// for. C headers can declare functions which don't return or which return
+/// strings and pointers, unlike the Rust bindings.
+pub(crate) fn unsupported_shapes(
+    doc: &Document,
+    funcs: &[Rc<InterfaceFunc>],
+    rust_bindings: bool,
+) -> Vec<String> {
+    let mut errors = doc
+        .typenames()
+        .flat_map(|nt| unsupported_type(&nt))
+        .collect::<Vec<_>>();
+    for func in funcs {
+        errors.extend(unsupported_func(func, rust_bindings));
+    }
+    errors
+}
+
+/// Describes what about the typename `nt` code can't be generated for.
+pub(crate) fn unsupported_type(nt: &NamedType) -> Vec<String> {
+    let mut errors = Vec::new();
+    let name = nt.name.as_str();
+    if let TypeRef::Value(ty) = &nt.tref {
+        match &**ty {
+            Type::Struct(s) => {
+                for m in &s.members {
+                    if !nameable(&m.tref) {
+                        errors.push(format!(
+                            "member `{}` of struct `{}` has an anonymous type which isn't \
+                             supported; give it a typename",
+                            m.name.as_str(),
+                            name
+                        ));
+                    }
+                }
+            }
+            Type::Union(u) => {
+                for v in &u.variants {
+                    if v.tref.as_ref().map_or(false, |t| !nameable(t)) {
+                        errors.push(format!(
+                            "variant `{}` of union `{}` has an anonymous type which isn't \
+                             supported; give it a typename",
+                            v.name.as_str(),
+                            name
+                        ));
+                    }
+                }
+            }
+            Type::Flags(f) => {
+                let bits = f.repr.mem_size_align().size * 8;
+                if f.flags.len() > bits {
+                    errors.push(format!(
+                        "flags `{}` has {} flags, more than fit in its {}-bit repr",
+                        name,
+                        f.flags.len(),
+                        bits
+                    ));
+                }
+            }
+            Type::Pointer(p) | Type::ConstPointer(p) | Type::Array(p) if !nameable(p) => {
+                errors.push(format!(
+                    "`{}` refers to an anonymous type which isn't supported; give it a \
+                     typename",
+                    name
+                ));
+            }
+            _ => {}
+        }
+    }
+    errors
+}
+
+/// Describes what about `func` code can't be generated for, in Rust bindings
+/// or, if `rust_bindings` is false, in a C header.
+pub(crate) fn unsupported_func(func: &InterfaceFunc, rust_bindings: bool) -> Vec<String> {
+    let mut errors = Vec::new();
+    let funcname = func.name.as_str();
+    if func.noreturn && rust_bindings {
+        errors.push(format!(
+            "function `{}` doesn't return, which isn't supported",
+            funcname
+        ));
+    }
+    for param in func.params.iter().chain(&func.results) {
+        if !nameable(&param.tref) {
+            errors.push(format!(
+                "`{}` of function `{}` has an anonymous type which isn't supported; \
+                 give it a typename",
+                param.name.as_str(),
+                funcname
+            ));
+        }
+    }
+    if rust_bindings {
+        for result in func.results.iter().skip(1) {
+            let kind = match &*result.tref.type_() {
+                Type::Builtin(witx::BuiltinType::String) => "a string",
+                Type::Pointer(_) | Type::ConstPointer(_) => "a pointer",
+                Type::Array(_) => "an array",
+                _ => continue,
+            };
+            errors.push(format!(
+                "result `{}` of function `{}` is {}, which isn't supported",
+                result.name.as_str(),
+                funcname,
+                kind
+            ));
+        }
+    }
+    errors
+}
+
+/// Whether `tref` can be named in generated code: it's a typename, a builtin,
+/// or a pointer to one of those.
+fn nameable(tref: &TypeRef) -> bool {
+    match tref {
+        TypeRef::Name(_) => true,
+        TypeRef::Value(ty) => match &**ty {
+            Type::Builtin(_) => true,
+            Type::Pointer(p) | Type::ConstPointer(p) => nameable(p),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Config;
+
+    fn errors(witx: &str) -> Vec<String> {
+        let config: Config = "{ witx: [], ctx: Ctx }".parse().expect("parse config");
+        let doc = witx::parse(witx).expect("parse witx");
+        check_supported(&doc, &Names::new(&config))
+            .iter()
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn reports_unsupported_shapes() {
+        let errors = errors(
+            r#"
+            (typename $s (struct (field $a (array u8))))
+            (module $m
+              (@interface func (export "f")
+                (param $p (array u8)))
+              (@interface func (export "g")
+                (result $e u32)
+                (result $r string))
+              (@interface func (export "h") (@witx noreturn)))
+            "#,
+        );
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors[0].contains("member `a` of struct `s`"));
+        assert!(errors[1].contains("`p` of function `f`"));
+        assert!(errors[2].contains("result `r` of function `g` is a string"));
+        assert!(errors[3].contains("function `h` doesn't return"));
+    }
+
+    #[test]
+    fn accepts_supported_shapes() {
+        let errors = errors(
+            r#"
+            (typename $bytes (array u8))
+            (typename $s (struct (field $a (@witx pointer $bytes)) (field $b u32)))
+            (module $m
+              (@interface func (export "f")
+                (param $p $bytes)
+                (param $q string)
+                (result $e u32)
+                (result $r u64)))
+            "#,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    const UNSUPPORTED: &str = r#"
+        (typename $s (struct (field $a (array u8))))
+        (module $m
+          (@interface func (export "f")
+            (param $p (array u8)))
+          (@interface func (export "h") (@witx noreturn)))
+    "#;
+
+    fn names(config: &str) -> Names {
+        let config: Config = config.parse().expect("parse config");
+        Names::new(&config)
+    }
+
+    #[test]
+    fn parts_report_unsupported_shapes() {
+        let names = names("{ witx: [], ctx: Ctx }");
+        let doc = witx::parse(UNSUPPORTED).expect("parse witx");
+        let s = doc.typenames().next().expect("typename");
+        let generated = crate::define_datatype(&names, &s).to_string();
+        assert!(generated.contains("compile_error"), "{}", generated);
+        assert!(generated.contains("member `a` of struct `s`"));
+
+        let module = doc.modules().next().expect("module");
+        for func in module.funcs() {
+            let generated = crate::define_func(&names, &module, &func).to_string();
+            assert!(generated.contains("compile_error"), "{}", generated);
+        }
+        let generated = crate::define_module_trait(&names, &module).to_string();
+        assert!(generated.contains("anonymous types aren't supported"));
+    }
+
+    #[test]
+    fn guest_bindings_report_unsupported_shapes() {
+        let config: Config = "{ witx: [], guest: true }".parse().expect("parse config");
+        let doc = witx::parse(UNSUPPORTED).expect("parse witx");
+        let generated = crate::generate(&doc, &config).to_string();
+        assert!(generated.contains("function `h` doesn't return"));
+    }
+}
@@ -0,0 +1,114 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::funcs::{core_rets_type, core_signature};
+use crate::names::Names;
+use witx::Module;
+
+/// Defines an `add_to_linker` function registering every function of `m`
+/// with a `wasmtime::Linker`.
+///
+/// The functions are registered under the module's witx name, and run against
+/// the `memory` export of the calling instance. The generated code refers to
+/// `wasmtime` directly, so the crate invoking the macro must depend on it.
+///
+/// Nothing is generated for async interfaces, or for interfaces taking a
+/// named memory type or multiple memories: their functions can't be called
+/// with the linear memory of a wasmtime instance as it is. Nor is it for a
+/// generic ctx, since the linker needs to know the ctx type.
+pub fn define_add_to_linker(names: &Names, m: &Module) -> TokenStream {
+    if names.is_async()
+        || names.multi_memory()
+        || names.memory_type_name().is_some()
+        || names.generic_ctx()
+    {
+        return quote!();
+    }
+    let module_name = m.name.as_str();
+    let ctx_type = names.ctx_type();
+
+    let registrations = names.funcs(m).map(|func| {
+        let funcname = func.name.as_str();
+        let ident = names.func(&func.name);
+        let (core_args, core_rets) = core_signature(names, &func);
+        let (params, args): (Vec<_>, Vec<_>) = core_args
+            .iter()
+            .map(|arg| {
+                let name = names.func_core_arg(arg);
+                let atom = names.atom_type(names.core_arg_atom(arg));
+                (quote!(#name: #atom), quote!(#name))
+            })
+            .unzip();
+        let ret = core_rets_type(names, &core_rets);
+        quote! {
+            linker.func_wrap(
+                #module_name,
+                #funcname,
+                move |mut caller: wasmtime::Caller<'_, T>, #(#params),*| -> wasmtime::Result<#ret> {
+                    let memory = match caller.get_export("memory") {
+                        Some(wasmtime::Extern::Memory(memory)) => memory,
+                        _ => return Err(wasmtime::Error::msg("missing required memory export")),
+                    };
+                    let (memory, data) = memory.data_and_store_mut(&mut caller);
+                    let memory = wiggle::SliceMemory::new(memory);
+                    Ok(#ident(get_ctx(data), &memory, #(#args),*))
+                },
+            )?;
+        }
+    });
+
+    quote! {
+        /// Adds every function of this module to `linker`, with `get_ctx`
+        /// picking the ctx out of the store's data.
+        pub fn add_to_linker<T>(
+            linker: &mut wasmtime::Linker<T>,
+            get_ctx: impl Fn(&mut T) -> &mut #ctx_type + Send + Sync + Copy + 'static,
+        ) -> wasmtime::Result<()> {
+            #(#registrations)*
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Config;
+
+    const WITX: &str = r#"
+        (typename $errno (enum u16 $success $badf))
+        (typename $fd (handle))
+        (module $example
+          (@interface func (export "fd_close")
+            (param $fd $fd)
+            (result $error $errno))
+          (@interface func (export "sched_yield")))
+    "#;
+
+    fn generate(config: &str) -> String {
+        let config: Config = syn::parse_str(config).expect("parse config");
+        let doc = witx::parse(WITX).expect("parse witx");
+        let module = doc.modules().next().expect("module");
+        define_add_to_linker(&Names::new(&config), &module).to_string()
+    }
+
+    #[test]
+    fn registers_every_function() {
+        let linker = generate("{ witx: [], ctx: Ctx }");
+        assert!(linker.contains(
+            "pub fn add_to_linker < T > (linker : & mut wasmtime :: Linker < T > , \
+             get_ctx : impl Fn (& mut T) -> & mut Ctx"
+        ));
+        assert!(linker.contains(
+            "linker . func_wrap (\"example\" , \"fd_close\" , move | mut caller : \
+             wasmtime :: Caller < '_ , T > , fd : i32 | -> wasmtime :: Result < i32 >"
+        ));
+        assert!(linker.contains("Ok (fd_close (get_ctx (data) , & memory , fd))"));
+        assert!(linker.contains(
+            "\"sched_yield\" , move | mut caller : wasmtime :: Caller < '_ , T > , | \
+             -> wasmtime :: Result < () >"
+        ));
+
+        assert_eq!(generate("{ witx: [], ctx: Ctx, async: true }"), "");
+    }
+}
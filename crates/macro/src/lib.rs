@@ -0,0 +1,90 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use std::env;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use syn::parse_macro_input;
+use wiggle_generate::config::WitxConf;
+
+/// Whether this compiler process has written to `WIGGLE_DEBUG_FILE` yet.
+static DEBUG_FILE_WRITTEN: AtomicBool = AtomicBool::new(false);
+
+#[proc_macro]
+pub fn from_witx(args: TokenStream) -> TokenStream {
+    let config = parse_macro_input!(args as wiggle_generate::Config);
+    let doc = match &config.witx {
+        WitxConf::Paths { paths, span, .. } => match wiggle_generate::load_document(paths) {
+            Ok(doc) => doc,
+            Err(e) => {
+                let msg = format!("loading witx: {}", e.report());
+                return syn::Error::new(*span, msg).to_compile_error().into();
+            }
+        },
+        WitxConf::Literal { text, span } => match wiggle_generate::parse_literal(text, *span) {
+            Ok(doc) => doc,
+            Err(e) => return e.to_compile_error().into(),
+        },
+    };
+    let code = wiggle_generate::generate(&doc, &config);
+    if let Err(e) = write_debug_file(&config, &code) {
+        let msg = format!("writing WIGGLE_DEBUG_FILE: {}", e);
+        return syn::Error::new(proc_macro2::Span::call_site(), msg)
+            .to_compile_error()
+            .into();
+    }
+    let tracking = track_witx_files(&config);
+    TokenStream::from(quote!(#code #tracking))
+}
+
+/// Includes every file making up the witx document in the expansion, so that
+/// cargo rebuilds the crate when one of them changes, and reads the
+/// environment variables interpolated into their paths for the same reason.
+/// The values go unused. (`proc_macro::tracked_path` would do this directly,
+/// but isn't stable.)
+fn track_witx_files(config: &wiggle_generate::Config) -> proc_macro2::TokenStream {
+    // The documents `adapter` and `conversions` convert to are read as well.
+    let witx = std::iter::once(&config.witx)
+        .chain(config.adapter.as_ref().map(|a| &a.witx))
+        .chain(config.conversions.as_ref().map(|c| &c.witx));
+    let mut paths = Vec::new();
+    let mut env_vars = Vec::new();
+    for witx in witx {
+        if let WitxConf::Paths {
+            paths: witx_paths,
+            env_vars: witx_env_vars,
+            ..
+        } = witx
+        {
+            paths.extend(wiggle_generate::document_paths(witx_paths).unwrap_or_default());
+            env_vars.extend(witx_env_vars.iter().cloned());
+        }
+    }
+    let paths = paths.iter().filter_map(|p| p.to_str());
+    quote! {
+        #(const _: &[u8] = include_bytes!(#paths);)*
+        #(const _: Option<&str> = option_env!(#env_vars);)*
+    }
+}
+
+/// Writes the expansion to the file named by `WIGGLE_DEBUG_FILE`, relative to
+/// the crate being compiled, if it's set. Every invocation in a crate is
+/// written to the file, which is truncated by the first. Cargo doesn't know
+/// the expansion depends on the variable, so setting it only takes effect
+/// once the crate is rebuilt for some other reason.
+fn write_debug_file(
+    config: &wiggle_generate::Config,
+    code: &proc_macro2::TokenStream,
+) -> std::io::Result<()> {
+    let path = match env::var_os("WIGGLE_DEBUG_FILE") {
+        Some(path) => PathBuf::from(path),
+        None => return Ok(()),
+    };
+    let path = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => PathBuf::from(dir).join(path),
+        None => path,
+    };
+    let first = !DEBUG_FILE_WRITTEN.swap(true, Ordering::SeqCst);
+    wiggle_generate::write_debug_file(&path, config, code, first)
+}
@@ -0,0 +1,85 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::cell::UnsafeCell;
+use wiggle_runtime::{CallMemory, GuestMemory, GuestPtr};
+
+#[repr(align(8))]
+struct BenchMemory {
+    buffer: UnsafeCell<[u8; 4096]>,
+}
+
+unsafe impl GuestMemory for BenchMemory {
+    fn base(&self) -> (*mut u8, u64) {
+        (self.buffer.get() as *mut u8, 4096)
+    }
+}
+
+fn primitives(c: &mut Criterion) {
+    let mem = BenchMemory {
+        buffer: UnsafeCell::new([0; 4096]),
+    };
+    let mut group = c.benchmark_group("GuestPtr primitives");
+    group.bench_function("u32 read", |b| {
+        let ptr = GuestPtr::<u32>::new(&mem, 64);
+        b.iter(|| black_box(ptr.read().expect("in bounds")))
+    });
+    group.bench_function("u32 write", |b| {
+        let ptr = GuestPtr::<u32>::new(&mem, 64);
+        b.iter(|| ptr.write(black_box(7)).expect("in bounds"))
+    });
+    group.bench_function("u64 read", |b| {
+        let ptr = GuestPtr::<u64>::new(&mem, 64);
+        b.iter(|| black_box(ptr.read().expect("in bounds")))
+    });
+    group.bench_function("pointer read", |b| {
+        let ptr = GuestPtr::<GuestPtr<u8>>::new(&mem, 64);
+        b.iter(|| black_box(ptr.read().expect("in bounds")))
+    });
+    group.bench_function("out of bounds read", |b| {
+        let ptr = GuestPtr::<u32>::new(&mem, 4094);
+        b.iter(|| black_box(ptr.read().expect_err("out of bounds")))
+    });
+    group.finish();
+}
+
+fn records(c: &mut Criterion) {
+    let mem = BenchMemory {
+        buffer: UnsafeCell::new([0; 4096]),
+    };
+    let mut group = c.benchmark_group("GuestPtr records");
+    // Tuples are laid out like structs, and read member by member.
+    group.bench_function("(u64, u32, u8) read", |b| {
+        let ptr = GuestPtr::<(u64, u32, u8)>::new(&mem, 64);
+        b.iter(|| black_box(ptr.read().expect("in bounds")))
+    });
+    group.bench_function("(u64, u32, u8) write", |b| {
+        let ptr = GuestPtr::<(u64, u32, u8)>::new(&mem, 64);
+        b.iter(|| ptr.write(black_box((1, 2, 3))).expect("in bounds"))
+    });
+    group.finish();
+}
+
+fn borrows(c: &mut Criterion) {
+    let mem = BenchMemory {
+        buffer: UnsafeCell::new([0; 4096]),
+    };
+    let mut group = c.benchmark_group("GuestPtr borrows");
+    // A hostcall holding a few buffers at once, like `fd_write` with several
+    // iovecs.
+    group.bench_function("8 slices per call", |b| {
+        b.iter(|| {
+            let call = CallMemory::new(&mem);
+            let slices = (0..8u64)
+                .map(|i| {
+                    GuestPtr::<[u8]>::new(&call, (i * 512, 256))
+                        .as_slice()
+                        .expect("not borrowed")
+                })
+                .collect::<Vec<_>>();
+            black_box(slices);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, primitives, records, borrows);
+criterion_main!(benches);
@@ -0,0 +1,121 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::cell::UnsafeCell;
+use wiggle_runtime::{GuestBorrows, GuestError, GuestMemory, GuestPtr, GuestType};
+
+struct BenchMemory {
+    buffer: UnsafeCell<Vec<u8>>,
+}
+
+unsafe impl GuestMemory for BenchMemory {
+    fn base(&self) -> (*mut u8, u64) {
+        unsafe {
+            let buffer = &mut *self.buffer.get();
+            (buffer.as_mut_ptr(), buffer.len() as u64)
+        }
+    }
+}
+
+// The number of iovecs per call, and the size of their table in memory.
+const IOVECS: u32 = 16;
+const IOVEC_TABLE: u32 = IOVECS * 8;
+
+/// Lays out `IOVECS` `(buf, len)` pairs at offset 0, each pointing at a
+/// `buf_len` byte buffer after the table.
+fn iovec_memory(buf_len: u32) -> BenchMemory {
+    let mut buffer = vec![0u8; (IOVEC_TABLE + IOVECS * buf_len) as usize];
+    for i in 0..IOVECS {
+        let buf = IOVEC_TABLE + i * buf_len;
+        let entry = (i * 8) as usize;
+        buffer[entry..entry + 4].copy_from_slice(&buf.to_le_bytes());
+        buffer[entry + 4..entry + 8].copy_from_slice(&buf_len.to_le_bytes());
+    }
+    for (i, byte) in buffer[IOVEC_TABLE as usize..].iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    BenchMemory {
+        buffer: UnsafeCell::new(buffer),
+    }
+}
+
+/// Reads the iovec table, as a hostcall like `fd_read` would before touching
+/// the buffers.
+fn iovecs(mem: &BenchMemory) -> Vec<GuestPtr<'_, [u8]>> {
+    let table: GuestPtr<[(u32, u32)]> = mem.ptr((0, IOVECS.into()));
+    table
+        .iter()
+        .map(|entry| {
+            let (buf, len) = entry.unwrap().read().unwrap();
+            mem.ptr((buf.into(), len.into()))
+        })
+        .collect()
+}
+
+/// Reads a slice one element at a time, as `to_vec` did before transparent
+/// types got a single `memcpy`.
+fn read_per_element<'a>(ptr: &GuestPtr<'a, [u8]>) -> Result<Vec<u8>, GuestError> {
+    ptr.iter().map(|elem| elem?.read()).collect()
+}
+
+fn gather(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iovec gather");
+    for buf_len in [16u32, 256, 4096, 65536].iter() {
+        let mem = iovec_memory(*buf_len);
+        group.throughput(Throughput::Bytes((IOVECS * buf_len).into()));
+        group.bench_with_input(BenchmarkId::new("to_vec", buf_len), &mem, |b, mem| {
+            b.iter(|| {
+                for iov in iovecs(mem) {
+                    black_box(iov.to_vec().expect("valid iovec"));
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("per element", buf_len), &mem, |b, mem| {
+            b.iter(|| {
+                for iov in iovecs(mem) {
+                    black_box(read_per_element(&iov).expect("valid iovec"));
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("as_raw", buf_len), &mem, |b, mem| {
+            b.iter(|| {
+                let mut bc = GuestBorrows::new();
+                for iov in iovecs(mem) {
+                    black_box(iov.as_raw_const(&mut bc).expect("valid iovec"));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn scatter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iovec scatter");
+    for buf_len in [16u32, 256, 4096, 65536].iter() {
+        let mem = iovec_memory(*buf_len);
+        let src = vec![0xa5u8; *buf_len as usize];
+        group.throughput(Throughput::Bytes((IOVECS * buf_len).into()));
+        group.bench_with_input(
+            BenchmarkId::new("copy_from_slice", buf_len),
+            &mem,
+            |b, mem| {
+                b.iter(|| {
+                    for iov in iovecs(mem) {
+                        iov.copy_from_slice(black_box(&src)).expect("valid iovec");
+                    }
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("per element", buf_len), &mem, |b, mem| {
+            b.iter(|| {
+                for iov in iovecs(mem) {
+                    for (elem, val) in iov.iter().zip(black_box(&src)) {
+                        u8::write(&elem.unwrap(), *val).expect("valid iovec");
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, gather, scatter);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::cell::UnsafeCell;
+use wiggle_runtime::{GuestBorrows, GuestMemory, GuestPtr};
+
+struct BenchMemory {
+    buffer: UnsafeCell<Vec<u8>>,
+}
+
+unsafe impl GuestMemory for BenchMemory {
+    fn base(&self) -> (*mut u8, u64) {
+        unsafe {
+            let buffer = &mut *self.buffer.get();
+            (buffer.as_mut_ptr(), buffer.len() as u64)
+        }
+    }
+}
+
+fn as_raw_str(c: &mut Criterion) {
+    let mut group = c.benchmark_group("GuestPtr<str>::as_raw");
+    for len in [64u32, 4096, 65536, 1 << 20].iter() {
+        // Mix ASCII with multi-byte sequences so neither validator gets to
+        // take an all-ASCII shortcut for the whole input.
+        let text = "wiggle: καλημέρα κόσμε, こんにちは世界 "
+            .chars()
+            .cycle()
+            .scan(0u32, |size, ch| {
+                *size += ch.len_utf8() as u32;
+                if *size <= *len {
+                    Some(ch)
+                } else {
+                    None
+                }
+            })
+            .collect::<String>();
+        let mem = BenchMemory {
+            buffer: UnsafeCell::new(text.into_bytes()),
+        };
+        let (_, mem_len) = mem.base();
+        group.throughput(Throughput::Bytes(mem_len));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &mem, |b, mem| {
+            let ptr: GuestPtr<str> = mem.ptr((0, mem_len));
+            b.iter(|| {
+                let mut bc = GuestBorrows::new();
+                black_box(ptr.as_raw(&mut bc).expect("valid string"));
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, as_raw_str);
+criterion_main!(benches);
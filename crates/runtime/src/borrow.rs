@@ -1,64 +1,484 @@
 use crate::region::Region;
-use crate::{GuestError, GuestPtr, GuestType};
+use crate::{CallLimits, GuestError, GuestLimits, GuestMemory, GuestPtr, GuestType};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
 
-#[derive(Debug)]
+/// Whether a borrow allows the region to be modified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorrowKind {
+    /// A read-only borrow, which may overlap other shared borrows.
+    Shared,
+    /// A read-write borrow, which may not overlap any other borrow.
+    Mut,
+}
+
+/// A borrow made with a [`GuestBorrows`], which can be handed back to
+/// [`GuestBorrows::unborrow`] to release the region before the end of the
+/// hostcall.
+pub struct BorrowHandle<'a> {
+    memory: u32,
+    region: Region,
+    kind: BorrowKind,
+    mem: Option<&'a dyn GuestMemory>,
+}
+
+impl BorrowHandle<'_> {
+    /// The index of the memory borrowed from, see
+    /// [`GuestMemory::memory_index`].
+    pub fn memory(&self) -> u32 {
+        self.memory
+    }
+
+    /// The region borrowed.
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn kind(&self) -> BorrowKind {
+        self.kind
+    }
+}
+
+impl fmt::Debug for BorrowHandle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BorrowHandle")
+            .field("memory", &self.memory)
+            .field("region", &self.region)
+            .field("kind", &self.kind)
+            .finish()
+    }
+}
+
+/// Tracks the regions of guest memory currently borrowed, with the usual
+/// rules: any number of shared borrows of a region, or a single mutable one.
+///
+/// Borrowed memory is kept as a sorted map of disjoint segments, so checking
+/// and making a borrow takes logarithmic time in the number of borrows
+/// outstanding.
+///
+/// Borrows are tracked separately for each memory of an instance using
+/// multiple memories, by [`GuestMemory::memory_index`], so that regions of
+/// different memories never conflict. Regions borrowed directly, with methods
+/// like [`GuestBorrows::borrow_mut`], are in memory 0.
+#[derive(Debug, Default)]
 pub struct GuestBorrows {
-    borrows: Vec<Region>,
+    /// Borrowed segments, keyed by their memory and start offset.
+    segments: BTreeMap<(u32, u64), Segment>,
+    /// The shared borrows making up the segments, so that only borrows which
+    /// were actually made get released.
+    shared: BTreeMap<(u32, u64, u64), usize>,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    end: u64,
+    state: SegmentState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SegmentState {
+    Mut,
+    /// Covered by this many shared borrows.
+    Shared(usize),
 }
 
 impl GuestBorrows {
     pub fn new() -> Self {
-        Self {
-            borrows: Vec::new(),
+        Self::default()
+    }
+
+    /// The number of borrows outstanding.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Releases every borrow, so that the checker can be reused for another
+    /// call.
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.shared.clear();
+        self.len = 0;
+    }
+
+    /// The segments of memory `m` overlapping `r`, in order.
+    fn overlapping(&self, m: u32, r: Region) -> impl Iterator<Item = (&(u32, u64), &Segment)> {
+        // Segments are disjoint, so only the last one starting before `r`
+        // can reach into it.
+        let before = self
+            .segments
+            .range((m, 0)..(m, r.start))
+            .next_back()
+            .filter(|(_, seg)| seg.end > r.start);
+        before
+            .into_iter()
+            .chain(self.segments.range((m, r.start)..(m, r.end())))
+    }
+
+    pub(crate) fn conflicts(&self, m: u32, r: Region, kind: BorrowKind) -> bool {
+        if r.is_empty() {
+            return false;
+        }
+        self.overlapping(m, r)
+            .any(|(_, seg)| kind == BorrowKind::Mut || seg.state == SegmentState::Mut)
+    }
+
+    /// Splits the segment of memory `m` containing `at`, if any, so that a
+    /// segment starts there.
+    fn split(&mut self, m: u32, at: u64) {
+        let (start, seg) = match self.segments.range((m, 0)..(m, at)).next_back() {
+            Some((start, seg)) if seg.end > at => (*start, *seg),
+            _ => return,
+        };
+        self.segments.insert(start, Segment { end: at, ..seg });
+        self.segments.insert((m, at), seg);
+    }
+
+    /// Records a borrow of `r` in memory `m` which has already been checked
+    /// not to conflict.
+    fn insert(&mut self, m: u32, r: Region, kind: BorrowKind) {
+        self.len += 1;
+        if r.is_empty() {
+            return;
+        }
+        let end = r.end();
+        match kind {
+            BorrowKind::Mut => {
+                let seg = Segment {
+                    end,
+                    state: SegmentState::Mut,
+                };
+                self.segments.insert((m, r.start), seg);
+            }
+            BorrowKind::Shared => {
+                *self.shared.entry((m, r.start, r.len)).or_insert(0) += 1;
+                self.split(m, r.start);
+                self.split(m, end);
+                let covered = self
+                    .segments
+                    .range((m, r.start)..(m, end))
+                    .map(|((_, start), seg)| (*start, seg.end))
+                    .collect::<Vec<_>>();
+                let mut cursor = r.start;
+                for (start, seg_end) in covered {
+                    if start > cursor {
+                        self.insert_shared_gap(m, cursor, start);
+                    }
+                    if let Some(seg) = self.segments.get_mut(&(m, start)) {
+                        if let SegmentState::Shared(n) = &mut seg.state {
+                            *n += 1;
+                        }
+                    }
+                    cursor = seg_end;
+                }
+                if cursor < end {
+                    self.insert_shared_gap(m, cursor, end);
+                }
+            }
         }
     }
 
-    fn is_borrowed(&self, r: Region) -> bool {
-        !self.borrows.iter().all(|b| !b.overlaps(r))
+    fn insert_shared_gap(&mut self, m: u32, start: u64, end: u64) {
+        let seg = Segment {
+            end,
+            state: SegmentState::Shared(1),
+        };
+        self.segments.insert((m, start), seg);
     }
 
-    pub(crate) fn borrow(&mut self, r: Region) -> Result<(), GuestError> {
-        if self.is_borrowed(r) {
+    pub(crate) fn borrow(&mut self, m: u32, r: Region, kind: BorrowKind) -> Result<(), GuestError> {
+        if self.conflicts(m, r, kind) {
             Err(GuestError::PtrBorrowed(r))
         } else {
-            self.borrows.push(r);
+            self.insert(m, r, kind);
             Ok(())
         }
     }
 
+    fn handle(r: Region, kind: BorrowKind) -> BorrowHandle<'static> {
+        BorrowHandle {
+            memory: 0,
+            region: r,
+            kind,
+            mem: None,
+        }
+    }
+
+    /// Borrows `r` for reading. Fails if any part of it is mutably borrowed.
+    pub fn borrow_shared(&mut self, r: Region) -> Result<BorrowHandle<'static>, GuestError> {
+        self.borrow(0, r, BorrowKind::Shared)?;
+        Ok(Self::handle(r, BorrowKind::Shared))
+    }
+
+    /// Borrows `r` for writing. Fails if any part of it is borrowed at all.
+    pub fn borrow_mut(&mut self, r: Region) -> Result<BorrowHandle<'static>, GuestError> {
+        self.borrow(0, r, BorrowKind::Mut)?;
+        Ok(Self::handle(r, BorrowKind::Mut))
+    }
+
+    /// Releases a borrow, both from this `GuestBorrows` and from the borrow
+    /// checker shared by the hostcall it was registered with, if any.
+    pub fn unborrow(&mut self, handle: BorrowHandle<'_>) {
+        self.release(handle.memory, handle.region, handle.kind);
+        if let Some(Ok(mut shared)) = handle
+            .mem
+            .and_then(|mem| mem.borrows())
+            .map(RefCell::try_borrow_mut)
+        {
+            shared.release(handle.memory, handle.region, handle.kind);
+        }
+    }
+
+    /// Mutably borrows `r` for the duration of `f`, which is passed the
+    /// `GuestBorrows` to make further borrows with.
+    pub fn with_borrow<R>(
+        &mut self,
+        r: Region,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> Result<R, GuestError> {
+        let handle = self.borrow_mut(r)?;
+        let ret = f(self);
+        self.unborrow(handle);
+        Ok(ret)
+    }
+
+    /// Releases a borrow of exactly `r` in memory `m` made with `borrow`.
+    pub(crate) fn release(&mut self, m: u32, r: Region, kind: BorrowKind) {
+        if r.is_empty() {
+            self.len = self.len.saturating_sub(1);
+            return;
+        }
+        let end = r.end();
+        match kind {
+            BorrowKind::Mut => match self.segments.get(&(m, r.start)) {
+                Some(seg) if seg.state == SegmentState::Mut && seg.end == end => {
+                    self.segments.remove(&(m, r.start));
+                }
+                _ => return,
+            },
+            BorrowKind::Shared => {
+                match self.shared.get_mut(&(m, r.start, r.len)) {
+                    Some(1) => {
+                        self.shared.remove(&(m, r.start, r.len));
+                    }
+                    Some(n) => *n -= 1,
+                    None => return,
+                }
+                // The borrow was made, so the segments exactly cover `r`.
+                let covered = self
+                    .segments
+                    .range((m, r.start)..(m, end))
+                    .map(|(start, _)| *start)
+                    .collect::<Vec<_>>();
+                for start in covered {
+                    let seg = self.segments.get_mut(&start).expect("covered segment");
+                    match &mut seg.state {
+                        SegmentState::Shared(1) => {
+                            self.segments.remove(&start);
+                        }
+                        SegmentState::Shared(n) => *n -= 1,
+                        SegmentState::Mut => unreachable!("shared borrow overlaps a mutable one"),
+                    }
+                }
+            }
+        }
+        self.len -= 1;
+    }
+
+    /// Borrow a region of `mem`, also registering it with the borrow checker
+    /// shared by the current hostcall, if `mem` has one.
+    pub(crate) fn borrow_in<'a>(
+        &mut self,
+        mem: &'a dyn GuestMemory,
+        r: Region,
+        kind: BorrowKind,
+    ) -> Result<BorrowHandle<'a>, GuestError> {
+        #[cfg(feature = "perf-counters")]
+        crate::counters::count_borrow_check(mem);
+        let m = mem.memory_index();
+        // If the shared checker is already mutably borrowed then it's the one
+        // we've been handed as `self`.
+        if let Some(Ok(mut shared)) = mem.borrows().map(RefCell::try_borrow_mut) {
+            if shared.conflicts(m, r, kind) {
+                return Err(GuestError::PtrBorrowed(r));
+            }
+            self.borrow(m, r, kind)?;
+            shared.insert(m, r, kind);
+        } else {
+            self.borrow(m, r, kind)?;
+        }
+        Ok(BorrowHandle {
+            memory: m,
+            region: r,
+            kind,
+            mem: Some(mem),
+        })
+    }
+
     /// Borrow the region of memory pointed to by a `GuestPtr`. This is required for safety if
     /// you are dereferencing `GuestPtr`s while holding a reference to a slice via
     /// `GuestPtr::as_raw`.
-    pub fn borrow_pointee<'a, T>(&mut self, p: &GuestPtr<'a, T>) -> Result<(), GuestError>
+    pub fn borrow_pointee<'a, T, M>(
+        &mut self,
+        p: &GuestPtr<'a, T, M>,
+    ) -> Result<BorrowHandle<'a>, GuestError>
     where
         T: GuestType<'a>,
+        M: ?Sized + GuestMemory,
     {
-        self.borrow(Region {
-            start: p.offset(),
-            len: T::guest_size(),
-        })
+        self.borrow_in(
+            p.mem().as_guest_memory(),
+            Region {
+                start: p.offset(),
+                len: T::guest_size().into(),
+            },
+            BorrowKind::Mut,
+        )
     }
 
     /// Borrow the slice of memory pointed to by a `GuestPtr<[T]>`. This is required for safety if
     /// you are dereferencing the `GuestPtr`s while holding a reference to another slice via
     /// `GuestPtr::as_raw`. Not required if using `GuestPtr::as_raw` on this pointer.
-    pub fn borrow_slice<'a, T>(&mut self, p: &GuestPtr<'a, [T]>) -> Result<(), GuestError>
+    pub fn borrow_slice<'a, T, M>(
+        &mut self,
+        p: &GuestPtr<'a, [T], M>,
+    ) -> Result<BorrowHandle<'a>, GuestError>
     where
         T: GuestType<'a>,
+        M: ?Sized + GuestMemory,
     {
         let (start, elems) = p.offset();
-        let len = T::guest_size()
+        let len = u64::from(T::guest_size())
             .checked_mul(elems)
-            .ok_or_else(|| GuestError::PtrOverflow)?;
-        self.borrow(Region { start, len })
+            .ok_or(GuestError::LenOverflow)?;
+        self.borrow_in(
+            p.mem().as_guest_memory(),
+            Region { start, len },
+            BorrowKind::Mut,
+        )
     }
 
     /// Borrow the slice of memory pointed to by a `GuestPtr<str>`. This is required for safety if
     /// you are dereferencing the `GuestPtr`s while holding a reference to another slice via
     /// `GuestPtr::as_raw`. Not required if using `GuestPtr::as_raw` on this pointer.
-    pub fn borrow_str(&mut self, p: &GuestPtr<str>) -> Result<(), GuestError> {
+    pub fn borrow_str<'a, M>(
+        &mut self,
+        p: &GuestPtr<'a, str, M>,
+    ) -> Result<BorrowHandle<'a>, GuestError>
+    where
+        M: ?Sized + GuestMemory,
+    {
         let (start, len) = p.offset();
-        self.borrow(Region { start, len })
+        self.borrow_in(
+            p.mem().as_guest_memory(),
+            Region { start, len },
+            BorrowKind::Mut,
+        )
+    }
+}
+
+/// A [`GuestMemory`] which tracks every borrow made through it.
+///
+/// Generated trampolines wrap the memory they are handed in a `CallMemory`
+/// for the duration of the hostcall, so that every `GuestPtr` created during
+/// the call, including pointers read out of structs and arrays, shares a
+/// single borrow checker. See [`GuestMemory::borrows`].
+///
+/// Like [`GuestPtr`], a `CallMemory` refers to the memory it wraps as a `dyn
+/// GuestMemory` unless its type is given as `M`.
+pub struct CallMemory<'a, M: ?Sized + GuestMemory = dyn GuestMemory + 'a> {
+    mem: &'a M,
+    borrows: RefCell<GuestBorrows>,
+    limits: Option<CallLimits>,
+    index: Option<u32>,
+}
+
+impl<'a, M: ?Sized + GuestMemory> CallMemory<'a, M> {
+    pub fn new(mem: &'a M) -> Self {
+        Self {
+            mem,
+            borrows: RefCell::new(GuestBorrows::new()),
+            limits: None,
+            index: None,
+        }
+    }
+
+    /// Like [`CallMemory::new`], but also enforces `limits` on the slices and
+    /// strings accessed during the call. See [`GuestMemory::limits`].
+    pub fn with_limits(mem: &'a M, limits: GuestLimits) -> Self {
+        Self {
+            limits: Some(CallLimits::new(limits)),
+            ..Self::new(mem)
+        }
+    }
+
+    /// Sets the index of the wrapped memory among the memories of the
+    /// instance. See [`GuestMemory::memory_index`].
+    pub fn with_memory_index(self, index: u32) -> Self {
+        Self {
+            index: Some(index),
+            ..self
+        }
+    }
+}
+
+unsafe impl<M: ?Sized + GuestMemory> GuestMemory for CallMemory<'_, M> {
+    #[inline]
+    fn base(&self) -> (*mut u8, u64) {
+        self.mem.base()
+    }
+    #[inline]
+    fn validate_size_align(
+        &self,
+        offset: u64,
+        align: usize,
+        len: u64,
+    ) -> Result<*mut u8, GuestError> {
+        self.mem.validate_size_align(offset, align, len)
+    }
+    #[inline]
+    fn validate_size(&self, offset: u64, len: u64) -> Result<*mut u8, GuestError> {
+        self.mem.validate_size(offset, len)
+    }
+    #[inline]
+    fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<(), GuestError> {
+        self.mem.read_bytes(offset, buf)
+    }
+    #[inline]
+    fn write_bytes(&self, offset: u64, bytes: &[u8]) -> Result<(), GuestError> {
+        self.mem.write_bytes(offset, bytes)
+    }
+    fn require_alignment(&self) -> bool {
+        self.mem.require_alignment()
+    }
+    fn canonicalize_nans(&self) -> bool {
+        self.mem.canonicalize_nans()
+    }
+    fn max_copy_len(&self) -> Option<u64> {
+        self.mem.max_copy_len()
+    }
+    fn memory_index(&self) -> u32 {
+        self.index.unwrap_or_else(|| self.mem.memory_index())
+    }
+    fn borrows(&self) -> Option<&RefCell<GuestBorrows>> {
+        // A hostcall made while servicing another one shares the borrows of
+        // the outermost call.
+        self.mem.borrows().or(Some(&self.borrows))
+    }
+    fn limits(&self) -> Option<&CallLimits> {
+        // Likewise, nested hostcalls count against the outermost call's
+        // limits.
+        self.mem.limits().or(self.limits.as_ref())
+    }
+    #[cfg(feature = "perf-counters")]
+    fn perf_counters(&self) -> Option<&crate::PerfCounters> {
+        self.mem.perf_counters()
     }
 }
 
@@ -71,15 +491,15 @@ mod test {
         let r1 = Region::new(0, 10);
         let r2 = Region::new(10, 10);
         assert!(!r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        bs.borrow(r2).expect("can borrow r2");
+        bs.borrow_mut(r1).expect("can borrow r1");
+        bs.borrow_mut(r2).expect("can borrow r2");
 
         let mut bs = GuestBorrows::new();
         let r1 = Region::new(10, 10);
         let r2 = Region::new(0, 10);
         assert!(!r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        bs.borrow(r2).expect("can borrow r2");
+        bs.borrow_mut(r1).expect("can borrow r1");
+        bs.borrow_mut(r2).expect("can borrow r2");
     }
 
     #[test]
@@ -88,29 +508,29 @@ mod test {
         let r1 = Region::new(0, 10);
         let r2 = Region::new(9, 10);
         assert!(r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        assert!(bs.borrow(r2).is_err(), "cant borrow r2");
+        bs.borrow_mut(r1).expect("can borrow r1");
+        assert!(bs.borrow_mut(r2).is_err(), "cant borrow r2");
 
         let mut bs = GuestBorrows::new();
         let r1 = Region::new(0, 10);
         let r2 = Region::new(2, 5);
         assert!(r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        assert!(bs.borrow(r2).is_err(), "cant borrow r2");
+        bs.borrow_mut(r1).expect("can borrow r1");
+        assert!(bs.borrow_mut(r2).is_err(), "cant borrow r2");
 
         let mut bs = GuestBorrows::new();
         let r1 = Region::new(9, 10);
         let r2 = Region::new(0, 10);
         assert!(r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        assert!(bs.borrow(r2).is_err(), "cant borrow r2");
+        bs.borrow_mut(r1).expect("can borrow r1");
+        assert!(bs.borrow_mut(r2).is_err(), "cant borrow r2");
 
         let mut bs = GuestBorrows::new();
         let r1 = Region::new(2, 5);
         let r2 = Region::new(0, 10);
         assert!(r1.overlaps(r2));
-        bs.borrow(r1).expect("can borrow r1");
-        assert!(bs.borrow(r2).is_err(), "cant borrow r2");
+        bs.borrow_mut(r1).expect("can borrow r1");
+        assert!(bs.borrow_mut(r2).is_err(), "cant borrow r2");
 
         let mut bs = GuestBorrows::new();
         let r1 = Region::new(2, 5);
@@ -118,9 +538,164 @@ mod test {
         let r3 = Region::new(15, 5);
         let r4 = Region::new(0, 10);
         assert!(r1.overlaps(r4));
-        bs.borrow(r1).expect("can borrow r1");
-        bs.borrow(r2).expect("can borrow r2");
-        bs.borrow(r3).expect("can borrow r3");
-        assert!(bs.borrow(r4).is_err(), "cant borrow r4");
+        bs.borrow_mut(r1).expect("can borrow r1");
+        bs.borrow_mut(r2).expect("can borrow r2");
+        bs.borrow_mut(r3).expect("can borrow r3");
+        assert!(bs.borrow_mut(r4).is_err(), "cant borrow r4");
+    }
+
+    #[test]
+    fn shared() {
+        let mut bs = GuestBorrows::new();
+        let r1 = Region::new(0, 10);
+        let r2 = Region::new(5, 10);
+        let h1 = bs.borrow_shared(r1).expect("can borrow r1");
+        let h1_again = bs.borrow_shared(r1).expect("can borrow r1 again");
+        let h2 = bs.borrow_shared(r2).expect("can borrow overlapping r2");
+        assert!(bs.borrow_mut(r2).is_err(), "cant borrow r2 mutably");
+        bs.borrow_mut(Region::new(15, 5))
+            .expect("can mutably borrow past the shared borrows");
+        assert!(
+            bs.borrow_shared(Region::new(19, 1)).is_err(),
+            "cant share a mutable borrow"
+        );
+
+        bs.unborrow(h2);
+        bs.unborrow(h1);
+        assert!(bs.borrow_mut(r1).is_err(), "r1 is still shared once");
+        bs.unborrow(h1_again);
+        bs.borrow_mut(r1)
+            .expect("can borrow r1 mutably once released");
+    }
+
+    #[test]
+    fn unborrow() {
+        let mut bs = GuestBorrows::new();
+        let r1 = Region::new(0, 10);
+        let handle = bs.borrow_mut(r1).expect("can borrow r1");
+        assert!(bs.borrow_shared(r1).is_err(), "cant borrow r1 twice");
+        bs.unborrow(handle);
+        let handle = bs.borrow_shared(r1).expect("can borrow r1 once released");
+        assert_eq!(handle.region(), r1);
+        assert_eq!(handle.kind(), BorrowKind::Shared);
+    }
+
+    #[test]
+    fn with_borrow() {
+        let mut bs = GuestBorrows::new();
+        let r1 = Region::new(0, 10);
+        let inner = bs
+            .with_borrow(r1, |bs| {
+                assert!(bs.borrow_shared(r1).is_err(), "r1 is borrowed");
+                bs.borrow_mut(Region::new(10, 10)).is_ok()
+            })
+            .expect("can borrow r1");
+        assert!(inner, "can borrow next to r1");
+        bs.borrow_mut(r1).expect("r1 was released");
+        assert!(bs.with_borrow(r1, |_| ()).is_err(), "r1 is borrowed again");
+    }
+
+    #[test]
+    fn memories_borrowed_separately() {
+        let mem0 = crate::OwnedMemory::new(16);
+        let mem1 = crate::OwnedMemory::new(16);
+        let mem1 = CallMemory::new(&mem1).with_memory_index(1);
+        let mut bs = GuestBorrows::new();
+        let in0 = GuestPtr::<[u8]>::new(&mem0, (0, 8));
+        let in1 = GuestPtr::<[u8]>::new(&mem1, (0, 8));
+        bs.borrow_slice(&in0).expect("can borrow in memory 0");
+        let handle = bs.borrow_slice(&in1).expect("can borrow in memory 1");
+        assert_eq!(handle.memory(), 1);
+        assert!(bs.borrow_slice(&in1).is_err(), "already borrowed");
+        bs.unborrow(handle);
+        bs.borrow_slice(&in1).expect("released in memory 1");
+        assert!(bs.borrow_mut(Region::new(4, 1)).is_err(), "memory 0");
+    }
+
+    #[test]
+    fn failed_as_raw_releases_its_borrow() {
+        let mem = crate::OwnedMemory::new(16);
+        mem.write_bytes(0, &[0xff, 0xfe]).expect("write");
+        let call = CallMemory::new(&mem);
+        let mut bs = GuestBorrows::new();
+        let invalid = GuestPtr::<str>::new(&call, (0, 2));
+        assert!(matches!(
+            invalid.as_raw(&mut bs),
+            Err(GuestError::InvalidUtf8 { .. })
+        ));
+        assert!(matches!(
+            invalid.as_raw_const(&mut bs),
+            Err(GuestError::InvalidUtf8 { .. })
+        ));
+        assert!(bs.is_empty());
+        GuestPtr::<[u8]>::new(&call, (0, 2))
+            .as_slice_mut()
+            .expect("not left borrowed by the failed views");
+        bs.borrow_mut(Region::new(0, 2))
+            .expect("not left borrowed in the local checker either");
+    }
+
+    #[test]
+    fn reads_and_writes_respect_borrowed_slices() {
+        let mem = crate::OwnedMemory::new(16);
+        let call = CallMemory::new(&mem);
+        let slice = GuestPtr::<[u8]>::new(&call, (0, 4));
+        let inside = GuestPtr::<u32>::new(&call, 0);
+        let outside = GuestPtr::<u32>::new(&call, 4);
+
+        let shared = slice.as_slice().expect("borrow shared");
+        inside.read().expect("reads share the slice");
+        assert!(matches!(inside.write(1), Err(GuestError::PtrBorrowed(_))));
+        assert!(slice.copy_from_slice(&[1, 2, 3, 4]).is_err());
+        drop(shared);
+
+        let mut exclusive = slice.as_slice_mut().expect("borrow mutably");
+        exclusive[0] = 1;
+        assert!(matches!(inside.read(), Err(GuestError::PtrBorrowed(_))));
+        assert!(slice.to_vec().is_err());
+        outside.write(2).expect("outside the slice");
+        drop(exclusive);
+
+        inside.write(3).expect("released");
+        assert_eq!(inside.read().expect("read"), 3);
+    }
+
+    #[test]
+    fn len_and_clear() {
+        let mut bs = GuestBorrows::new();
+        assert!(bs.is_empty());
+        let h1 = bs.borrow_shared(Region::new(0, 10)).expect("can borrow");
+        bs.borrow_shared(Region::new(5, 10)).expect("can borrow");
+        bs.borrow_mut(Region::new(20, 10)).expect("can borrow");
+        assert_eq!(bs.len(), 3);
+        bs.unborrow(h1);
+        assert_eq!(bs.len(), 2);
+        bs.clear();
+        assert!(bs.is_empty());
+        bs.borrow_mut(Region::new(0, 30))
+            .expect("can borrow everything once cleared");
+    }
+
+    #[test]
+    fn shared_spanning_many() {
+        let mut bs = GuestBorrows::new();
+        for i in 0..100 {
+            bs.borrow_shared(Region::new(i * 10, 5))
+                .expect("can borrow");
+        }
+        let wide = bs
+            .borrow_shared(Region::new(0, 1000))
+            .expect("can share across the others");
+        assert!(
+            bs.borrow_mut(Region::new(997, 1)).is_err(),
+            "covered by wide"
+        );
+        bs.unborrow(wide);
+        bs.borrow_mut(Region::new(997, 1))
+            .expect("gap is free once wide is released");
+        assert!(bs.borrow_mut(Region::new(504, 1)).is_err(), "still shared");
+        // Releasing a borrow which was never made leaves the rest alone.
+        bs.release(0, Region::new(500, 10), BorrowKind::Shared);
+        assert!(bs.borrow_mut(Region::new(504, 1)).is_err(), "still shared");
     }
 }
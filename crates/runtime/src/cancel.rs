@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// A cooperative cancellation flag, with an optional deadline, shared between
+/// an embedder and the hostcalls it runs.
+///
+/// Clones of a token share their state, so the embedder can keep one clone to
+/// cancel calls from another thread while the ctx holds another. Cancellation
+/// is checked on entry to every generated function when it is enabled with the
+/// `cancel` field of `from_witx!`, and long-running host implementations can
+/// poll [`CancelToken::is_cancelled`] themselves.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all calls from now on, until [`CancelToken::reset`] is called.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Cancels all calls made after `deadline`.
+    pub fn set_deadline(&self, deadline: Instant) {
+        *self.inner.deadline.lock().unwrap() = Some(deadline);
+    }
+
+    /// Clears both an explicit cancellation and the deadline.
+    pub fn reset(&self) {
+        self.inner.cancelled.store(false, Ordering::SeqCst);
+        *self.inner.deadline.lock().unwrap() = None;
+    }
+
+    /// Returns whether the token has been cancelled or its deadline has
+    /// passed.
+    pub fn is_cancelled(&self) -> bool {
+        if self.inner.cancelled.load(Ordering::SeqCst) {
+            return true;
+        }
+        match *self.inner.deadline.lock().unwrap() {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        }
+    }
+}
+
+/// Implemented by contexts whose hostcalls can be cancelled through a
+/// [`CancelToken`].
+pub trait CancelCtx<E> {
+    /// Returns the token to check, or `None` if calls can't be cancelled.
+    fn cancel_token(&self) -> Option<&CancelToken>;
+
+    /// The error a cancelled call returns to the guest. Returning `None`, the
+    /// default, makes cancelled calls trap by panicking instead, as do
+    /// cancelled calls of functions which have no error result.
+    fn cancelled_error(&self) -> Option<E> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn cancel_is_shared_between_clones() {
+        let token = CancelToken::new();
+        let held = token.clone();
+        assert!(!held.is_cancelled());
+        token.cancel();
+        assert!(held.is_cancelled());
+        token.reset();
+        assert!(!held.is_cancelled());
+    }
+
+    #[test]
+    fn deadline() {
+        let token = CancelToken::new();
+        token.set_deadline(Instant::now() + Duration::from_secs(3600));
+        assert!(!token.is_cancelled());
+        token.set_deadline(Instant::now() - Duration::from_millis(1));
+        assert!(token.is_cancelled());
+        token.reset();
+        assert!(!token.is_cancelled());
+    }
+}
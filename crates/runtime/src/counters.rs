@@ -0,0 +1,100 @@
+use crate::GuestMemory;
+use core::cell::Cell;
+
+/// Counts the checks made on a memory's behalf, for profiling embedders.
+///
+/// A memory hands its counters out through [`GuestMemory::perf_counters`],
+/// and every [`CallMemory`] wrapping it during a hostcall forwards them, so
+/// resetting them before a call and reading them after gives the cost of that
+/// call.
+///
+/// Validations are counted by the default implementation of
+/// [`GuestMemory::validate_size`], which all bounds checks of host-mapped
+/// memories go through unless it's overridden.
+///
+/// [`CallMemory`]: crate::CallMemory
+#[derive(Debug, Default)]
+pub struct PerfCounters {
+    validations: Cell<u64>,
+    borrow_checks: Cell<u64>,
+}
+
+impl PerfCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of pointers bounds checked.
+    pub fn validations(&self) -> u64 {
+        self.validations.get()
+    }
+
+    /// The number of regions checked against the borrows outstanding, for raw
+    /// views and guards like [`GuestSlice`](crate::GuestSlice).
+    pub fn borrow_checks(&self) -> u64 {
+        self.borrow_checks.get()
+    }
+
+    /// Sets every counter back to zero.
+    pub fn reset(&self) {
+        self.validations.set(0);
+        self.borrow_checks.set(0);
+    }
+}
+
+#[inline]
+pub(crate) fn count_validation<M: ?Sized + GuestMemory>(mem: &M) {
+    if let Some(c) = mem.perf_counters() {
+        c.validations.set(c.validations.get() + 1);
+    }
+}
+
+#[inline]
+pub(crate) fn count_borrow_check<M: ?Sized + GuestMemory>(mem: &M) {
+    if let Some(c) = mem.perf_counters() {
+        c.borrow_checks.set(c.borrow_checks.get() + 1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CallMemory, GuestMemory, GuestPtr, PerfCounters};
+    use core::cell::UnsafeCell;
+
+    #[repr(C, align(8))]
+    struct Counted {
+        bytes: UnsafeCell<[u8; 16]>,
+        counters: PerfCounters,
+    }
+
+    unsafe impl GuestMemory for Counted {
+        fn base(&self) -> (*mut u8, u64) {
+            (self.bytes.get() as *mut u8, 16)
+        }
+        fn perf_counters(&self) -> Option<&PerfCounters> {
+            Some(&self.counters)
+        }
+    }
+
+    #[test]
+    fn counts_through_call_memory() {
+        let mem = Counted {
+            bytes: UnsafeCell::new([0; 16]),
+            counters: PerfCounters::new(),
+        };
+        let call = CallMemory::new(&mem);
+        GuestPtr::<u32>::new(&call, 0).write(1).expect("write");
+        GuestPtr::<u32>::new(&call, 4).read().expect("read");
+        {
+            let _slice = GuestPtr::<[u8]>::new(&call, (8, 8))
+                .as_slice()
+                .expect("borrow");
+        }
+        assert_eq!(mem.counters.validations(), 3);
+        assert_eq!(mem.counters.borrow_checks(), 1);
+
+        mem.counters.reset();
+        assert_eq!(mem.counters.validations(), 0);
+        assert_eq!(mem.counters.borrow_checks(), 0);
+    }
+}
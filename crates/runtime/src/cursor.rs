@@ -0,0 +1,69 @@
+use crate::{GuestError, GuestMemory, GuestPtr, GuestType};
+
+/// Reads and writes a sequence of values in a guest byte buffer, such as the
+/// variable-length records of an `fd_readdir` buffer.
+///
+/// The cursor keeps track of the offset of the next value, and checks that
+/// each value lies entirely within the buffer before touching it, returning
+/// [`GuestError::IndexOutOfBounds`] otherwise. Values are laid out back to
+/// back, with no padding, so they are only aligned if the buffer and the
+/// preceding values make them so.
+pub struct GuestCursor<'a, M: ?Sized + GuestMemory = dyn GuestMemory + 'a> {
+    buf: GuestPtr<'a, [u8], M>,
+    pos: u64,
+}
+
+impl<'a, M: ?Sized + GuestMemory> GuestCursor<'a, M> {
+    /// Creates a cursor at the start of `buf`.
+    pub fn new(buf: GuestPtr<'a, [u8], M>) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// The offset of the next value from the start of the buffer.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// The number of bytes left after the cursor.
+    pub fn remaining(&self) -> u64 {
+        self.buf.len() - self.pos
+    }
+
+    /// Reads the next value, and advances past it.
+    pub fn read_next<T: GuestType<'a> + 'a>(&mut self) -> Result<T, GuestError> {
+        let val = self.next::<T>()?.read()?;
+        self.pos += u64::from(T::guest_size());
+        Ok(val)
+    }
+
+    /// Writes `val` as the next value, and advances past it.
+    pub fn write_next<T: GuestType<'a> + 'a>(&mut self, val: T) -> Result<(), GuestError> {
+        self.next::<T>()?.write(val)?;
+        self.pos += u64::from(T::guest_size());
+        Ok(())
+    }
+
+    /// Advances `n` bytes without touching them.
+    pub fn skip(&mut self, n: u64) -> Result<(), GuestError> {
+        self.check(n)?;
+        self.pos += n;
+        Ok(())
+    }
+
+    /// Returns a pointer to the next `T`, after checking it is within the
+    /// buffer.
+    fn next<T: GuestType<'a> + 'a>(&self) -> Result<GuestPtr<'a, T, M>, GuestError> {
+        self.check(T::guest_size().into())?;
+        Ok(self.buf.as_ptr().add(self.pos)?.cast())
+    }
+
+    fn check(&self, n: u64) -> Result<(), GuestError> {
+        if n > self.remaining() {
+            return Err(GuestError::IndexOutOfBounds {
+                index: self.pos..self.pos.saturating_add(n),
+                len: self.buf.len(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use alloc::string::String;
+use core::error::Error;
+use core::fmt;
+
+/// A core wasm value, passed to and returned from the `dispatch` functions
+/// of generated modules.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl Value {
+    /// The type of the value.
+    pub fn ty(self) -> ValueType {
+        match self {
+            Value::I32(_) => ValueType::I32,
+            Value::I64(_) => ValueType::I64,
+            Value::F32(_) => ValueType::F32,
+            Value::F64(_) => ValueType::F64,
+        }
+    }
+    /// The value, if it's an `i32`.
+    pub fn i32(self) -> Option<i32> {
+        match self {
+            Value::I32(v) => Some(v),
+            _ => None,
+        }
+    }
+    /// The value, if it's an `i64`.
+    pub fn i64(self) -> Option<i64> {
+        match self {
+            Value::I64(v) => Some(v),
+            _ => None,
+        }
+    }
+    /// The value, if it's an `f32`.
+    pub fn f32(self) -> Option<f32> {
+        match self {
+            Value::F32(v) => Some(v),
+            _ => None,
+        }
+    }
+    /// The value, if it's an `f64`.
+    pub fn f64(self) -> Option<f64> {
+        match self {
+            Value::F64(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Value {
+        Value::I32(v)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Value {
+        Value::I64(v)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(v: f32) -> Value {
+        Value::F32(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Value {
+        Value::F64(v)
+    }
+}
+
+/// The type of a core wasm value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+/// A generated function taking and returning core values, as listed in the
+/// `ABI_FUNCTIONS` table of its module, for embedders wiring up imports
+/// themselves.
+///
+/// `func` points to the function, which is a
+/// `fn(&Ctx, &Memory, params...) -> results` with the core types of
+/// `params` and `results`, with the results as a tuple when there's more
+/// than one. With `multi_memory`, the memory is instead passed as a
+/// `&dyn MemoryProvider` and a `u32` memory index. It has to be transmuted
+/// back to exactly that type to be called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbiFunc {
+    /// The witx name of the function, which is the name it's imported as.
+    pub name: &'static str,
+    /// The function.
+    pub func: *const (),
+    /// The core types of the params.
+    pub params: &'static [ValueType],
+    /// The core types of the results.
+    pub results: &'static [ValueType],
+}
+
+/// Why a `dispatch` function couldn't call a function. Errors of the
+/// function itself are returned as its error code, as for direct calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DispatchError {
+    /// The module has no function of this name, or it wasn't generated.
+    UnknownFunction(String),
+    /// The function takes `expected` core values, but was passed `got`.
+    ArgCount {
+        func: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    /// The value at `index` has the wrong core type.
+    ArgType { func: &'static str, index: usize },
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DispatchError::UnknownFunction(name) => write!(f, "unknown function `{}`", name),
+            DispatchError::ArgCount {
+                func,
+                expected,
+                got,
+            } => write!(
+                f,
+                "`{}` takes {} arguments, but was passed {}",
+                func, expected, got
+            ),
+            DispatchError::ArgType { func, index } => {
+                write!(f, "argument {} to `{}` has the wrong type", index, func)
+            }
+        }
+    }
+}
+
+impl Error for DispatchError {}
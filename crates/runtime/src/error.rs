@@ -1,36 +1,538 @@
-use crate::Region;
-use thiserror::Error;
+use crate::{GuestLimit, Region};
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::error::Error;
+use core::fmt;
+use core::num::TryFromIntError;
+use core::ops::Range;
+use core::str::Utf8Error;
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum GuestError {
-    #[error("Invalid flag value {0}")]
     InvalidFlagValue(&'static str),
-    #[error("Invalid enum value {0}")]
     InvalidEnumValue(&'static str),
-    #[error("Pointer overflow")]
     PtrOverflow,
-    #[error("Pointer out of bounds: {0:?}")]
     PtrOutOfBounds(Region),
-    #[error("Pointer not aligned to {1}: {0:?}")]
+    /// An access starts past the end of a memory of `memory_size` bytes.
+    OffsetOutOfBounds {
+        offset: u64,
+        memory_size: u64,
+    },
+    /// An access at `region` starts in a memory of `memory_size` bytes, but is
+    /// too long to fit in it.
+    LenOutOfBounds {
+        region: Region,
+        memory_size: u64,
+    },
+    /// The length of an access, in bytes, runs past the end of the address
+    /// space.
+    LenOverflow,
     PtrNotAligned(Region, u32),
-    #[error("Pointer already borrowed: {0:?}")]
     PtrBorrowed(Region),
-    #[error("In func {funcname}:{location}:")]
+    IndexOutOfBounds {
+        index: Range<u64>,
+        len: u64,
+    },
+    NoBorrowChecker,
+    UnknownMemory(u32),
+    LimitExceeded {
+        limit: GuestLimit,
+        requested: u64,
+        max: u64,
+    },
+    TooLarge {
+        len: u64,
+        max: u64,
+    },
     InFunc {
         funcname: &'static str,
         location: &'static str,
-        #[source]
+        /// Where the parameter or result is declared, if known.
+        witx: Option<WitxLocation>,
         err: Box<GuestError>,
     },
-    #[error("In data {typename}.{field}:")]
     InDataField {
         typename: String,
         field: String,
-        #[source]
+        /// Where the field is declared, if known.
+        witx: Option<WitxLocation>,
         err: Box<GuestError>,
     },
-    #[error("Invalid UTF-8 encountered: {0:?}")]
-    InvalidUtf8(#[from] ::std::str::Utf8Error),
-    #[error("Int conversion error: {0:?}")]
-    TryFromIntError(#[from] ::std::num::TryFromIntError),
+    /// A string at `region` isn't UTF-8. The error says where in the string
+    /// decoding failed.
+    InvalidUtf8 {
+        region: Region,
+        err: Utf8Error,
+    },
+    TryFromIntError(TryFromIntError),
+    /// A failure of the embedder's own, threaded through code working on
+    /// guest memory. See [`GuestError::custom`].
+    Custom(CustomError),
+}
+
+impl fmt::Display for GuestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GuestError::InvalidFlagValue(name) => write!(f, "Invalid flag value {}", name),
+            GuestError::InvalidEnumValue(name) => write!(f, "Invalid enum value {}", name),
+            GuestError::PtrOverflow => write!(f, "Pointer overflow"),
+            GuestError::PtrOutOfBounds(region) => write!(f, "Pointer out of bounds: {:?}", region),
+            GuestError::OffsetOutOfBounds {
+                offset,
+                memory_size,
+            } => write!(
+                f,
+                "Offset {} out of bounds of a memory of {} bytes",
+                offset, memory_size
+            ),
+            GuestError::LenOutOfBounds {
+                region,
+                memory_size,
+            } => write!(
+                f,
+                "Length of {:?} out of bounds of a memory of {} bytes",
+                region, memory_size
+            ),
+            GuestError::LenOverflow => write!(f, "Length overflow"),
+            GuestError::PtrNotAligned(region, align) => {
+                write!(f, "Pointer not aligned to {}: {:?}", align, region)
+            }
+            GuestError::PtrBorrowed(region) => write!(f, "Pointer already borrowed: {:?}", region),
+            GuestError::IndexOutOfBounds { index, len } => write!(
+                f,
+                "Index {:?} out of bounds for a slice of length {}",
+                index, len
+            ),
+            GuestError::NoBorrowChecker => {
+                write!(f, "Memory has no borrow checker shared by the hostcall")
+            }
+            GuestError::UnknownMemory(index) => write!(f, "No memory with index {}", index),
+            GuestError::LimitExceeded {
+                limit,
+                requested,
+                max,
+            } => write!(
+                f,
+                "Guest data exceeds {} limit: {} > {}",
+                limit, requested, max
+            ),
+            GuestError::TooLarge { len, max } => {
+                write!(f, "Guest data too large to copy: {} > {}", len, max)
+            }
+            GuestError::InFunc {
+                funcname,
+                location,
+                witx,
+                ..
+            } => {
+                write!(f, "In func {}:{}", funcname, location)?;
+                write_at(f, witx)?;
+                write!(f, ":")
+            }
+            GuestError::InDataField {
+                typename,
+                field,
+                witx,
+                ..
+            } => {
+                write!(f, "In data {}.{}", typename, field)?;
+                write_at(f, witx)?;
+                write!(f, ":")
+            }
+            GuestError::InvalidUtf8 { region, err } => write!(
+                f,
+                "Invalid UTF-8 encountered in string at {:?}: {}",
+                region, err
+            ),
+            GuestError::TryFromIntError(err) => write!(f, "Int conversion error: {:?}", err),
+            GuestError::Custom(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for GuestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GuestError::InFunc { err, .. } | GuestError::InDataField { err, .. } => Some(&**err),
+            GuestError::InvalidUtf8 { err, .. } => Some(err),
+            GuestError::TryFromIntError(err) => Some(err),
+            // Custom errors are transparent, so their source is the wrapped
+            // error's.
+            GuestError::Custom(err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<TryFromIntError> for GuestError {
+    fn from(err: TryFromIntError) -> Self {
+        GuestError::TryFromIntError(err)
+    }
+}
+
+impl From<CustomError> for GuestError {
+    fn from(err: CustomError) -> Self {
+        GuestError::Custom(err)
+    }
+}
+
+impl GuestError {
+    /// Wraps an error of the embedder's own as a [`GuestError::Custom`].
+    pub fn custom(err: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        GuestError::Custom(CustomError(err.into()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for GuestError {
+    fn from(err: std::io::Error) -> Self {
+        GuestError::custom(err)
+    }
+}
+
+/// An arbitrary error carried by [`GuestError::Custom`].
+///
+/// Custom errors can't be compared, so they are only equal to themselves.
+pub struct CustomError(Box<dyn Error + Send + Sync>);
+
+impl CustomError {
+    /// Returns the wrapped error if it is an `E`.
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+
+    pub fn into_inner(self) -> Box<dyn Error + Send + Sync> {
+        self.0
+    }
+}
+
+impl fmt::Debug for CustomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for CustomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for CustomError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl PartialEq for CustomError {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(
+            &*self.0 as *const _ as *const u8,
+            &*other.0 as *const _ as *const u8,
+        )
+    }
+}
+
+impl Eq for CustomError {}
+
+/// A position in a witx file, recorded by generated code so errors can point
+/// back at the interface definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WitxLocation {
+    pub path: &'static str,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for WitxLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path, self.line, self.column)
+    }
+}
+
+/// The generated function a [`GuestError`] happened in, passed to
+/// [`GuestErrorType::from_call_error`](crate::GuestErrorType::from_call_error)
+/// so errors can be accounted per function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallContext {
+    /// The witx name of the module.
+    pub module: &'static str,
+    /// The witx name of the function.
+    pub function: &'static str,
+    /// The witx name of the param or result being read or written, if the
+    /// error is about one.
+    pub param: Option<&'static str>,
+}
+
+impl fmt::Display for CallContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}::{}", self.module, self.function)?;
+        if let Some(param) = self.param {
+            write!(f, " ({})", param)?;
+        }
+        Ok(())
+    }
+}
+
+/// The error converting a value of a generated type to the type of the same
+/// name generated from another witx document, which has no counterpart for
+/// the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConversionError {
+    /// The witx name of the type converted from.
+    pub from: &'static str,
+    /// The witx name of the type converted to.
+    pub to: &'static str,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "this `{}` has no counterpart in `{}`",
+            self.from, self.to
+        )
+    }
+}
+
+impl Error for ConversionError {}
+
+/// The error parsing a generated enum or flags type from a name which isn't
+/// one of its variants or flags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNameError {
+    pub typename: &'static str,
+    pub name: String,
+}
+
+impl fmt::Display for ParseNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` isn't a name of {}", self.name, self.typename)
+    }
+}
+
+impl Error for ParseNameError {}
+
+/// The error building a generated struct without setting a field which has
+/// no default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingFieldError {
+    pub typename: &'static str,
+    pub field: &'static str,
+}
+
+impl fmt::Display for MissingFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "field `{}` of {} wasn't set", self.field, self.typename)
+    }
+}
+
+impl Error for MissingFieldError {}
+
+fn write_at(f: &mut fmt::Formatter, witx: &Option<WitxLocation>) -> fmt::Result {
+    match witx {
+        Some(witx) => write!(f, " (at {})", witx),
+        None => Ok(()),
+    }
+}
+
+/// A stable, machine-readable code for each kind of [`GuestError`].
+///
+/// Unlike the error itself, codes carry no details and won't change between
+/// releases, so they can be logged, counted, or sent elsewhere as numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+#[non_exhaustive]
+pub enum GuestErrorCode {
+    InvalidFlagValue = 1,
+    InvalidEnumValue = 2,
+    PtrOverflow = 3,
+    PtrOutOfBounds = 4,
+    PtrNotAligned = 5,
+    PtrBorrowed = 6,
+    IndexOutOfBounds = 7,
+    NoBorrowChecker = 8,
+    LimitExceeded = 9,
+    TooLarge = 10,
+    InvalidUtf8 = 11,
+    TryFromIntError = 12,
+    Custom = 13,
+    UnknownMemory = 14,
+    OffsetOutOfBounds = 15,
+    LenOutOfBounds = 16,
+    LenOverflow = 17,
+}
+
+impl GuestError {
+    /// Returns the code for this kind of error.
+    ///
+    /// Errors wrapped in [`GuestError::InFunc`] or [`GuestError::InDataField`]
+    /// for context have the code of the error they wrap.
+    pub fn code(&self) -> GuestErrorCode {
+        match self {
+            GuestError::InvalidFlagValue(_) => GuestErrorCode::InvalidFlagValue,
+            GuestError::InvalidEnumValue(_) => GuestErrorCode::InvalidEnumValue,
+            GuestError::PtrOverflow => GuestErrorCode::PtrOverflow,
+            GuestError::PtrOutOfBounds(_) => GuestErrorCode::PtrOutOfBounds,
+            GuestError::OffsetOutOfBounds { .. } => GuestErrorCode::OffsetOutOfBounds,
+            GuestError::LenOutOfBounds { .. } => GuestErrorCode::LenOutOfBounds,
+            GuestError::LenOverflow => GuestErrorCode::LenOverflow,
+            GuestError::PtrNotAligned(..) => GuestErrorCode::PtrNotAligned,
+            GuestError::PtrBorrowed(_) => GuestErrorCode::PtrBorrowed,
+            GuestError::IndexOutOfBounds { .. } => GuestErrorCode::IndexOutOfBounds,
+            GuestError::NoBorrowChecker => GuestErrorCode::NoBorrowChecker,
+            GuestError::UnknownMemory(_) => GuestErrorCode::UnknownMemory,
+            GuestError::LimitExceeded { .. } => GuestErrorCode::LimitExceeded,
+            GuestError::TooLarge { .. } => GuestErrorCode::TooLarge,
+            GuestError::InFunc { err, .. } | GuestError::InDataField { err, .. } => err.code(),
+            GuestError::InvalidUtf8 { .. } => GuestErrorCode::InvalidUtf8,
+            GuestError::TryFromIntError(_) => GuestErrorCode::TryFromIntError,
+            GuestError::Custom(_) => GuestErrorCode::Custom,
+        }
+    }
+}
+
+/// Maps [`GuestError`]s onto an errno type, such as WASI's `errno`.
+///
+/// Implementors only say which of their values stand for the few kinds of
+/// failure guest memory accesses have, and get a consistent mapping for every
+/// error. `GuestErrorType::from_error` impls can then delegate to
+/// [`MapGuestError::map_guest_error`] after logging or recording the error.
+pub trait MapGuestError: Sized {
+    /// Bad address, like `EFAULT`: for pointers out of bounds, misaligned,
+    /// already borrowed, or into a memory which doesn't exist.
+    fn fault() -> Self;
+    /// Illegal byte sequence, like `EILSEQ`: for strings which aren't UTF-8.
+    fn illegal_sequence() -> Self;
+    /// Invalid argument, like `EINVAL`: for invalid enum and flag values, and
+    /// anything else, including custom errors.
+    fn invalid() -> Self;
+    /// Argument list too long, like `E2BIG`: for data over a limit.
+    fn too_big() -> Self;
+
+    /// Maps `e` to an errno.
+    fn map_guest_error(e: &GuestError) -> Self {
+        match e.code() {
+            GuestErrorCode::PtrOverflow
+            | GuestErrorCode::PtrOutOfBounds
+            | GuestErrorCode::OffsetOutOfBounds
+            | GuestErrorCode::LenOutOfBounds
+            | GuestErrorCode::LenOverflow
+            | GuestErrorCode::PtrNotAligned
+            | GuestErrorCode::PtrBorrowed
+            | GuestErrorCode::IndexOutOfBounds
+            | GuestErrorCode::UnknownMemory => Self::fault(),
+            GuestErrorCode::InvalidUtf8 => Self::illegal_sequence(),
+            GuestErrorCode::LimitExceeded | GuestErrorCode::TooLarge => Self::too_big(),
+            _ => Self::invalid(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Errno {
+        Fault,
+        Ilseq,
+        Inval,
+        TooBig,
+    }
+
+    impl MapGuestError for Errno {
+        fn fault() -> Self {
+            Errno::Fault
+        }
+        fn illegal_sequence() -> Self {
+            Errno::Ilseq
+        }
+        fn invalid() -> Self {
+            Errno::Inval
+        }
+        fn too_big() -> Self {
+            Errno::TooBig
+        }
+    }
+
+    #[test]
+    fn codes_see_through_context() {
+        let region = Region { start: 4, len: 8 };
+        let e = GuestError::InFunc {
+            funcname: "fd_write",
+            location: "iovs",
+            witx: None,
+            err: Box::new(GuestError::InDataField {
+                typename: "ciovec".to_string(),
+                field: "buf".to_string(),
+                witx: Some(WitxLocation {
+                    path: "wasi.witx",
+                    line: 3,
+                    column: 12,
+                }),
+                err: Box::new(GuestError::PtrOutOfBounds(region)),
+            }),
+        };
+        assert_eq!(e.to_string(), "In func fd_write:iovs:");
+        match &e {
+            GuestError::InFunc { err, .. } => {
+                assert_eq!(err.to_string(), "In data ciovec.buf (at wasi.witx:3:12):")
+            }
+            e => panic!("expected InFunc, got {:?}", e),
+        }
+        assert_eq!(e.code(), GuestErrorCode::PtrOutOfBounds);
+        assert_eq!(GuestErrorCode::PtrOutOfBounds as u32, 4);
+        assert_eq!(Errno::map_guest_error(&e), Errno::Fault);
+    }
+
+    #[test]
+    fn default_mapping() {
+        let region = Region { start: 0, len: 1 };
+        let bytes = vec![0xff];
+        let utf8 = core::str::from_utf8(&bytes).unwrap_err();
+        let cases = [
+            (GuestError::PtrBorrowed(region), Errno::Fault),
+            (GuestError::PtrNotAligned(region, 4), Errno::Fault),
+            (GuestError::InvalidUtf8 { region, err: utf8 }, Errno::Ilseq),
+            (GuestError::InvalidEnumValue("Excuse"), Errno::Inval),
+            (GuestError::NoBorrowChecker, Errno::Inval),
+            (GuestError::UnknownMemory(1), Errno::Fault),
+            (
+                GuestError::OffsetOutOfBounds {
+                    offset: 8,
+                    memory_size: 4,
+                },
+                Errno::Fault,
+            ),
+            (GuestError::LenOverflow, Errno::Fault),
+            (GuestError::TooLarge { len: 2, max: 1 }, Errno::TooBig),
+        ];
+        for (e, errno) in cases.iter() {
+            assert_eq!(&Errno::map_guest_error(e), errno, "{:?}", e);
+        }
+    }
+
+    #[test]
+    fn custom_errors() {
+        let e = GuestError::custom("no such preopen");
+        assert_eq!(e.to_string(), "no such preopen");
+        assert_eq!(e.code(), GuestErrorCode::Custom);
+        assert_eq!(e, e);
+        assert_ne!(e, GuestError::custom("no such preopen"));
+        assert_eq!(Errno::map_guest_error(&e), Errno::Inval);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_errors_are_custom() {
+        let e = GuestError::from(std::io::Error::from(std::io::ErrorKind::WriteZero));
+        match e {
+            GuestError::Custom(e) => {
+                let io = e.downcast_ref::<std::io::Error>().expect("io error");
+                assert_eq!(io.kind(), std::io::ErrorKind::WriteZero);
+            }
+            e => panic!("expected custom error, got {:?}", e),
+        }
+    }
 }
@@ -0,0 +1,228 @@
+use crate::borrow::BorrowKind;
+use crate::{GuestError, GuestMemory, Region};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// A borrow of a region of guest memory, registered with the borrow checker
+/// shared by the current hostcall until it is dropped.
+pub(crate) struct Borrow<'a> {
+    mem: &'a dyn GuestMemory,
+    region: Region,
+    kind: BorrowKind,
+}
+
+impl<'a> Borrow<'a> {
+    pub(crate) fn new(
+        mem: &'a dyn GuestMemory,
+        region: Region,
+        kind: BorrowKind,
+    ) -> Result<Self, GuestError> {
+        // Empty regions can't alias anything.
+        if !region.is_empty() {
+            #[cfg(feature = "perf-counters")]
+            crate::counters::count_borrow_check(mem);
+            let borrows = mem.borrows().ok_or(GuestError::NoBorrowChecker)?;
+            // The checker is only ever mutably borrowed while it's being
+            // handed out as a `GuestBorrows` of its own, which may well cover
+            // this region.
+            let mut borrows = borrows
+                .try_borrow_mut()
+                .map_err(|_| GuestError::PtrBorrowed(region))?;
+            borrows.borrow(mem.memory_index(), region, kind)?;
+        }
+        Ok(Borrow { mem, region, kind })
+    }
+}
+
+/// Checks that `region` of `mem` can be accessed as `kind` without aliasing a
+/// slice or string borrowed from the borrow checker shared by the hostcall:
+/// reads fail on regions borrowed mutably, and writes on regions borrowed at
+/// all.
+///
+/// Memories without a shared borrow checker can't hand out borrows, so
+/// everything may be accessed.
+#[inline]
+pub(crate) fn check_access<M: ?Sized + GuestMemory>(
+    mem: &M,
+    region: Region,
+    kind: BorrowKind,
+) -> Result<(), GuestError> {
+    let borrows = match mem.borrows() {
+        Some(borrows) => borrows,
+        None => return Ok(()),
+    };
+    match borrows.try_borrow() {
+        Ok(borrows) if borrows.is_empty() => Ok(()),
+        Ok(borrows) if !borrows.conflicts(mem.memory_index(), region, kind) => Ok(()),
+        // As in `Borrow::new`, a checker which is mutably borrowed may cover
+        // the region.
+        _ => Err(GuestError::PtrBorrowed(region)),
+    }
+}
+
+impl Drop for Borrow<'_> {
+    fn drop(&mut self) {
+        if self.region.is_empty() {
+            return;
+        }
+        if let Some(Ok(mut borrows)) = self.mem.borrows().map(|b| b.try_borrow_mut()) {
+            borrows.release(self.mem.memory_index(), self.region, self.kind);
+        }
+    }
+}
+
+/// A borrowed slice of guest memory, returned by `GuestPtr::<[T]>::as_slice`.
+///
+/// The slice stays borrowed, and can't be borrowed again by anything else in
+/// the same hostcall, until the guard is dropped.
+pub struct GuestSlice<'a, T> {
+    slice: &'a [T],
+    _borrow: Borrow<'a>,
+}
+
+impl<'a, T> GuestSlice<'a, T> {
+    pub(crate) fn new(slice: &'a [T], borrow: Borrow<'a>) -> Self {
+        Self {
+            slice,
+            _borrow: borrow,
+        }
+    }
+}
+
+impl<T> Deref for GuestSlice<'_, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        self.slice
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for GuestSlice<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.slice.fmt(f)
+    }
+}
+
+/// A mutably borrowed slice of guest memory, returned by
+/// `GuestPtr::<[T]>::as_slice_mut`.
+///
+/// The slice stays borrowed, and can't be borrowed again by anything else in
+/// the same hostcall, until the guard is dropped.
+pub struct GuestSliceMut<'a, T> {
+    slice: &'a mut [T],
+    _borrow: Borrow<'a>,
+}
+
+impl<'a, T> GuestSliceMut<'a, T> {
+    pub(crate) fn new(slice: &'a mut [T], borrow: Borrow<'a>) -> Self {
+        Self {
+            slice,
+            _borrow: borrow,
+        }
+    }
+}
+
+impl<T> Deref for GuestSliceMut<'_, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        self.slice
+    }
+}
+
+impl<T> DerefMut for GuestSliceMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.slice
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for GuestSliceMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.slice.fmt(f)
+    }
+}
+
+/// A borrowed string in guest memory, returned by `GuestPtr::<str>::as_str`.
+///
+/// The string stays borrowed, and can't be borrowed again by anything else in
+/// the same hostcall, until the guard is dropped.
+pub struct GuestStr<'a> {
+    s: &'a str,
+    _borrow: Borrow<'a>,
+}
+
+impl<'a> GuestStr<'a> {
+    pub(crate) fn new(s: &'a str, borrow: Borrow<'a>) -> Self {
+        Self { s, _borrow: borrow }
+    }
+}
+
+impl Deref for GuestStr<'_> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.s
+    }
+}
+
+impl fmt::Debug for GuestStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.s.fmt(f)
+    }
+}
+
+impl fmt::Display for GuestStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.s.fmt(f)
+    }
+}
+
+/// A string in guest memory which may not be UTF-8, returned by
+/// `GuestPtr::<str>::as_str_lossy`.
+///
+/// Valid strings are borrowed in place, like a [`GuestStr`], until the guard
+/// is dropped. Anything else is copied, with invalid sequences replaced by
+/// U+FFFD REPLACEMENT CHARACTER, and isn't borrowed at all.
+pub struct GuestStrCow<'a> {
+    s: Cow<'a, str>,
+    _borrow: Option<Borrow<'a>>,
+}
+
+impl<'a> GuestStrCow<'a> {
+    pub(crate) fn new(s: Cow<'a, str>, borrow: Borrow<'a>) -> Self {
+        let borrow = match s {
+            Cow::Borrowed(_) => Some(borrow),
+            Cow::Owned(_) => None,
+        };
+        Self { s, _borrow: borrow }
+    }
+
+    /// Returns whether the string was valid UTF-8, and so is borrowed rather
+    /// than copied.
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.s, Cow::Borrowed(_))
+    }
+
+    /// Converts to a `String`, copying the string if it is borrowed.
+    pub fn into_owned(self) -> String {
+        self.s.into_owned()
+    }
+}
+
+impl Deref for GuestStrCow<'_> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.s
+    }
+}
+
+impl fmt::Debug for GuestStrCow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.s.fmt(f)
+    }
+}
+
+impl fmt::Display for GuestStrCow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.s.fmt(f)
+    }
+}
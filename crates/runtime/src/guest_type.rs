@@ -1,10 +1,34 @@
-use crate::{GuestError, GuestPtr};
-use std::mem;
+use crate::borrow::BorrowKind;
+use crate::guard::check_access;
+use crate::{CallContext, GuestError, GuestMemory, GuestPtr, Region};
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::mem;
 
 pub trait GuestErrorType<'a> {
     type Context;
     fn success() -> Self;
     fn from_error(e: GuestError, ctx: &Self::Context) -> Self;
+    /// Converts `e`, which happened in the call described by `call`. This
+    /// is what generated functions call; it defaults to `from_error`.
+    fn from_call_error(e: GuestError, ctx: &Self::Context, call: CallContext) -> Self
+    where
+        Self: Sized,
+    {
+        let _ = call;
+        Self::from_error(e, ctx)
+    }
+}
+
+/// Converts guest errors to the error type `E`, for functions generated with
+/// `ctx: generic`. It's implemented by the ctx rather than by `E`, since a
+/// crate choosing the ctx can't implement [`GuestErrorType`] for the error
+/// types of the crate generating the bindings.
+pub trait GuestErrorCtx<E> {
+    fn success(&self) -> E;
+    /// Converts `e`, which happened in the call described by `call`.
+    fn guest_error(&self, e: GuestError, call: CallContext) -> E;
 }
 
 /// A trait for types that are intended to be pointees in `GuestPtr<T>`.
@@ -30,20 +54,174 @@ pub trait GuestType<'a>: Sized {
     /// Typically if you're implementing this by hand you'll want to delegate to
     /// other safe implementations of this trait (e.g. for primitive types like
     /// `u32`) rather than writing lots of raw code yourself.
-    fn read(ptr: &GuestPtr<'a, Self>) -> Result<Self, GuestError>;
+    ///
+    /// This is generic over the type of memory `ptr` refers to, so that reads
+    /// through pointers to a concrete memory type are monomorphized.
+    fn read<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'a, Self, M>) -> Result<Self, GuestError>;
 
     /// Writes a value to `ptr` after verifying that `ptr` is indeed valid to
     /// store `val`.
     ///
     /// Similar to `read`, you'll probably want to implement this in terms of
     /// other primitives.
-    fn write(ptr: &GuestPtr<'_, Self>, val: Self) -> Result<(), GuestError>;
+    fn write<M: ?Sized + GuestMemory>(
+        ptr: &GuestPtr<'_, Self, M>,
+        val: Self,
+    ) -> Result<(), GuestError>;
+
+    /// Reads every value in the array `ptr` points to.
+    ///
+    /// The default implementation reads each element in turn.
+    /// [`GuestTypeTransparent`] types override it with
+    /// [`read_transparent_slice`], which copies the whole array at once.
+    fn read_slice<M: ?Sized + GuestMemory>(
+        ptr: &GuestPtr<'a, [Self], M>,
+    ) -> Result<Vec<Self>, GuestError> {
+        ptr.iter().map(|elem| elem?.read()).collect()
+    }
+
+    /// Writes `vals` to the array `ptr` points to, which must have the same
+    /// length.
+    ///
+    /// As for `read_slice`, the default implementation writes each element in
+    /// turn, and [`GuestTypeTransparent`] types override it with
+    /// [`write_transparent_slice`].
+    fn write_slice<M: ?Sized + GuestMemory>(
+        ptr: &GuestPtr<'a, [Self], M>,
+        vals: &[Self],
+    ) -> Result<(), GuestError>
+    where
+        Self: Clone,
+    {
+        assert_slice_len(ptr, vals);
+        for (elem, val) in ptr.iter().zip(vals) {
+            elem?.write(val.clone())?;
+        }
+        Ok(())
+    }
+}
+
+fn assert_slice_len<T, M: ?Sized + GuestMemory>(ptr: &GuestPtr<'_, [T], M>, vals: &[T]) {
+    assert_eq!(
+        ptr.len(),
+        vals.len() as u64,
+        "source slice length does not match the guest slice length"
+    );
+}
+
+/// Implements [`GuestType::read_slice`] for a transparent type: the array is
+/// bounds checked and validated once, then copied out with a single `memcpy`.
+pub fn read_transparent_slice<'a, T, M>(ptr: &GuestPtr<'a, [T], M>) -> Result<Vec<T>, GuestError>
+where
+    T: GuestTypeTransparent<'a>,
+    M: ?Sized + GuestMemory,
+{
+    let (host_ptr, region) = match ptr.validate_raw() {
+        // Misaligned arrays in memories which allow them, and arrays in
+        // memories without a host mapping, can only be read one element at a
+        // time.
+        Err(GuestError::PtrNotAligned(..)) if !ptr.mem().require_alignment() => {
+            return ptr.iter().map(|elem| elem?.read()).collect();
+        }
+        Err(GuestError::PtrOutOfBounds(..)) if ptr.mem().base().0.is_null() => {
+            return ptr.iter().map(|elem| elem?.read()).collect();
+        }
+        result => result?,
+    };
+    check_access(ptr.mem(), region, BorrowKind::Shared)?;
+    T::validate_slice(host_ptr, ptr.len())?;
+    // `validate_raw` checked that the whole array fits in host memory.
+    let len = ptr.len() as usize;
+    let mut vals = Vec::with_capacity(len);
+    // SAFETY: the array is in bounds, aligned, and holds valid values of
+    // `T`, which has the same representation in guest memory as in Rust.
+    // Guest memory is never part of a Rust allocation, so it doesn't overlap
+    // `vals`.
+    unsafe {
+        core::ptr::copy_nonoverlapping(host_ptr, vals.as_mut_ptr(), len);
+        vals.set_len(len);
+    }
+    Ok(vals)
+}
+
+/// Implements [`GuestType::write_slice`] for a transparent type, with a
+/// single `memcpy`.
+///
+/// Only use this for types without padding: the padding bytes of `vals` are
+/// uninitialized, and would be copied into guest memory along with the rest.
+pub fn write_transparent_slice<'a, T, M>(
+    ptr: &GuestPtr<'a, [T], M>,
+    vals: &[T],
+) -> Result<(), GuestError>
+where
+    T: GuestTypeTransparent<'a> + Clone,
+    M: ?Sized + GuestMemory,
+{
+    assert_slice_len(ptr, vals);
+    let (host_ptr, region) = match ptr.validate_raw() {
+        Err(GuestError::PtrNotAligned(..)) if !ptr.mem().require_alignment() => {
+            for (elem, val) in ptr.iter().zip(vals) {
+                elem?.write(val.clone())?;
+            }
+            return Ok(());
+        }
+        Err(GuestError::PtrOutOfBounds(..)) if ptr.mem().base().0.is_null() => {
+            for (elem, val) in ptr.iter().zip(vals) {
+                elem?.write(val.clone())?;
+            }
+            return Ok(());
+        }
+        result => result?,
+    };
+    check_access(ptr.mem(), region, BorrowKind::Mut)?;
+    // SAFETY: as for `read_transparent_slice`. Every value of `T` is valid to
+    // store in guest memory.
+    unsafe {
+        core::ptr::copy_nonoverlapping(vals.as_ptr(), host_ptr, vals.len());
+    }
+    Ok(())
+}
+
+/// Reads a transparent value with a single bounds check, copying it out whole
+/// and then validating it, for the [`GuestType::read`] of compound types.
+///
+/// Returns `None` if the value can't be read in one go: it's out of bounds,
+/// misaligned, mutably borrowed or invalid, or its memory canonicalizes NaNs.
+/// The caller then reads it piece by piece, which reports the precise error.
+#[inline]
+pub fn read_transparent<'a, T, M>(ptr: &GuestPtr<'a, T, M>) -> Option<T>
+where
+    T: GuestTypeTransparent<'a>,
+    M: ?Sized + GuestMemory,
+{
+    if ptr.mem().canonicalize_nans()
+        || check_align(ptr).is_err()
+        || check_unborrowed(ptr, BorrowKind::Shared).is_err()
+    {
+        return None;
+    }
+    // Start from zeroes so the whole value can be viewed as initialized bytes.
+    let mut val = mem::MaybeUninit::<T>::zeroed();
+    // SAFETY: `T` has the same representation in guest memory as in Rust, so
+    // it takes up `guest_size` bytes, all of which are initialized.
+    let bytes = unsafe {
+        core::slice::from_raw_parts_mut(val.as_mut_ptr() as *mut u8, T::guest_size() as usize)
+    };
+    ptr.mem().read_bytes(ptr.offset(), bytes).ok()?;
+    T::validate(val.as_mut_ptr()).ok()?;
+    // SAFETY: `validate` checked the bytes are a valid `T`.
+    Some(unsafe { val.assume_init() })
 }
 
 /// A trait for `GuestType`s that have the same representation in guest memory
 /// as in Rust. These types can be used with the `GuestPtr::as_raw` method to
 /// view as a slice.
 ///
+/// Guest memory is little-endian, so on big-endian hosts only types made up
+/// of single bytes are transparent.
+///
+/// # Safety
+///
 /// Unsafe trait because a correct GuestTypeTransparent implemengation ensures that the
 /// GuestPtr::as_raw methods are safe. This trait should only ever be implemented
 /// by wiggle_generate-produced code.
@@ -54,68 +232,275 @@ pub unsafe trait GuestTypeTransparent<'a>: GuestType<'a> {
     /// has been checked to be aligned correctly and reside in memory using
     /// `GuestMemory::validate_size_align`
     fn validate(ptr: *mut Self) -> Result<(), GuestError>;
+
+    /// Checks that the `len` contiguous values starting at `ptr` are all valid
+    /// representations of `Self`.
+    ///
+    /// The default implementation calls `validate` on each element in turn.
+    /// Types with a cheap bulk check (e.g. a range check over the raw
+    /// representation) override this with a loop the compiler can vectorize.
+    ///
+    /// Makes the same assumptions as `validate`, for the whole array.
+    #[inline]
+    fn validate_slice(ptr: *mut Self, len: u64) -> Result<(), GuestError> {
+        for offs in 0..len {
+            // Caller has validated bounds and alignment of the whole array
+            // starting at `ptr`, so every element pointer is in bounds.
+            Self::validate(ptr.wrapping_add(offs as usize))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `ptr` is aligned for a `T`, if its memory requires alignment.
+///
+/// `T::guest_align()` is known statically here, so for byte-aligned types the
+/// check compiles away entirely. Primitives are copied in and out of memory as
+/// bytes, so it's the guest offset which must be aligned, not the host
+/// address.
+#[inline]
+fn check_align<'a, T: GuestType<'a>, M: ?Sized + GuestMemory>(
+    ptr: &GuestPtr<'_, T, M>,
+) -> Result<(), GuestError> {
+    let align = T::guest_align();
+    if align > 1 && ptr.mem().require_alignment() && ptr.offset() % align as u64 != 0 {
+        let region = Region {
+            start: ptr.offset(),
+            len: T::guest_size().into(),
+        };
+        return Err(GuestError::PtrNotAligned(region, align as u32));
+    }
+    Ok(())
+}
+
+/// Checks that the value `ptr` points to can be accessed as `kind`, without
+/// aliasing a slice or string borrowed during the hostcall.
+#[inline]
+fn check_unborrowed<'a, T: GuestType<'a>, M: ?Sized + GuestMemory>(
+    ptr: &GuestPtr<'_, T, M>,
+    kind: BorrowKind,
+) -> Result<(), GuestError> {
+    let region = Region {
+        start: ptr.offset(),
+        len: T::guest_size().into(),
+    };
+    check_access(ptr.mem(), region, kind)
+}
+
+/// Primitives are naturally aligned in guest memory, as they are in wasm's C
+/// ABI. Hosts may align some of them less strictly, such as `u64` on 32-bit
+/// x86 or `u128` on older compilers, so this is never less than the host
+/// alignment either.
+const fn natural_align<T>() -> usize {
+    let size = mem::size_of::<T>();
+    let align = mem::align_of::<T>();
+    if size > align {
+        size
+    } else {
+        align
+    }
+}
+
+/// Canonicalizes the NaNs of floats read from and written to memories which
+/// ask for it, see [`GuestMemory::canonicalize_nans`]. Other primitives are
+/// left alone.
+trait Canonicalize: Clone {
+    #[inline]
+    fn canonicalize<M: ?Sized + GuestMemory>(self, _mem: &M) -> Self {
+        self
+    }
+
+    #[inline]
+    fn canonicalize_all<'v, M: ?Sized + GuestMemory>(
+        vals: &'v [Self],
+        _mem: &M,
+    ) -> Cow<'v, [Self]> {
+        Cow::Borrowed(vals)
+    }
+}
+
+macro_rules! canonical_floats {
+    ($($f:ident $nan:expr)*) => ($(
+        impl Canonicalize for $f {
+            #[inline]
+            fn canonicalize<M: ?Sized + GuestMemory>(self, mem: &M) -> Self {
+                if self.is_nan() && mem.canonicalize_nans() {
+                    $f::from_bits($nan)
+                } else {
+                    self
+                }
+            }
+
+            fn canonicalize_all<'v, M: ?Sized + GuestMemory>(vals: &'v [Self], mem: &M) -> Cow<'v, [Self]> {
+                if mem.canonicalize_nans() && vals.iter().any(|v| v.is_nan()) {
+                    Cow::Owned(vals.iter().map(|v| if v.is_nan() { $f::from_bits($nan) } else { *v }).collect())
+                } else {
+                    Cow::Borrowed(vals)
+                }
+            }
+        }
+    )*)
+}
+
+// The canonical NaNs of wasm: positive, quiet, with no other payload bits.
+canonical_floats! {
+    f32 0x7fc0_0000
+    f64 0x7ff8_0000_0000_0000
 }
 
+impl Canonicalize for i8 {}
+impl Canonicalize for i16 {}
+impl Canonicalize for i32 {}
+impl Canonicalize for i64 {}
+impl Canonicalize for i128 {}
+impl Canonicalize for isize {}
+impl Canonicalize for u8 {}
+impl Canonicalize for u16 {}
+impl Canonicalize for u32 {}
+impl Canonicalize for u64 {}
+impl Canonicalize for u128 {}
+impl Canonicalize for usize {}
+
 macro_rules! primitives {
-    ($($i:ident)*) => ($(
+    (#[$transparent:meta] $($i:ident)*) => ($(
         impl<'a> GuestType<'a> for $i {
             fn guest_size() -> u32 { mem::size_of::<Self>() as u32 }
-            fn guest_align() -> usize { mem::align_of::<Self>() }
+            fn guest_align() -> usize { natural_align::<Self>() }
 
             #[inline]
-            fn read(ptr: &GuestPtr<'a, Self>) -> Result<Self, GuestError> {
+            fn read<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'a, Self, M>) -> Result<Self, GuestError> {
                 // Any bit pattern for any primitive implemented with this
-                // macro is safe, so our `validate_size_align` method will
-                // guarantee that if we are given a pointer it's valid for the
-                // size of our type as well as properly aligned. Consequently we
-                // should be able to safely ready the pointer just after we
-                // validated it, returning it along here.
-                let host_ptr = ptr.mem().validate_size_align(
-                    ptr.offset(),
-                    Self::guest_align(),
-                    Self::guest_size(),
-                )?;
-                Ok(unsafe { *host_ptr.cast::<Self>() })
+                // macro is valid, so once the pointer is known to be aligned
+                // the bytes can be read straight out of the memory, which
+                // checks they are in bounds. Guest memory is little-endian,
+                // whatever the host is.
+                check_align(ptr)?;
+                check_unborrowed(ptr, BorrowKind::Shared)?;
+                let mut bytes = [0; mem::size_of::<$i>()];
+                ptr.mem().read_bytes(ptr.offset(), &mut bytes)?;
+                Ok($i::from_le_bytes(bytes).canonicalize(ptr.mem()))
             }
 
             #[inline]
-            fn write(ptr: &GuestPtr<'_, Self>, val: Self) -> Result<(), GuestError> {
-                let host_ptr = ptr.mem().validate_size_align(
-                    ptr.offset(),
-                    Self::guest_align(),
-                    Self::guest_size(),
-                )?;
-                // Similar to above `as_raw` will do a lot of validation, and
-                // then afterwards we can safely write our value into the
-                // memory location.
-                unsafe {
-                    *host_ptr.cast::<Self>() = val;
+            fn write<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'_, Self, M>, val: Self) -> Result<(), GuestError> {
+                check_align(ptr)?;
+                check_unborrowed(ptr, BorrowKind::Mut)?;
+                let val = val.canonicalize(ptr.mem());
+                ptr.mem().write_bytes(ptr.offset(), &val.to_le_bytes())
+            }
+
+            #[$transparent]
+            #[inline]
+            fn read_slice<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'a, [Self], M>) -> Result<Vec<Self>, GuestError> {
+                let vals = read_transparent_slice(ptr)?;
+                if let Cow::Owned(vals) = Self::canonicalize_all(&vals, ptr.mem()) {
+                    return Ok(vals);
                 }
-                Ok(())
+                Ok(vals)
+            }
+
+            #[$transparent]
+            #[inline]
+            fn write_slice<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'a, [Self], M>, vals: &[Self]) -> Result<(), GuestError> {
+                write_transparent_slice(ptr, &Self::canonicalize_all(vals, ptr.mem()))
             }
         }
 
+        #[$transparent]
         unsafe impl<'a> GuestTypeTransparent<'a> for $i {
             #[inline]
             fn validate(_ptr: *mut $i) -> Result<(), GuestError> {
                 // All bit patterns are safe, nothing to do here
                 Ok(())
             }
+
+            #[inline]
+            fn validate_slice(_ptr: *mut $i, _len: u64) -> Result<(), GuestError> {
+                // All bit patterns are safe, nothing to do here
+                Ok(())
+            }
         }
 
     )*)
 }
 
+// Bytes look the same in guest memory on any host.
 primitives! {
+    #[cfg(all())]
+    i8 u8
+}
+
+// Wider primitives are little-endian in guest memory, so they only have the
+// same representation on the host, and are only transparent, on little-endian
+// hosts.
+primitives! {
+    #[cfg(target_endian = "little")]
     // signed
-    i8 i16 i32 i64 i128 isize
+    i16 i32 i64 i128 isize
     // unsigned
-    u8 u16 u32 u64 u128 usize
+    u16 u32 u64 u128 usize
     // floats
     f32 f64
 }
 
-// Support pointers-to-pointers where pointers are always 32-bits in wasm land
+/// Rounds `offset` up to a multiple of `align`, which is a power of two.
+fn align_to(offset: u32, align: usize) -> u32 {
+    let align = align as u32;
+    (offset + align - 1) & !(align - 1)
+}
+
+// Tuples are laid out like C structs with the same fields, so that scratch
+// areas holding several values can be read and written in one go.
+macro_rules! tuples {
+    ($(($($t:ident $v:ident),+))*) => ($(
+        impl<'a, $($t: GuestType<'a>),+> GuestType<'a> for ($($t,)+) {
+            fn guest_size() -> u32 {
+                let mut size = 0;
+                $(size = align_to(size, $t::guest_align()) + $t::guest_size();)+
+                align_to(size, Self::guest_align())
+            }
+
+            fn guest_align() -> usize {
+                1 $(.max($t::guest_align()))+
+            }
+
+            #[allow(unused_assignments)]
+            fn read<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'a, Self, M>) -> Result<Self, GuestError> {
+                let base = ptr.cast::<u8>();
+                let mut offset = 0;
+                $(
+                    offset = align_to(offset, $t::guest_align());
+                    let $v = base.add(offset.into())?.cast::<$t>().read()?;
+                    offset += $t::guest_size();
+                )+
+                Ok(($($v,)+))
+            }
+
+            #[allow(unused_assignments)]
+            fn write<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'_, Self, M>, val: Self) -> Result<(), GuestError> {
+                let base = ptr.cast::<u8>();
+                let ($($v,)+) = val;
+                let mut offset = 0;
+                $(
+                    offset = align_to(offset, $t::guest_align());
+                    $t::write(&base.add(offset.into())?.cast(), $v)?;
+                    offset += $t::guest_size();
+                )+
+                Ok(())
+            }
+        }
+    )*)
+}
+
+tuples! {
+    (A a)
+    (A a, B b)
+    (A a, B b, C c)
+}
+
+// Support pointers-to-pointers where pointers are always 32-bits in wasm land.
+// witx only defines the 32-bit layout of types in memory, so this holds even
+// for memory64 guests, whose pointers stored in memory can't be represented.
 impl<'a, T> GuestType<'a> for GuestPtr<'a, T> {
     fn guest_size() -> u32 {
         u32::guest_size()
@@ -125,12 +510,49 @@ impl<'a, T> GuestType<'a> for GuestPtr<'a, T> {
         u32::guest_align()
     }
 
-    fn read(ptr: &GuestPtr<'a, Self>) -> Result<Self, GuestError> {
+    fn read<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'a, Self, M>) -> Result<Self, GuestError> {
         let offset = ptr.cast::<u32>().read()?;
-        Ok(GuestPtr::new(ptr.mem(), offset))
+        Ok(GuestPtr::new(ptr.mem().as_guest_memory(), offset.into()))
+    }
+
+    fn write<M: ?Sized + GuestMemory>(
+        ptr: &GuestPtr<'_, Self, M>,
+        val: Self,
+    ) -> Result<(), GuestError> {
+        ptr.cast::<u32>().write(u32::try_from(val.offset())?)
+    }
+}
+
+// Nullable pointers, with offset 0 as null, laid out like any other pointer.
+impl<'a, T> GuestType<'a> for Option<GuestPtr<'a, T>> {
+    fn guest_size() -> u32 {
+        u32::guest_size()
+    }
+
+    fn guest_align() -> usize {
+        u32::guest_align()
+    }
+
+    fn read<M: ?Sized + GuestMemory>(ptr: &GuestPtr<'a, Self, M>) -> Result<Self, GuestError> {
+        let offset = ptr.cast::<u32>().read()?;
+        if offset == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(GuestPtr::new(
+                ptr.mem().as_guest_memory(),
+                offset.into(),
+            )))
+        }
     }
 
-    fn write(ptr: &GuestPtr<'_, Self>, val: Self) -> Result<(), GuestError> {
-        ptr.cast::<u32>().write(val.offset())
+    fn write<M: ?Sized + GuestMemory>(
+        ptr: &GuestPtr<'_, Self, M>,
+        val: Self,
+    ) -> Result<(), GuestError> {
+        let offset = match val {
+            Some(val) => u32::try_from(val.offset())?,
+            None => 0,
+        };
+        ptr.cast::<u32>().write(offset)
     }
 }
@@ -0,0 +1,220 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::marker::PhantomData;
+
+/// A table of host objects, such as open files, indexed by the handles given
+/// out to guests.
+///
+/// Tables are keyed by a handle type `K`, typically a handle generated by
+/// `wiggle_generate` like `types::Fd`, so a table of files can't be indexed
+/// with any other kind of handle. Any type converting to and from `u32` will
+/// do.
+///
+/// Handles are dense: inserting reuses the lowest handle freed by a removal
+/// before growing the table, like POSIX file descriptors.
+pub struct HandleTable<K, T> {
+    entries: Vec<Option<T>>,
+    free: BTreeSet<u32>,
+    len: usize,
+    _key: PhantomData<fn(K) -> K>,
+}
+
+impl<K, T> HandleTable<K, T>
+where
+    K: Copy + From<u32> + Into<u32>,
+{
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            free: BTreeSet::new(),
+            len: 0,
+            _key: PhantomData,
+        }
+    }
+
+    /// The number of entries in the table.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts `val` under the lowest free handle, and returns the handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every `u32` is already a handle.
+    pub fn insert(&mut self, val: T) -> K {
+        let index = match self.free.pop_first() {
+            Some(index) => index,
+            None => {
+                let index = u32::try_from(self.entries.len()).expect("handle table is full");
+                self.entries.push(None);
+                index
+            }
+        };
+        self.entries[index as usize] = Some(val);
+        self.len += 1;
+        K::from(index)
+    }
+
+    /// Inserts `val` under `key`, such as for the standard streams of a
+    /// process, returning what was there before.
+    pub fn insert_at(&mut self, key: K, val: T) -> Option<T> {
+        let index = key.into();
+        let slot = index as usize;
+        if slot >= self.entries.len() {
+            let old_len = self.entries.len() as u32;
+            self.entries.resize_with(slot + 1, || None);
+            self.free.extend(old_len..index);
+        }
+        self.free.remove(&index);
+        let prev = self.entries[slot].replace(val);
+        if prev.is_none() {
+            self.len += 1;
+        }
+        prev
+    }
+
+    pub fn contains(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: K) -> Option<&T> {
+        self.entries.get(key.into() as usize)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: K) -> Option<&mut T> {
+        self.entries.get_mut(key.into() as usize)?.as_mut()
+    }
+
+    /// Removes the entry under `key`, freeing the handle for reuse.
+    pub fn remove(&mut self, key: K) -> Option<T> {
+        let index = key.into();
+        let val = self.entries.get_mut(index as usize)?.take()?;
+        self.len -= 1;
+        if index as usize == self.entries.len() - 1 {
+            // Shrink rather than leave free handles at the end, so the table
+            // doesn't stay at its largest size forever.
+            self.entries.pop();
+            while let Some(None) = self.entries.last() {
+                self.entries.pop();
+                self.free.remove(&(self.entries.len() as u32));
+            }
+        } else {
+            self.free.insert(index);
+        }
+        Some(val)
+    }
+
+    /// Moves the entry under `from` to `to`, like WASI's `fd_renumber`,
+    /// dropping the entry already there.
+    ///
+    /// Both handles must be in the table. If either isn't, nothing changes
+    /// and `false` is returned.
+    pub fn renumber(&mut self, from: K, to: K) -> bool {
+        if !self.contains(from) || !self.contains(to) {
+            return false;
+        }
+        if from.into() != to.into() {
+            let val = self.remove(from).unwrap();
+            self.insert_at(to, val);
+        }
+        true
+    }
+
+    /// Iterates over the entries and their handles, in handle order.
+    pub fn iter(&self) -> impl Iterator<Item = (K, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, val)| Some((K::from(i as u32), val.as_ref()?)))
+    }
+}
+
+impl<K, T> Default for HandleTable<K, T>
+where
+    K: Copy + From<u32> + Into<u32>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, T> fmt::Debug for HandleTable<K, T>
+where
+    K: Copy + From<u32> + Into<u32> + fmt::Debug,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reuses_lowest_handle() {
+        let mut table: HandleTable<u32, &str> = HandleTable::new();
+        assert_eq!(table.insert("a"), 0);
+        assert_eq!(table.insert("b"), 1);
+        assert_eq!(table.insert("c"), 2);
+        assert_eq!(table.insert("d"), 3);
+        assert_eq!(table.remove(2), Some("c"));
+        assert_eq!(table.remove(0), Some("a"));
+        assert_eq!(table.remove(0), None);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.insert("e"), 0);
+        assert_eq!(table.insert("f"), 2);
+        assert_eq!(table.insert("g"), 4);
+        assert_eq!(table.get(3), Some(&"d"));
+        *table.get_mut(3).unwrap() = "h";
+        assert_eq!(table.get(3), Some(&"h"));
+    }
+
+    #[test]
+    fn shrinks_after_removing_the_last_entries() {
+        let mut table: HandleTable<u32, u8> = HandleTable::new();
+        for i in 0..4 {
+            table.insert(i);
+        }
+        table.remove(2);
+        table.remove(3);
+        assert_eq!(table.insert(9), 2);
+        assert_eq!(table.insert(9), 3);
+        assert_eq!(table.insert(9), 4);
+    }
+
+    #[test]
+    fn insert_at() {
+        let mut table: HandleTable<u32, &str> = HandleTable::new();
+        assert_eq!(table.insert_at(3, "preopen"), None);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.insert("a"), 0);
+        assert_eq!(table.insert("b"), 1);
+        assert_eq!(table.insert("c"), 2);
+        assert_eq!(table.insert("d"), 4);
+        assert_eq!(table.insert_at(1, "e"), Some("b"));
+        assert_eq!(table.len(), 5);
+    }
+
+    #[test]
+    fn renumber() {
+        let mut table: HandleTable<u32, &str> = HandleTable::new();
+        let a = table.insert("a");
+        let b = table.insert("b");
+        assert!(!table.renumber(b, 7), "7 isn't in the table");
+        assert!(table.renumber(a, a));
+        assert_eq!(table.get(a), Some(&"a"));
+        assert!(table.renumber(b, a));
+        assert_eq!(table.get(a), Some(&"b"));
+        assert!(!table.contains(b));
+        assert_eq!(table.len(), 1);
+    }
+}
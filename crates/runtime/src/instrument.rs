@@ -0,0 +1,35 @@
+use alloc::string::String;
+
+/// Observes the hostcalls made through generated functions, for logging,
+/// auditing or replay.
+///
+/// Instrumentation is enabled per `from_witx!` invocation with
+/// `instrument: true`. The generated functions then look up the hook through
+/// [`InstrumentationCtx`], and call [`InstrumentationHook::before_call`] once
+/// the arguments have been marshalled, and
+/// [`InstrumentationHook::after_call`] with whatever the host implementation
+/// returned. Calls which fail before reaching the host implementation, for
+/// example because the guest passed an invalid enum value, are only reported
+/// through `GuestErrorType::from_error`.
+///
+/// Values are passed as their `Debug` representation.
+pub trait InstrumentationHook {
+    /// Called before the host implementation of `function`, with its
+    /// arguments by witx parameter name.
+    fn before_call(&self, function: &str, args: &[(&str, String)]) {
+        let _ = (function, args);
+    }
+
+    /// Called after the host implementation of `function` returns, with its
+    /// results, or the error it returned.
+    fn after_call(&self, function: &str, result: Result<&str, &str>) {
+        let _ = (function, result);
+    }
+}
+
+/// Implemented by contexts whose hostcalls are observed by an
+/// [`InstrumentationHook`].
+pub trait InstrumentationCtx {
+    /// Returns the hook in effect, or `None` to skip instrumentation.
+    fn instrumentation(&self) -> Option<&dyn InstrumentationHook>;
+}
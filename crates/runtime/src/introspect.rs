@@ -0,0 +1,84 @@
+use crate::{GuestError, GuestMemory, GuestPtr};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Describes a generated module, so that guests can feature-detect instead of
+/// probing functions and interpreting `ENOSYS`.
+///
+/// Every generated module has an `INTERFACE` constant of this type, and a
+/// `wiggle_interface_info` function exposing it to guests through
+/// [`InterfaceInfo::answer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceInfo {
+    /// The name of the witx module.
+    pub name: &'static str,
+    /// The version given with the `version` field of `from_witx!`, or 0.
+    pub version: u32,
+    /// The functions of the module, in witx order. A function's position in
+    /// this list is its bit in the implemented function set.
+    pub functions: &'static [&'static str],
+    /// Whether the host implements each function in `functions`.
+    pub implemented: &'static [bool],
+}
+
+impl InterfaceInfo {
+    /// Returns whether the host implements the function named `name`.
+    pub fn is_implemented(&self, name: &str) -> bool {
+        self.functions
+            .iter()
+            .zip(self.implemented)
+            .any(|(f, implemented)| *f == name && *implemented)
+    }
+
+    /// The set of implemented functions as a little-endian bitset, with bit
+    /// `i` (bit `i % 8` of byte `i / 8`) set if `functions[i]` is implemented.
+    pub fn implemented_bits(&self) -> Vec<u8> {
+        let mut bits = vec![0u8; self.functions.len().div_ceil(8)];
+        for (i, implemented) in self.implemented.iter().enumerate() {
+            if *implemented {
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+        bits
+    }
+
+    /// Answers a guest's query: writes the version to `version_ptr`, and as
+    /// much of the implemented function bitset as fits into the `bits_len`
+    /// bytes at `bits_ptr`. Returns the size of the whole bitset, so guests
+    /// can tell whether they got all of it.
+    pub fn answer(
+        &self,
+        mem: &dyn GuestMemory,
+        version_ptr: u64,
+        bits_ptr: u64,
+        bits_len: u64,
+    ) -> Result<u32, GuestError> {
+        GuestPtr::<u32>::new(mem, version_ptr).write(self.version)?;
+        let bits = self.implemented_bits();
+        let dest = GuestPtr::<[u8]>::new(mem, (bits_ptr, bits_len));
+        for (slot, byte) in dest.iter().zip(bits.iter()) {
+            slot?.write(*byte)?;
+        }
+        Ok(bits.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INFO: InterfaceInfo = InterfaceInfo {
+        name: "example",
+        version: 2,
+        functions: &["a", "b", "c", "d", "e", "f", "g", "h", "i"],
+        implemented: &[true, false, true, true, true, true, true, true, true],
+    };
+
+    #[test]
+    fn bits() {
+        assert_eq!(INFO.implemented_bits(), vec![0b1111_1101, 0b1]);
+        assert!(INFO.is_implemented("a"));
+        assert!(!INFO.is_implemented("b"));
+        assert!(!INFO.is_implemented("z"));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{GuestSlice, GuestSliceMut};
+use std::io;
+
+/// Reads from a buffer in guest memory, returned by
+/// `GuestPtr::<[u8]>::reader`.
+///
+/// The buffer is borrowed shared for as long as the reader is alive, so it
+/// can be handed to `io::copy` or anything else expecting an `io::Read`.
+#[derive(Debug)]
+pub struct GuestReader<'a> {
+    buf: GuestSlice<'a, u8>,
+    pos: usize,
+}
+
+impl<'a> GuestReader<'a> {
+    pub(crate) fn new(buf: GuestSlice<'a, u8>) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// The number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The bytes not read yet.
+    pub fn remaining(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+}
+
+impl io::Read for GuestReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.remaining().read(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl io::BufRead for GuestReader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+/// Writes to a buffer in guest memory, returned by
+/// `GuestPtr::<[u8]>::writer`.
+///
+/// The buffer is borrowed mutably for as long as the writer is alive. Like
+/// writing to a `&mut [u8]`, writes past the end of the buffer are short, and
+/// once it is full they write nothing.
+#[derive(Debug)]
+pub struct GuestWriter<'a> {
+    buf: GuestSliceMut<'a, u8>,
+    pos: usize,
+}
+
+impl<'a> GuestWriter<'a> {
+    pub(crate) fn new(buf: GuestSliceMut<'a, u8>) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// The number of bytes written so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The number of bytes which can still be written.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
+impl io::Write for GuestWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = (&mut self.buf[self.pos..]).write(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+#[cfg(feature = "std")]
+use crate::borrow::BorrowKind;
+#[cfg(feature = "std")]
+use crate::guard::Borrow;
+use crate::GuestPtr;
+#[cfg(feature = "std")]
+use crate::{GuestError, GuestMemory};
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+#[cfg(feature = "std")]
+use std::io::{IoSlice, IoSliceMut};
+
+/// The number of buffers an [`IoVecs`] holds inline by default before
+/// spilling to the heap.
+///
+/// Real guests almost always pass between 1 and 8 iovecs per call, so this
+/// keeps gathering them free of allocations in the common case.
+pub const IOVEC_INLINE_LEN: usize = 32;
+
+/// A list of buffers gathered from a guest iovec array.
+///
+/// The first `N` entries are stored inline, and only once more than `N` are
+/// pushed does the list move to a heap allocation. Embedders which expect
+/// larger iovec arrays can pick a different `N`.
+///
+/// `IoVecs` dereferences to a slice, so it can be handed straight to
+/// `Read::read_vectored` or `Write::write_vectored`.
+pub struct IoVecs<T, const N: usize = IOVEC_INLINE_LEN> {
+    inline: [MaybeUninit<T>; N],
+    inline_len: usize,
+    heap: Option<Vec<T>>,
+}
+
+impl<T, const N: usize> IoVecs<T, N> {
+    pub fn new() -> Self {
+        Self {
+            // SAFETY: an array of `MaybeUninit` doesn't require initialization.
+            inline: unsafe { MaybeUninit::uninit().assume_init() },
+            inline_len: 0,
+            heap: None,
+        }
+    }
+
+    /// Returns whether the entries have spilled from inline storage onto the
+    /// heap.
+    pub fn spilled(&self) -> bool {
+        self.heap.is_some()
+    }
+
+    /// Appends a buffer to the end of the list.
+    pub fn push(&mut self, val: T) {
+        if let Some(heap) = &mut self.heap {
+            heap.push(val);
+        } else if self.inline_len < N {
+            self.inline[self.inline_len] = MaybeUninit::new(val);
+            self.inline_len += 1;
+        } else {
+            let mut heap = Vec::with_capacity(N * 2);
+            // SAFETY: the first `inline_len` entries are initialized, and by
+            // setting `inline_len` to 0 we take ownership of them.
+            unsafe {
+                heap.extend(
+                    self.inline[..self.inline_len]
+                        .iter()
+                        .map(|v| ptr::read(v.as_ptr())),
+                );
+            }
+            self.inline_len = 0;
+            heap.push(val);
+            self.heap = Some(heap);
+        }
+    }
+}
+
+impl<T, const N: usize> Default for IoVecs<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Deref for IoVecs<T, N> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        match &self.heap {
+            Some(heap) => heap,
+            // SAFETY: the first `inline_len` entries are initialized.
+            None => unsafe {
+                core::slice::from_raw_parts(self.inline.as_ptr() as *const T, self.inline_len)
+            },
+        }
+    }
+}
+
+impl<T, const N: usize> DerefMut for IoVecs<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match &mut self.heap {
+            Some(heap) => heap,
+            // SAFETY: the first `inline_len` entries are initialized.
+            None => unsafe {
+                core::slice::from_raw_parts_mut(self.inline.as_mut_ptr() as *mut T, self.inline_len)
+            },
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for IoVecs<T, N> {
+    fn drop(&mut self) {
+        // SAFETY: the first `inline_len` entries are initialized, and aren't
+        // used again.
+        unsafe {
+            ptr::drop_in_place(&mut self[..] as *mut [T]);
+        }
+        if let Some(heap) = &mut self.heap {
+            // The entries were dropped in place above.
+            unsafe { heap.set_len(0) };
+        }
+    }
+}
+
+impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for IoVecs<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Implemented by guest types describing a byte buffer, like the `iovec` and
+/// `ciovec` structs of WASI, so that arrays of them can be gathered into
+/// [`GuestIoSlices`] with [`GuestPtr::io_slices`].
+///
+/// `wiggle_generate` implements this for every struct made up of just a
+/// pointer to `u8` followed by a length.
+pub trait AsGuestBuffer<'a> {
+    fn as_guest_buffer(&self) -> GuestPtr<'a, [u8]>;
+}
+
+/// Guest buffers borrowed for the duration of a hostcall, ready to be passed
+/// to `Write::write_vectored`.
+///
+/// Each buffer is bounds checked, checked against the call's limits, and
+/// borrowed shared as with [`GuestPtr::as_slice`], until this is dropped.
+#[cfg(feature = "std")]
+pub struct GuestIoSlices<'a> {
+    slices: IoVecs<IoSlice<'a>>,
+    _borrows: IoVecs<Borrow<'a>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> GuestIoSlices<'a> {
+    pub fn new<M>(bufs: impl IntoIterator<Item = GuestPtr<'a, [u8], M>>) -> Result<Self, GuestError>
+    where
+        M: ?Sized + GuestMemory + 'a,
+    {
+        let mut slices = IoVecs::new();
+        let mut borrows = IoVecs::new();
+        for buf in bufs {
+            let (ptr, region) = buf.validate_raw()?;
+            borrows.push(Borrow::new(
+                buf.mem().as_guest_memory(),
+                region,
+                BorrowKind::Shared,
+            )?);
+            // SAFETY: the buffer is in bounds, and borrowed until `self` is
+            // dropped.
+            let slice = unsafe { core::slice::from_raw_parts(ptr, buf.len() as usize) };
+            slices.push(IoSlice::new(slice));
+        }
+        Ok(Self {
+            slices,
+            _borrows: borrows,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Deref for GuestIoSlices<'a> {
+    type Target = [IoSlice<'a>];
+    fn deref(&self) -> &[IoSlice<'a>] {
+        &self.slices
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for GuestIoSlices<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.slices.fmt(f)
+    }
+}
+
+/// Like [`GuestIoSlices`], but with the buffers borrowed mutably, ready to be
+/// passed to `Read::read_vectored`.
+#[cfg(feature = "std")]
+pub struct GuestIoSlicesMut<'a> {
+    slices: IoVecs<IoSliceMut<'a>>,
+    _borrows: IoVecs<Borrow<'a>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> GuestIoSlicesMut<'a> {
+    pub fn new<M>(bufs: impl IntoIterator<Item = GuestPtr<'a, [u8], M>>) -> Result<Self, GuestError>
+    where
+        M: ?Sized + GuestMemory + 'a,
+    {
+        let mut slices = IoVecs::new();
+        let mut borrows = IoVecs::new();
+        for buf in bufs {
+            let (ptr, region) = buf.validate_raw()?;
+            borrows.push(Borrow::new(
+                buf.mem().as_guest_memory(),
+                region,
+                BorrowKind::Mut,
+            )?);
+            // SAFETY: the buffer is in bounds, and borrowed mutably until
+            // `self` is dropped, so it doesn't overlap any other buffer.
+            let slice = unsafe { core::slice::from_raw_parts_mut(ptr, buf.len() as usize) };
+            slices.push(IoSliceMut::new(slice));
+        }
+        Ok(Self {
+            slices,
+            _borrows: borrows,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Deref for GuestIoSlicesMut<'a> {
+    type Target = [IoSliceMut<'a>];
+    fn deref(&self) -> &[IoSliceMut<'a>] {
+        &self.slices
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> DerefMut for GuestIoSlicesMut<'a> {
+    fn deref_mut(&mut self) -> &mut [IoSliceMut<'a>] {
+        &mut self.slices
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for GuestIoSlicesMut<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.slices.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::rc::Rc;
+
+    #[test]
+    fn stays_inline() {
+        let mut v: IoVecs<u32, 4> = IoVecs::new();
+        for i in 0..4 {
+            v.push(i);
+        }
+        assert!(!v.spilled());
+        assert_eq!(&*v, &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn spills_to_heap() {
+        let mut v: IoVecs<u32, 4> = IoVecs::new();
+        for i in 0..10 {
+            v.push(i);
+        }
+        assert!(v.spilled());
+        assert_eq!(&*v, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        v[9] = 42;
+        assert_eq!(v.last(), Some(&42));
+    }
+
+    #[test]
+    fn drops_every_entry_once() {
+        let counter = Rc::new(());
+        let mut inline: IoVecs<Rc<()>, 4> = IoVecs::new();
+        let mut spilled: IoVecs<Rc<()>, 4> = IoVecs::new();
+        for _ in 0..3 {
+            inline.push(counter.clone());
+        }
+        for _ in 0..6 {
+            spilled.push(counter.clone());
+        }
+        assert_eq!(Rc::strong_count(&counter), 10);
+        drop(inline);
+        assert_eq!(Rc::strong_count(&counter), 7);
+        drop(spilled);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+}
@@ -1,20 +1,88 @@
-use std::cell::Cell;
-use std::fmt;
-use std::marker;
-use std::rc::Rc;
-use std::slice;
-use std::str;
-use std::sync::Arc;
+//! The runtime support for code generated by `wiggle_generate`.
+//!
+//! With the default `std` feature disabled, the runtime only depends on
+//! `core` and `alloc`. Cancellation, rate limiting and the `std::io`
+//! integrations, like [`GuestReader`] and [`GuestIoSlices`], need `std`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::convert::TryFrom;
+use core::fmt;
+use core::marker;
+use core::ops::Range;
+use core::slice;
 
 mod borrow;
+#[cfg(feature = "std")]
+mod cancel;
+#[cfg(feature = "perf-counters")]
+mod counters;
+mod cursor;
+mod dispatch;
 mod error;
+mod guard;
 mod guest_type;
+mod handle_table;
+mod instrument;
+mod introspect;
+#[cfg(feature = "std")]
+mod io;
+mod iovec;
+mod limits;
+mod memory;
+mod metadata;
+mod mock;
+mod policy;
+#[cfg(feature = "std")]
+mod rate_limit;
 mod region;
+mod utf8;
 
-pub use borrow::GuestBorrows;
-pub use error::GuestError;
-pub use guest_type::{GuestErrorType, GuestType, GuestTypeTransparent};
+pub use borrow::{BorrowHandle, BorrowKind, CallMemory, GuestBorrows};
+#[cfg(feature = "std")]
+pub use cancel::{CancelCtx, CancelToken};
+#[cfg(feature = "perf-counters")]
+pub use counters::PerfCounters;
+pub use cursor::GuestCursor;
+pub use dispatch::{AbiFunc, DispatchError, Value, ValueType};
+pub use error::{
+    CallContext, ConversionError, CustomError, GuestError, GuestErrorCode, MapGuestError,
+    MissingFieldError, ParseNameError, WitxLocation,
+};
+pub use guard::{GuestSlice, GuestSliceMut, GuestStr, GuestStrCow};
+pub use guest_type::{
+    read_transparent, read_transparent_slice, write_transparent_slice, GuestErrorCtx,
+    GuestErrorType, GuestType, GuestTypeTransparent,
+};
+pub use handle_table::HandleTable;
+pub use instrument::{InstrumentationCtx, InstrumentationHook};
+pub use introspect::InterfaceInfo;
+#[cfg(feature = "std")]
+pub use io::{GuestReader, GuestWriter};
+pub use iovec::{AsGuestBuffer, IoVecs, IOVEC_INLINE_LEN};
+#[cfg(feature = "std")]
+pub use iovec::{GuestIoSlices, GuestIoSlicesMut};
+pub use limits::{CallLimits, GuestLimit, GuestLimits, LimitsCtx};
+pub use memory::{MemoryProvider, OwnedMemory, SliceMemory};
+pub use metadata::{FuncMetadata, ParamMetadata, TypeDesc, TypeKind, TypeMetadata};
+pub use mock::MockCall;
+pub use policy::{Policy, PolicyArg, PolicyCall, PolicyCtx, PolicyDecision};
+#[cfg(feature = "std")]
+pub use rate_limit::{RateLimit, RateLimiter};
 pub use region::Region;
+/// Re-exported for the serde implementations of types generated with
+/// `serde: true`.
+#[cfg(feature = "serde")]
+pub use serde;
 
 /// A trait which abstracts how to get at the region of host memory taht
 /// contains guest memory.
@@ -78,7 +146,7 @@ pub use region::Region;
 /// guest pointers it's recommended to be extremely cautious and thoroughly
 /// consider possible ramifications with respect to this API before codifying
 /// implementation details.
-pub unsafe trait GuestMemory {
+pub unsafe trait GuestMemory: AsGuestMemory {
     /// Returns the base allocation of this guest memory, located in host
     /// memory.
     ///
@@ -89,7 +157,7 @@ pub unsafe trait GuestMemory {
     /// Note that there are safety guarantees about this method that
     /// implementations must uphold, and for more details see the
     /// [`GuestMemory`] documentation.
-    fn base(&self) -> (*mut u8, u32);
+    fn base(&self) -> (*mut u8, u64);
 
     /// Validates a guest-relative pointer given various attributes, and returns
     /// the corresponding host pointer.
@@ -103,48 +171,193 @@ pub unsafe trait GuestMemory {
     ///
     /// This function will guarantee that the returned pointer is in-bounds of
     /// `base`, *at this time*, for `len` bytes and has alignment `align`. If
-    /// any guarantees are not upheld then an error will be returned.
+    /// any guarantees are not upheld then an error will be returned. The
+    /// exception is memories which don't
+    /// [require alignment](GuestMemory::require_alignment), for which the
+    /// returned pointer may be misaligned.
+    ///
+    /// Offsets past the end of memory fail with
+    /// [`GuestError::OffsetOutOfBounds`], and lengths running past it with
+    /// [`GuestError::LenOutOfBounds`], or [`GuestError::LenOverflow`] if they
+    /// run past the end of the address space too.
     ///
     /// Note that the returned pointer is an unsafe pointer. This is not safe to
     /// use in general because guest memory can be relocated. Additionally the
     /// guest may be modifying/reading memory as well. Consult the
     /// [`GuestMemory`] documentation for safety information about using this
     /// returned pointer.
+    #[inline]
     fn validate_size_align(
         &self,
-        offset: u32,
+        offset: u64,
         align: usize,
-        len: u32,
+        len: u64,
     ) -> Result<*mut u8, GuestError> {
+        let start = self.validate_size(offset, len)?;
+        // Byte-aligned accesses, by far the most common kind for buffers and
+        // strings, can't be misaligned so skip the division entirely.
+        if align > 1 && self.require_alignment() && (start as usize) % align != 0 {
+            let region = Region { start: offset, len };
+            return Err(GuestError::PtrNotAligned(region, align as u32));
+        }
+        Ok(start)
+    }
+
+    /// Validates a guest-relative pointer, without any alignment requirement,
+    /// and returns the corresponding host pointer.
+    ///
+    /// This is the same as [`GuestMemory::validate_size_align`] with an
+    /// alignment of 1, and has all the same caveats about the returned
+    /// pointer.
+    #[inline]
+    fn validate_size(&self, offset: u64, len: u64) -> Result<*mut u8, GuestError> {
+        #[cfg(feature = "perf-counters")]
+        counters::count_validation(self);
         let (base_ptr, base_len) = self.base();
         let region = Region { start: offset, len };
 
-        // Figure out our pointer to the start of memory
-        let start = match (base_ptr as usize).checked_add(offset as usize) {
-            Some(ptr) => ptr,
-            None => return Err(GuestError::PtrOverflow),
-        };
-        // and use that to figure out the end pointer
-        let end = match start.checked_add(len as usize) {
-            Some(ptr) => ptr,
-            None => return Err(GuestError::PtrOverflow),
-        };
-        // and then verify that our end doesn't reach past the end of our memory
-        if end > (base_ptr as usize) + (base_len as usize) {
+        // Memories without a host mapping can only be accessed through
+        // `read_bytes` and `write_bytes`.
+        if base_ptr.is_null() {
             return Err(GuestError::PtrOutOfBounds(region));
         }
-        // and finally verify that the alignment is correct
-        if start % align != 0 {
-            return Err(GuestError::PtrNotAligned(region, align as u32));
+
+        // The offset and length are checked separately, so that guests can
+        // tell which of them is wrong. Both are compared with the size of the
+        // memory, which is mapped into the host, so the pointer to any offset
+        // within it can't overflow.
+        if offset > base_len || (offset == base_len && len > 0) {
+            return Err(GuestError::OffsetOutOfBounds {
+                offset,
+                memory_size: base_len,
+            });
         }
-        Ok(start as *mut u8)
+        if len > base_len - offset {
+            return Err(match offset.checked_add(len) {
+                Some(_) => GuestError::LenOutOfBounds {
+                    region,
+                    memory_size: base_len,
+                },
+                None => GuestError::LenOverflow,
+            });
+        }
+        Ok((base_ptr as usize + offset as usize) as *mut u8)
+    }
+
+    /// Copies the `buf.len()` bytes of guest memory at `offset` into `buf`.
+    ///
+    /// Values like integers and floats are read through this method, as are
+    /// slices of them copied with methods like [`GuestPtr::to_vec`]. The
+    /// default implementation copies out of [`GuestMemory::base`].
+    ///
+    /// Memories which aren't a single contiguous region of host memory, such
+    /// as segmented memories or ones read from a snapshot or a remote process,
+    /// can override this and [`GuestMemory::write_bytes`] instead, and return
+    /// a null pointer along with their size from `base`. Bounds are checked
+    /// against that size, but anything viewing guest memory in place, like
+    /// [`GuestPtr::as_slice`] or [`GuestPtr::as_str`], fails with
+    /// [`GuestError::PtrOutOfBounds`].
+    #[inline]
+    fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<(), GuestError> {
+        let start = self.validate_size(offset, buf.len() as u64)?;
+        // SAFETY: `validate_size` checked the bytes are in guest memory,
+        // which is never part of a Rust allocation, so doesn't overlap `buf`.
+        unsafe { core::ptr::copy_nonoverlapping(start, buf.as_mut_ptr(), buf.len()) };
+        Ok(())
+    }
+
+    /// Copies `bytes` into guest memory at `offset`.
+    ///
+    /// This is the counterpart of [`GuestMemory::read_bytes`], and must be
+    /// overridden along with it.
+    #[inline]
+    fn write_bytes(&self, offset: u64, bytes: &[u8]) -> Result<(), GuestError> {
+        let start = self.validate_size(offset, bytes.len() as u64)?;
+        // SAFETY: as for `read_bytes`.
+        unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), start, bytes.len()) };
+        Ok(())
+    }
+
+    /// Returns whether pointers into this memory must be aligned for the type
+    /// they point to.
+    ///
+    /// Some guests, such as hand-written assembly, produce misaligned
+    /// pointers. Memories returning `false` accept them for reading and
+    /// writing individual values, which is done with unaligned loads and
+    /// stores. Slices are still only viewed in place, with methods like
+    /// [`GuestPtr::as_slice`], if they are aligned, since Rust references
+    /// must be.
+    fn require_alignment(&self) -> bool {
+        true
+    }
+
+    /// Returns whether NaNs read from and written to this memory as `f32` or
+    /// `f64` are canonicalized, for deterministic execution.
+    ///
+    /// NaNs with any sign or payload are replaced with the canonical NaN of
+    /// wasm, which is positive, quiet, and has no other payload bits. This
+    /// applies to reading and writing values and copying slices with methods
+    /// like [`GuestPtr::to_vec`], but not to slices viewed in place.
+    fn canonicalize_nans(&self) -> bool {
+        false
+    }
+
+    /// Returns the maximum number of bytes copied out of this memory into a
+    /// single host allocation, by methods like [`GuestPtr::to_vec`], if there
+    /// is one.
+    ///
+    /// Copying larger slices and strings fails with
+    /// [`GuestError::TooLarge`], instead of letting a guest make the host
+    /// allocate however much it likes. There is no maximum by default.
+    fn max_copy_len(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns which memory of the instance this is, under the multi-memory
+    /// proposal.
+    ///
+    /// Borrows are tracked per memory, so borrows of the same region of
+    /// different memories don't conflict. Instances have a single memory, 0,
+    /// by default. Generated trampolines taking a [`MemoryProvider`] wrap each
+    /// memory they look up in a [`CallMemory`] with its index.
+    fn memory_index(&self) -> u32 {
+        0
+    }
+
+    /// Returns the borrow checker shared by everything accessing this memory
+    /// for the duration of the current hostcall, if there is one.
+    ///
+    /// Memories don't track borrows by default. Generated trampolines wrap the
+    /// memory they're given in a [`CallMemory`], which does, so that all raw
+    /// views created while servicing a single call are checked against each
+    /// other, no matter which [`GuestBorrows`] they were created with.
+    fn borrows(&self) -> Option<&RefCell<GuestBorrows>> {
+        None
+    }
+
+    /// Returns the limits on how much data guests can pass to the current
+    /// hostcall, if there are any.
+    ///
+    /// Memories aren't limited by default. Generated trampolines configured
+    /// with `limits` wrap the memory they're given in a [`CallMemory`] which
+    /// is, and every slice and string accessed through it during the call is
+    /// checked against them.
+    fn limits(&self) -> Option<&CallLimits> {
+        None
+    }
+
+    /// Returns the counters of the checks made on this memory's behalf, if it
+    /// keeps any. See [`PerfCounters`].
+    #[cfg(feature = "perf-counters")]
+    fn perf_counters(&self) -> Option<&PerfCounters> {
+        None
     }
 
     /// Convenience method for creating a `GuestPtr` at a particular offset.
     ///
-    /// Note that `T` can be almost any type, and typically `offset` is a `u32`.
-    /// The exception is slices and strings, in which case `offset` is a `(u32,
-    /// u32)` of `(offset, length)`.
+    /// Note that `T` can be almost any type, and typically `offset` is a `u64`.
+    /// The exception is slices and strings, in which case `offset` is a `(u64,
+    /// u64)` of `(offset, length)`.
     fn ptr<'a, T>(&'a self, offset: T::Pointer) -> GuestPtr<'a, T>
     where
         Self: Sized,
@@ -154,36 +367,192 @@ pub unsafe trait GuestMemory {
     }
 }
 
+/// Erases the type of a [`GuestMemory`], for code which is generic over
+/// memories but needs to hand one to type-erased code, like the pointers
+/// passed to generated host traits.
+///
+/// This is implemented for every sized `GuestMemory`, and so for `dyn
+/// GuestMemory` as well, and never needs to be implemented by hand.
+pub trait AsGuestMemory {
+    fn as_guest_memory(&self) -> &dyn GuestMemory;
+}
+
+impl<M: GuestMemory> AsGuestMemory for M {
+    fn as_guest_memory(&self) -> &dyn GuestMemory {
+        self
+    }
+}
+
 // Forwarding trait implementations to the original type
 
-unsafe impl<'a, T: ?Sized + GuestMemory> GuestMemory for &'a T {
-    fn base(&self) -> (*mut u8, u32) {
+unsafe impl<T: ?Sized + GuestMemory> GuestMemory for &T {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<(), GuestError> {
+        T::read_bytes(self, offset, buf)
+    }
+    fn write_bytes(&self, offset: u64, bytes: &[u8]) -> Result<(), GuestError> {
+        T::write_bytes(self, offset, bytes)
+    }
+    fn require_alignment(&self) -> bool {
+        T::require_alignment(self)
+    }
+    fn canonicalize_nans(&self) -> bool {
+        T::canonicalize_nans(self)
+    }
+    fn max_copy_len(&self) -> Option<u64> {
+        T::max_copy_len(self)
+    }
+    fn memory_index(&self) -> u32 {
+        T::memory_index(self)
+    }
+    fn borrows(&self) -> Option<&RefCell<GuestBorrows>> {
+        T::borrows(self)
+    }
+    fn limits(&self) -> Option<&CallLimits> {
+        T::limits(self)
+    }
+    #[cfg(feature = "perf-counters")]
+    fn perf_counters(&self) -> Option<&PerfCounters> {
+        T::perf_counters(self)
+    }
 }
 
-unsafe impl<'a, T: ?Sized + GuestMemory> GuestMemory for &'a mut T {
-    fn base(&self) -> (*mut u8, u32) {
+unsafe impl<T: ?Sized + GuestMemory> GuestMemory for &mut T {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<(), GuestError> {
+        T::read_bytes(self, offset, buf)
+    }
+    fn write_bytes(&self, offset: u64, bytes: &[u8]) -> Result<(), GuestError> {
+        T::write_bytes(self, offset, bytes)
+    }
+    fn require_alignment(&self) -> bool {
+        T::require_alignment(self)
+    }
+    fn canonicalize_nans(&self) -> bool {
+        T::canonicalize_nans(self)
+    }
+    fn max_copy_len(&self) -> Option<u64> {
+        T::max_copy_len(self)
+    }
+    fn memory_index(&self) -> u32 {
+        T::memory_index(self)
+    }
+    fn borrows(&self) -> Option<&RefCell<GuestBorrows>> {
+        T::borrows(self)
+    }
+    fn limits(&self) -> Option<&CallLimits> {
+        T::limits(self)
+    }
+    #[cfg(feature = "perf-counters")]
+    fn perf_counters(&self) -> Option<&PerfCounters> {
+        T::perf_counters(self)
+    }
 }
 
 unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Box<T> {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<(), GuestError> {
+        T::read_bytes(self, offset, buf)
+    }
+    fn write_bytes(&self, offset: u64, bytes: &[u8]) -> Result<(), GuestError> {
+        T::write_bytes(self, offset, bytes)
+    }
+    fn require_alignment(&self) -> bool {
+        T::require_alignment(self)
+    }
+    fn canonicalize_nans(&self) -> bool {
+        T::canonicalize_nans(self)
+    }
+    fn max_copy_len(&self) -> Option<u64> {
+        T::max_copy_len(self)
+    }
+    fn memory_index(&self) -> u32 {
+        T::memory_index(self)
+    }
+    fn borrows(&self) -> Option<&RefCell<GuestBorrows>> {
+        T::borrows(self)
+    }
+    fn limits(&self) -> Option<&CallLimits> {
+        T::limits(self)
+    }
+    #[cfg(feature = "perf-counters")]
+    fn perf_counters(&self) -> Option<&PerfCounters> {
+        T::perf_counters(self)
+    }
 }
 
 unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Rc<T> {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<(), GuestError> {
+        T::read_bytes(self, offset, buf)
+    }
+    fn write_bytes(&self, offset: u64, bytes: &[u8]) -> Result<(), GuestError> {
+        T::write_bytes(self, offset, bytes)
+    }
+    fn require_alignment(&self) -> bool {
+        T::require_alignment(self)
+    }
+    fn canonicalize_nans(&self) -> bool {
+        T::canonicalize_nans(self)
+    }
+    fn max_copy_len(&self) -> Option<u64> {
+        T::max_copy_len(self)
+    }
+    fn memory_index(&self) -> u32 {
+        T::memory_index(self)
+    }
+    fn borrows(&self) -> Option<&RefCell<GuestBorrows>> {
+        T::borrows(self)
+    }
+    fn limits(&self) -> Option<&CallLimits> {
+        T::limits(self)
+    }
+    #[cfg(feature = "perf-counters")]
+    fn perf_counters(&self) -> Option<&PerfCounters> {
+        T::perf_counters(self)
+    }
 }
 
 unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Arc<T> {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         T::base(self)
     }
+    fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<(), GuestError> {
+        T::read_bytes(self, offset, buf)
+    }
+    fn write_bytes(&self, offset: u64, bytes: &[u8]) -> Result<(), GuestError> {
+        T::write_bytes(self, offset, bytes)
+    }
+    fn require_alignment(&self) -> bool {
+        T::require_alignment(self)
+    }
+    fn canonicalize_nans(&self) -> bool {
+        T::canonicalize_nans(self)
+    }
+    fn max_copy_len(&self) -> Option<u64> {
+        T::max_copy_len(self)
+    }
+    fn memory_index(&self) -> u32 {
+        T::memory_index(self)
+    }
+    fn borrows(&self) -> Option<&RefCell<GuestBorrows>> {
+        T::borrows(self)
+    }
+    fn limits(&self) -> Option<&CallLimits> {
+        T::limits(self)
+    }
+    #[cfg(feature = "perf-counters")]
+    fn perf_counters(&self) -> Option<&PerfCounters> {
+        T::perf_counters(self)
+    }
 }
 
 /// A *guest* pointer into host memory.
@@ -208,6 +577,11 @@ unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Arc<T> {
 /// Unsized types such as this may have extra methods and won't have methods
 /// like [`GuestPtr::read`] or [`GuestPtr::write`].
 ///
+/// The contents of a `GuestPtr<'_, str>` are not validated as UTF-8 until they
+/// are viewed as a string, for example with [`GuestPtr::as_raw`]. Host
+/// functions which only shuttle the bytes around (e.g. writing them to a file)
+/// can use the byte-level accessors instead and never pay for validation.
+///
 /// ## Type parameter and pointee
 ///
 /// The `T` type parameter is largely intended for more static safety in Rust as
@@ -218,8 +592,15 @@ unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Arc<T> {
 /// construct a `T` from a `U`.
 ///
 /// For example `GuestPtr<GuestPtr<T>>` is a valid type, but this is actually
-/// more equivalent to `GuestPtr<u32>` because guest pointers are always
-/// 32-bits. That being said you can create a `GuestPtr<T>` from a `u32`.
+/// more equivalent to `GuestPtr<u32>` because guest pointers stored in guest
+/// memory are always 32-bits, as laid out by witx. That being said you can
+/// create a `GuestPtr<T>` from a `u32`.
+///
+/// ## Offsets
+///
+/// Offsets and lengths are `u64`s, so that pointers can address 64-bit
+/// (memory64) guest memories as well as 32-bit ones. Pointers from 32-bit
+/// guests are simply zero-extended.
 ///
 /// Additionally `GuestPtr<MyEnum>` will actually delegate, typically, to and
 /// implementation which loads the underlying data as `GuestPtr<u8>` (or
@@ -230,19 +611,29 @@ unsafe impl<T: ?Sized + GuestMemory> GuestMemory for Arc<T> {
 /// methods. In general though be extremely careful about writing `unsafe` code
 /// when working with a `GuestPtr` if you're not using one of the
 /// already-attached helper methods.
-pub struct GuestPtr<'a, T: ?Sized + Pointee> {
-    mem: &'a (dyn GuestMemory + 'a),
+///
+/// ## Memory type
+///
+/// By default a `GuestPtr` refers to its memory as a `dyn GuestMemory`, so
+/// every bounds check is a dynamic call. Embedders with a single memory type
+/// can name it as the `M` parameter instead, for example
+/// `GuestPtr<'a, u32, MyMemory>`, and have all accesses through the pointer
+/// monomorphized and inlined. Generated trampolines do this when `from_witx!`
+/// is given the `memory` field. Pointers stored in guest memory, and those
+/// handed to generated host traits, are always type-erased.
+pub struct GuestPtr<'a, T: ?Sized + Pointee, M: ?Sized + GuestMemory = dyn GuestMemory + 'a> {
+    mem: &'a M,
     pointer: T::Pointer,
     _marker: marker::PhantomData<&'a Cell<T>>,
 }
 
-impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
+impl<'a, T: ?Sized + Pointee, M: ?Sized + GuestMemory> GuestPtr<'a, T, M> {
     /// Creates a new `GuestPtr` from the given `mem` and `pointer` values.
     ///
     /// Note that for sized types like `u32`, `GuestPtr<T>`, etc, the `pointer`
-    /// vlue is a `u32` offset into guest memory. For slices and strings,
-    /// `pointer` is a `(u32, u32)` offset/length pair.
-    pub fn new(mem: &'a (dyn GuestMemory + 'a), pointer: T::Pointer) -> GuestPtr<'_, T> {
+    /// vlue is a `u64` offset into guest memory. For slices and strings,
+    /// `pointer` is a `(u64, u64)` offset/length pair.
+    pub fn new(mem: &'a M, pointer: T::Pointer) -> GuestPtr<'a, T, M> {
         GuestPtr {
             mem,
             pointer,
@@ -252,26 +643,37 @@ impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
 
     /// Returns the offset of this pointer in guest memory.
     ///
-    /// Note that for sized types this returns a `u32`, but for slices and
-    /// strings it returns a `(u32, u32)` pointer/length pair.
+    /// Note that for sized types this returns a `u64`, but for slices and
+    /// strings it returns a `(u64, u64)` pointer/length pair.
     pub fn offset(&self) -> T::Pointer {
         self.pointer
     }
 
     /// Returns the guest memory that this pointer is coming from.
-    pub fn mem(&self) -> &'a (dyn GuestMemory + 'a) {
+    pub fn mem(&self) -> &'a M {
         self.mem
     }
 
+    /// Returns the index of the memory this pointer points into, for
+    /// instances with multiple memories. See [`GuestMemory::memory_index`].
+    pub fn memory_index(&self) -> u32 {
+        self.mem.memory_index()
+    }
+
+    /// Returns this pointer with the type of its memory erased.
+    pub fn erase(&self) -> GuestPtr<'a, T> {
+        GuestPtr::new(self.mem.as_guest_memory(), self.pointer)
+    }
+
     /// Casts this `GuestPtr` type to a different type.
     ///
     /// This is a safe method which is useful for simply reinterpreting the type
     /// parameter on this `GuestPtr`. Note that this is a safe method, where
     /// again there's no guarantees about alignment, validity, in-bounds-ness,
     /// etc of the returned pointer.
-    pub fn cast<U>(&self) -> GuestPtr<'a, U>
+    pub fn cast<U>(&self) -> GuestPtr<'a, U, M>
     where
-        T: Pointee<Pointer = u32>,
+        T: Pointee<Pointer = u64>,
     {
         GuestPtr::new(self.mem, self.pointer)
     }
@@ -329,12 +731,12 @@ impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
     /// This will either return the resulting pointer or `Err` if the pointer
     /// arithmetic calculation would overflow around the end of the address
     /// space.
-    pub fn add(&self, amt: u32) -> Result<GuestPtr<'a, T>, GuestError>
+    pub fn add(&self, amt: u64) -> Result<GuestPtr<'a, T, M>, GuestError>
     where
-        T: GuestType<'a> + Pointee<Pointer = u32>,
+        T: GuestType<'a> + Pointee<Pointer = u64>,
     {
         let offset = amt
-            .checked_mul(T::guest_size())
+            .checked_mul(T::guest_size().into())
             .and_then(|o| self.pointer.checked_add(o));
         let offset = match offset {
             Some(o) => o,
@@ -345,40 +747,173 @@ impl<'a, T: ?Sized + Pointee> GuestPtr<'a, T> {
 
     /// Returns a `GuestPtr` for an array of `T`s using this pointer as the
     /// base.
-    pub fn as_array(&self, elems: u32) -> GuestPtr<'a, [T]>
+    pub fn as_array(&self, elems: u64) -> GuestPtr<'a, [T], M>
     where
-        T: GuestType<'a> + Pointee<Pointer = u32>,
+        T: GuestType<'a> + Pointee<Pointer = u64>,
     {
         GuestPtr::new(self.mem, (self.pointer, elems))
     }
 }
 
-impl<'a, T> GuestPtr<'a, [T]> {
+impl<'a, T, M: ?Sized + GuestMemory> GuestPtr<'a, [T], M> {
     /// For slices, specifically returns the relative pointer to the base of the
     /// array.
     ///
     /// This is similar to `<[T]>::as_ptr()`
-    pub fn offset_base(&self) -> u32 {
+    pub fn offset_base(&self) -> u64 {
         self.pointer.0
     }
 
     /// For slices, returns the length of the slice, in units.
-    pub fn len(&self) -> u32 {
+    pub fn len(&self) -> u64 {
         self.pointer.1
     }
 
+    /// Returns `true` if the slice has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.pointer.1 == 0
+    }
+
+    /// Returns a pointer to the element at `index`, like `<[T]>::get`, or
+    /// [`GuestError::IndexOutOfBounds`] if it is past the end of the slice.
+    pub fn get(&self, index: u64) -> Result<GuestPtr<'a, T, M>, GuestError>
+    where
+        T: GuestType<'a>,
+    {
+        if index >= self.len() {
+            return Err(GuestError::IndexOutOfBounds {
+                index: index..index.saturating_add(1),
+                len: self.len(),
+            });
+        }
+        self.as_ptr().add(index)
+    }
+
+    /// Returns a pointer to the subslice `range`, like `<[T]>::get`, or
+    /// [`GuestError::IndexOutOfBounds`] if it isn't within the slice.
+    pub fn get_range(&self, range: Range<u64>) -> Result<GuestPtr<'a, [T], M>, GuestError>
+    where
+        T: GuestType<'a>,
+    {
+        if range.start > range.end || range.end > self.len() {
+            return Err(GuestError::IndexOutOfBounds {
+                index: range,
+                len: self.len(),
+            });
+        }
+        let start = self.as_ptr().add(range.start)?;
+        Ok(GuestPtr::new(
+            self.mem,
+            (start.offset(), range.end - range.start),
+        ))
+    }
+
+    /// Splits the slice in two at `mid`, like `<[T]>::split_at`, returning
+    /// [`GuestError::IndexOutOfBounds`] if `mid` is past the end.
+    ///
+    /// The halves don't overlap, so they can be borrowed independently.
+    #[allow(clippy::type_complexity)]
+    pub fn split_at(
+        &self,
+        mid: u64,
+    ) -> Result<(GuestPtr<'a, [T], M>, GuestPtr<'a, [T], M>), GuestError>
+    where
+        T: GuestType<'a>,
+    {
+        let head = self.get_range(0..mid)?;
+        let tail = self.get_range(mid..self.len())?;
+        Ok((head, tail))
+    }
+
+    /// Reads an array of buffer descriptors, like WASI's `ciovec_array`, and
+    /// borrows all the buffers for reading. See [`GuestIoSlices`].
+    #[cfg(feature = "std")]
+    pub fn io_slices(&self) -> Result<GuestIoSlices<'a>, GuestError>
+    where
+        T: GuestType<'a> + AsGuestBuffer<'a>,
+    {
+        GuestIoSlices::new(self.guest_buffers()?.iter().copied())
+    }
+
+    /// Reads an array of buffer descriptors, like WASI's `iovec_array`, and
+    /// borrows all the buffers for writing. See [`GuestIoSlicesMut`].
+    #[cfg(feature = "std")]
+    pub fn io_slices_mut(&self) -> Result<GuestIoSlicesMut<'a>, GuestError>
+    where
+        T: GuestType<'a> + AsGuestBuffer<'a>,
+    {
+        GuestIoSlicesMut::new(self.guest_buffers()?.iter().copied())
+    }
+
+    #[cfg(feature = "std")]
+    fn guest_buffers(&self) -> Result<IoVecs<GuestPtr<'a, [u8]>>, GuestError>
+    where
+        T: GuestType<'a> + AsGuestBuffer<'a>,
+    {
+        let mut bufs = IoVecs::new();
+        for elem in self.iter() {
+            bufs.push(elem?.read()?.as_guest_buffer());
+        }
+        Ok(bufs)
+    }
+
     /// Returns an iterator over interior pointers.
     ///
     /// Each item is a `Result` indicating whether it overflowed past the end of
     /// the address space or not.
     pub fn iter<'b>(
         &'b self,
-    ) -> impl ExactSizeIterator<Item = Result<GuestPtr<'a, T>, GuestError>> + 'b
+    ) -> impl ExactSizeIterator<Item = Result<GuestPtr<'a, T, M>, GuestError>> + 'b
     where
         T: GuestType<'a>,
     {
         let base = self.as_ptr();
-        (0..self.len()).map(move |i| base.add(i))
+        // A slice longer than the host's address space can't be in bounds,
+        // so every pointer past `usize::MAX` would be an error anyway.
+        let len = usize::try_from(self.len()).unwrap_or(usize::MAX);
+        (0..len).map(move |i| base.add(i as u64))
+    }
+
+    /// Copies the slice out of guest memory.
+    ///
+    /// Types with the same representation in guest memory as in Rust are
+    /// validated once and copied with a single `memcpy`, others are read
+    /// element by element.
+    ///
+    /// Fails with [`GuestError::TooLarge`] if the slice takes up more bytes
+    /// than the memory's [`GuestMemory::max_copy_len`].
+    pub fn to_vec(&self) -> Result<Vec<T>, GuestError>
+    where
+        T: GuestType<'a>,
+    {
+        let bytes = self.len().saturating_mul(T::guest_size().into());
+        check_copy_len(bytes, self.mem.max_copy_len())?;
+        T::read_slice(self)
+    }
+
+    /// Like [`GuestPtr::to_vec`], but fails with [`GuestError::TooLarge`]
+    /// before copying anything if the slice has more than `max` elements.
+    pub fn to_vec_limited(&self, max: u64) -> Result<Vec<T>, GuestError>
+    where
+        T: GuestType<'a>,
+    {
+        check_copy_len(self.len(), Some(max))?;
+        self.to_vec()
+    }
+
+    /// Copies all of `src` into the slice, like `<[T]>::copy_from_slice`.
+    ///
+    /// As for [`GuestPtr::to_vec`], transparent types are copied with a single
+    /// `memcpy`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` doesn't have the same length as the slice.
+    pub fn copy_from_slice(&self, src: &[T]) -> Result<(), GuestError>
+    where
+        T: GuestType<'a> + Clone,
+    {
+        T::write_slice(self, src)
     }
 
     /// Attempts to read a raw `*mut [T]` pointer from this pointer, performing
@@ -397,28 +932,25 @@ impl<'a, T> GuestPtr<'a, [T]> {
     ///
     /// For safety against overlapping mutable borrows, the user must use the
     /// same `GuestBorrows` to create all *mut str or *mut [T] that are alive
-    /// at the same time.
+    /// at the same time. Within a generated hostcall the borrow is also
+    /// checked against every other borrow made during that call, see
+    /// [`GuestMemory::borrows`].
+    ///
+    /// The slice is borrowed mutably, so it can't overlap any other borrow.
+    /// Use [`GuestPtr::as_raw_const`] to only read it.
     pub fn as_raw(&self, bc: &mut GuestBorrows) -> Result<*mut [T], GuestError>
     where
         T: GuestTypeTransparent<'a>,
     {
-        let len = match self.pointer.1.checked_mul(T::guest_size()) {
-            Some(l) => l,
-            None => return Err(GuestError::PtrOverflow),
-        };
-        let ptr =
-            self.mem
-                .validate_size_align(self.pointer.0, T::guest_align(), len)? as *mut T;
+        let (ptr, region) = self.validate_raw()?;
+        let handle = bc.borrow_in(self.mem.as_guest_memory(), region, BorrowKind::Mut)?;
 
-        bc.borrow(Region {
-            start: self.pointer.0,
-            len,
-        })?;
-
-        // Validate all elements in slice.
+        // Validate all elements in slice, releasing the borrow again if
+        // they're invalid so that it doesn't outlive the failed call.
         // SAFETY: ptr has been validated by self.mem.validate_size_align
-        for offs in 0..self.pointer.1 {
-            T::validate(unsafe { ptr.add(offs as usize) })?;
+        if let Err(e) = T::validate_slice(ptr, self.pointer.1) {
+            bc.unborrow(handle);
+            return Err(e);
         }
 
         // SAFETY: iff there are no overlapping borrows (all uses of as_raw use this same
@@ -429,31 +961,229 @@ impl<'a, T> GuestPtr<'a, [T]> {
         }
     }
 
+    /// Like [`GuestPtr::as_raw`], but returns a `*const [T]` to be used as a
+    /// `&[T]`.
+    ///
+    /// The slice is only borrowed shared, so it may overlap other shared
+    /// borrows, such as other `as_raw_const` views of the same buffer.
+    pub fn as_raw_const(&self, bc: &mut GuestBorrows) -> Result<*const [T], GuestError>
+    where
+        T: GuestTypeTransparent<'a>,
+    {
+        let (ptr, region) = self.validate_raw()?;
+        let handle = bc.borrow_in(self.mem.as_guest_memory(), region, BorrowKind::Shared)?;
+        // SAFETY: ptr has been validated by self.mem.validate_size_align
+        if let Err(e) = T::validate_slice(ptr, self.pointer.1) {
+            bc.unborrow(handle);
+            return Err(e);
+        }
+        // SAFETY: the region is only borrowed shared, so it's valid to
+        // construct a *const [T]
+        unsafe {
+            let s = slice::from_raw_parts(ptr, self.pointer.1 as usize);
+            Ok(s as *const [T])
+        }
+    }
+
+    /// Borrows the slice for the duration of the current hostcall, returning a
+    /// guard which dereferences to `&[T]`.
+    ///
+    /// This performs the same checks as [`GuestPtr::as_raw`], but registers
+    /// the borrow with the borrow checker shared by the hostcall (see
+    /// [`GuestMemory::borrows`]) instead of a `GuestBorrows` of your own, and
+    /// releases it again when the guard is dropped. Memories which don't
+    /// share a borrow checker, like those not wrapped in a [`CallMemory`],
+    /// can't be borrowed this way and return
+    /// [`GuestError::NoBorrowChecker`].
+    ///
+    /// While the guard is alive, writing to the slice, such as with
+    /// [`GuestPtr::write`], fails with [`GuestError::PtrBorrowed`]. So does
+    /// reading a slice borrowed with [`GuestPtr::as_slice_mut`].
+    pub fn as_slice(&self) -> Result<GuestSlice<'a, T>, GuestError>
+    where
+        T: GuestTypeTransparent<'a>,
+    {
+        let (ptr, region) = self.validate_raw()?;
+        let borrow = guard::Borrow::new(self.mem.as_guest_memory(), region, BorrowKind::Shared)?;
+        T::validate_slice(ptr, self.pointer.1)?;
+        // SAFETY: the region is in bounds, its contents are valid, and it is
+        // borrowed until the guard is dropped.
+        let slice = unsafe { slice::from_raw_parts(ptr, self.pointer.1 as usize) };
+        Ok(GuestSlice::new(slice, borrow))
+    }
+
+    /// Like [`GuestPtr::as_slice`], but returns a guard which dereferences to
+    /// `&mut [T]`.
+    pub fn as_slice_mut(&self) -> Result<GuestSliceMut<'a, T>, GuestError>
+    where
+        T: GuestTypeTransparent<'a>,
+    {
+        let (ptr, region) = self.validate_raw()?;
+        let borrow = guard::Borrow::new(self.mem.as_guest_memory(), region, BorrowKind::Mut)?;
+        T::validate_slice(ptr, self.pointer.1)?;
+        // SAFETY: as for `as_slice`.
+        let slice = unsafe { slice::from_raw_parts_mut(ptr, self.pointer.1 as usize) };
+        Ok(GuestSliceMut::new(slice, borrow))
+    }
+
+    /// Checks that the slice is in bounds, aligned, and within the call's
+    /// limits, returning the host pointer to it and the region it covers.
+    fn validate_raw(&self) -> Result<(*mut T, Region), GuestError>
+    where
+        T: GuestTypeTransparent<'a>,
+    {
+        let len = match self.pointer.1.checked_mul(T::guest_size().into()) {
+            Some(l) => l,
+            None => return Err(GuestError::LenOverflow),
+        };
+        let ptr =
+            self.mem
+                .validate_size_align(self.pointer.0, T::guest_align(), len)? as *mut T;
+        let region = Region {
+            start: self.pointer.0,
+            len,
+        };
+        // Memories which don't require alignment don't check it above, but
+        // the slice can only be viewed in place if it is aligned.
+        if (ptr as usize) % T::guest_align() != 0 {
+            return Err(GuestError::PtrNotAligned(region, T::guest_align() as u32));
+        }
+        if let Some(limits) = self.mem.limits() {
+            limits.check_slice(self.pointer.1, len)?;
+        }
+        Ok((ptr, region))
+    }
+
     /// Returns a `GuestPtr` pointing to the base of the array for the interior
     /// type `T`.
-    pub fn as_ptr(&self) -> GuestPtr<'a, T> {
+    pub fn as_ptr(&self) -> GuestPtr<'a, T, M> {
         GuestPtr::new(self.mem, self.offset_base())
     }
 }
 
-impl<'a> GuestPtr<'a, str> {
+#[cfg(feature = "std")]
+impl<'a, M: ?Sized + GuestMemory> GuestPtr<'a, [u8], M> {
+    /// Borrows the buffer for reading with `std::io::Read`, for example to
+    /// `io::copy` it into a host file.
+    ///
+    /// The buffer is bounds checked and borrowed shared as with
+    /// [`GuestPtr::as_slice`], until the reader is dropped.
+    pub fn reader(&self) -> Result<GuestReader<'a>, GuestError> {
+        Ok(GuestReader::new(self.as_slice()?))
+    }
+
+    /// Borrows the buffer for writing with `std::io::Write`, for example to
+    /// `io::copy` a host file into it.
+    ///
+    /// The buffer is bounds checked and borrowed mutably as with
+    /// [`GuestPtr::as_slice_mut`], until the writer is dropped.
+    pub fn writer(&self) -> Result<GuestWriter<'a>, GuestError> {
+        Ok(GuestWriter::new(self.as_slice_mut()?))
+    }
+}
+
+/// Copies the bytes `src` points to into `dst`, which may be in another
+/// guest memory, for example to pipe data between instances.
+///
+/// Both buffers are bounds checked in their own memory. When they are in the
+/// same memory they may overlap, in which case the copy behaves as if `src`
+/// were first copied into a temporary buffer. As with [`GuestType::write`],
+/// the copy fails if `src` is mutably borrowed, or `dst` borrowed at all,
+/// during the hostcall.
+///
+/// # Panics
+///
+/// Panics if `src` and `dst` don't have the same length.
+pub fn copy<S, D>(src: GuestPtr<'_, [u8], S>, dst: GuestPtr<'_, [u8], D>) -> Result<(), GuestError>
+where
+    S: ?Sized + GuestMemory,
+    D: ?Sized + GuestMemory,
+{
+    assert_eq!(
+        src.len(),
+        dst.len(),
+        "source length does not match the destination length"
+    );
+    let src_ptr = src.mem().validate_size(src.offset_base(), src.len())?;
+    let dst_ptr = dst.mem().validate_size(dst.offset_base(), dst.len())?;
+    let src_region = Region {
+        start: src.offset_base(),
+        len: src.len(),
+    };
+    let dst_region = Region {
+        start: dst.offset_base(),
+        len: dst.len(),
+    };
+    guard::check_access(src.mem(), src_region, BorrowKind::Shared)?;
+    guard::check_access(dst.mem(), dst_region, BorrowKind::Mut)?;
+    // Lengths which fit in memory fit in a `usize`.
+    let len = src.len() as usize;
+    let same_memory = src.mem().base().0 == dst.mem().base().0;
+    // SAFETY: both buffers were just validated to be in bounds of their
+    // memories.
+    unsafe {
+        if same_memory && src_ptr.wrapping_add(len) > dst_ptr && dst_ptr.wrapping_add(len) > src_ptr
+        {
+            core::ptr::copy(src_ptr, dst_ptr, len);
+        } else {
+            core::ptr::copy_nonoverlapping(src_ptr, dst_ptr, len);
+        }
+    }
+    Ok(())
+}
+
+fn check_copy_len(len: u64, max: Option<u64>) -> Result<(), GuestError> {
+    match max {
+        Some(max) if len > max => Err(GuestError::TooLarge { len, max }),
+        _ => Ok(()),
+    }
+}
+
+impl<'a, M: ?Sized + GuestMemory> GuestPtr<'a, str, M> {
     /// For strings, returns the relative pointer to the base of the string
     /// allocation.
-    pub fn offset_base(&self) -> u32 {
+    pub fn offset_base(&self) -> u64 {
         self.pointer.0
     }
 
     /// Returns the length, in bytes, of th estring.
-    pub fn len(&self) -> u32 {
+    pub fn len(&self) -> u64 {
         self.pointer.1
     }
 
+    /// Returns `true` if the string has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.pointer.1 == 0
+    }
+
     /// Returns a raw pointer for the underlying slice of bytes that this
     /// pointer points to.
-    pub fn as_bytes(&self) -> GuestPtr<'a, [u8]> {
+    pub fn as_bytes(&self) -> GuestPtr<'a, [u8], M> {
         GuestPtr::new(self.mem, self.pointer)
     }
 
+    /// Attempts to read a raw `*mut [u8]` pointer to the bytes of this string,
+    /// performing bounds checks but *no* utf-8 checks.
+    ///
+    /// This is the same as calling `as_raw` on [`GuestPtr::as_bytes`], and
+    /// allows the string to be handled as opaque data without paying for
+    /// validation. Validation is deferred until the string is actually viewed
+    /// as a `str`, for example by [`GuestPtr::as_raw`].
+    ///
+    /// The same rules as for [`GuestPtr::as_raw`] apply to using the returned
+    /// pointer.
+    pub fn as_raw_bytes(&self, bc: &mut GuestBorrows) -> Result<*mut [u8], GuestError> {
+        let (ptr, region) = self.validate_raw()?;
+        bc.borrow_in(self.mem.as_guest_memory(), region, BorrowKind::Mut)?;
+
+        // SAFETY: iff there are no overlapping borrows (all uses of as_raw use this same
+        // GuestBorrows), its valid to construct a *mut [u8]
+        unsafe {
+            let s = slice::from_raw_parts_mut(ptr, self.pointer.1 as usize);
+            Ok(s as *mut [u8])
+        }
+    }
+
     /// Attempts to read a raw `*mut str` pointer from this pointer, performing
     /// bounds checks and utf-8 checks.
     /// The resulting `*mut str` can be used as a `&mut str` as long as the
@@ -463,6 +1193,10 @@ impl<'a> GuestPtr<'a, str> {
     /// succeed (valid utf-8, valid pointers, etc). If any checks fail then
     /// `GuestError` will be returned.
     ///
+    /// UTF-8 validation is performed with the standard library by default, or
+    /// with the vectorized `simdutf8` validator if the `simdutf8` feature of
+    /// this crate is enabled.
+    ///
     /// Note that the `*mut str` pointer is still unsafe to use in general, but
     /// there are specific situations that it is safe to use. For more
     /// information about using the raw pointer, consult the [`GuestMemory`]
@@ -470,38 +1204,138 @@ impl<'a> GuestPtr<'a, str> {
     ///
     /// For safety against overlapping mutable borrows, the user must use the
     /// same `GuestBorrows` to create all *mut str or *mut [T] that are alive
-    /// at the same time.
+    /// at the same time. Within a generated hostcall the borrow is also
+    /// checked against every other borrow made during that call, see
+    /// [`GuestMemory::borrows`].
     pub fn as_raw(&self, bc: &mut GuestBorrows) -> Result<*mut str, GuestError> {
-        let ptr = self
-            .mem
-            .validate_size_align(self.pointer.0, 1, self.pointer.1)?;
-
-        bc.borrow(Region {
-            start: self.pointer.0,
-            len: self.pointer.1,
-        })?;
+        let (ptr, region) = self.validate_raw()?;
+        let handle = bc.borrow_in(self.mem.as_guest_memory(), region, BorrowKind::Mut)?;
 
         // SAFETY: iff there are no overlapping borrows (all uses of as_raw use this same
         // GuestBorrows), its valid to construct a *mut str
-        unsafe {
-            let s = slice::from_raw_parts_mut(ptr, self.pointer.1 as usize);
-            match str::from_utf8_mut(s) {
-                Ok(s) => Ok(s),
-                Err(e) => Err(GuestError::InvalidUtf8(e)),
+        let bytes = unsafe { slice::from_raw_parts_mut(ptr, self.pointer.1 as usize) };
+        match utf8::from_utf8_mut(bytes) {
+            Ok(s) => Ok(s),
+            Err(err) => {
+                // Don't leave the borrow behind for a string which can't be
+                // used.
+                bc.unborrow(handle);
+                Err(GuestError::InvalidUtf8 { region, err })
             }
         }
     }
+
+    /// Like [`GuestPtr::as_raw`], but returns a `*const str` to be used as a
+    /// `&str`.
+    ///
+    /// The string is only borrowed shared, so it may overlap other shared
+    /// borrows.
+    pub fn as_raw_const(&self, bc: &mut GuestBorrows) -> Result<*const str, GuestError> {
+        let (ptr, region) = self.validate_raw()?;
+        let handle = bc.borrow_in(self.mem.as_guest_memory(), region, BorrowKind::Shared)?;
+        // SAFETY: the region is only borrowed shared, so it's valid to
+        // construct a *const str once it's been validated
+        let bytes = unsafe { slice::from_raw_parts(ptr, self.pointer.1 as usize) };
+        match utf8::from_utf8(bytes) {
+            Ok(s) => Ok(s as *const str),
+            Err(err) => {
+                bc.unborrow(handle);
+                Err(GuestError::InvalidUtf8 { region, err })
+            }
+        }
+    }
+
+    /// Borrows the string for the duration of the current hostcall, returning
+    /// a guard which dereferences to `&str`.
+    ///
+    /// The string is checked to be valid UTF-8 once, when the guard is
+    /// created. Like [`GuestPtr::as_slice`], the borrow is registered with the
+    /// borrow checker shared by the hostcall and released again when the
+    /// guard is dropped.
+    pub fn as_str(&self) -> Result<GuestStr<'a>, GuestError> {
+        let (ptr, region) = self.validate_raw()?;
+        let borrow = guard::Borrow::new(self.mem.as_guest_memory(), region, BorrowKind::Shared)?;
+        // SAFETY: the region is in bounds and borrowed until the guard is
+        // dropped.
+        let bytes = unsafe { slice::from_raw_parts(ptr, self.pointer.1 as usize) };
+        let s = utf8::from_utf8(bytes).map_err(|err| GuestError::InvalidUtf8 { region, err })?;
+        Ok(GuestStr::new(s, borrow))
+    }
+
+    /// Copies the string out of guest memory, after checking it is valid
+    /// UTF-8.
+    ///
+    /// Fails with [`GuestError::TooLarge`] if the string is longer than the
+    /// memory's [`GuestMemory::max_copy_len`].
+    pub fn to_string(&self) -> Result<String, GuestError> {
+        check_copy_len(self.len(), self.mem.max_copy_len())?;
+        let (ptr, region) = self.validate_raw()?;
+        guard::check_access(self.mem, region, BorrowKind::Shared)?;
+        // SAFETY: `validate_raw` checked the string is in bounds, it isn't
+        // mutably borrowed, and it is copied out before anything else can
+        // touch it, as for `to_vec`.
+        let bytes = unsafe { slice::from_raw_parts(ptr, self.pointer.1 as usize) };
+        let s = utf8::from_utf8(bytes).map_err(|err| GuestError::InvalidUtf8 { region, err })?;
+        Ok(s.to_owned())
+    }
+
+    /// Like [`GuestPtr::to_string`], but copies strings which aren't UTF-8
+    /// too, replacing invalid sequences with U+FFFD REPLACEMENT CHARACTER.
+    pub fn to_string_lossy(&self) -> Result<String, GuestError> {
+        check_copy_len(self.len(), self.mem.max_copy_len())?;
+        let (ptr, region) = self.validate_raw()?;
+        guard::check_access(self.mem, region, BorrowKind::Shared)?;
+        // SAFETY: as for `to_string`.
+        let bytes = unsafe { slice::from_raw_parts(ptr, self.pointer.1 as usize) };
+        Ok(utf8::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Like [`GuestPtr::to_string`], but fails with [`GuestError::TooLarge`]
+    /// before copying anything if the string is longer than `max` bytes.
+    pub fn to_string_limited(&self, max: u64) -> Result<String, GuestError> {
+        check_copy_len(self.len(), Some(max))?;
+        self.to_string()
+    }
+
+    /// Like [`GuestPtr::as_str`], but accepts strings which aren't UTF-8.
+    ///
+    /// Valid strings are borrowed in place. Others are copied, with invalid
+    /// sequences replaced by U+FFFD REPLACEMENT CHARACTER, which is the usual
+    /// way to handle things like paths and log lines which are only mostly
+    /// text.
+    pub fn as_str_lossy(&self) -> Result<GuestStrCow<'a>, GuestError> {
+        let (ptr, region) = self.validate_raw()?;
+        let borrow = guard::Borrow::new(self.mem.as_guest_memory(), region, BorrowKind::Shared)?;
+        // SAFETY: the region is in bounds and borrowed until the guard is
+        // dropped, or until the string has been copied.
+        let bytes = unsafe { slice::from_raw_parts(ptr, self.pointer.1 as usize) };
+        Ok(GuestStrCow::new(utf8::from_utf8_lossy(bytes), borrow))
+    }
+
+    /// Checks that the string is in bounds and within the call's limits,
+    /// returning the host pointer to it and the region it covers.
+    fn validate_raw(&self) -> Result<(*mut u8, Region), GuestError> {
+        let ptr = self.mem.validate_size(self.pointer.0, self.pointer.1)?;
+        if let Some(limits) = self.mem.limits() {
+            limits.check_string(self.pointer.1)?;
+        }
+        let region = Region {
+            start: self.pointer.0,
+            len: self.pointer.1,
+        };
+        Ok((ptr, region))
+    }
 }
 
-impl<T: ?Sized + Pointee> Clone for GuestPtr<'_, T> {
+impl<T: ?Sized + Pointee, M: ?Sized + GuestMemory> Clone for GuestPtr<'_, T, M> {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<T: ?Sized + Pointee> Copy for GuestPtr<'_, T> {}
+impl<T: ?Sized + Pointee, M: ?Sized + GuestMemory> Copy for GuestPtr<'_, T, M> {}
 
-impl<T: ?Sized + Pointee> fmt::Debug for GuestPtr<'_, T> {
+impl<T: ?Sized + Pointee, M: ?Sized + GuestMemory> fmt::Debug for GuestPtr<'_, T, M> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         T::debug(self.pointer, f)
     }
@@ -526,21 +1360,21 @@ pub trait Pointee: private::Sealed {
 }
 
 impl<T> Pointee for T {
-    type Pointer = u32;
+    type Pointer = u64;
     fn debug(pointer: Self::Pointer, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "*guest {:#x}", pointer)
     }
 }
 
 impl<T> Pointee for [T] {
-    type Pointer = (u32, u32);
+    type Pointer = (u64, u64);
     fn debug(pointer: Self::Pointer, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "*guest {:#x}/{}", pointer.0, pointer.1)
     }
 }
 
 impl Pointee for str {
-    type Pointer = (u32, u32);
+    type Pointer = (u64, u64);
     fn debug(pointer: Self::Pointer, f: &mut fmt::Formatter) -> fmt::Result {
         <[u8]>::debug(pointer, f)
     }
@@ -0,0 +1,175 @@
+use crate::GuestError;
+use core::cell::Cell;
+use core::fmt;
+
+/// Limits on the amount of guest data a single hostcall will touch.
+///
+/// Without limits, a guest can hand the host a slice or string covering all
+/// of its memory and have it validated or copied on every call. Limits are set
+/// per `from_witx!` invocation with the `limits` field, either to fixed values
+/// or to whatever the ctx returns from [`LimitsCtx::limits`]. `None` means no
+/// limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GuestLimits {
+    /// The maximum number of elements in a slice.
+    pub max_slice_len: Option<u32>,
+    /// The maximum number of bytes in a string.
+    pub max_string_len: Option<u32>,
+    /// The maximum number of bytes of slices and strings accessed over the
+    /// course of a single hostcall.
+    pub max_call_bytes: Option<u64>,
+}
+
+/// Implemented by contexts which pick the [`GuestLimits`] for each call.
+pub trait LimitsCtx {
+    fn limits(&self) -> GuestLimits;
+}
+
+/// The limit a guest exceeded, reported in [`GuestError::LimitExceeded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuestLimit {
+    SliceLen,
+    StringLen,
+    CallBytes,
+}
+
+impl fmt::Display for GuestLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            GuestLimit::SliceLen => "slice length",
+            GuestLimit::StringLen => "string length",
+            GuestLimit::CallBytes => "bytes per call",
+        })
+    }
+}
+
+/// [`GuestLimits`] being enforced for the duration of a hostcall, along with
+/// the number of bytes accessed so far. See [`GuestMemory::limits`].
+///
+/// [`GuestMemory::limits`]: crate::GuestMemory::limits
+#[derive(Debug)]
+pub struct CallLimits {
+    limits: GuestLimits,
+    used: Cell<u64>,
+}
+
+impl CallLimits {
+    pub fn new(limits: GuestLimits) -> Self {
+        Self {
+            limits,
+            used: Cell::new(0),
+        }
+    }
+
+    pub fn limits(&self) -> &GuestLimits {
+        &self.limits
+    }
+
+    /// The number of bytes accessed so far.
+    pub fn used(&self) -> u64 {
+        self.used.get()
+    }
+
+    /// Accounts for a slice of `len` elements taking up `bytes` bytes.
+    pub fn check_slice(&self, len: u64, bytes: u64) -> Result<(), GuestError> {
+        check(
+            GuestLimit::SliceLen,
+            len,
+            self.limits.max_slice_len.map(u64::from),
+        )?;
+        self.charge(bytes)
+    }
+
+    /// Accounts for a string of `bytes` bytes.
+    pub fn check_string(&self, bytes: u64) -> Result<(), GuestError> {
+        check(
+            GuestLimit::StringLen,
+            bytes,
+            self.limits.max_string_len.map(u64::from),
+        )?;
+        self.charge(bytes)
+    }
+
+    fn charge(&self, bytes: u64) -> Result<(), GuestError> {
+        let used = self.used.get().saturating_add(bytes);
+        check(GuestLimit::CallBytes, used, self.limits.max_call_bytes)?;
+        self.used.set(used);
+        Ok(())
+    }
+}
+
+fn check(limit: GuestLimit, requested: u64, max: Option<u64>) -> Result<(), GuestError> {
+    match max {
+        Some(max) if requested > max => Err(GuestError::LimitExceeded {
+            limit,
+            requested,
+            max,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unlimited() {
+        let limits = CallLimits::new(GuestLimits::default());
+        limits
+            .check_slice(u64::from(u32::MAX), u64::from(u32::MAX))
+            .expect("no slice limit");
+        limits
+            .check_string(u64::from(u32::MAX))
+            .expect("no string limit");
+        assert_eq!(limits.used(), 2 * u64::from(u32::MAX));
+    }
+
+    #[test]
+    fn per_access_limits() {
+        let limits = CallLimits::new(GuestLimits {
+            max_slice_len: Some(4),
+            max_string_len: Some(16),
+            max_call_bytes: None,
+        });
+        limits.check_slice(4, 32).expect("slice at limit");
+        assert_eq!(
+            limits.check_slice(5, 40),
+            Err(GuestError::LimitExceeded {
+                limit: GuestLimit::SliceLen,
+                requested: 5,
+                max: 4,
+            })
+        );
+        limits.check_string(16).expect("string at limit");
+        assert_eq!(
+            limits.check_string(17),
+            Err(GuestError::LimitExceeded {
+                limit: GuestLimit::StringLen,
+                requested: 17,
+                max: 16,
+            })
+        );
+        assert_eq!(limits.used(), 48);
+    }
+
+    #[test]
+    fn call_bytes_accumulate() {
+        let limits = CallLimits::new(GuestLimits {
+            max_call_bytes: Some(100),
+            ..GuestLimits::default()
+        });
+        limits.check_string(60).expect("first string fits");
+        assert_eq!(
+            limits.check_slice(10, 41),
+            Err(GuestError::LimitExceeded {
+                limit: GuestLimit::CallBytes,
+                requested: 101,
+                max: 100,
+            })
+        );
+        // The rejected access isn't counted.
+        limits.check_slice(10, 40).expect("second slice fits");
+        assert_eq!(limits.used(), 100);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::GuestMemory;
+use alloc::alloc::{alloc_zeroed, dealloc, handle_alloc_error, realloc, Layout};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
+/// Guest memory borrowed from a byte slice, such as the linear memory of an
+/// engine handed to a hostcall.
+///
+/// The slice is borrowed mutably for as long as the `SliceMemory` is alive, so
+/// nothing else, the guest included, can touch or move the memory in the
+/// meantime.
+#[derive(Debug)]
+pub struct SliceMemory<'a> {
+    ptr: *mut u8,
+    len: u64,
+    _borrow: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> SliceMemory<'a> {
+    pub fn new(mem: &'a mut [u8]) -> Self {
+        Self {
+            ptr: mem.as_mut_ptr(),
+            len: mem.len() as u64,
+            _borrow: PhantomData,
+        }
+    }
+}
+
+// SAFETY: the slice is exclusively borrowed for `'a`, so it stays valid and in
+// place for the lifetime of the memory, and all accesses go through `base`.
+unsafe impl GuestMemory for SliceMemory<'_> {
+    fn base(&self) -> (*mut u8, u64) {
+        (self.ptr, self.len)
+    }
+}
+
+/// Guest memory owned by the host, for embedders without an engine to
+/// provide it, such as interpreters and tests.
+///
+/// The memory is a zeroed heap allocation, aligned well enough for any guest
+/// type. It can only be grown through a `&mut OwnedMemory`, so never while
+/// `GuestPtr`s into it are in use.
+pub struct OwnedMemory {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+impl OwnedMemory {
+    /// The alignment of the start of the memory.
+    pub const ALIGN: usize = 16;
+
+    /// Allocates `size` bytes of zeroed memory.
+    pub fn new(size: usize) -> Self {
+        let mut mem = OwnedMemory {
+            ptr: Self::dangling(),
+            size: 0,
+        };
+        mem.grow(size);
+        mem
+    }
+
+    /// The size of the memory, in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Grows the memory by `delta` zeroed bytes, returning the old size.
+    ///
+    /// Like `memory.grow`, this may move the memory, which is why it requires
+    /// exclusive access.
+    pub fn grow(&mut self, delta: usize) -> usize {
+        let old_size = self.size;
+        if delta == 0 {
+            return old_size;
+        }
+        let new_size = old_size.checked_add(delta).expect("memory size overflow");
+        let new_layout = Self::layout(new_size);
+        // SAFETY: `new_layout` has a non-zero size, and `ptr` was allocated
+        // with the layout for `old_size` if that is non-zero.
+        let ptr = unsafe {
+            if old_size == 0 {
+                alloc_zeroed(new_layout)
+            } else {
+                let ptr = realloc(self.ptr.as_ptr(), Self::layout(old_size), new_size);
+                if !ptr.is_null() {
+                    ptr.add(old_size).write_bytes(0, delta);
+                }
+                ptr
+            }
+        };
+        self.ptr = NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(new_layout));
+        self.size = new_size;
+        old_size
+    }
+
+    /// The contents of the memory.
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: the memory is `size` initialized bytes, and can't be
+        // written through `GuestPtr`s while it is borrowed shared, since
+        // they would conflict.
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.size) }
+    }
+
+    /// The contents of the memory, mutably.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: the memory is `size` initialized bytes, borrowed
+        // exclusively.
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.size) }
+    }
+
+    fn layout(size: usize) -> Layout {
+        Layout::from_size_align(size, Self::ALIGN).expect("memory size overflow")
+    }
+
+    fn dangling() -> NonNull<u8> {
+        #[repr(align(16))]
+        struct Align16;
+        NonNull::<Align16>::dangling().cast()
+    }
+}
+
+impl Drop for OwnedMemory {
+    fn drop(&mut self) {
+        if self.size > 0 {
+            // SAFETY: `ptr` was allocated with the layout for `size`.
+            unsafe { dealloc(self.ptr.as_ptr(), Self::layout(self.size)) }
+        }
+    }
+}
+
+impl core::fmt::Debug for OwnedMemory {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("OwnedMemory")
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+// SAFETY: the memory is owned, so can be moved to another thread. It isn't
+// `Sync`, since `GuestPtr`s write to it through shared references.
+unsafe impl Send for OwnedMemory {}
+
+// SAFETY: the allocation is valid for `size` bytes until the memory is
+// grown or dropped, both of which require exclusive access.
+unsafe impl GuestMemory for OwnedMemory {
+    fn base(&self) -> (*mut u8, u64) {
+        (self.ptr.as_ptr(), self.size as u64)
+    }
+}
+
+/// The memories of an instance using the multi-memory proposal, by index.
+///
+/// Generated trampolines configured with `multi_memory: true` take a
+/// `&dyn MemoryProvider` and the index of the memory their pointers are in,
+/// instead of a single memory. Lists of memories are providers, with each
+/// memory at its position in the list.
+pub trait MemoryProvider {
+    /// Returns the memory at `index`, if there is one.
+    fn memory(&self, index: u32) -> Option<&dyn GuestMemory>;
+}
+
+impl<M: GuestMemory> MemoryProvider for [M] {
+    fn memory(&self, index: u32) -> Option<&dyn GuestMemory> {
+        let mem = self.get(usize::try_from(index).ok()?)?;
+        Some(mem)
+    }
+}
+
+impl<M: GuestMemory, const N: usize> MemoryProvider for [M; N] {
+    fn memory(&self, index: u32) -> Option<&dyn GuestMemory> {
+        self[..].memory(index)
+    }
+}
+
+impl<M: GuestMemory> MemoryProvider for Vec<M> {
+    fn memory(&self, index: u32) -> Option<&dyn GuestMemory> {
+        self[..].memory(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{GuestError, GuestPtr, Region};
+    use core::cell::RefCell;
+
+    #[repr(align(8))]
+    struct Aligned([u8; 16]);
+
+    #[test]
+    fn reads_and_writes_the_slice() {
+        let mut bytes = Aligned([0; 16]);
+        {
+            let mem = SliceMemory::new(&mut bytes.0);
+            GuestPtr::<u32>::new(&mem, 4)
+                .write(0x0403_0201)
+                .expect("write");
+            assert!(GuestPtr::<u32>::new(&mem, 16).read().is_err());
+        }
+        assert_eq!(bytes.0[4..8], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn reports_which_bound_is_exceeded() {
+        let mut bytes = Aligned([0; 16]);
+        let mem = SliceMemory::new(&mut bytes.0);
+        assert!(mem.validate_size(16, 0).is_ok());
+        assert_eq!(
+            mem.validate_size(16, 1),
+            Err(GuestError::OffsetOutOfBounds {
+                offset: 16,
+                memory_size: 16,
+            })
+        );
+        assert_eq!(
+            mem.validate_size(u64::MAX, 0),
+            Err(GuestError::OffsetOutOfBounds {
+                offset: u64::MAX,
+                memory_size: 16,
+            })
+        );
+        assert_eq!(
+            mem.validate_size(8, 9),
+            Err(GuestError::LenOutOfBounds {
+                region: Region::new(8, 9),
+                memory_size: 16,
+            })
+        );
+        assert_eq!(mem.validate_size(8, u64::MAX), Err(GuestError::LenOverflow));
+    }
+
+    #[test]
+    fn owned_memory_grows() {
+        let mut mem = OwnedMemory::new(0);
+        assert_eq!(mem.size(), 0);
+        assert!(GuestPtr::<u8>::new(&mem, 0).read().is_err());
+
+        assert_eq!(mem.grow(8), 0);
+        assert_eq!(mem.base().0 as usize % OwnedMemory::ALIGN, 0);
+        GuestPtr::<u64>::new(&mem, 0)
+            .write(u64::MAX)
+            .expect("write");
+
+        assert_eq!(mem.grow(65536), 8);
+        assert_eq!(mem.size(), 65544);
+        assert_eq!(mem.base().0 as usize % OwnedMemory::ALIGN, 0);
+        assert_eq!(GuestPtr::<u64>::new(&mem, 0).read(), Ok(u64::MAX));
+        assert!(mem.as_bytes()[8..].iter().all(|b| *b == 0), "grown zeroed");
+        mem.as_bytes_mut()[65543] = 1;
+        assert_eq!(GuestPtr::<u8>::new(&mem, 65543).read(), Ok(1));
+    }
+
+    /// A memory split over two host allocations, only accessible a byte at a
+    /// time.
+    struct Segmented {
+        segments: [RefCell<Vec<u8>>; 2],
+    }
+
+    impl Segmented {
+        fn byte(&self, offset: u64) -> Result<(usize, usize), GuestError> {
+            let seg = offset as usize / 8;
+            if seg >= self.segments.len() {
+                return Err(GuestError::PtrOutOfBounds(Region::new(offset, 1)));
+            }
+            Ok((seg, offset as usize % 8))
+        }
+    }
+
+    unsafe impl GuestMemory for Segmented {
+        fn base(&self) -> (*mut u8, u64) {
+            (core::ptr::null_mut(), 16)
+        }
+        fn read_bytes(&self, offset: u64, buf: &mut [u8]) -> Result<(), GuestError> {
+            for (i, b) in buf.iter_mut().enumerate() {
+                let (seg, at) = self.byte(offset + i as u64)?;
+                *b = self.segments[seg].borrow()[at];
+            }
+            Ok(())
+        }
+        fn write_bytes(&self, offset: u64, bytes: &[u8]) -> Result<(), GuestError> {
+            for (i, b) in bytes.iter().enumerate() {
+                let (seg, at) = self.byte(offset + i as u64)?;
+                self.segments[seg].borrow_mut()[at] = *b;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn memory_without_host_mapping() {
+        let mem = Segmented {
+            segments: [RefCell::new(vec![0; 8]), RefCell::new(vec![0; 8])],
+        };
+        GuestPtr::<u32>::new(&mem, 4)
+            .write(0x0403_0201)
+            .expect("write");
+        GuestPtr::<u32>::new(&mem, 8)
+            .write(0x0807_0605)
+            .expect("write");
+        assert_eq!(mem.segments[0].borrow()[4..], [1, 2, 3, 4]);
+        assert_eq!(GuestPtr::<u64>::new(&mem, 0).read(), Ok(0x0403_0201 << 32));
+        assert!(GuestPtr::<u32>::new(&mem, 16).read().is_err());
+
+        let array = GuestPtr::<[u16]>::new(&mem, (4, 4));
+        assert_eq!(array.to_vec(), Ok(vec![0x0201, 0x0403, 0x0605, 0x0807]));
+        array.copy_from_slice(&[1, 2, 3, 4]).expect("copy");
+        assert_eq!(GuestPtr::<u64>::new(&mem, 8).read(), Ok(0x0004_0003));
+        assert!(array.as_slice().is_err(), "no host memory to view");
+    }
+
+    #[test]
+    fn reads_transparent_values_whole() {
+        let mem = Segmented {
+            segments: [RefCell::new(vec![0; 8]), RefCell::new(vec![0; 8])],
+        };
+        GuestPtr::<u64>::new(&mem, 8)
+            .write(0x0807_0605_0403_0201)
+            .expect("write");
+        let ptr = GuestPtr::<u64>::new(&mem, 8);
+        assert_eq!(crate::read_transparent(&ptr), Some(0x0807_0605_0403_0201));
+        assert_eq!(
+            crate::read_transparent(&GuestPtr::<u64>::new(&mem, 12)),
+            None
+        );
+        assert_eq!(
+            crate::read_transparent(&GuestPtr::<u64>::new(&mem, 16)),
+            None
+        );
+    }
+
+    #[test]
+    fn lists_provide_memories() {
+        let mems = [OwnedMemory::new(8), OwnedMemory::new(16)];
+        let provider: &dyn MemoryProvider = &mems;
+        assert_eq!(provider.memory(1).map(|m| m.base().1), Some(16));
+        assert!(provider.memory(2).is_none());
+    }
+}
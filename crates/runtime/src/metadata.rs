@@ -0,0 +1,208 @@
+use core::convert::TryFrom;
+use core::fmt;
+
+/// Describes a function of a generated module, so that tools like tracers
+/// can print any call without knowing the interface.
+///
+/// Every invocation of `from_witx!` has a `metadata` module with a
+/// `FUNCTIONS` table of these, in witx order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuncMetadata {
+    /// The name of the witx module the function is in.
+    pub module: &'static str,
+    /// The witx name of the function.
+    pub name: &'static str,
+    /// The params, in witx order.
+    pub params: &'static [ParamMetadata],
+    /// The results, in witx order. The first is the error, if there is one.
+    pub results: &'static [ParamMetadata],
+}
+
+impl FuncMetadata {
+    /// The param named `name`, if there is one.
+    pub fn param(&self, name: &str) -> Option<&ParamMetadata> {
+        self.params.iter().find(|p| p.name == name)
+    }
+}
+
+impl fmt::Display for FuncMetadata {
+    /// Prints the function's signature, e.g.
+    /// `fd_read(fd: $fd, iovs: $iovec_array) -> (error: $errno, nread: $size)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}(", self.name)?;
+        write_params(f, self.params)?;
+        write!(f, ")")?;
+        if !self.results.is_empty() {
+            write!(f, " -> (")?;
+            write_params(f, self.results)?;
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_params(f: &mut fmt::Formatter, params: &[ParamMetadata]) -> fmt::Result {
+    for (i, param) in params.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: {}", param.name, param.type_)?;
+    }
+    Ok(())
+}
+
+/// Describes a param or result of a function, or a member of a struct.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamMetadata {
+    /// The witx name.
+    pub name: &'static str,
+    /// The type.
+    pub type_: TypeDesc,
+}
+
+/// Refers to a type from a function, struct or union, by the witx name of a
+/// named type or by the structure of an anonymous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeDesc {
+    /// A named type, described in the `TYPES` table.
+    Named(&'static str),
+    /// A builtin type, by its witx name, e.g. `u32` or `string`.
+    Builtin(&'static str),
+    /// A pointer to guest memory.
+    Pointer(&'static TypeDesc),
+    /// A pointer to guest memory the host only reads.
+    ConstPointer(&'static TypeDesc),
+    /// An array in guest memory.
+    Array(&'static TypeDesc),
+}
+
+impl fmt::Display for TypeDesc {
+    /// Prints the type as it's written in witx.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeDesc::Named(name) => write!(f, "${}", name),
+            TypeDesc::Builtin(name) => write!(f, "{}", name),
+            TypeDesc::Pointer(to) => write!(f, "(@witx pointer {})", to),
+            TypeDesc::ConstPointer(to) => write!(f, "(@witx const_pointer {})", to),
+            TypeDesc::Array(elem) => write!(f, "(array {})", elem),
+        }
+    }
+}
+
+/// Describes a named type of the witx document.
+///
+/// Every invocation of `from_witx!` has a `metadata` module with a `TYPES`
+/// table of these, in witx order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeMetadata {
+    /// The witx name of the type.
+    pub name: &'static str,
+    /// The size of the type in guest memory.
+    pub size: usize,
+    /// The alignment of the type in guest memory.
+    pub align: usize,
+    /// What kind of type it is.
+    pub kind: TypeKind,
+}
+
+/// The definition of a named type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeKind {
+    /// An enum with the variants, in order of their values.
+    Enum {
+        repr: &'static str,
+        variants: &'static [&'static str],
+    },
+    /// Flags, in order of their bits.
+    Flags {
+        repr: &'static str,
+        flags: &'static [&'static str],
+    },
+    /// An integer with named constants.
+    Int {
+        repr: &'static str,
+        consts: &'static [(&'static str, u64)],
+    },
+    /// A struct, with each member's offset.
+    Struct {
+        members: &'static [(ParamMetadata, usize)],
+    },
+    /// A union with the enum of its `tag`, and each variant's contents, if
+    /// it has any.
+    Union {
+        tag: &'static str,
+        variants: &'static [(&'static str, Option<TypeDesc>)],
+    },
+    /// A handle.
+    Handle,
+    /// Another name for a type.
+    Alias(TypeDesc),
+}
+
+impl TypeMetadata {
+    /// The name of the enum variant or int constant with the value `value`,
+    /// for printing it symbolically.
+    pub fn value_name(&self, value: u64) -> Option<&'static str> {
+        match self.kind {
+            TypeKind::Enum { variants, .. } => usize::try_from(value)
+                .ok()
+                .and_then(|i| variants.get(i))
+                .copied(),
+            TypeKind::Int { consts, .. } => consts
+                .iter()
+                .find(|(_, v)| *v == value)
+                .map(|(name, _)| *name),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::string::ToString;
+
+    const READ: FuncMetadata = FuncMetadata {
+        module: "example",
+        name: "read",
+        params: &[
+            ParamMetadata {
+                name: "fd",
+                type_: TypeDesc::Named("fd"),
+            },
+            ParamMetadata {
+                name: "buf",
+                type_: TypeDesc::Pointer(&TypeDesc::Builtin("u8")),
+            },
+        ],
+        results: &[ParamMetadata {
+            name: "error",
+            type_: TypeDesc::Named("errno"),
+        }],
+    };
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            READ.to_string(),
+            "read(fd: $fd, buf: (@witx pointer u8)) -> (error: $errno)"
+        );
+        assert_eq!(READ.param("buf").unwrap().name, "buf");
+        assert!(READ.param("len").is_none());
+    }
+
+    #[test]
+    fn value_names() {
+        let errno = TypeMetadata {
+            name: "errno",
+            size: 4,
+            align: 4,
+            kind: TypeKind::Enum {
+                repr: "u32",
+                variants: &["ok", "inval"],
+            },
+        };
+        assert_eq!(errno.value_name(1), Some("inval"));
+        assert_eq!(errno.value_name(2), None);
+    }
+}
@@ -0,0 +1,11 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A call recorded by a generated mock of a module trait.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    /// The witx name of the function called.
+    pub func: &'static str,
+    /// The `Debug` output of each argument, in witx order.
+    pub args: Vec<String>,
+}
@@ -0,0 +1,111 @@
+use crate::GuestPtr;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A deployment-time rule set consulted before each hostcall is dispatched
+/// to the host implementation.
+///
+/// Policies are enabled per `from_witx!` invocation with the `policy` field,
+/// which names the error type `E` denied calls return. The generated
+/// functions then look up the policy through [`PolicyCtx`] and call
+/// [`Policy::check`] once the arguments have been received from the guest, but
+/// before any of them are marshalled.
+pub trait Policy<E> {
+    fn check(&self, call: &PolicyCall) -> PolicyDecision<E>;
+}
+
+/// Implemented by contexts whose hostcalls are subject to a [`Policy`].
+pub trait PolicyCtx<E> {
+    /// Returns the policy in effect, or `None` to allow every call.
+    fn policy(&self) -> Option<&dyn Policy<E>>;
+}
+
+/// The outcome of checking a call against a [`Policy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision<E> {
+    /// Dispatch the call to the host implementation.
+    Allow,
+    /// Return the given error to the guest without calling the host.
+    Deny(E),
+    /// Abort the call by panicking. This is also what happens to denied calls
+    /// of functions which have no error result.
+    Trap,
+}
+
+/// A hostcall being checked against a [`Policy`].
+#[derive(Debug, Clone)]
+pub struct PolicyCall<'a> {
+    /// The name of the witx function, as exported by its module.
+    pub function: &'a str,
+    /// The arguments a policy can be keyed on, by witx parameter name.
+    /// Parameters which are not integers, handles, enums, flags or strings
+    /// are left out.
+    pub args: &'a [(&'a str, PolicyArg)],
+}
+
+impl<'a> PolicyCall<'a> {
+    /// Looks up an argument by its witx parameter name.
+    pub fn arg(&self, name: &str) -> Option<&PolicyArg> {
+        self.args
+            .iter()
+            .find(|(arg_name, _)| *arg_name == name)
+            .map(|(_, arg)| arg)
+    }
+}
+
+/// The value of an argument passed to a hostcall.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyArg {
+    /// An unsigned integer, or the representation of a handle, enum or flags
+    /// value.
+    Unsigned(u64),
+    /// A signed integer.
+    Signed(i64),
+    /// A string, with any invalid UTF-8 replaced by `U+FFFD`.
+    String(String),
+}
+
+impl PolicyArg {
+    /// Copies a guest string out for a policy to inspect.
+    ///
+    /// The bytes are read one at a time rather than borrowed, so the check
+    /// doesn't hold a borrow that would conflict with the host implementation
+    /// later borrowing the same string. Returns `None` if the string isn't in
+    /// bounds or is over the call's limits, in which case the host won't be
+    /// able to use it either.
+    pub fn from_guest_str(ptr: &GuestPtr<'_, str>) -> Option<PolicyArg> {
+        if let Some(limits) = ptr.mem().limits() {
+            limits.check_string(ptr.len()).ok()?;
+        }
+        let bytes = ptr
+            .as_bytes()
+            .iter()
+            .map(|byte| byte.and_then(|byte| byte.read()))
+            .collect::<Result<Vec<u8>, _>>()
+            .ok()?;
+        Some(PolicyArg::String(
+            String::from_utf8_lossy(&bytes).into_owned(),
+        ))
+    }
+
+    pub fn as_unsigned(&self) -> Option<u64> {
+        match self {
+            PolicyArg::Unsigned(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_signed(&self) -> Option<i64> {
+        match self {
+            PolicyArg::Signed(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PolicyArg::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
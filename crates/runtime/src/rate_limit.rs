@@ -0,0 +1,210 @@
+use crate::{Policy, PolicyCall, PolicyDecision};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// A [`Policy`] throttling how often a guest can call each function, and how
+/// much work it can cause in total.
+///
+/// Each function can be given a [`RateLimit`], enforced with a token bucket,
+/// and a cost, charged against a budget shared by all functions. Calls beyond
+/// either are denied with the error given to [`RateLimiter::new`], typically
+/// an `EAGAIN`-style errno. Functions without a rate limit or a cost are always
+/// allowed.
+///
+/// To use it, return it from the ctx's `PolicyCtx` implementation, or consult
+/// it from another policy.
+#[derive(Debug)]
+pub struct RateLimiter<E> {
+    exhausted: E,
+    state: Mutex<State>,
+}
+
+/// Allows bursts of up to `burst` calls, refilled at `per_second` calls per
+/// second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+#[derive(Debug)]
+struct State {
+    buckets: HashMap<String, Bucket>,
+    costs: HashMap<String, u64>,
+    budget: Option<u64>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst.into());
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<E: Clone> RateLimiter<E> {
+    /// Creates a rate limiter which doesn't limit anything yet, and denies
+    /// calls with `exhausted` once it does.
+    pub fn new(exhausted: E) -> Self {
+        Self {
+            exhausted,
+            state: Mutex::new(State {
+                buckets: HashMap::new(),
+                costs: HashMap::new(),
+                budget: None,
+            }),
+        }
+    }
+
+    /// Limits the rate at which `function` can be called. The bucket starts
+    /// out full.
+    pub fn limit(self, function: &str, limit: RateLimit) -> Self {
+        let bucket = Bucket {
+            limit,
+            tokens: limit.burst.into(),
+            refilled: Instant::now(),
+        };
+        self.lock().buckets.insert(function.to_owned(), bucket);
+        self
+    }
+
+    /// Charges `cost` against the budget for every call to `function`.
+    pub fn cost(self, function: &str, cost: u64) -> Self {
+        self.lock().costs.insert(function.to_owned(), cost);
+        self
+    }
+
+    /// Sets the budget that function costs are charged against. Without a
+    /// budget, costs aren't enforced.
+    pub fn budget(self, budget: u64) -> Self {
+        self.set_budget(budget);
+        self
+    }
+
+    /// Replaces the remaining budget, for example to top it up periodically.
+    pub fn set_budget(&self, budget: u64) {
+        self.lock().budget = Some(budget);
+    }
+
+    /// The budget left, if there is one.
+    pub fn remaining_budget(&self) -> Option<u64> {
+        self.lock().budget
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl<E: Clone> Policy<E> for RateLimiter<E> {
+    fn check(&self, call: &PolicyCall) -> PolicyDecision<E> {
+        let mut state = self.lock();
+        let cost = state.costs.get(call.function).copied().unwrap_or(0);
+        if let Some(budget) = state.budget {
+            if cost > budget {
+                return PolicyDecision::Deny(self.exhausted.clone());
+            }
+        }
+        if let Some(bucket) = state.buckets.get_mut(call.function) {
+            if !bucket.take(Instant::now()) {
+                return PolicyDecision::Deny(self.exhausted.clone());
+            }
+        }
+        if let Some(budget) = &mut state.budget {
+            *budget -= cost;
+        }
+        PolicyDecision::Allow
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn call(function: &str) -> PolicyCall<'_> {
+        PolicyCall {
+            function,
+            args: &[],
+        }
+    }
+
+    #[test]
+    fn unlimited_functions_are_allowed() {
+        let limiter = RateLimiter::new("again").limit(
+            "fd_write",
+            RateLimit {
+                burst: 1,
+                per_second: 0.0,
+            },
+        );
+        for _ in 0..100 {
+            assert_eq!(limiter.check(&call("fd_read")), PolicyDecision::Allow);
+        }
+    }
+
+    #[test]
+    fn burst_then_denied() {
+        let limiter = RateLimiter::new("again").limit(
+            "fd_write",
+            RateLimit {
+                burst: 3,
+                per_second: 0.0,
+            },
+        );
+        for _ in 0..3 {
+            assert_eq!(limiter.check(&call("fd_write")), PolicyDecision::Allow);
+        }
+        assert_eq!(
+            limiter.check(&call("fd_write")),
+            PolicyDecision::Deny("again")
+        );
+    }
+
+    #[test]
+    fn refills() {
+        let limiter = RateLimiter::new("again").limit(
+            "fd_write",
+            RateLimit {
+                burst: 1,
+                per_second: 1000.0,
+            },
+        );
+        assert_eq!(limiter.check(&call("fd_write")), PolicyDecision::Allow);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert_eq!(limiter.check(&call("fd_write")), PolicyDecision::Allow);
+    }
+
+    #[test]
+    fn budget() {
+        let limiter = RateLimiter::new("again")
+            .cost("fd_write", 10)
+            .cost("fd_read", 3)
+            .budget(25);
+        assert_eq!(limiter.check(&call("fd_write")), PolicyDecision::Allow);
+        assert_eq!(limiter.check(&call("fd_write")), PolicyDecision::Allow);
+        assert_eq!(limiter.remaining_budget(), Some(5));
+        assert_eq!(
+            limiter.check(&call("fd_write")),
+            PolicyDecision::Deny("again")
+        );
+        // Cheaper calls can still use up what's left.
+        assert_eq!(limiter.check(&call("fd_read")), PolicyDecision::Allow);
+        assert_eq!(limiter.remaining_budget(), Some(2));
+        limiter.set_budget(10);
+        assert_eq!(limiter.check(&call("fd_write")), PolicyDecision::Allow);
+    }
+}
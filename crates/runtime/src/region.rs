@@ -1,43 +1,74 @@
 /// Represents a contiguous region in memory.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Region {
-    pub start: u32,
-    pub len: u32,
+    pub start: u64,
+    pub len: u64,
 }
 
 impl Region {
-    pub fn new(start: u32, len: u32) -> Self {
-        assert!(len > 0, "Region cannot have 0 length");
+    pub fn new(start: u64, len: u64) -> Self {
         Self { start, len }
     }
 
-    /// Checks if this `Region` overlaps with `rhs` `Region`.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The offset just past the end of this `Region`. Regions running off the
+    /// end of the address space can't be in bounds, so they are cut short at
+    /// `u64::MAX`.
+    pub fn end(&self) -> u64 {
+        self.start.saturating_add(self.len)
+    }
+
+    /// Checks if this `Region` overlaps with `rhs` `Region`. Empty regions
+    /// never overlap anything.
     pub fn overlaps(&self, rhs: Region) -> bool {
-        let self_start = self.start as u64;
-        let self_end = self_start + (self.len - 1) as u64;
+        self.intersection(rhs).is_some()
+    }
 
-        let rhs_start = rhs.start as u64;
-        let rhs_end = rhs_start + (rhs.len - 1) as u64;
+    /// Checks if `rhs` lies entirely within this `Region`. Empty regions are
+    /// within any region they start in or at the end of.
+    pub fn contains(&self, rhs: Region) -> bool {
+        // Compare offsets from `self.start`, which can't overflow.
+        match rhs.start.checked_sub(self.start) {
+            Some(offset) if offset <= self.len => rhs.len <= self.len - offset,
+            _ => false,
+        }
+    }
 
-        if self_start <= rhs_start {
-            self_end >= rhs_start
+    /// Returns the bytes in both this `Region` and `rhs`, if there are any.
+    pub fn intersection(&self, rhs: Region) -> Option<Region> {
+        let (first, second) = if self.start <= rhs.start {
+            (self, &rhs)
         } else {
-            rhs_end >= self_start
+            (&rhs, self)
+        };
+        let offset = second.start - first.start;
+        if offset >= first.len || second.is_empty() {
+            return None;
         }
+        Some(Region {
+            start: second.start,
+            len: (first.len - offset).min(second.len),
+        })
     }
 
-    pub fn extend(&self, times: u32) -> Self {
-        let len = self.len * times;
-        Self {
+    /// Returns the `Region` starting at the same offset, but `times` as long,
+    /// or `None` if its length overflows.
+    pub fn extend(&self, times: u64) -> Option<Self> {
+        let len = self.len.checked_mul(times)?;
+        Some(Self {
             start: self.start,
             len,
-        }
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn nonoverlapping() {
@@ -68,4 +99,79 @@ mod test {
         let r2 = Region::new(0, 10);
         assert!(r1.overlaps(r2));
     }
+
+    #[test]
+    fn empty_regions_never_overlap() {
+        let empty = Region::new(5, 0);
+        assert!(!empty.overlaps(Region::new(0, 10)));
+        assert!(!Region::new(0, 10).overlaps(empty));
+        assert!(!empty.overlaps(empty));
+        assert!(!Region::new(0, 0).overlaps(Region::new(0, 0)));
+        assert!(Region::new(0, 10).contains(empty));
+        assert!(Region::new(0, 5).contains(empty));
+        assert!(!Region::new(0, 4).contains(empty));
+    }
+
+    #[test]
+    fn end_of_address_space() {
+        let last = Region::new(u64::MAX, 1);
+        assert_eq!(last.end(), u64::MAX);
+        assert!(last.overlaps(last));
+        assert!(Region::new(u64::MAX - 1, u64::MAX).overlaps(last));
+        assert!(Region::new(0, u64::MAX).contains(Region::new(u64::MAX - 1, 1)));
+        assert!(!Region::new(0, u64::MAX).contains(last));
+        assert_eq!(
+            Region::new(1, u64::MAX).intersection(Region::new(u64::MAX, u64::MAX)),
+            Some(last)
+        );
+    }
+
+    #[test]
+    fn extend() {
+        assert_eq!(Region::new(8, 4).extend(3), Some(Region::new(8, 12)));
+        assert_eq!(Region::new(8, 4).extend(0), Some(Region::new(8, 0)));
+        assert_eq!(Region::new(8, u64::MAX / 2 + 1).extend(2), None);
+    }
+
+    /// Regions as ranges of `u128`s, which can't overflow.
+    fn range(r: Region) -> core::ops::Range<u128> {
+        u128::from(r.start)..u128::from(r.start) + u128::from(r.len)
+    }
+
+    fn region() -> impl Strategy<Value = Region> {
+        // Mostly small regions close together, so that they often overlap,
+        // along with some near the end of the address space.
+        prop_oneof![
+            (0..64u64, 0..64u64).prop_map(|(start, len)| Region::new(start, len)),
+            (u64::MAX - 64..=u64::MAX, any::<u64>())
+                .prop_map(|(start, len)| Region::new(start, len)),
+            (any::<u64>(), any::<u64>()).prop_map(|(start, len)| Region::new(start, len)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn intersection_matches_ranges(r1 in region(), r2 in region()) {
+            let (a, b) = (range(r1), range(r2));
+            let start = a.start.max(b.start);
+            let end = a.end.min(b.end);
+            let expected = if start < end {
+                Some(Region::new(start as u64, (end - start) as u64))
+            } else {
+                None
+            };
+            prop_assert_eq!(r1.intersection(r2), expected);
+            prop_assert_eq!(r2.intersection(r1), expected);
+            prop_assert_eq!(r1.overlaps(r2), expected.is_some());
+        }
+
+        #[test]
+        fn contains_matches_ranges(r1 in region(), r2 in region()) {
+            let (a, b) = (range(r1), range(r2));
+            prop_assert_eq!(r1.contains(r2), a.start <= b.start && b.end <= a.end);
+            if let Some(i) = r1.intersection(r2) {
+                prop_assert!(r1.contains(i) && r2.contains(i));
+            }
+        }
+    }
 }
@@ -0,0 +1,78 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::str::{self, Utf8Error};
+
+/// Validates that `bytes` is UTF-8, returning it as a `&mut str`.
+///
+/// This is the single place where guest strings get checked, so that the
+/// validation strategy can be swapped out. With the `simdutf8` feature enabled
+/// the check runs through `simdutf8`'s vectorized validator, which is
+/// considerably faster for long strings. That validator only reports whether
+/// the input was valid, so on failure we re-run the standard library's
+/// validation to produce a detailed `Utf8Error`.
+#[cfg(feature = "simdutf8")]
+pub(crate) fn from_utf8_mut(bytes: &mut [u8]) -> Result<&mut str, Utf8Error> {
+    match simdutf8::basic::from_utf8(bytes) {
+        // SAFETY: `simdutf8` has just validated that `bytes` is UTF-8.
+        Ok(_) => Ok(unsafe { str::from_utf8_unchecked_mut(bytes) }),
+        Err(_) => str::from_utf8_mut(bytes),
+    }
+}
+
+/// Validates that `bytes` is UTF-8, returning it as a `&mut str`.
+#[cfg(not(feature = "simdutf8"))]
+pub(crate) fn from_utf8_mut(bytes: &mut [u8]) -> Result<&mut str, Utf8Error> {
+    str::from_utf8_mut(bytes)
+}
+
+/// Validates that `bytes` is UTF-8, returning it as a `&str`.
+#[cfg(feature = "simdutf8")]
+pub(crate) fn from_utf8(bytes: &[u8]) -> Result<&str, Utf8Error> {
+    match simdutf8::basic::from_utf8(bytes) {
+        Ok(s) => Ok(s),
+        Err(_) => str::from_utf8(bytes),
+    }
+}
+
+/// Validates that `bytes` is UTF-8, returning it as a `&str`.
+#[cfg(not(feature = "simdutf8"))]
+pub(crate) fn from_utf8(bytes: &[u8]) -> Result<&str, Utf8Error> {
+    str::from_utf8(bytes)
+}
+
+/// Converts `bytes` to a `&str` if it is UTF-8, or otherwise to a `String`
+/// with invalid sequences replaced by U+FFFD REPLACEMENT CHARACTER.
+pub(crate) fn from_utf8_lossy(bytes: &[u8]) -> Cow<'_, str> {
+    match from_utf8(bytes) {
+        Ok(s) => Cow::Borrowed(s),
+        Err(_) => String::from_utf8_lossy(bytes),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        let mut bytes = "καλημέρα κόσμε".as_bytes().to_vec();
+        assert_eq!(
+            from_utf8_mut(&mut bytes).expect("valid utf-8"),
+            "καλημέρα κόσμε"
+        );
+    }
+
+    #[test]
+    fn invalid_reports_position() {
+        let mut bytes = b"hello \xff world".to_vec();
+        let err = from_utf8_mut(&mut bytes).expect_err("invalid utf-8");
+        assert_eq!(err.valid_up_to(), 6);
+        assert_eq!(err.error_len(), Some(1));
+    }
+
+    #[test]
+    fn lossy() {
+        assert!(matches!(from_utf8_lossy(b"valid"), Cow::Borrowed("valid")));
+        assert_eq!(from_utf8_lossy(b"in\xffvalid"), "in\u{fffd}valid");
+    }
+}
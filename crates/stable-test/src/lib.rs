@@ -0,0 +1,17 @@
+//! Builds a full WASI snapshot generation on the stable toolchain.
+//!
+//! This crate is pinned to stable by its `rust-toolchain` file, and denies
+//! warnings and unstable features, so that nothing the macro expands to can
+//! come to rely on nightly.
+//!
+//! Neither the macro nor the code it generates has any nightly-only
+//! features, so there is nothing to gate yet. One added later, like
+//! `proc_macro::Diagnostic` warnings, must be detected from the compiler
+//! and gated rather than emitted unconditionally, and this crate must keep
+//! building without it.
+#![deny(warnings)]
+#![forbid(unstable_features)]
+
+// Reuse the WASI host from the integration tests rather than maintaining a
+// second copy of it.
+include!("../../../tests/wasi.rs");
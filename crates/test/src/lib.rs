@@ -5,6 +5,12 @@ use wiggle_runtime::GuestMemory;
 
 #[derive(Debug, Clone)]
 pub struct MemAreas(Vec<MemArea>);
+impl Default for MemAreas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MemAreas {
     pub fn new() -> Self {
         MemAreas(Vec::new())
@@ -29,16 +35,16 @@ where
 {
     fn from(ms: R) -> MemAreas {
         let mut out = MemAreas::new();
-        for m in ms.as_ref().into_iter() {
+        for m in ms.as_ref().iter() {
             out.insert(*m);
         }
         out
     }
 }
 
-impl Into<Vec<MemArea>> for MemAreas {
-    fn into(self) -> Vec<MemArea> {
-        self.0.clone()
+impl From<MemAreas> for Vec<MemArea> {
+    fn from(val: MemAreas) -> Self {
+        val.0.clone()
     }
 }
 
@@ -46,6 +52,12 @@ impl Into<Vec<MemArea>> for MemAreas {
 pub struct HostMemory {
     buffer: UnsafeCell<[u8; 4096]>,
 }
+impl Default for HostMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HostMemory {
     pub fn new() -> Self {
         HostMemory {
@@ -99,16 +111,16 @@ impl HostMemory {
 
         Just(available)
             .prop_filter("available memory for allocation", |a| !a.is_empty())
-            .prop_flat_map(|a| prop::sample::select(a))
+            .prop_flat_map(prop::sample::select)
             .boxed()
     }
 }
 
 unsafe impl GuestMemory for HostMemory {
-    fn base(&self) -> (*mut u8, u32) {
+    fn base(&self) -> (*mut u8, u64) {
         unsafe {
             let ptr = self.buffer.get();
-            ((*ptr).as_mut_ptr(), (*ptr).len() as u32)
+            ((*ptr).as_mut_ptr(), (*ptr).len() as u64)
         }
     }
 }
@@ -147,7 +159,7 @@ impl MemArea {
                 return true;
             }
         }
-        return false;
+        false
     }
     pub fn non_overlapping_set<M>(areas: M) -> bool
     where
@@ -164,20 +176,66 @@ impl MemArea {
                 }
             }
         }
-        return true;
+        true
     }
 
     /// Enumerate all memareas of size `len` inside a given area
     fn inside(&self, len: u32) -> impl Iterator<Item = MemArea> {
         let end: i64 = self.len as i64 - len as i64;
         let start = self.ptr;
-        (0..end).into_iter().map(move |v| MemArea {
+        (0..end).map(move |v| MemArea {
             ptr: start + v as u32,
             len,
         })
     }
 }
 
+use std::cell::RefCell;
+use wiggle_runtime::GuestError;
+
+// In lucet, our Ctx struct needs a lifetime, so we're using one
+// on the test as well.
+pub struct WasiCtx<'a> {
+    pub guest_errors: RefCell<Vec<GuestError>>,
+    lifetime: marker::PhantomData<&'a ()>,
+}
+
+impl<'a> Default for WasiCtx<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> WasiCtx<'a> {
+    pub fn new() -> Self {
+        Self {
+            guest_errors: RefCell::new(vec![]),
+            lifetime: marker::PhantomData,
+        }
+    }
+}
+
+// Errno is used as a first return value in the functions above, therefore
+// it must implement GuestErrorType with type Context = WasiCtx.
+// The context type should let you do logging or debugging or whatever you need
+// with these errors. We just push them to vecs.
+#[macro_export]
+macro_rules! impl_errno {
+    ( $errno:ty ) => {
+        impl<'a> wiggle_runtime::GuestErrorType<'a> for $errno {
+            type Context = WasiCtx<'a>;
+            fn success() -> $errno {
+                <$errno>::Ok
+            }
+            fn from_error(e: GuestError, ctx: &WasiCtx) -> $errno {
+                eprintln!("GUEST ERROR: {:?}", e);
+                ctx.guest_errors.borrow_mut().push(e);
+                types::Errno::InvalidArg
+            }
+        }
+    };
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -255,7 +313,7 @@ mod test {
         let a = MemArea { ptr: 24, len: 4072 };
         let interior = a.inside(24).collect::<Vec<_>>();
 
-        assert!(interior.len() > 0);
+        assert!(!interior.is_empty());
     }
 
     proptest! {
@@ -287,43 +345,3 @@ mod test {
         }
     }
 }
-
-use std::cell::RefCell;
-use wiggle_runtime::GuestError;
-
-// In lucet, our Ctx struct needs a lifetime, so we're using one
-// on the test as well.
-pub struct WasiCtx<'a> {
-    pub guest_errors: RefCell<Vec<GuestError>>,
-    lifetime: marker::PhantomData<&'a ()>,
-}
-
-impl<'a> WasiCtx<'a> {
-    pub fn new() -> Self {
-        Self {
-            guest_errors: RefCell::new(vec![]),
-            lifetime: marker::PhantomData,
-        }
-    }
-}
-
-// Errno is used as a first return value in the functions above, therefore
-// it must implement GuestErrorType with type Context = WasiCtx.
-// The context type should let you do logging or debugging or whatever you need
-// with these errors. We just push them to vecs.
-#[macro_export]
-macro_rules! impl_errno {
-    ( $errno:ty ) => {
-        impl<'a> wiggle_runtime::GuestErrorType<'a> for $errno {
-            type Context = WasiCtx<'a>;
-            fn success() -> $errno {
-                <$errno>::Ok
-            }
-            fn from_error(e: GuestError, ctx: &WasiCtx) -> $errno {
-                eprintln!("GUEST ERROR: {:?}", e);
-                ctx.guest_errors.borrow_mut().push(e);
-                types::Errno::InvalidArg
-            }
-        }
-    };
-}
@@ -0,0 +1,50 @@
+//! A [`GuestMemory`] for the linear memory of a wasmtime instance.
+//!
+//! This crate is kept out of the workspace so that building wiggle itself
+//! doesn't pull in wasmtime.
+
+use wasmtime::{Memory, StoreContextMut};
+use wiggle_runtime::{GuestMemory, SliceMemory};
+
+/// The linear memory of a wasmtime instance, for the duration of a hostcall.
+///
+/// # Reentry
+///
+/// [`GuestMemory`] requires that guest memory doesn't move or change size
+/// while host pointers into it are in use, which means the guest mustn't be
+/// reentered: it could run `memory.grow`. `WasmtimeGuestMemory` upholds this
+/// by mutably borrowing the store for its whole lifetime, so nothing can call
+/// back into the instance until it has been dropped.
+pub struct WasmtimeGuestMemory<'a> {
+    mem: SliceMemory<'a>,
+}
+
+impl<'a> WasmtimeGuestMemory<'a> {
+    /// Borrows `memory` out of `store`.
+    pub fn new<T: 'a>(memory: Memory, store: impl Into<StoreContextMut<'a, T>>) -> Self {
+        WasmtimeGuestMemory {
+            mem: SliceMemory::new(memory.data_mut(store)),
+        }
+    }
+
+    /// Borrows `memory` out of `store`, along with the store's data, which
+    /// usually holds the ctx hostcalls are made with.
+    pub fn with_data<T: 'a>(
+        memory: Memory,
+        store: impl Into<StoreContextMut<'a, T>>,
+    ) -> (Self, &'a mut T) {
+        let (mem, data) = memory.data_and_store_mut(store);
+        let mem = WasmtimeGuestMemory {
+            mem: SliceMemory::new(mem),
+        };
+        (mem, data)
+    }
+}
+
+// SAFETY: the memory is a `SliceMemory` over the instance's linear memory,
+// which can't move while the store is borrowed.
+unsafe impl GuestMemory for WasmtimeGuestMemory<'_> {
+    fn base(&self) -> (*mut u8, u64) {
+        self.mem.base()
+    }
+}
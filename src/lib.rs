@@ -1,11 +1,9 @@
-extern crate proc_macro;
+//! Generate idiomatic Rust bindings from `*.witx` interface definitions.
+//!
+//! This crate is the single dependency needed to use wiggle: it provides the
+//! [`from_witx!`] macro along with all of the runtime support which the code
+//! it generates refers to. Since the generated code only ever names items
+//! through this crate, the macro and the runtime are always in sync.
 
-use proc_macro::TokenStream;
-use syn::parse_macro_input;
-
-#[proc_macro]
-pub fn from_witx(args: TokenStream) -> TokenStream {
-    let config = parse_macro_input!(args as wiggle_generate::Config);
-    let doc = witx::load(&config.witx.paths).expect("loading witx");
-    TokenStream::from(wiggle_generate::generate(&doc, &config))
-}
+pub use wiggle_macro::from_witx;
+pub use wiggle_runtime::*;
@@ -0,0 +1,198 @@
+use std::cell::RefCell;
+use wiggle::{GuestBorrows, GuestError, GuestErrorType, GuestMemory, GuestPtr};
+use wiggle_test::HostMemory;
+
+mod preview1 {
+    pub use super::Ctx;
+
+    wiggle::from_witx!({
+        witx: ["tests/adapter_preview1.witx"],
+        ctx: Ctx,
+    });
+}
+
+mod unstable {
+    pub use super::Ctx;
+
+    wiggle::from_witx!({
+        witx: ["tests/adapter_unstable.witx"],
+        ctx: Ctx,
+        adapter: {
+            witx: ["tests/adapter_preview1.witx"],
+            to: crate::preview1,
+            renames: { example_unstable: example_preview1, st_size: size, creat: create },
+        },
+        // The adapter uses these conversions rather than defining its own.
+        conversions: {
+            witx: ["tests/adapter_preview1.witx"],
+            to: crate::preview1,
+            renames: { st_size: size, creat: create },
+        },
+    });
+}
+
+/// Implements only the preview1 interface, recording the calls it sees.
+#[derive(Default)]
+pub struct Ctx {
+    calls: RefCell<Vec<String>>,
+}
+
+impl<'a> GuestErrorType<'a> for preview1::types::Errno {
+    type Context = Ctx;
+    fn success() -> Self {
+        preview1::types::Errno::Success
+    }
+    fn from_error(_e: GuestError, _ctx: &Ctx) -> Self {
+        preview1::types::Errno::Inval
+    }
+}
+
+impl<'a> GuestErrorType<'a> for unstable::types::Errno {
+    type Context = Ctx;
+    fn success() -> Self {
+        unstable::types::Errno::Success
+    }
+    fn from_error(_e: GuestError, _ctx: &Ctx) -> Self {
+        unstable::types::Errno::Inval
+    }
+}
+
+impl preview1::example_preview1::ExamplePreview1 for Ctx {
+    fn fd_seek(
+        &self,
+        fd: preview1::types::Fd,
+        offset: i64,
+        whence: preview1::types::Whence,
+    ) -> Result<u64, preview1::types::Errno> {
+        self.calls
+            .borrow_mut()
+            .push(format!("fd_seek({}, {}, {:?})", fd, offset, whence));
+        Ok(offset as u64)
+    }
+
+    fn path_open(
+        &self,
+        dirfd: preview1::types::Fd,
+        path: &GuestPtr<str>,
+        oflags: preview1::types::Oflags,
+    ) -> Result<preview1::types::Fd, preview1::types::Errno> {
+        let mut bc = GuestBorrows::new();
+        let path = path
+            .as_raw(&mut bc)
+            .map_err(|_| preview1::types::Errno::Inval)?;
+        let path = unsafe { &*path };
+        self.calls
+            .borrow_mut()
+            .push(format!("path_open({}, {}, {})", dirfd, path, oflags));
+        Ok(preview1::types::Fd::from(u32::from(dirfd) + 1))
+    }
+
+    fn fd_filestat_get(
+        &self,
+        fd: preview1::types::Fd,
+    ) -> Result<preview1::types::Filestat, preview1::types::Errno> {
+        if u32::from(fd) == 0 {
+            return Err(preview1::types::Errno::Badf);
+        }
+        Ok(preview1::types::Filestat {
+            dev: 1,
+            nlink: u64::MAX,
+            size: 42,
+        })
+    }
+
+    fn fd_write<'a>(
+        &self,
+        _fd: preview1::types::Fd,
+        iovs: &GuestPtr<'a, [preview1::types::Iovec<'a>]>,
+    ) -> Result<u32, preview1::types::Errno> {
+        let mut written = 0;
+        for iov in iovs.iter() {
+            let iov = iov
+                .and_then(|iov| iov.read())
+                .map_err(|_| preview1::types::Errno::Inval)?;
+            written += iov.buf_len;
+        }
+        Ok(written)
+    }
+}
+
+#[test]
+fn arguments_are_converted() {
+    let ctx = Ctx::default();
+    let host_memory = HostMemory::new();
+
+    // `cur` is 0 in the unstable snapshot, and 1 in preview1.
+    let e = unstable::example_unstable::fd_seek(&ctx, &host_memory, 3, -2, 0, 0);
+    assert_eq!(e, i32::from(unstable::types::Errno::Success));
+    assert_eq!(
+        host_memory.ptr::<u64>(0).read().expect("read offset"),
+        -2i64 as u64
+    );
+
+    let path = host_memory.ptr::<str>((8, 3));
+    for (slot, byte) in path.as_bytes().iter().zip(b"tmp".iter()) {
+        slot.expect("valid pointer").write(*byte).expect("write");
+    }
+    let oflags = u16::from(unstable::types::Oflags::CREAT) as i32;
+    let e = unstable::example_unstable::path_open(&ctx, &host_memory, 3, 8, 3, oflags, 16);
+    assert_eq!(e, i32::from(unstable::types::Errno::Success));
+    assert_eq!(
+        host_memory
+            .ptr::<unstable::types::Fd>(16)
+            .read()
+            .expect("read fd"),
+        unstable::types::Fd::from(4)
+    );
+
+    assert_eq!(
+        *ctx.calls.borrow(),
+        vec!["fd_seek(Fd(3), -2, Cur)", "path_open(Fd(3), tmp, CREATE)"]
+    );
+}
+
+#[test]
+fn results_are_converted() {
+    let ctx = Ctx::default();
+    let host_memory = HostMemory::new();
+
+    let e = unstable::example_unstable::fd_filestat_get(&ctx, &host_memory, 3, 0);
+    assert_eq!(e, i32::from(unstable::types::Errno::Success));
+    let filestat = host_memory
+        .ptr::<unstable::types::Filestat>(0)
+        .read()
+        .expect("read filestat");
+    assert_eq!(
+        filestat,
+        unstable::types::Filestat {
+            dev: 1,
+            nlink: u32::MAX,
+            st_size: 42,
+        }
+    );
+
+    let e = unstable::example_unstable::fd_filestat_get(&ctx, &host_memory, 0, 0);
+    assert_eq!(e, i32::from(unstable::types::Errno::Badf));
+}
+
+#[test]
+fn pointers_are_passed_on() {
+    let ctx = Ctx::default();
+    let host_memory = HostMemory::new();
+
+    let iovs = host_memory.ptr::<[unstable::types::Iovec]>((0, 2));
+    for (i, iov) in iovs.iter().enumerate() {
+        let iov = iov.expect("valid pointer");
+        iov.write(unstable::types::Iovec {
+            buf: host_memory.ptr(64),
+            buf_len: (i as u32 + 1) * 10,
+        })
+        .expect("write iovec");
+    }
+    let e = unstable::example_unstable::fd_write(&ctx, &host_memory, 3, 0, 2, 32);
+    assert_eq!(e, i32::from(unstable::types::Errno::Success));
+    assert_eq!(
+        host_memory.ptr::<u32>(32).read().expect("read nwritten"),
+        30
+    );
+}
@@ -1,10 +1,11 @@
 use proptest::prelude::*;
-use wiggle_runtime::{GuestError, GuestMemory, GuestPtr};
+use wiggle::{CallMemory, GuestBorrows, GuestError, GuestMemory, GuestPtr};
 use wiggle_test::{impl_errno, HostMemory, MemArea, WasiCtx};
 
 wiggle::from_witx!({
     witx: ["tests/arrays.witx"],
     ctx: WasiCtx,
+    proptest: true,
 });
 
 impl_errno!(types::Errno);
@@ -52,7 +53,7 @@ impl ReduceExcusesExcercise {
             .prop_flat_map(|len| {
                 let len_usize = len as usize;
                 (
-                    proptest::collection::vec(excuse_strat(), len_usize..=len_usize),
+                    proptest::collection::vec(any::<types::Excuse>(), len_usize..=len_usize),
                     proptest::collection::vec(HostMemory::mem_area_strat(4), len_usize..=len_usize),
                     HostMemory::mem_area_strat(4 * len),
                     HostMemory::mem_area_strat(4),
@@ -75,31 +76,33 @@ impl ReduceExcusesExcercise {
     }
 
     pub fn test(&self) {
-        let mut ctx = WasiCtx::new();
-        let mut host_memory = HostMemory::new();
+        let ctx = WasiCtx::new();
+        let host_memory = HostMemory::new();
 
         // Populate memory with pointers to generated Excuse values
         for (&excuse, ptr) in self.excuse_values.iter().zip(self.excuse_ptr_locs.iter()) {
             host_memory
-                .ptr(ptr.ptr)
+                .ptr(ptr.ptr.into())
                 .write(excuse)
                 .expect("deref ptr mut to Excuse value");
         }
 
         // Populate the array with pointers to generated Excuse values
         {
-            let array: GuestPtr<'_, [GuestPtr<types::Excuse>]> =
-                host_memory.ptr((self.array_ptr_loc.ptr, self.excuse_ptr_locs.len() as u32));
+            let array: GuestPtr<'_, [GuestPtr<types::Excuse>]> = host_memory.ptr((
+                self.array_ptr_loc.ptr.into(),
+                self.excuse_ptr_locs.len() as u64,
+            ));
             for (slot, ptr) in array.iter().zip(&self.excuse_ptr_locs) {
                 let slot = slot.expect("array should be in bounds");
-                slot.write(host_memory.ptr(ptr.ptr))
+                slot.write(host_memory.ptr(ptr.ptr.into()))
                     .expect("should succeed in writing array");
             }
         }
 
         let res = arrays::reduce_excuses(
-            &mut ctx,
-            &mut host_memory,
+            &ctx,
+            &host_memory,
             self.array_ptr_loc.ptr as i32,
             self.excuse_ptr_locs.len() as i32,
             self.return_ptr_loc.ptr as i32,
@@ -112,7 +115,7 @@ impl ReduceExcusesExcercise {
             .last()
             .expect("generated vec of excuses should be non-empty");
         let given: types::Excuse = host_memory
-            .ptr(self.return_ptr_loc.ptr)
+            .ptr(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref ptr to returned value");
         assert_eq!(expected, given, "reduce excuses return val");
@@ -125,15 +128,6 @@ proptest! {
     }
 }
 
-fn excuse_strat() -> impl Strategy<Value = types::Excuse> {
-    prop_oneof![
-        Just(types::Excuse::DogAte),
-        Just(types::Excuse::Traffic),
-        Just(types::Excuse::Sleeping),
-    ]
-    .boxed()
-}
-
 #[derive(Debug)]
 struct PopulateExcusesExcercise {
     array_ptr_loc: MemArea,
@@ -168,12 +162,12 @@ impl PopulateExcusesExcercise {
 
         // Populate array with valid pointers to Excuse type in memory
         let ptr = host_memory.ptr::<[GuestPtr<'_, types::Excuse>]>((
-            self.array_ptr_loc.ptr,
-            self.elements.len() as u32,
+            self.array_ptr_loc.ptr.into(),
+            self.elements.len() as u64,
         ));
         for (ptr, val) in ptr.iter().zip(&self.elements) {
             ptr.expect("should be valid pointer")
-                .write(host_memory.ptr(val.ptr))
+                .write(host_memory.ptr(val.ptr.into()))
                 .expect("failed to write value");
         }
 
@@ -186,7 +180,7 @@ impl PopulateExcusesExcercise {
         assert_eq!(res, types::Errno::Ok.into(), "populate excuses errno");
 
         let arr: GuestPtr<'_, [GuestPtr<'_, types::Excuse>]> =
-            host_memory.ptr((self.array_ptr_loc.ptr, self.elements.len() as u32));
+            host_memory.ptr((self.array_ptr_loc.ptr.into(), self.elements.len() as u64));
         for el in arr.iter() {
             let ptr_to_ptr = el
                 .expect("valid ptr to ptr")
@@ -208,3 +202,317 @@ proptest! {
         e.test()
     }
 }
+
+#[test]
+fn as_raw_validates_every_excuse() {
+    let host_memory = HostMemory::new();
+    let excuses: GuestPtr<'_, [types::Excuse]> = host_memory.ptr((0, 8));
+    for (i, slot) in excuses.iter().enumerate() {
+        slot.expect("valid ptr")
+            .write(match i % 3 {
+                0 => types::Excuse::DogAte,
+                1 => types::Excuse::Traffic,
+                _ => types::Excuse::Sleeping,
+            })
+            .expect("write excuse");
+    }
+    let mut bc = GuestBorrows::new();
+    excuses.as_raw(&mut bc).expect("all excuses are valid");
+
+    // Sneak an out-of-range value in at the end of the array.
+    host_memory.ptr::<u8>(7).write(3).expect("write raw byte");
+    let mut bc = GuestBorrows::new();
+    assert_eq!(
+        excuses.as_raw(&mut bc).err(),
+        Some(GuestError::InvalidEnumValue("Excuse"))
+    );
+}
+
+#[test]
+fn slice_guard_releases_borrow() {
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let excuses: GuestPtr<'_, [types::Excuse]> = call_memory.ptr((0, 4));
+
+    {
+        let mut slice = excuses.as_slice_mut().expect("borrow excuses");
+        slice[1] = types::Excuse::Sleeping;
+        assert!(matches!(
+            excuses.as_slice(),
+            Err(GuestError::PtrBorrowed(_))
+        ));
+        let mut bc = GuestBorrows::new();
+        assert!(matches!(
+            excuses.as_raw(&mut bc),
+            Err(GuestError::PtrBorrowed(_))
+        ));
+    }
+
+    // Dropping the guard released the borrow.
+    let slice = excuses.as_slice().expect("borrow excuses again");
+    assert_eq!(
+        &*slice,
+        &[
+            types::Excuse::DogAte,
+            types::Excuse::Sleeping,
+            types::Excuse::DogAte,
+            types::Excuse::DogAte,
+        ]
+    );
+}
+
+#[test]
+fn slice_guard_validates() {
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let excuses: GuestPtr<'_, [types::Excuse]> = call_memory.ptr((0, 4));
+    host_memory.ptr::<u8>(2).write(3).expect("write raw byte");
+    assert_eq!(
+        excuses.as_slice().err(),
+        Some(GuestError::InvalidEnumValue("Excuse"))
+    );
+    // A failed validation doesn't leave the slice borrowed.
+    host_memory.ptr::<u8>(2).write(0).expect("write raw byte");
+    excuses.as_slice().expect("valid excuses");
+}
+
+#[test]
+fn slice_guard_needs_borrow_checker() {
+    let host_memory = HostMemory::new();
+    let excuses: GuestPtr<'_, [types::Excuse]> = host_memory.ptr((0, 4));
+    assert_eq!(excuses.as_slice().err(), Some(GuestError::NoBorrowChecker));
+}
+
+#[test]
+fn shared_borrows_overlap() {
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let excuses: GuestPtr<'_, [types::Excuse]> = call_memory.ptr((0, 4));
+
+    let first = excuses.as_slice().expect("borrow excuses");
+    let second = excuses.as_slice().expect("borrow excuses again");
+    let mut bc = GuestBorrows::new();
+    let raw = excuses.as_raw_const(&mut bc).expect("raw view of excuses");
+    assert_eq!(&*first, &*second);
+    assert_eq!(&*first, unsafe { &*raw });
+
+    assert!(matches!(
+        excuses.as_slice_mut(),
+        Err(GuestError::PtrBorrowed(_))
+    ));
+    let mut bc = GuestBorrows::new();
+    assert!(matches!(
+        excuses.as_raw(&mut bc),
+        Err(GuestError::PtrBorrowed(_))
+    ));
+}
+
+#[test]
+fn unborrow_releases_call_borrow() {
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let excuses: GuestPtr<'_, [types::Excuse]> = call_memory.ptr((0, 4));
+
+    let mut bc = GuestBorrows::new();
+    let handle = bc.borrow_slice(&excuses).expect("borrow excuses");
+    assert!(matches!(
+        excuses.as_slice(),
+        Err(GuestError::PtrBorrowed(_))
+    ));
+    bc.unborrow(handle);
+    excuses
+        .as_slice_mut()
+        .expect("borrow was released mid-call");
+}
+
+#[test]
+fn bulk_copy() {
+    use types::Excuse::*;
+    let host_memory = HostMemory::new();
+    let excuses: GuestPtr<'_, [types::Excuse]> = host_memory.ptr((0, 4));
+    excuses
+        .copy_from_slice(&[DogAte, Traffic, Sleeping, DogAte])
+        .expect("copy excuses in");
+    assert_eq!(
+        excuses.to_vec().expect("copy excuses out"),
+        vec![DogAte, Traffic, Sleeping, DogAte]
+    );
+
+    host_memory.ptr::<u8>(3).write(3).expect("write raw byte");
+    assert_eq!(
+        excuses.to_vec().err(),
+        Some(GuestError::InvalidEnumValue("Excuse"))
+    );
+
+    // Pointers aren't transparent, so are copied one at a time.
+    let ptrs: GuestPtr<'_, [GuestPtr<'_, types::Excuse>]> = host_memory.ptr((8, 2));
+    let targets = [host_memory.ptr(0), host_memory.ptr(2)];
+    ptrs.copy_from_slice(&targets).expect("copy pointers in");
+    let copied = ptrs.to_vec().expect("copy pointers out");
+    assert_eq!(copied[0].read(), Ok(DogAte));
+    assert_eq!(copied[1].read(), Ok(Sleeping));
+}
+
+#[test]
+#[should_panic(expected = "source slice length")]
+fn bulk_copy_length_mismatch() {
+    let host_memory = HostMemory::new();
+    let excuses: GuestPtr<'_, [types::Excuse]> = host_memory.ptr((0, 4));
+    let _ = excuses.copy_from_slice(&[types::Excuse::DogAte]);
+}
+
+#[test]
+fn io_adapters() {
+    use std::io::{self, Read, Write};
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let src: GuestPtr<'_, [u8]> = call_memory.ptr((0, 8));
+    let dst: GuestPtr<'_, [u8]> = call_memory.ptr((16, 4));
+    src.copy_from_slice(b"abcdefgh").expect("fill source");
+
+    let mut reader = src.reader().expect("borrow source");
+    let mut writer = dst.writer().expect("borrow destination");
+    assert!(matches!(dst.as_slice(), Err(GuestError::PtrBorrowed(_))));
+    let mut head = [0; 2];
+    reader.read_exact(&mut head).expect("read head");
+    assert_eq!(&head, b"ab");
+    // The destination is too short to take the rest of the source.
+    assert_eq!(
+        io::copy(&mut reader, &mut writer).map_err(|e| e.kind()),
+        Err(io::ErrorKind::WriteZero)
+    );
+    assert_eq!(writer.remaining(), 0);
+    assert_eq!(writer.write(b"z").expect("write when full"), 0);
+    drop((reader, writer));
+
+    assert_eq!(dst.to_vec().expect("read destination"), b"cdef");
+    dst.as_slice_mut().expect("writer released its borrow");
+}
+
+#[test]
+fn copy_between_memories() {
+    let from = HostMemory::new();
+    let to = HostMemory::new();
+    let src: GuestPtr<'_, [u8]> = from.ptr((0, 6));
+    src.copy_from_slice(b"wiggle").expect("fill source");
+
+    wiggle::copy(src, to.ptr((100, 6))).expect("copy to other memory");
+    assert_eq!(to.ptr::<[u8]>((100, 6)).to_vec(), Ok(b"wiggle".to_vec()));
+
+    // Within one memory, overlapping buffers are copied as if through a
+    // temporary buffer.
+    wiggle::copy(src, from.ptr((2, 6))).expect("overlapping copy");
+    assert_eq!(from.ptr::<[u8]>((0, 8)).to_vec(), Ok(b"wiwiggle".to_vec()));
+
+    assert!(matches!(
+        wiggle::copy(src, to.ptr((4094, 6))),
+        Err(GuestError::LenOutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn checked_element_access() {
+    use types::Excuse::*;
+    let host_memory = HostMemory::new();
+    let excuses: GuestPtr<'_, [types::Excuse]> = host_memory.ptr((8, 4));
+    excuses
+        .copy_from_slice(&[DogAte, Traffic, Sleeping, DogAte])
+        .expect("fill excuses");
+
+    assert_eq!(excuses.get(2).and_then(|p| p.read()), Ok(Sleeping));
+    assert_eq!(
+        excuses.get(4).err(),
+        Some(GuestError::IndexOutOfBounds {
+            index: 4..5,
+            len: 4
+        })
+    );
+
+    let middle = excuses.get_range(1..3).expect("middle two");
+    assert_eq!(middle.offset_base(), 9);
+    assert_eq!(middle.to_vec(), Ok(vec![Traffic, Sleeping]));
+    assert!(excuses.get_range(4..4).expect("empty tail").is_empty());
+    assert_eq!(
+        excuses.get_range(2..5).err(),
+        Some(GuestError::IndexOutOfBounds {
+            index: 2..5,
+            len: 4
+        })
+    );
+    #[allow(clippy::reversed_empty_ranges)]
+    let backwards = 3..1;
+    assert!(matches!(
+        excuses.get_range(backwards),
+        Err(GuestError::IndexOutOfBounds { len: 4, .. })
+    ));
+}
+
+#[test]
+fn split_at() {
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let buf: GuestPtr<'_, [u32]> = call_memory.ptr((16, 4));
+    buf.copy_from_slice(&[1, 2, 3, 4]).expect("fill buffer");
+
+    let (head, tail) = buf.split_at(1).expect("split");
+    assert_eq!(head.to_vec(), Ok(vec![1]));
+    assert_eq!(tail.offset_base(), 20);
+    assert_eq!(tail.to_vec(), Ok(vec![2, 3, 4]));
+
+    // The halves can be borrowed mutably at the same time.
+    let mut head = head.as_slice_mut().expect("borrow head");
+    let mut tail = tail.as_slice_mut().expect("borrow tail");
+    head[0] = 10;
+    tail[0] = 20;
+    drop((head, tail));
+    assert_eq!(buf.to_vec(), Ok(vec![10, 20, 3, 4]));
+
+    let (all, empty) = buf.split_at(4).expect("split at the end");
+    assert_eq!((all.len(), empty.len()), (4, 0));
+    assert_eq!(
+        buf.split_at(5).err(),
+        Some(GuestError::IndexOutOfBounds {
+            index: 0..5,
+            len: 4
+        })
+    );
+}
+
+#[test]
+fn cursor() {
+    use wiggle::GuestCursor;
+    let host_memory = HostMemory::new();
+    let buf: GuestPtr<'_, [u8]> = host_memory.ptr((16, 28));
+
+    // Two records of a `u64` cookie, a `u16` length and that many bytes.
+    let mut writer = GuestCursor::new(buf);
+    for (cookie, name) in [(1u64, &b"abcdef"[..]), (2, b"gh")] {
+        writer.write_next(cookie).expect("write cookie");
+        writer.write_next(name.len() as u16).expect("write length");
+        for byte in name {
+            writer.write_next(*byte).expect("write name");
+        }
+    }
+    assert_eq!(writer.position(), 28);
+    assert_eq!(writer.remaining(), 0);
+    assert_eq!(
+        writer.write_next(0u8).err(),
+        Some(GuestError::IndexOutOfBounds {
+            index: 28..29,
+            len: 28
+        })
+    );
+
+    let mut reader = GuestCursor::new(buf);
+    assert_eq!(reader.read_next::<u64>(), Ok(1));
+    let len = reader.read_next::<u16>().expect("read length");
+    reader.skip(len.into()).expect("skip name");
+    assert_eq!(reader.read_next::<u64>(), Ok(2));
+    assert_eq!(reader.read_next::<u16>(), Ok(2));
+    assert!(matches!(
+        reader.skip(3),
+        Err(GuestError::IndexOutOfBounds { .. })
+    ));
+    assert_eq!(reader.remaining(), 2);
+    assert_eq!(reader.read_next::<u8>(), Ok(b'g'));
+}
@@ -0,0 +1,113 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use wiggle::{GuestError, GuestErrorType, GuestMemory, GuestPtr};
+use wiggle_test::HostMemory;
+
+wiggle::from_witx!({
+    witx: ["tests/policy.witx"],
+    ctx: Host,
+    async: true,
+});
+
+pub struct Host;
+
+impl<'a> GuestErrorType<'a> for types::Errno {
+    type Context = Host;
+    fn success() -> types::Errno {
+        types::Errno::Ok
+    }
+    fn from_error(e: GuestError, _ctx: &Host) -> types::Errno {
+        eprintln!("GUEST ERROR: {:?}", e);
+        types::Errno::InvalidArg
+    }
+}
+
+/// Returns `Pending` once, like waiting on I/O.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+fn block_on<F: Future>(f: F) -> F::Output {
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    let mut f = Box::pin(f);
+    loop {
+        if let Poll::Ready(out) = f.as_mut().poll(&mut cx) {
+            return out;
+        }
+    }
+}
+
+impl policy_examples::PolicyExamples for Host {
+    async fn path_open(
+        &self,
+        dirfd: types::Fd,
+        path: &GuestPtr<'_, str>,
+    ) -> Result<types::Fd, types::Errno> {
+        let s = path.as_str().map_err(|_| types::Errno::InvalidArg)?;
+        YieldNow(false).await;
+        // The string is still borrowed after the await, so the guest's memory
+        // can't be mutably borrowed out from under it.
+        assert!(matches!(
+            path.as_bytes().as_slice_mut(),
+            Err(GuestError::PtrBorrowed(_))
+        ));
+        if &*s == "/etc" {
+            return Err(types::Errno::DontWantTo);
+        }
+        Ok(types::Fd::from(u32::from(dirfd) + 1))
+    }
+    async fn fd_close(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        YieldNow(false).await;
+        Ok(())
+    }
+}
+
+fn path_open(host_memory: &HostMemory, path: &str) -> i32 {
+    let ptr = host_memory.ptr::<str>((8, path.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(path.bytes()) {
+        slot.expect("valid pointer").write(byte).expect("write");
+    }
+    block_on(policy_examples::path_open(
+        &Host,
+        host_memory,
+        3,
+        8,
+        path.len() as i32,
+        0,
+    ))
+}
+
+#[test]
+fn async_calls() {
+    let host_memory = HostMemory::new();
+    assert_eq!(path_open(&host_memory, "/tmp"), types::Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<u32>(0).read(), Ok(4));
+    assert_eq!(
+        path_open(&host_memory, "/etc"),
+        types::Errno::DontWantTo.into()
+    );
+    assert_eq!(
+        block_on(policy_examples::fd_close(&Host, &host_memory, 4)),
+        types::Errno::Ok.into()
+    );
+}
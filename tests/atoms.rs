@@ -1,5 +1,5 @@
 use proptest::prelude::*;
-use wiggle_runtime::{GuestError, GuestMemory};
+use wiggle::{GuestError, GuestMemory};
 use wiggle_test::{impl_errno, HostMemory, MemArea, WasiCtx};
 
 wiggle::from_witx!({
@@ -69,7 +69,7 @@ impl DoubleIntExercise {
         );
 
         let return_val = host_memory
-            .ptr::<types::AliasToFloat>(self.return_loc.ptr)
+            .ptr::<types::AliasToFloat>(self.return_loc.ptr.into())
             .read()
             .expect("failed to read return");
         assert_eq!(e, types::Errno::Ok.into(), "errno");
@@ -89,3 +89,114 @@ proptest! {
         e.test()
     }
 }
+
+struct Deterministic<'a>(&'a HostMemory);
+
+unsafe impl GuestMemory for Deterministic<'_> {
+    fn base(&self) -> (*mut u8, u64) {
+        self.0.base()
+    }
+    fn canonicalize_nans(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn canonicalize_nans() {
+    const NOISY_NAN: u32 = 0xffc0_1234;
+    let host_memory = HostMemory::new();
+    host_memory
+        .ptr::<u32>(0)
+        .write(NOISY_NAN)
+        .expect("write nan");
+    let raw = host_memory.ptr::<f32>(0).read().expect("read raw nan");
+    assert_eq!(raw.to_bits(), NOISY_NAN);
+
+    let mem = Deterministic(&host_memory);
+    let read = mem.ptr::<f32>(0).read().expect("read nan");
+    assert_eq!(read.to_bits(), 0x7fc0_0000);
+
+    mem.ptr::<f64>(8)
+        .write(f64::from_bits(0xfff0_0000_0000_0001))
+        .expect("write nan");
+    assert_eq!(host_memory.ptr::<u64>(8).read(), Ok(0x7ff8_0000_0000_0000));
+
+    let floats = mem.ptr::<[f32]>((0, 2));
+    floats
+        .copy_from_slice(&[f32::from_bits(NOISY_NAN), 1.5])
+        .expect("write floats");
+    assert_eq!(host_memory.ptr::<u32>(0).read(), Ok(0x7fc0_0000));
+    host_memory
+        .ptr::<u32>(0)
+        .write(NOISY_NAN)
+        .expect("write nan");
+    let copied = floats.to_vec().expect("read floats");
+    assert_eq!(copied[0].to_bits(), 0x7fc0_0000);
+    assert_eq!(copied[1], 1.5);
+}
+
+mod floats {
+    wiggle::from_witx!({
+        witx_literal: r#"
+            (typename $point (struct (field $x f32) (field $y f64)))
+        "#,
+        ctx: WasiCtx,
+    });
+}
+
+#[test]
+fn canonicalize_nans_in_struct_slices() {
+    use floats::types::Point;
+    let host_memory = HostMemory::new();
+    let mem = Deterministic(&host_memory);
+    let noisy = Point {
+        x: f32::from_bits(0xffc0_1234),
+        y: f64::from_bits(0xfff0_0000_0000_0001),
+    };
+    let points = mem.ptr::<[Point]>((0, 2));
+    points
+        .copy_from_slice(&[noisy, Point { x: 1.5, y: 2.5 }])
+        .expect("write points");
+    assert_eq!(host_memory.ptr::<u32>(0).read(), Ok(0x7fc0_0000));
+    assert_eq!(host_memory.ptr::<u64>(8).read(), Ok(0x7ff8_0000_0000_0000));
+
+    host_memory
+        .ptr::<[Point]>((0, 1))
+        .copy_from_slice(&[noisy])
+        .expect("write raw nans");
+    let copied = points.to_vec().expect("read points");
+    assert_eq!(copied[0].x.to_bits(), 0x7fc0_0000);
+    assert_eq!(copied[0].y.to_bits(), 0x7ff8_0000_0000_0000);
+    assert_eq!((copied[1].x, copied[1].y), (1.5, 2.5));
+}
+
+#[test]
+fn errors_point_at_witx() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+
+    let e = atoms::double_int_return_float(&ctx, &host_memory, 1, 4096);
+    assert_eq!(e, types::Errno::InvalidArg.into(), "errno");
+
+    let errors = ctx.guest_errors.borrow();
+    match &errors[..] {
+        [GuestError::InFunc {
+            funcname,
+            location,
+            witx: Some(witx),
+            err,
+        }] => {
+            assert_eq!(*funcname, "double_int_return_float");
+            assert_eq!(*location, "doubled_it:result_ptr_mut");
+            assert_eq!(witx.to_string(), "tests/atoms.witx:13:13");
+            assert_eq!(
+                **err,
+                GuestError::OffsetOutOfBounds {
+                    offset: 4096,
+                    memory_size: 4096,
+                }
+            );
+        }
+        errors => panic!("unexpected errors {:?}", errors),
+    }
+}
@@ -0,0 +1,62 @@
+use std::cell::RefCell;
+use wiggle::{CallContext, GuestError, GuestErrorType, GuestPtr};
+use wiggle_test::HostMemory;
+
+wiggle::from_witx!({
+    witx: ["tests/policy.witx"],
+    ctx: Host,
+});
+
+/// Records the call of every guest error.
+#[derive(Default)]
+pub struct Host {
+    errors: RefCell<Vec<CallContext>>,
+}
+
+impl<'a> GuestErrorType<'a> for types::Errno {
+    type Context = Host;
+    fn success() -> types::Errno {
+        types::Errno::Ok
+    }
+    fn from_error(_e: GuestError, _ctx: &Host) -> types::Errno {
+        unreachable!("generated functions call from_call_error")
+    }
+    fn from_call_error(_e: GuestError, ctx: &Host, call: CallContext) -> types::Errno {
+        ctx.errors.borrow_mut().push(call);
+        types::Errno::InvalidArg
+    }
+}
+
+impl policy_examples::PolicyExamples for Host {
+    fn path_open(
+        &self,
+        dirfd: types::Fd,
+        _path: &GuestPtr<str>,
+    ) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(u32::from(dirfd) + 1))
+    }
+    fn fd_close(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        Ok(())
+    }
+}
+
+#[test]
+fn errors_know_their_call() {
+    let host = Host::default();
+    let host_memory = HostMemory::new();
+
+    // The result pointer is out of bounds.
+    let e = policy_examples::path_open(&host, &host_memory, 3, 8, 0, i32::MAX);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+
+    let errors = host.errors.borrow();
+    assert_eq!(
+        *errors,
+        vec![CallContext {
+            module: "policy_examples",
+            function: "path_open",
+            param: Some("opened"),
+        }]
+    );
+    assert_eq!(errors[0].to_string(), "policy_examples::path_open (opened)");
+}
@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+use wiggle::{CancelCtx, CancelToken, GuestError, GuestErrorType};
+use wiggle_test::HostMemory;
+
+wiggle::from_witx!({
+    witx: ["tests/handles.witx"],
+    ctx: Host,
+    cancel: Errno,
+});
+
+pub struct Host {
+    token: CancelToken,
+    cancelled_error: Option<types::Errno>,
+}
+
+impl Host {
+    fn new(cancelled_error: Option<types::Errno>) -> Self {
+        Host {
+            token: CancelToken::new(),
+            cancelled_error,
+        }
+    }
+}
+
+impl<'a> GuestErrorType<'a> for types::Errno {
+    type Context = Host;
+    fn success() -> types::Errno {
+        types::Errno::Ok
+    }
+    fn from_error(e: GuestError, _ctx: &Host) -> types::Errno {
+        eprintln!("GUEST ERROR: {:?}", e);
+        types::Errno::InvalidArg
+    }
+}
+
+impl CancelCtx<types::Errno> for Host {
+    fn cancel_token(&self) -> Option<&CancelToken> {
+        Some(&self.token)
+    }
+    fn cancelled_error(&self) -> Option<types::Errno> {
+        self.cancelled_error
+    }
+}
+
+impl handle_examples::HandleExamples for Host {
+    fn fd_create(&self) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(1))
+    }
+    fn fd_consume(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        Ok(())
+    }
+}
+
+#[test]
+fn uncancelled_call_runs() {
+    let host = Host::new(Some(types::Errno::PicketLine));
+    let host_memory = HostMemory::new();
+    let e = handle_examples::fd_consume(&host, &host_memory, 1);
+    assert_eq!(e, types::Errno::Ok.into());
+}
+
+#[test]
+fn cancelled_call_returns_error() {
+    let host = Host::new(Some(types::Errno::PicketLine));
+    let host_memory = HostMemory::new();
+    host.token.clone().cancel();
+    let e = handle_examples::fd_consume(&host, &host_memory, 1);
+    assert_eq!(e, types::Errno::PicketLine.into());
+
+    host.token.reset();
+    let e = handle_examples::fd_consume(&host, &host_memory, 1);
+    assert_eq!(e, types::Errno::Ok.into());
+}
+
+#[test]
+fn deadline_cancels_call() {
+    let host = Host::new(Some(types::Errno::PicketLine));
+    let host_memory = HostMemory::new();
+    host.token
+        .set_deadline(Instant::now() - Duration::from_millis(1));
+    let e = handle_examples::fd_create(&host, &host_memory, 0);
+    assert_eq!(e, types::Errno::PicketLine.into());
+}
+
+#[test]
+#[should_panic(expected = "call to `fd_consume` cancelled")]
+fn cancelled_call_traps() {
+    let host = Host::new(None);
+    let host_memory = HostMemory::new();
+    host.token.cancel();
+    handle_examples::fd_consume(&host, &host_memory, 1);
+}
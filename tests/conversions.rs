@@ -0,0 +1,116 @@
+use std::convert::TryFrom;
+use wiggle::ConversionError;
+
+// The documents only define types, so the ctx is never used.
+mod v2 {
+    wiggle::from_witx!({
+        witx: ["tests/conversions_v2.witx"],
+        ctx: Ctx,
+    });
+}
+
+mod v1 {
+    wiggle::from_witx!({
+        witx: ["tests/conversions_v1.witx"],
+        ctx: Ctx,
+        conversions: {
+            witx: ["tests/conversions_v2.witx"],
+            to: crate::v2,
+            renames: { fs_flags: flags, fs_rights: rights, fs_errno: errno },
+        },
+    });
+}
+
+#[test]
+fn converts_to_a_superset() {
+    assert_eq!(
+        v2::types::Errno::from(v1::types::Errno::Badf),
+        v2::types::Errno::Badf
+    );
+    assert_eq!(
+        v2::types::Fd::from(v1::types::Fd::from(3)),
+        v2::types::Fd::from(3)
+    );
+    assert_eq!(
+        v2::types::Rights::from(v1::types::Rights::READ | v1::types::Rights::WRITE),
+        v2::types::Rights::READ | v2::types::Rights::WRITE
+    );
+    assert_eq!(
+        v2::types::Fdstat::from(v1::types::Fdstat {
+            fs_flags: 1,
+            fs_rights: v1::types::Rights::READ,
+            fs_errno: v1::types::Errno::Inval,
+        }),
+        v2::types::Fdstat {
+            flags: 1,
+            rights: v2::types::Rights::READ,
+            errno: v2::types::Errno::Inval,
+        }
+    );
+    assert_eq!(
+        v2::types::Filestat::from(v1::types::Filestat {
+            offset: -1,
+            size: u32::MAX,
+        }),
+        v2::types::Filestat {
+            offset: -1,
+            size: u32::MAX.into(),
+        }
+    );
+    // Floats only widen, so `Timing` only converts this way.
+    assert_eq!(
+        v2::types::Timing::from(v1::types::Timing { ratio: 0.5 }),
+        v2::types::Timing { ratio: 0.5 }
+    );
+}
+
+#[test]
+fn converts_from_a_superset() {
+    assert_eq!(
+        v1::types::Errno::try_from(v2::types::Errno::Inval),
+        Ok(v1::types::Errno::Inval)
+    );
+    let err = v1::types::Errno::try_from(v2::types::Errno::Notsup).unwrap_err();
+    assert_eq!(
+        err,
+        ConversionError {
+            from: "errno",
+            to: "errno"
+        }
+    );
+    assert!(v1::types::Rights::try_from(v2::types::Rights::SEEK).is_err());
+
+    // Members of structs convert the same way, and integers saturate.
+    let fdstat = v1::types::Fdstat::try_from(v2::types::Fdstat {
+        flags: u32::MAX,
+        rights: v2::types::Rights::WRITE,
+        errno: v2::types::Errno::Success,
+    })
+    .expect("convert fdstat");
+    assert_eq!(
+        fdstat,
+        v1::types::Fdstat {
+            fs_flags: u16::MAX,
+            fs_rights: v1::types::Rights::WRITE,
+            fs_errno: v1::types::Errno::Success,
+        }
+    );
+    assert!(v1::types::Fdstat::try_from(v2::types::Fdstat {
+        flags: 0,
+        rights: v2::types::Rights::READ,
+        errno: v2::types::Errno::Notsup,
+    })
+    .is_err());
+}
+
+#[test]
+fn saturates_integers() {
+    // Signed integers saturate in both directions.
+    let saturate = |offset, size| {
+        let filestat = v1::types::Filestat::from(v2::types::Filestat { offset, size });
+        (filestat.offset, filestat.size)
+    };
+    assert_eq!(saturate(-5, 5), (-5, 5));
+    assert_eq!(saturate(i64::MIN, -1), (i32::MIN, 0));
+    assert_eq!(saturate(i64::MAX, i64::MAX), (i32::MAX, u32::MAX));
+}
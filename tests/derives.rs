@@ -0,0 +1,38 @@
+mod errno {
+    wiggle::from_witx!({
+        witx: ["tests/errno.witx"],
+        ctx: WasiCtx,
+        derive: { _: [PartialOrd, Ord] },
+        attributes: { errno: [#[must_use] #[doc = "Errors."]] },
+    });
+}
+
+mod structs {
+    wiggle::from_witx!({
+        witx: ["tests/structs.witx"],
+        ctx: WasiCtx,
+        derive: { pair_ints: [Default, core::cmp::Eq], errno: [PartialOrd] },
+        exclude_modules: [structs],
+    });
+}
+
+#[test]
+fn global_derives() {
+    use errno::types::Errno;
+    assert!(Errno::Ok < Errno::InvalidArg);
+    assert_eq!(Errno::PicketLine.max(Errno::DontWantTo), Errno::PicketLine);
+}
+
+#[test]
+fn per_type_derives() {
+    use structs::types::{Errno, PairInts};
+    let pair = PairInts::default();
+    assert_eq!(
+        pair,
+        PairInts {
+            first: 0,
+            second: 0
+        }
+    );
+    assert!(Errno::Ok < Errno::InvalidArg);
+}
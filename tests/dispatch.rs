@@ -0,0 +1,84 @@
+use wiggle::{DispatchError, GuestError, GuestMemory, Value, ValueType};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/handles.witx"],
+    ctx: WasiCtx,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> handle_examples::HandleExamples for WasiCtx<'a> {
+    fn fd_create(&self) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(7))
+    }
+    fn fd_consume(&self, fd: types::Fd) -> Result<(), types::Errno> {
+        if fd == types::Fd::from(7) {
+            Ok(())
+        } else {
+            Err(types::Errno::InvalidArg)
+        }
+    }
+}
+
+#[test]
+fn dispatches_by_name() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+
+    let results = handle_examples::dispatch("fd_create", &ctx, &host_memory, &[Value::I32(8)])
+        .expect("dispatch fd_create");
+    assert_eq!(results, &[Value::I32(types::Errno::Ok as i32)]);
+    let fd = host_memory.ptr::<u32>(8).read().expect("read fd");
+    assert_eq!(fd, 7);
+
+    let results =
+        handle_examples::dispatch("fd_consume", &ctx, &host_memory, &[Value::I32(fd as i32)])
+            .expect("dispatch fd_consume");
+    assert_eq!(results, &[Value::I32(types::Errno::Ok as i32)]);
+}
+
+#[test]
+fn reports_bad_calls() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+
+    assert_eq!(
+        handle_examples::dispatch("fd_destroy", &ctx, &host_memory, &[]),
+        Err(DispatchError::UnknownFunction("fd_destroy".to_string()))
+    );
+    assert_eq!(
+        handle_examples::dispatch("fd_consume", &ctx, &host_memory, &[]),
+        Err(DispatchError::ArgCount {
+            func: "fd_consume",
+            expected: 1,
+            got: 0,
+        })
+    );
+    assert_eq!(
+        handle_examples::dispatch("fd_consume", &ctx, &host_memory, &[Value::I64(7)]),
+        Err(DispatchError::ArgType {
+            func: "fd_consume",
+            index: 0,
+        })
+    );
+}
+
+#[test]
+fn abi_functions_table() {
+    let names = handle_examples::ABI_FUNCTIONS
+        .iter()
+        .map(|f| f.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, &["fd_create", "fd_consume"]);
+
+    let fd_consume = &handle_examples::ABI_FUNCTIONS[1];
+    assert_eq!(fd_consume.params, &[ValueType::I32]);
+    assert_eq!(fd_consume.results, &[ValueType::I32]);
+
+    let func: fn(&WasiCtx, &dyn GuestMemory, i32) -> i32 =
+        unsafe { std::mem::transmute(fd_consume.func) };
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    assert_eq!(func(&ctx, &host_memory, 7), types::Errno::Ok as i32);
+}
@@ -0,0 +1,57 @@
+use wiggle::{GuestError, GuestErrorType, GuestMemory};
+use wiggle_test::HostMemory;
+
+wiggle::from_witx!({
+    witx: ["tests/handles.witx"],
+    ctx: DynCtx,
+});
+
+type DynCtx = Box<dyn handle_examples::HandleExamples>;
+
+impl<'a> GuestErrorType<'a> for types::Errno {
+    type Context = DynCtx;
+    fn success() -> types::Errno {
+        types::Errno::Ok
+    }
+    fn from_error(e: GuestError, _ctx: &DynCtx) -> types::Errno {
+        eprintln!("GUEST ERROR: {:?}", e);
+        types::Errno::InvalidArg
+    }
+}
+
+struct Counting(u32);
+
+impl handle_examples::HandleExamples for Counting {
+    fn fd_create(&self) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(self.0))
+    }
+    fn fd_consume(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        Ok(())
+    }
+}
+
+struct Unwilling;
+
+impl handle_examples::HandleExamples for Unwilling {
+    fn fd_create(&self) -> Result<types::Fd, types::Errno> {
+        Err(types::Errno::DontWantTo)
+    }
+    fn fd_consume(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        Err(types::Errno::DontWantTo)
+    }
+}
+
+#[test]
+fn swap_boxed_implementation() {
+    let host_memory = HostMemory::new();
+    let mut ctx: DynCtx = Box::new(Counting(7));
+
+    let e = handle_examples::fd_create(&ctx, &host_memory, 0);
+    assert_eq!(e, types::Errno::Ok.into());
+    let fd = host_memory.ptr::<u32>(0).read().expect("read fd");
+    assert_eq!(fd, 7);
+
+    ctx = Box::new(Unwilling);
+    let e = handle_examples::fd_consume(&ctx, &host_memory, fd as i32);
+    assert_eq!(e, types::Errno::DontWantTo.into());
+}
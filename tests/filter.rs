@@ -0,0 +1,64 @@
+use wiggle_test::{HostMemory, WasiCtx};
+
+// Only one of the two functions in the module is generated, so that's all
+// the trait asks to be implemented.
+mod included {
+    use wiggle::GuestError;
+    use wiggle_test::{impl_errno, WasiCtx};
+
+    wiggle::from_witx!({
+        witx: ["tests/atoms.witx"],
+        ctx: WasiCtx,
+        include_functions: [double_int_return_float],
+    });
+
+    impl_errno!(types::Errno);
+
+    impl<'a> atoms::Atoms for WasiCtx<'a> {
+        fn double_int_return_float(
+            &self,
+            an_int: u32,
+        ) -> Result<types::AliasToFloat, types::Errno> {
+            Ok((an_int as f32) * 2.0)
+        }
+    }
+}
+
+// Without a module there's no trait or trampolines, just the types.
+mod excluded {
+    wiggle::from_witx!({
+        witx: ["tests/atoms.witx"],
+        ctx: WasiCtx,
+        exclude_modules: [atoms],
+    });
+
+    pub use types::Errno;
+}
+
+#[test]
+fn included_functions() {
+    use wiggle::GuestMemory;
+
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let e = included::atoms::double_int_return_float(&ctx, &host_memory, 21, 8);
+    assert_eq!(e, included::types::Errno::Ok.into());
+    let doubled = host_memory.ptr::<f32>(8).read().expect("read result");
+    assert_eq!(doubled, 42.0);
+}
+
+#[test]
+fn filtered_out_functions_are_not_implemented() {
+    let info = included::atoms::INTERFACE;
+    assert_eq!(
+        info.functions,
+        &["int_float_args", "double_int_return_float"]
+    );
+    assert!(!info.is_implemented("int_float_args"));
+    assert!(info.is_implemented("double_int_return_float"));
+}
+
+#[test]
+fn excluded_modules() {
+    assert_eq!(excluded::Errno::Ok as u32, 0);
+}
@@ -1,11 +1,12 @@
 use proptest::prelude::*;
 use std::convert::TryFrom;
-use wiggle_runtime::{GuestError, GuestMemory, GuestPtr};
+use wiggle::{GuestBorrows, GuestError, GuestMemory, GuestPtr};
 use wiggle_test::{impl_errno, HostMemory, MemArea, WasiCtx};
 
 wiggle::from_witx!({
     witx: ["tests/flags.witx"],
     ctx: WasiCtx,
+    proptest: true,
 });
 
 impl_errno!(types::Errno);
@@ -57,7 +58,7 @@ impl ConfigureCarExercise {
                 },
             )
             .prop_filter("non-overlapping ptrs", |e| {
-                MemArea::non_overlapping_set(&[e.other_config_by_ptr, e.return_ptr_loc])
+                MemArea::non_overlapping_set([e.other_config_by_ptr, e.return_ptr_loc])
             })
             .boxed()
     }
@@ -68,7 +69,7 @@ impl ConfigureCarExercise {
 
         // Populate input ptr
         host_memory
-            .ptr(self.other_config_by_ptr.ptr)
+            .ptr(self.other_config_by_ptr.ptr.into())
             .write(self.other_config)
             .expect("deref ptr mut to CarConfig");
 
@@ -82,7 +83,7 @@ impl ConfigureCarExercise {
         assert_eq!(res, types::Errno::Ok.into(), "configure car errno");
 
         let res_config = host_memory
-            .ptr::<types::CarConfig>(self.return_ptr_loc.ptr)
+            .ptr::<types::CarConfig>(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref to CarConfig value");
 
@@ -99,3 +100,102 @@ proptest! {
         e.test()
     }
 }
+
+#[test]
+fn as_raw_validates_every_car_config() {
+    let host_memory = HostMemory::new();
+    let configs: GuestPtr<'_, [types::CarConfig]> = host_memory.ptr((0, 8));
+    for slot in configs.iter() {
+        slot.expect("valid ptr")
+            .write(types::CarConfig::AWD | types::CarConfig::SUV)
+            .expect("write car config");
+    }
+    let mut bc = GuestBorrows::new();
+    configs.as_raw(&mut bc).expect("all car configs are valid");
+
+    // Set a bit that doesn't correspond to any flag in one of the elements.
+    host_memory
+        .ptr::<u8>(5)
+        .write(0b1000)
+        .expect("write raw byte");
+    let mut bc = GuestBorrows::new();
+    assert_eq!(
+        configs.as_raw(&mut bc).err(),
+        Some(GuestError::InvalidFlagValue("CarConfig"))
+    );
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_car_configs_are_valid(config in any::<types::CarConfig>()) {
+        prop_assert!(types::CarConfig::try_from(u8::from(config)).is_ok());
+        prop_assert!(types::CarConfig::ALL_FLAGS.contains(&config));
+    }
+}
+
+#[test]
+fn flag_names() {
+    let names = types::CarConfig::flag_names()
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["AUTOMATIC", "AWD", "SUV"]);
+
+    let config = types::CarConfig::AUTOMATIC | types::CarConfig::SUV;
+    assert_eq!(
+        config.iter().collect::<Vec<_>>(),
+        [
+            ("AUTOMATIC", types::CarConfig::AUTOMATIC),
+            ("SUV", types::CarConfig::SUV)
+        ]
+    );
+    assert_eq!(types::CarConfig::EMPTY_FLAGS.iter().count(), 0);
+}
+
+#[test]
+fn display_flag_names() {
+    let config = types::CarConfig::AUTOMATIC | types::CarConfig::SUV;
+    assert_eq!(config.to_string(), "AUTOMATIC|SUV");
+    assert_eq!(types::CarConfig::AWD.to_string(), "AWD");
+    assert_eq!(types::CarConfig::EMPTY_FLAGS.to_string(), "EMPTY_FLAGS");
+    assert_eq!((!types::CarConfig::SUV).to_string(), "AUTOMATIC|AWD|0xf8");
+    assert_eq!((!types::CarConfig::ALL_FLAGS).to_string(), "0xf8");
+}
+
+#[test]
+fn parse_flag_names() {
+    let config = types::CarConfig::AUTOMATIC | types::CarConfig::SUV;
+    assert_eq!("automatic|suv".parse(), Ok(config));
+    assert_eq!("suv, automatic".parse(), Ok(config));
+    assert_eq!(config.to_string().parse(), Ok(config));
+    assert_eq!("".parse(), Ok(types::CarConfig::EMPTY_FLAGS));
+    assert_eq!("EMPTY_FLAGS".parse(), Ok(types::CarConfig::EMPTY_FLAGS));
+    assert_eq!(
+        "awd|fwd".parse::<types::CarConfig>(),
+        Err(wiggle::ParseNameError {
+            typename: "CarConfig",
+            name: "fwd".to_string(),
+        })
+    );
+}
+
+#[test]
+fn bitflags_methods() {
+    let mut config = types::CarConfig::AUTOMATIC;
+    assert!(config.intersects(&(types::CarConfig::AUTOMATIC | types::CarConfig::SUV)));
+    assert!(!config.intersects(&types::CarConfig::SUV));
+
+    config.insert(types::CarConfig::SUV);
+    assert_eq!(config, types::CarConfig::AUTOMATIC | types::CarConfig::SUV);
+    config.remove(types::CarConfig::AUTOMATIC | types::CarConfig::AWD);
+    assert_eq!(config, types::CarConfig::SUV);
+    config.toggle(types::CarConfig::SUV | types::CarConfig::AWD);
+    assert_eq!(config, types::CarConfig::AWD);
+
+    assert_eq!(config.bits(), 0b010);
+    assert_eq!(
+        types::CarConfig::from_bits(0b101),
+        Some(types::CarConfig::AUTOMATIC | types::CarConfig::SUV)
+    );
+    assert_eq!(types::CarConfig::from_bits(0b1000), None);
+}
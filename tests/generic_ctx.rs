@@ -0,0 +1,79 @@
+use wiggle::{CallContext, GuestError, GuestErrorCtx, GuestMemory, GuestPtr};
+use wiggle_test::HostMemory;
+
+/// Bindings as a library would generate them, without knowing the ctx.
+mod bindings {
+    wiggle::from_witx!({
+        witx: ["tests/policy.witx"],
+        ctx: generic,
+    });
+}
+
+use bindings::{policy_examples, types};
+
+/// Two ctx types using the same bindings.
+pub struct Counter {
+    next: u32,
+}
+
+pub struct Refuser;
+
+impl GuestErrorCtx<types::Errno> for Counter {
+    fn success(&self) -> types::Errno {
+        types::Errno::Ok
+    }
+    fn guest_error(&self, _e: GuestError, _call: CallContext) -> types::Errno {
+        types::Errno::InvalidArg
+    }
+}
+
+impl GuestErrorCtx<types::Errno> for Refuser {
+    fn success(&self) -> types::Errno {
+        types::Errno::Ok
+    }
+    fn guest_error(&self, _e: GuestError, call: CallContext) -> types::Errno {
+        panic!("guest error in {}", call)
+    }
+}
+
+impl policy_examples::PolicyExamples for Counter {
+    fn path_open(
+        &self,
+        _dirfd: types::Fd,
+        _path: &GuestPtr<str>,
+    ) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(self.next))
+    }
+    fn fd_close(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        Ok(())
+    }
+}
+
+impl policy_examples::PolicyExamples for Refuser {
+    fn path_open(
+        &self,
+        _dirfd: types::Fd,
+        _path: &GuestPtr<str>,
+    ) -> Result<types::Fd, types::Errno> {
+        Err(types::Errno::DontWantTo)
+    }
+    fn fd_close(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        Err(types::Errno::DontWantTo)
+    }
+}
+
+#[test]
+fn functions_take_any_ctx() {
+    let host_memory = HostMemory::new();
+
+    let counter = Counter { next: 7 };
+    let e = policy_examples::path_open(&counter, &host_memory, 3, 8, 0, 16);
+    assert_eq!(e, types::Errno::Ok.into());
+    let opened = host_memory.ptr::<types::Fd>(16).read().expect("read fd");
+    assert_eq!(opened, types::Fd::from(7));
+    let e = policy_examples::path_open(&counter, &host_memory, 3, 8, 0, i32::MAX);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+
+    let e = policy_examples::fd_close(&Refuser, &host_memory, 3);
+    assert_eq!(e, types::Errno::DontWantTo.into());
+}
@@ -0,0 +1,52 @@
+mod guest {
+    wiggle::from_witx!({
+        witx: ["tests/structs.witx", "tests/union.witx", "tests/flags.witx"],
+        guest: true,
+    });
+}
+
+#[test]
+fn guest_types_have_the_witx_layout() {
+    use std::mem::{align_of, size_of};
+
+    assert_eq!(size_of::<guest::types::PairInts>(), 8);
+    assert_eq!(align_of::<guest::types::PairInts>(), 4);
+    assert_eq!(size_of::<guest::types::Reason>(), 8);
+    assert_eq!(align_of::<guest::types::Reason>(), 4);
+}
+
+#[test]
+fn guest_enums_convert_from_raw_values() {
+    assert_eq!(
+        guest::types::Excuse::from_raw(1),
+        Some(guest::types::Excuse::Traffic)
+    );
+    assert_eq!(guest::types::Excuse::from_raw(3), None);
+    assert_eq!(
+        guest::types::Errno::from_raw(0),
+        Some(guest::types::Errno::Ok)
+    );
+}
+
+#[test]
+fn guest_flags_combine() {
+    use guest::types::CarConfig;
+
+    let config = CarConfig::AUTOMATIC | CarConfig::SUV;
+    assert!(config.contains(CarConfig::SUV));
+    assert!(!config.contains(CarConfig::AWD));
+    assert_eq!(config & CarConfig::AUTOMATIC, CarConfig::AUTOMATIC);
+    assert_eq!(CarConfig::ALL_FLAGS.0, 0b111);
+}
+
+#[test]
+fn guest_wrappers_take_rust_types() {
+    use guest::types::{CarConfig, Errno, PairInts};
+    use guest::HostError;
+
+    // The imports only resolve when running in a wasm host, so only check
+    // that the wrappers have the expected signatures.
+    let _: fn(&PairInts) -> Result<i64, HostError<Errno>> = guest::structs::sum_of_pair;
+    let _: unsafe fn(CarConfig, *const CarConfig) -> Result<CarConfig, HostError<Errno>> =
+        guest::flags::configure_car;
+}
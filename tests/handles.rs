@@ -1,5 +1,5 @@
 use proptest::prelude::*;
-use wiggle_runtime::{GuestError, GuestMemory, GuestType};
+use wiggle::{GuestError, GuestMemory, GuestType};
 use wiggle_test::{impl_errno, HostMemory, MemArea, WasiCtx};
 
 const FD_VAL: u32 = 123;
@@ -40,7 +40,7 @@ impl HandleExercise {
         assert_eq!(e, types::Errno::Ok.into(), "fd_create error");
 
         let h_got: u32 = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref_mut");
 
@@ -72,3 +72,21 @@ proptest! {
         e.test()
     }
 }
+
+#[test]
+fn handle_table_keyed_by_generated_handles() {
+    let mut table: wiggle::HandleTable<types::Fd, &str> = wiggle::HandleTable::new();
+    table.insert_at(types::Fd::from(FD_VAL), "preopen");
+    let fd: types::Fd = table.insert("file");
+    assert_eq!(fd, types::Fd::from(0));
+    assert_eq!(table.get(fd), Some(&"file"));
+    assert!(table.renumber(fd, types::Fd::from(FD_VAL)));
+    assert_eq!(table.get(types::Fd::from(FD_VAL)), Some(&"file"));
+    assert_eq!(table.remove(fd), None);
+}
+
+#[test]
+fn layout() {
+    assert_eq!(types::Fd::GUEST_SIZE, 4);
+    assert_eq!(types::Fd::GUEST_ALIGN, 4);
+}
@@ -0,0 +1,98 @@
+use std::cell::RefCell;
+use wiggle::{
+    GuestError, GuestErrorType, GuestMemory, GuestPtr, InstrumentationCtx, InstrumentationHook,
+};
+use wiggle_test::HostMemory;
+
+wiggle::from_witx!({
+    witx: ["tests/policy.witx"],
+    ctx: Host,
+    instrument: true,
+});
+
+/// Records every call it sees.
+#[derive(Default)]
+pub struct Host {
+    log: RefCell<Vec<String>>,
+}
+
+impl<'a> GuestErrorType<'a> for types::Errno {
+    type Context = Host;
+    fn success() -> types::Errno {
+        types::Errno::Ok
+    }
+    fn from_error(e: GuestError, _ctx: &Host) -> types::Errno {
+        eprintln!("GUEST ERROR: {:?}", e);
+        types::Errno::InvalidArg
+    }
+}
+
+impl InstrumentationHook for Host {
+    fn before_call(&self, function: &str, args: &[(&str, String)]) {
+        let args = args
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>();
+        self.log
+            .borrow_mut()
+            .push(format!("{}({})", function, args.join(", ")));
+    }
+
+    fn after_call(&self, function: &str, result: Result<&str, &str>) {
+        self.log
+            .borrow_mut()
+            .push(format!("{} -> {:?}", function, result));
+    }
+}
+
+impl InstrumentationCtx for Host {
+    fn instrumentation(&self) -> Option<&dyn InstrumentationHook> {
+        Some(self)
+    }
+}
+
+impl policy_examples::PolicyExamples for Host {
+    fn path_open(
+        &self,
+        dirfd: types::Fd,
+        _path: &GuestPtr<str>,
+    ) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(u32::from(dirfd) + 1))
+    }
+    fn fd_close(&self, fd: types::Fd) -> Result<(), types::Errno> {
+        if u32::from(fd) == 0 {
+            Err(types::Errno::DontWantTo)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn hook_sees_calls() {
+    let host = Host::default();
+    let host_memory = HostMemory::new();
+    let path = host_memory.ptr::<str>((8, 3));
+    for (slot, byte) in path.as_bytes().iter().zip(b"tmp".iter()) {
+        slot.expect("valid pointer").write(*byte).expect("write");
+    }
+
+    let e = policy_examples::path_open(&host, &host_memory, 3, 8, 3, 0);
+    assert_eq!(e, types::Errno::Ok.into());
+    let e = policy_examples::fd_close(&host, &host_memory, 0);
+    assert_eq!(e, types::Errno::DontWantTo.into());
+    let e = policy_examples::fd_close(&host, &host_memory, 4);
+    assert_eq!(e, types::Errno::Ok.into());
+
+    assert_eq!(
+        *host.log.borrow(),
+        vec![
+            "path_open(dirfd=Fd(3), path=*guest 0x8/3)",
+            "path_open -> Ok(\"Fd(4)\")",
+            "fd_close(fd=Fd(0))",
+            "fd_close -> Err(\"DontWantTo\")",
+            "fd_close(fd=Fd(4))",
+            "fd_close -> Ok(\"()\")",
+        ]
+    );
+}
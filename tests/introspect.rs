@@ -0,0 +1,42 @@
+use wiggle::{GuestError, GuestMemory};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/handles.witx"],
+    ctx: WasiCtx,
+    version: 3,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> handle_examples::HandleExamples for WasiCtx<'a> {
+    fn fd_create(&self) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(1))
+    }
+    fn fd_consume(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        Ok(())
+    }
+}
+
+#[test]
+fn interface_constant() {
+    let info = handle_examples::INTERFACE;
+    assert_eq!(info.name, "handle_examples");
+    assert_eq!(info.version, 3);
+    assert_eq!(info.functions, &["fd_create", "fd_consume"]);
+    assert!(info.is_implemented("fd_consume"));
+}
+
+#[test]
+fn guest_query() {
+    let host_memory = HostMemory::new();
+    let len = handle_examples::wiggle_interface_info(&host_memory, 0, 8, 1);
+    assert_eq!(len, 1);
+    let version = host_memory.ptr::<u32>(0).read().expect("read version");
+    assert_eq!(version, 3);
+    let bits = host_memory.ptr::<u8>(8).read().expect("read bits");
+    assert_eq!(bits, 0b11);
+
+    let len = handle_examples::wiggle_interface_info(&host_memory, 4095, 8, 1);
+    assert_eq!(len, -1);
+}
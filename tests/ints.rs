@@ -1,11 +1,11 @@
 use proptest::prelude::*;
-use std::convert::TryFrom;
-use wiggle_runtime::{GuestError, GuestMemory};
+use wiggle::{GuestError, GuestMemory};
 use wiggle_test::{impl_errno, HostMemory, MemArea, WasiCtx};
 
 wiggle::from_witx!({
     witx: ["tests/ints.witx"],
     ctx: WasiCtx,
+    proptest: true,
 });
 
 impl_errno!(types::Errno);
@@ -21,12 +21,6 @@ impl<'a> ints::Ints for WasiCtx<'a> {
     }
 }
 
-fn cookie_strat() -> impl Strategy<Value = types::Cookie> {
-    (0..std::u64::MAX)
-        .prop_map(|x| types::Cookie::try_from(x).expect("within range of cookie"))
-        .boxed()
-}
-
 #[derive(Debug)]
 struct CookieCutterExercise {
     cookie: types::Cookie,
@@ -35,7 +29,7 @@ struct CookieCutterExercise {
 
 impl CookieCutterExercise {
     pub fn strat() -> BoxedStrategy<Self> {
-        (cookie_strat(), HostMemory::mem_area_strat(4))
+        (any::<types::Cookie>(), HostMemory::mem_area_strat(4))
             .prop_map(|(cookie, return_ptr_loc)| Self {
                 cookie,
                 return_ptr_loc,
@@ -56,16 +50,12 @@ impl CookieCutterExercise {
         assert_eq!(res, types::Errno::Ok.into(), "cookie cutter errno");
 
         let is_cookie_start = host_memory
-            .ptr::<types::Bool>(self.return_ptr_loc.ptr)
+            .ptr::<types::Bool>(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref to Bool value");
 
         assert_eq!(
-            if is_cookie_start == types::Bool::True {
-                true
-            } else {
-                false
-            },
+            is_cookie_start == types::Bool::True,
             self.cookie == types::Cookie::START,
             "returned Bool should test if input was Cookie::START",
         );
@@ -77,3 +67,89 @@ proptest! {
         e.test()
     }
 }
+
+#[test]
+fn wide_integers() {
+    let host_memory = HostMemory::new();
+    assert_eq!(<u128 as wiggle::GuestType>::guest_size(), 16);
+    assert_eq!(<i128 as wiggle::GuestType>::guest_align(), 16);
+
+    let counter = host_memory.ptr::<u128>(16);
+    counter.write(u128::MAX - 1).expect("write u128");
+    assert_eq!(counter.read(), Ok(u128::MAX - 1));
+    assert_eq!(host_memory.ptr::<u64>(16).read(), Ok(u64::MAX - 1));
+    assert_eq!(host_memory.ptr::<i128>(16).read(), Ok(-2));
+    assert!(matches!(
+        host_memory.ptr::<u128>(8).read(),
+        Err(GuestError::PtrNotAligned(_, 16))
+    ));
+
+    let ids = host_memory.ptr::<[i128]>((32, 2));
+    ids.copy_from_slice(&[i128::MIN, 1]).expect("write i128s");
+    assert_eq!(ids.to_vec(), Ok(vec![i128::MIN, 1]));
+}
+
+#[test]
+fn guest_memory_is_little_endian() {
+    let host_memory = HostMemory::new();
+    let bytes = host_memory.ptr::<[u8]>((0, 8));
+    bytes
+        .copy_from_slice(&[0x78, 0x56, 0x34, 0x12, 0, 0, 0x80, 0x3f])
+        .expect("write bytes");
+    assert_eq!(host_memory.ptr::<u32>(0).read(), Ok(0x1234_5678));
+    assert_eq!(host_memory.ptr::<u16>(2).read(), Ok(0x1234));
+    assert_eq!(host_memory.ptr::<f32>(4).read(), Ok(1.0));
+
+    host_memory.ptr::<i64>(0).write(-2).expect("write i64");
+    assert_eq!(
+        bytes.to_vec(),
+        Ok(vec![0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff])
+    );
+    // Slices of wide primitives are copied element by element on big-endian
+    // hosts, and with a single memcpy on little-endian ones.
+    host_memory
+        .ptr::<[u16]>((0, 2))
+        .copy_from_slice(&[0x0102, 0x0304])
+        .expect("write u16s");
+    assert_eq!(bytes.to_vec().expect("read bytes")[..4], [2, 1, 4, 3]);
+}
+
+struct Unaligned<'a>(&'a HostMemory);
+
+unsafe impl GuestMemory for Unaligned<'_> {
+    fn base(&self) -> (*mut u8, u64) {
+        self.0.base()
+    }
+    fn require_alignment(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn lenient_alignment() {
+    let host_memory = HostMemory::new();
+    assert!(matches!(
+        host_memory.ptr::<u32>(1).write(1),
+        Err(GuestError::PtrNotAligned(_, 4))
+    ));
+
+    let lenient = Unaligned(&host_memory);
+    lenient
+        .ptr::<u32>(1)
+        .write(0xaabb_ccdd)
+        .expect("misaligned write");
+    assert_eq!(lenient.ptr::<u32>(1).read(), Ok(0xaabb_ccdd));
+    assert_eq!(host_memory.ptr::<u8>(1).read(), Ok(0xdd));
+
+    // Misaligned arrays are still copied, one element at a time, but can't be
+    // viewed in place.
+    let arr = lenient.ptr::<[u64]>((3, 2));
+    arr.copy_from_slice(&[1, u64::MAX])
+        .expect("misaligned array write");
+    assert_eq!(arr.to_vec(), Ok(vec![1, u64::MAX]));
+    let mut bc = wiggle::GuestBorrows::new();
+    assert!(matches!(
+        arr.as_raw(&mut bc),
+        Err(GuestError::PtrNotAligned(_, 8))
+    ));
+}
@@ -0,0 +1,94 @@
+use wiggle::{GuestBorrows, GuestError, GuestErrorType, GuestLimit, GuestMemory, GuestPtr};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/strings.witx"],
+    ctx: WasiCtx,
+    limits: { max_string_len: 8, max_call_bytes: 20 },
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> strings::Strings for WasiCtx<'a> {
+    fn hello_string(&self, a_string: &GuestPtr<str>) -> Result<u32, types::Errno> {
+        let mut bc = GuestBorrows::new();
+        let s = a_string
+            .as_raw(&mut bc)
+            .map_err(|e| types::Errno::from_error(e, self))?;
+        Ok(unsafe { (&*s).len() } as u32)
+    }
+
+    fn multi_string(
+        &self,
+        a: &GuestPtr<str>,
+        b: &GuestPtr<str>,
+        c: &GuestPtr<str>,
+    ) -> Result<u32, types::Errno> {
+        let mut bc = GuestBorrows::new();
+        let mut total = 0;
+        for s in [a, b, c].iter() {
+            let s = s
+                .as_raw(&mut bc)
+                .map_err(|e| types::Errno::from_error(e, self))?;
+            total += unsafe { (&*s).len() } as u32;
+        }
+        Ok(total)
+    }
+}
+
+fn write_str(host_memory: &HostMemory, loc: u32, s: &str) {
+    let ptr = host_memory.ptr::<str>((loc.into(), s.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(s.bytes()) {
+        slot.expect("valid pointer").write(byte).expect("write");
+    }
+}
+
+#[test]
+fn string_within_limit() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    write_str(&host_memory, 8, "wiggle");
+    let e = strings::hello_string(&ctx, &host_memory, 8, 6, 0);
+    assert_eq!(e, types::Errno::Ok.into());
+    assert!(ctx.guest_errors.borrow().is_empty());
+}
+
+#[test]
+fn string_over_limit() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    // The string is never looked at, so its contents don't matter.
+    let e = strings::hello_string(&ctx, &host_memory, 8, 4000, 0);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+    assert_eq!(
+        ctx.guest_errors.borrow().as_slice(),
+        &[GuestError::LimitExceeded {
+            limit: GuestLimit::StringLen,
+            requested: 4000,
+            max: 8,
+        }]
+    );
+}
+
+#[test]
+fn call_bytes_over_limit() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    write_str(&host_memory, 8, "abcdefgh");
+    write_str(&host_memory, 16, "ijklmnop");
+    write_str(&host_memory, 24, "qrstuvwx");
+    let e = strings::multi_string(&ctx, &host_memory, 8, 8, 16, 8, 24, 8, 0);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+    assert_eq!(
+        ctx.guest_errors.borrow().as_slice(),
+        &[GuestError::LimitExceeded {
+            limit: GuestLimit::CallBytes,
+            requested: 24,
+            max: 20,
+        }]
+    );
+
+    // Each call gets a fresh budget.
+    let e = strings::hello_string(&ctx, &host_memory, 8, 8, 0);
+    assert_eq!(e, types::Errno::Ok.into());
+}
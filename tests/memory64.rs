@@ -0,0 +1,75 @@
+use wiggle::{GuestBorrows, GuestError, GuestErrorType, GuestMemory, GuestPtr};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/strings.witx"],
+    ctx: WasiCtx,
+    memory64: true,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> strings::Strings for WasiCtx<'a> {
+    fn hello_string(&self, a_string: &GuestPtr<str>) -> Result<u32, types::Errno> {
+        let mut bc = GuestBorrows::new();
+        let s = a_string
+            .as_raw(&mut bc)
+            .map_err(|e| types::Errno::from_error(e, self))?;
+        Ok(unsafe { (&*s).len() } as u32)
+    }
+
+    fn multi_string(
+        &self,
+        _a: &GuestPtr<str>,
+        _b: &GuestPtr<str>,
+        _c: &GuestPtr<str>,
+    ) -> Result<u32, types::Errno> {
+        unimplemented!("multi_string")
+    }
+}
+
+const FOUR_GIB: i64 = 1 << 32;
+
+fn write_str(host_memory: &HostMemory, loc: u64, s: &str) {
+    let ptr = host_memory.ptr::<str>((loc, s.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(s.bytes()) {
+        slot.expect("valid pointer").write(byte).expect("write");
+    }
+}
+
+#[test]
+fn takes_64_bit_pointers() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    write_str(&host_memory, 8, "wiggle");
+    let e = strings::hello_string(&ctx, &host_memory, 8i64, 6i64, 64i64);
+    assert_eq!(e, types::Errno::Ok.into());
+    let len = host_memory.ptr::<u32>(64).read().expect("read result");
+    assert_eq!(len, 6);
+}
+
+#[test]
+fn offsets_are_not_truncated() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    write_str(&host_memory, 8, "wiggle");
+    // Truncated to 32 bits this would be the string above.
+    let e = strings::hello_string(&ctx, &host_memory, FOUR_GIB + 8, 6, 64);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+    assert_eq!(
+        ctx.guest_errors.borrow().as_slice(),
+        &[GuestError::OffsetOutOfBounds {
+            offset: FOUR_GIB as u64 + 8,
+            memory_size: 4096,
+        }]
+    );
+}
+
+#[test]
+fn interface_info() {
+    let host_memory = HostMemory::new();
+    let len = strings::wiggle_interface_info(&host_memory, 0i64, 8i64, 1i64);
+    assert_eq!(len, 1);
+    let len = strings::wiggle_interface_info(&host_memory, FOUR_GIB, 8, 1);
+    assert_eq!(len, -1);
+}
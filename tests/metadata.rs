@@ -0,0 +1,68 @@
+use wiggle::{GuestError, TypeDesc, TypeKind};
+use wiggle_test::{impl_errno, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/union.witx"],
+    ctx: WasiCtx,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> union_example::UnionExample for WasiCtx<'a> {
+    fn get_tag(&self, u: &types::Reason) -> Result<types::Excuse, types::Errno> {
+        Ok(u.tag())
+    }
+    fn reason_mult(
+        &self,
+        _u: &types::ReasonMut<'_>,
+        _multiply_by: u32,
+    ) -> Result<(), types::Errno> {
+        Ok(())
+    }
+}
+
+#[test]
+fn functions() {
+    let names = metadata::FUNCTIONS
+        .iter()
+        .map(|f| (f.module, f.name))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        &[
+            ("union_example", "get_tag"),
+            ("union_example", "reason_mult")
+        ]
+    );
+
+    let reason_mult = metadata::function("union_example", "reason_mult").expect("reason_mult");
+    assert_eq!(
+        reason_mult.to_string(),
+        "reason_mult(r: $reason_mut, multiply_by: u32) -> (error: $errno)"
+    );
+    assert!(metadata::function("union_example", "nope").is_none());
+}
+
+#[test]
+fn types() {
+    let errno = metadata::type_("errno").expect("errno");
+    assert_eq!((errno.size, errno.align), (4, 4));
+    assert_eq!(errno.value_name(1), Some("invalid_arg"));
+
+    let reason_mut = metadata::type_("reason_mut").expect("reason_mut");
+    assert_eq!(reason_mut.size, types::ReasonMut::GUEST_SIZE as usize);
+    match reason_mut.kind {
+        TypeKind::Union { tag, variants } => {
+            assert_eq!(tag, "excuse");
+            assert_eq!(
+                variants[0],
+                (
+                    "dog_ate",
+                    Some(TypeDesc::Pointer(&TypeDesc::Builtin("f32")))
+                )
+            );
+            assert_eq!(variants[2], ("sleeping", None));
+        }
+        kind => panic!("unexpected kind {:?}", kind),
+    }
+}
@@ -0,0 +1,56 @@
+use wiggle::{GuestError, GuestErrorType, MockCall};
+use wiggle_test::HostMemory;
+
+use handle_examples::mock::MockHandleExamples;
+use handle_examples::HandleExamples;
+
+type Ctx = MockHandleExamples;
+
+wiggle::from_witx!({
+    witx: ["tests/handles.witx"],
+    ctx: Ctx,
+    mock: true,
+});
+
+impl GuestErrorType<'_> for types::Errno {
+    type Context = Ctx;
+    fn success() -> types::Errno {
+        types::Errno::Ok
+    }
+    fn from_error(_e: GuestError, _ctx: &Ctx) -> types::Errno {
+        types::Errno::InvalidArg
+    }
+}
+
+#[test]
+fn records_calls() {
+    let mock = MockHandleExamples::new(types::Errno::DontWantTo);
+    assert_eq!(mock.fd_create(), Err(types::Errno::DontWantTo));
+    assert_eq!(
+        mock.fd_consume(types::Fd::from(5)),
+        Err(types::Errno::DontWantTo)
+    );
+    assert_eq!(
+        mock.calls(),
+        &[
+            MockCall {
+                func: "fd_create",
+                args: vec![],
+            },
+            MockCall {
+                func: "fd_consume",
+                args: vec![format!("{:?}", types::Fd::from(5))],
+            },
+        ]
+    );
+}
+
+#[test]
+fn serves_as_the_ctx() {
+    let mut mock = MockHandleExamples::new(types::Errno::DontWantTo);
+    mock.errno = types::Errno::PhysicallyUnable;
+    let host_memory = HostMemory::new();
+    let ret = handle_examples::fd_consume(&mock, &host_memory, 5);
+    assert_eq!(ret, types::Errno::PhysicallyUnable as i32);
+    assert_eq!(mock.calls().len(), 1);
+}
@@ -0,0 +1,56 @@
+use wiggle::{GuestError, GuestMemory, GuestPtr, GuestType};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/atoms.witx"],
+    ctx: WasiCtx,
+    memory: HostMemory,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> atoms::Atoms for WasiCtx<'a> {
+    fn int_float_args(&self, _an_int: u32, _an_float: f32) -> Result<(), types::Errno> {
+        Ok(())
+    }
+    fn double_int_return_float(&self, an_int: u32) -> Result<types::AliasToFloat, types::Errno> {
+        Ok((an_int as f32) * 2.0)
+    }
+}
+
+#[test]
+fn trampolines_take_the_memory_type() {
+    let f: fn(&WasiCtx, &HostMemory, i32, i32) -> i32 = atoms::double_int_return_float;
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let e = f(&ctx, &host_memory, 21, 8);
+    assert_eq!(e, types::Errno::Ok.into());
+    let doubled = host_memory.ptr::<f32>(8).read().expect("read result");
+    assert_eq!(doubled, 42.0);
+
+    let e = f(&ctx, &host_memory, 21, 4096);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+}
+
+#[test]
+fn pointers_to_a_memory_type() {
+    let host_memory = HostMemory::new();
+    let ptr: GuestPtr<u32, HostMemory> = GuestPtr::new(&host_memory, 8);
+    ptr.write(0xdead_beef).expect("write");
+    assert_eq!(ptr.read(), Ok(0xdead_beef));
+    assert_eq!(<u32 as GuestType>::read(&ptr.add(1).expect("add")), Ok(0));
+
+    // Erasing the memory type gives back the pointer generated host traits
+    // are handed.
+    let erased: GuestPtr<u32> = ptr.erase();
+    assert_eq!(erased.read(), Ok(0xdead_beef));
+
+    let oob: GuestPtr<[u8], HostMemory> = GuestPtr::new(&host_memory, (4000, 100));
+    assert!(matches!(
+        oob.as_slice(),
+        Err(GuestError::LenOutOfBounds {
+            memory_size: 4096,
+            ..
+        })
+    ));
+}
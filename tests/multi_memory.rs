@@ -0,0 +1,83 @@
+use wiggle::{GuestError, GuestErrorType, GuestMemory, GuestPtr, MemoryProvider};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/strings.witx"],
+    ctx: WasiCtx,
+    multi_memory: true,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> strings::Strings for WasiCtx<'a> {
+    fn hello_string(&self, a_string: &GuestPtr<str>) -> Result<u32, types::Errno> {
+        assert_eq!(a_string.memory_index(), 1);
+        let s = a_string
+            .as_str()
+            .map_err(|e| types::Errno::from_error(e, self))?;
+        Ok(s.len() as u32)
+    }
+
+    fn multi_string(
+        &self,
+        a: &GuestPtr<str>,
+        b: &GuestPtr<str>,
+        c: &GuestPtr<str>,
+    ) -> Result<u32, types::Errno> {
+        let strs = [a, b, c]
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| types::Errno::from_error(e, self))?;
+        Ok(strs.iter().map(|s| s.len() as u32).sum())
+    }
+}
+
+fn write_str(host_memory: &HostMemory, loc: u64, s: &str) {
+    let ptr = host_memory.ptr::<str>((loc, s.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(s.bytes()) {
+        slot.expect("valid pointer").write(byte).expect("write");
+    }
+}
+
+#[test]
+fn pointers_are_in_the_given_memory() {
+    let ctx = WasiCtx::new();
+    let memories = [HostMemory::new(), HostMemory::new()];
+    write_str(&memories[1], 8, "wiggle");
+    let e = strings::hello_string(&ctx, &memories, 1, 8, 6, 64);
+    assert_eq!(e, types::Errno::Ok.into());
+    let len = memories[1].ptr::<u32>(64).read().expect("read result");
+    assert_eq!(len, 6);
+    let untouched = memories[0].ptr::<u32>(64).read().expect("read");
+    assert_eq!(untouched, 0);
+}
+
+#[test]
+fn unknown_memories_are_errors() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let memories: Vec<&dyn GuestMemory> = vec![&host_memory];
+    let provider: &dyn MemoryProvider = &memories;
+    let e = strings::hello_string(&ctx, provider, 1, 8, 6, 64);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+    match ctx.guest_errors.borrow().as_slice() {
+        [GuestError::InFunc { location, err, .. }] => {
+            assert_eq!(*location, "memory_index");
+            assert_eq!(**err, GuestError::UnknownMemory(1));
+        }
+        errors => panic!("unexpected errors {:?}", errors),
+    };
+}
+
+#[test]
+fn strings_share_a_memory() {
+    let ctx = WasiCtx::new();
+    let memories = [HostMemory::new(), HostMemory::new()];
+    write_str(&memories[0], 8, "wiggle");
+    write_str(&memories[0], 16, "wasm");
+    let e = strings::multi_string(&ctx, &memories, 0, 8, 6, 16, 4, 8, 6, 64);
+    assert_eq!(e, types::Errno::Ok.into());
+    assert_eq!(memories[0].ptr::<u32>(64).read(), Ok(16));
+    assert_eq!(memories[1].ptr::<u32>(64).read(), Ok(0));
+}
@@ -0,0 +1,49 @@
+use wiggle::{GuestError, Value};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/handles.witx"],
+    ctx: WasiCtx,
+    multi_value: true,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> handle_examples::HandleExamples for WasiCtx<'a> {
+    fn fd_create(&self) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(7))
+    }
+    fn fd_consume(&self, fd: types::Fd) -> Result<(), types::Errno> {
+        if fd == types::Fd::from(7) {
+            Ok(())
+        } else {
+            Err(types::Errno::InvalidArg)
+        }
+    }
+}
+
+#[test]
+fn results_are_returned() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+
+    // No pointer to write the fd to is taken.
+    let (errno, fd) = handle_examples::fd_create(&ctx, &host_memory);
+    assert_eq!(errno, types::Errno::Ok as i32);
+    assert_eq!(fd, 7);
+
+    let errno = handle_examples::fd_consume(&ctx, &host_memory, 8);
+    assert_eq!(errno, types::Errno::InvalidArg as i32);
+}
+
+#[test]
+fn dispatch_returns_every_value() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let results = handle_examples::dispatch("fd_create", &ctx, &host_memory, &[])
+        .expect("dispatch fd_create");
+    assert_eq!(
+        results,
+        &[Value::I32(types::Errno::Ok as i32), Value::I32(7)]
+    );
+}
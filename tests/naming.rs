@@ -0,0 +1,109 @@
+use wiggle::{GuestError, GuestMemory};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx_literal: r#"
+        (typename $Errno (enum u32 $Ok $InvalidArg $2big))
+        (typename $Mode (flags u8 $Read $Write))
+        (typename $Span (struct (field $len u32)))
+        (module $Api
+          (@interface func (export "getMode")
+            (param $span $Span)
+            (result $error $Errno)
+            (result $mode $Mode)))
+    "#,
+    ctx: WasiCtx,
+    naming: {
+        types: preserve,
+        variants: preserve,
+        functions: preserve,
+        flags: shouty,
+        rename: { "2big": TooLarge, Mode: Permissions },
+    },
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> api::Api for WasiCtx<'a> {
+    #[allow(non_snake_case)]
+    fn getMode(&self, span: &types::Span) -> Result<types::Permissions, types::Errno> {
+        match span.len {
+            0 => Err(types::Errno::InvalidArg),
+            1 => Ok(types::Permissions::READ),
+            _ => Err(types::Errno::TooLarge),
+        }
+    }
+}
+
+#[test]
+fn configured_names() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    host_memory
+        .ptr::<types::Span>(0)
+        .write(types::Span { len: 1 })
+        .expect("write span");
+    let e = api::getMode(&ctx, &host_memory, 0, 8);
+    assert_eq!(e, types::Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<u8>(8).read(), Ok(1));
+
+    host_memory
+        .ptr::<types::Span>(0)
+        .write(types::Span { len: 2 })
+        .expect("write span");
+    let e = api::getMode(&ctx, &host_memory, 0, 8);
+    assert_eq!(e, types::Errno::TooLarge.into());
+}
+
+mod prefixed {
+    // No module uses the ctx; it's only required by the config.
+    #[allow(unused_imports)]
+    use wiggle_test::WasiCtx;
+
+    wiggle::from_witx!({
+        witx_literal: r#"
+            (typename $errno (enum u16 $success $badf $2big))
+            (typename $whence (enum u8 $set $cur))
+        "#,
+        ctx: WasiCtx,
+        naming: {
+            variant_prefixes: { errno: E },
+            rename: { "2big": E2Big },
+        },
+    });
+}
+
+#[test]
+fn variant_prefixes() {
+    use prefixed::types::{Errno, Whence};
+    assert_eq!(Errno::ESuccess as u16, 0);
+    assert_eq!(Errno::EBadf as u16, 1);
+    assert_eq!(Errno::E2Big as u16, 2);
+    assert_eq!(Whence::Set as u8, 0);
+    assert_eq!(Whence::Cur as u8, 1);
+}
+
+#[test]
+fn enum_names_are_witx_names() {
+    use prefixed::types::Errno;
+    assert_eq!(Errno::EBadf.name(), "badf");
+    assert_eq!(Errno::E2Big.name(), "2big");
+    assert_eq!(Errno::from_name("2big"), Some(Errno::E2Big));
+    assert_eq!(Errno::from_name("E2Big"), None);
+    assert_eq!("success".parse(), Ok(Errno::ESuccess));
+    assert_eq!(
+        "nope".parse::<Errno>().unwrap_err().to_string(),
+        "`nope` isn't a name of Errno"
+    );
+}
+
+#[test]
+fn enum_variants() {
+    use prefixed::types::{Errno, Whence};
+    assert_eq!(Errno::ALL, [Errno::ESuccess, Errno::EBadf, Errno::E2Big]);
+    assert_eq!(Errno::VARIANT_COUNT, 3);
+    assert_eq!(
+        Whence::iter().map(|w| w.name()).collect::<Vec<_>>(),
+        ["set", "cur"]
+    );
+}
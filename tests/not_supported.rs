@@ -0,0 +1,33 @@
+use wiggle::GuestError;
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/handles.witx"],
+    ctx: WasiCtx,
+    not_supported: { errno: physically_unable },
+});
+
+impl_errno!(types::Errno);
+
+// Only `fd_create` is supported; `fd_consume` is left to its default.
+impl<'a> handle_examples::HandleExamples for WasiCtx<'a> {
+    fn fd_create(&self) -> Result<types::Fd, types::Errno> {
+        Ok(types::Fd::from(1))
+    }
+}
+
+#[test]
+fn unimplemented_methods_return_not_supported() {
+    use handle_examples::HandleExamples;
+
+    let ctx = WasiCtx::new();
+    assert_eq!(ctx.fd_create(), Ok(types::Fd::from(1)));
+    assert_eq!(
+        ctx.fd_consume(types::Fd::from(1)),
+        Err(types::Errno::PhysicallyUnable)
+    );
+
+    let host_memory = HostMemory::new();
+    let ret = handle_examples::fd_consume(&ctx, &host_memory, 1);
+    assert_eq!(ret, types::Errno::PhysicallyUnable as i32);
+}
@@ -0,0 +1,84 @@
+use wiggle::{GuestError, GuestMemory, GuestPtr, GuestType};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/structs.witx"],
+    ctx: WasiCtx,
+    nullable: [pair_int_and_ptr.first, pair_int_ptrs.second],
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> structs::Structs for WasiCtx<'a> {
+    fn sum_of_pair(&self, an_pair: &types::PairInts) -> Result<i64, types::Errno> {
+        Ok(an_pair.first as i64 + an_pair.second as i64)
+    }
+
+    fn sum_of_pair_of_ptrs(&self, an_pair: &types::PairIntPtrs) -> Result<i64, types::Errno> {
+        let first = an_pair.first.read().map_err(|_| types::Errno::InvalidArg)?;
+        let second = match &an_pair.second {
+            Some(second) => second.read().map_err(|_| types::Errno::InvalidArg)?,
+            None => 0,
+        };
+        Ok(first as i64 + second as i64)
+    }
+
+    fn sum_of_int_and_ptr(&self, an_pair: &types::PairIntAndPtr) -> Result<i64, types::Errno> {
+        let first = match &an_pair.first {
+            Some(first) => first.read().map_err(|_| types::Errno::InvalidArg)?,
+            None => 0,
+        };
+        Ok(first as i64 + an_pair.second as i64)
+    }
+
+    fn return_pair_ints(&self) -> Result<types::PairInts, types::Errno> {
+        unimplemented!("return_pair_ints")
+    }
+
+    fn return_pair_of_ptrs<'b>(
+        &self,
+        first: GuestPtr<'b, i32>,
+        second: GuestPtr<'b, i32>,
+    ) -> Result<types::PairIntPtrs<'b>, types::Errno> {
+        Ok(types::PairIntPtrs {
+            first,
+            second: Some(second),
+        })
+    }
+}
+
+#[test]
+fn null_pointer_members() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    host_memory.ptr(8).write(40i32).expect("write int");
+
+    // { first: null, second: 2 }
+    host_memory.ptr(16).write(0u32).expect("write null first");
+    host_memory.ptr(20).write(2i32).expect("write second");
+    let e = structs::sum_of_int_and_ptr(&ctx, &host_memory, 16, 32);
+    assert_eq!(e, types::Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<i64>(32).read(), Ok(2));
+
+    // { first: &40, second: 2 }
+    host_memory.ptr(16).write(8u32).expect("write first");
+    let e = structs::sum_of_int_and_ptr(&ctx, &host_memory, 16, 32);
+    assert_eq!(e, types::Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<i64>(32).read(), Ok(42));
+}
+
+#[test]
+fn write_nullable_members() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let e = structs::return_pair_of_ptrs(&ctx, &host_memory, 8, 12, 16);
+    assert_eq!(e, types::Errno::Ok.into());
+    let pair = types::PairIntPtrs::read(&host_memory.ptr(16)).expect("read pair");
+    assert_eq!(pair.first.offset(), 8);
+    assert_eq!(pair.second.map(|p| p.offset()), Some(12));
+
+    let ptr: GuestPtr<Option<GuestPtr<i32>>> = host_memory.ptr(24);
+    ptr.write(None).expect("write null");
+    assert_eq!(host_memory.ptr::<u32>(24).read(), Ok(0));
+    assert!(ptr.read().expect("read null").is_none());
+}
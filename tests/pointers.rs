@@ -1,10 +1,11 @@
 use proptest::prelude::*;
-use wiggle_runtime::{GuestError, GuestMemory, GuestPtr};
+use wiggle::{GuestError, GuestMemory, GuestPtr};
 use wiggle_test::{impl_errno, HostMemory, MemArea, WasiCtx};
 
 wiggle::from_witx!({
     witx: ["tests/pointers.witx"],
     ctx: WasiCtx,
+    proptest: true,
 });
 
 impl_errno!(types::Errno);
@@ -61,15 +62,6 @@ impl<'a> pointers::Pointers for WasiCtx<'a> {
     }
 }
 
-fn excuse_strat() -> impl Strategy<Value = types::Excuse> {
-    prop_oneof![
-        Just(types::Excuse::DogAte),
-        Just(types::Excuse::Traffic),
-        Just(types::Excuse::Sleeping),
-    ]
-    .boxed()
-}
-
 #[derive(Debug)]
 struct PointersAndEnumsExercise {
     pub input1: types::Excuse,
@@ -85,12 +77,12 @@ struct PointersAndEnumsExercise {
 impl PointersAndEnumsExercise {
     pub fn strat() -> BoxedStrategy<Self> {
         (
-            excuse_strat(),
-            excuse_strat(),
+            any::<types::Excuse>(),
+            any::<types::Excuse>(),
             HostMemory::mem_area_strat(4),
-            excuse_strat(),
+            any::<types::Excuse>(),
             HostMemory::mem_area_strat(4),
-            excuse_strat(),
+            any::<types::Excuse>(),
             HostMemory::mem_area_strat(4),
             HostMemory::mem_area_strat(4),
         )
@@ -116,7 +108,7 @@ impl PointersAndEnumsExercise {
                 },
             )
             .prop_filter("non-overlapping pointers", |e| {
-                MemArea::non_overlapping_set(&[
+                MemArea::non_overlapping_set([
                     e.input2_loc,
                     e.input3_loc,
                     e.input4_loc,
@@ -130,22 +122,22 @@ impl PointersAndEnumsExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input2_loc.ptr)
+            .ptr(self.input2_loc.ptr.into())
             .write(self.input2)
             .expect("input2 ref_mut");
 
         host_memory
-            .ptr(self.input3_loc.ptr)
+            .ptr(self.input3_loc.ptr.into())
             .write(self.input3)
             .expect("input3 ref_mut");
 
         host_memory
-            .ptr(self.input4_loc.ptr)
+            .ptr(self.input4_loc.ptr.into())
             .write(self.input4)
             .expect("input4 ref_mut");
 
         host_memory
-            .ptr(self.input4_ptr_loc.ptr)
+            .ptr(self.input4_ptr_loc.ptr.into())
             .write(self.input4_loc.ptr)
             .expect("input4 ptr ref_mut");
 
@@ -161,7 +153,7 @@ impl PointersAndEnumsExercise {
 
         // Implementation of pointers_and_enums writes input3 to the input2_loc:
         let written_to_input2_loc: i32 = host_memory
-            .ptr(self.input2_loc.ptr)
+            .ptr(self.input2_loc.ptr.into())
             .read()
             .expect("input2 ref");
 
@@ -173,7 +165,7 @@ impl PointersAndEnumsExercise {
 
         // Implementation of pointers_and_enums writes input2_loc to input4_ptr_loc:
         let written_to_input4_ptr: u32 = host_memory
-            .ptr(self.input4_ptr_loc.ptr)
+            .ptr(self.input4_ptr_loc.ptr.into())
             .read()
             .expect("input4_ptr_loc ref");
 
@@ -0,0 +1,86 @@
+use wiggle::{GuestError, GuestMemory, GuestPtr, Policy, PolicyCall, PolicyCtx, PolicyDecision};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/policy.witx"],
+    ctx: WasiCtx,
+    policy: Errno,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> policy_examples::PolicyExamples for WasiCtx<'a> {
+    fn path_open(&self, dirfd: types::Fd, path: &GuestPtr<str>) -> Result<types::Fd, types::Errno> {
+        assert_ne!(path.as_bytes().len(), 0);
+        Ok(types::Fd::from(u32::from(dirfd) + 1))
+    }
+    fn fd_close(&self, _fd: types::Fd) -> Result<(), types::Errno> {
+        Ok(())
+    }
+}
+
+/// Keeps guests out of `/etc` and away from fd 0.
+struct Lockdown;
+
+impl Policy<types::Errno> for Lockdown {
+    fn check(&self, call: &PolicyCall) -> PolicyDecision<types::Errno> {
+        match call.function {
+            "path_open" => {
+                let path = call.arg("path").and_then(|p| p.as_str()).unwrap_or("");
+                if path.starts_with("/etc") {
+                    PolicyDecision::Deny(types::Errno::DontWantTo)
+                } else {
+                    PolicyDecision::Allow
+                }
+            }
+            "fd_close" if call.arg("fd").and_then(|fd| fd.as_unsigned()) == Some(0) => {
+                PolicyDecision::Trap
+            }
+            _ => PolicyDecision::Allow,
+        }
+    }
+}
+
+impl<'a> PolicyCtx<types::Errno> for WasiCtx<'a> {
+    fn policy(&self) -> Option<&dyn Policy<types::Errno>> {
+        Some(&Lockdown)
+    }
+}
+
+fn path_open(host_memory: &HostMemory, path: &str) -> i32 {
+    let ctx = WasiCtx::new();
+    let path_loc = 8;
+    let ptr = host_memory.ptr::<str>((path_loc, path.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(path.bytes()) {
+        slot.expect("valid pointer")
+            .write(byte)
+            .expect("failed to write");
+    }
+    policy_examples::path_open(&ctx, host_memory, 3, path_loc as i32, path.len() as i32, 0)
+}
+
+#[test]
+fn policy_allows_call() {
+    let host_memory = HostMemory::new();
+    let res = path_open(&host_memory, "/home/guest");
+    assert_eq!(res, types::Errno::Ok.into());
+    let opened = host_memory.ptr::<u32>(0).read().expect("read result");
+    assert_eq!(opened, 4);
+}
+
+#[test]
+fn policy_denies_call() {
+    let host_memory = HostMemory::new();
+    let res = path_open(&host_memory, "/etc/passwd");
+    assert_eq!(res, types::Errno::DontWantTo.into());
+    let opened = host_memory.ptr::<u32>(0).read().expect("read result");
+    assert_eq!(opened, 0, "result not written");
+}
+
+#[test]
+#[should_panic(expected = "policy trapped call to `fd_close`")]
+fn policy_traps_call() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    policy_examples::fd_close(&ctx, &host_memory, 0);
+}
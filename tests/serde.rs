@@ -0,0 +1,78 @@
+use serde_json::json;
+
+wiggle::from_witx!({
+    witx_literal: r#"
+        (typename $errno (enum u16 $success $badf $2big))
+        (typename $rights (flags u8 $fd_read $fd_write $fd_seek))
+        (typename $cookie (int u64 (const $start 0)))
+        (typename $fd (handle))
+        (typename $fdstat
+          (struct
+            (field $fs_flags $errno)
+            (field $fs_rights_base $rights)
+            (field $fs_cookie $cookie)
+            (field $fs_fd $fd)
+            (field $fs_size u64)))
+        (typename $iovec
+          (struct
+            (field $buf (@witx pointer u8))
+            (field $buf_len u32)))
+        (typename $whence (enum u8 $set $cur))
+        (typename $event
+          (union $whence
+            (field $set $fdstat)
+            (empty $cur)))
+        (typename $subscription
+          (struct
+            (field $event $event)))
+    "#,
+    ctx: WasiCtx,
+    serde: true,
+});
+
+#[test]
+fn enums_are_witx_names() {
+    assert_eq!(json!(types::Errno::Badf), json!("badf"));
+    assert_eq!(json!(types::Errno::TooBig), json!("2big"));
+    let errno: types::Errno = serde_json::from_value(json!("success")).unwrap();
+    assert_eq!(errno, types::Errno::Success);
+    assert!(serde_json::from_value::<types::Errno>(json!("nope")).is_err());
+}
+
+#[test]
+fn flags_are_lists_of_witx_names() {
+    let rights = types::Rights::FD_READ | types::Rights::FD_SEEK;
+    assert_eq!(json!(rights), json!(["fd_read", "fd_seek"]));
+    assert_eq!(json!(types::Rights::EMPTY_FLAGS), json!([]));
+    let rights: types::Rights = serde_json::from_value(json!(["fd_write", "fd_read"])).unwrap();
+    assert_eq!(rights, types::Rights::FD_READ | types::Rights::FD_WRITE);
+    let err = serde_json::from_value::<types::Rights>(json!(["fd_tell"])).unwrap_err();
+    assert!(
+        err.to_string().contains("unknown variant `fd_tell`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn structs_are_maps_of_witx_names() {
+    let fdstat = types::Fdstat {
+        fs_flags: types::Errno::Badf,
+        fs_rights_base: types::Rights::FD_WRITE,
+        fs_cookie: types::Cookie::START,
+        fs_fd: types::Fd::from(3u32),
+        fs_size: 1024,
+    };
+    let value = json!({
+        "fs_flags": "badf",
+        "fs_rights_base": ["fd_write"],
+        "fs_cookie": 0,
+        "fs_fd": 3,
+        "fs_size": 1024,
+    });
+    assert_eq!(serde_json::to_value(fdstat).unwrap(), value);
+    assert_eq!(
+        serde_json::from_value::<types::Fdstat>(value).unwrap(),
+        fdstat
+    );
+}
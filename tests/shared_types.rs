@@ -0,0 +1,101 @@
+use wiggle::{GuestMemory, GuestPtr};
+use wiggle_test::{HostMemory, WasiCtx};
+
+// Both documents `use` the same file of types, which is only generated once.
+mod merged {
+    use wiggle::GuestError;
+    use wiggle_test::{impl_errno, WasiCtx};
+
+    wiggle::from_witx!({
+        witx: ["tests/shapes.witx", "tests/paths.witx"],
+        ctx: WasiCtx,
+    });
+
+    impl_errno!(types::Errno);
+}
+
+// A separate invocation for one of the documents, sharing the types above.
+mod reusing {
+    use wiggle_test::WasiCtx;
+
+    wiggle::from_witx!({
+        witx: ["tests/paths.witx"],
+        ctx: WasiCtx,
+        types: crate::merged::types,
+    });
+}
+
+use merged::types::{Errno, Point};
+
+fn read_point(p: &GuestPtr<Point>) -> Result<Point, Errno> {
+    p.read().map_err(|_| Errno::InvalidArg)
+}
+
+impl<'a> merged::shapes::Shapes for WasiCtx<'a> {
+    fn area<'b>(&self, corner: GuestPtr<'b, Point>) -> Result<i32, Errno> {
+        let corner = read_point(&corner)?;
+        Ok(corner.x * corner.y)
+    }
+}
+
+fn distance(from: &GuestPtr<Point>, to: &GuestPtr<Point>) -> Result<i32, Errno> {
+    let (from, to) = (read_point(from)?, read_point(to)?);
+    Ok((to.x - from.x).abs() + (to.y - from.y).abs())
+}
+
+impl<'a> merged::paths::Paths for WasiCtx<'a> {
+    fn distance<'b>(
+        &self,
+        from: GuestPtr<'b, Point>,
+        to: GuestPtr<'b, Point>,
+    ) -> Result<i32, Errno> {
+        distance(&from, &to)
+    }
+}
+
+// The re-using invocation's trait is implemented with the shared types.
+impl<'a> reusing::paths::Paths for WasiCtx<'a> {
+    fn distance<'b>(
+        &self,
+        from: GuestPtr<'b, Point>,
+        to: GuestPtr<'b, Point>,
+    ) -> Result<i32, Errno> {
+        distance(&from, &to)
+    }
+}
+
+fn write_points(host_memory: &HostMemory) {
+    host_memory
+        .ptr::<Point>(0)
+        .write(Point { x: 1, y: 2 })
+        .expect("write");
+    host_memory
+        .ptr::<Point>(8)
+        .write(Point { x: 4, y: 6 })
+        .expect("write");
+}
+
+#[test]
+fn modules_from_several_documents() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    write_points(&host_memory);
+
+    let e = merged::shapes::area(&ctx, &host_memory, 8, 16);
+    assert_eq!(e, Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<i32>(16).read(), Ok(24));
+
+    let e = merged::paths::distance(&ctx, &host_memory, 0, 8, 16);
+    assert_eq!(e, Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<i32>(16).read(), Ok(7));
+}
+
+#[test]
+fn reused_types_module() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    write_points(&host_memory);
+    let e = reusing::paths::distance(&ctx, &host_memory, 0, 8, 16);
+    assert_eq!(e, Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<i32>(16).read(), Ok(7));
+}
@@ -0,0 +1,75 @@
+use wiggle::{GuestError, GuestMemory};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/union.witx"],
+    ctx: WasiCtx,
+    split_traits: { union_example: { Tags: [get_tag] } },
+    mock: true,
+});
+
+impl_errno!(types::Errno);
+
+// The parts of the module trait are implemented separately.
+impl<'a> union_example::Tags for WasiCtx<'a> {
+    fn get_tag(&self, u: &types::Reason) -> Result<types::Excuse, types::Errno> {
+        Ok(u.tag())
+    }
+}
+
+impl<'a> union_example::UnionExampleOther for WasiCtx<'a> {
+    fn reason_mult(
+        &self,
+        _u: &types::ReasonMut<'_>,
+        _multiply_by: u32,
+    ) -> Result<(), types::Errno> {
+        Err(types::Errno::DontWantTo)
+    }
+}
+
+fn tag_of(ctx: &impl union_example::UnionExample, reason: &types::Reason) -> types::Excuse {
+    ctx.get_tag(reason).expect("get_tag")
+}
+
+#[test]
+fn module_trait_combines_parts() {
+    let ctx = WasiCtx::new();
+    assert_eq!(
+        tag_of(&ctx, &types::Reason::Sleeping),
+        types::Excuse::Sleeping
+    );
+
+    let boxed: Box<dyn union_example::UnionExample> = Box::new(WasiCtx::new());
+    assert_eq!(
+        tag_of(&boxed, &types::Reason::Traffic(1)),
+        types::Excuse::Traffic
+    );
+}
+
+#[test]
+fn generated_functions_call_the_parts() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    host_memory
+        .ptr::<types::Reason>(0)
+        .write(types::Reason::DogAte(1.0))
+        .expect("write reason");
+    let e = union_example::get_tag(&ctx, &host_memory, 0, 16);
+    assert_eq!(e, types::Errno::Ok as i32);
+    let tag = host_memory
+        .ptr::<types::Excuse>(16)
+        .read()
+        .expect("read tag");
+    assert_eq!(tag, types::Excuse::DogAte);
+}
+
+#[test]
+fn mock_implements_parts() {
+    let mock = union_example::mock::MockUnionExample::new(types::Errno::PhysicallyUnable);
+    assert_eq!(tag_of_mock(&mock), Err(types::Errno::PhysicallyUnable));
+    assert_eq!(mock.calls()[0].func, "get_tag");
+}
+
+fn tag_of_mock(ctx: &impl union_example::UnionExample) -> Result<types::Excuse, types::Errno> {
+    ctx.get_tag(&types::Reason::Sleeping)
+}
@@ -1,5 +1,5 @@
 use proptest::prelude::*;
-use wiggle_runtime::{GuestBorrows, GuestError, GuestMemory, GuestPtr};
+use wiggle::{CallMemory, GuestBorrows, GuestError, GuestMemory, GuestPtr, Region};
 use wiggle_test::{impl_errno, HostMemory, MemArea, MemAreas, WasiCtx};
 
 wiggle::from_witx!({
@@ -15,7 +15,7 @@ impl<'a> strings::Strings for WasiCtx<'a> {
         let s = a_string.as_raw(&mut bc).expect("should be valid string");
         unsafe {
             println!("a_string='{}'", &*s);
-            Ok((*s).len() as u32)
+            Ok((&*s).len() as u32)
         }
     }
 
@@ -67,7 +67,7 @@ impl HelloStringExercise {
                 return_ptr_loc,
             })
             .prop_filter("non-overlapping pointers", |e| {
-                MemArea::non_overlapping_set(&[e.string_ptr_loc, e.return_ptr_loc])
+                MemArea::non_overlapping_set([e.string_ptr_loc, e.return_ptr_loc])
             })
             .boxed()
     }
@@ -77,7 +77,8 @@ impl HelloStringExercise {
         let host_memory = HostMemory::new();
 
         // Populate string in guest's memory
-        let ptr = host_memory.ptr::<str>((self.string_ptr_loc.ptr, self.test_word.len() as u32));
+        let ptr =
+            host_memory.ptr::<str>((self.string_ptr_loc.ptr.into(), self.test_word.len() as u64));
         for (slot, byte) in ptr.as_bytes().iter().zip(self.test_word.bytes()) {
             slot.expect("should be valid pointer")
                 .write(byte)
@@ -94,7 +95,7 @@ impl HelloStringExercise {
         assert_eq!(res, types::Errno::Ok.into(), "hello string errno");
 
         let given = host_memory
-            .ptr::<u32>(self.return_ptr_loc.ptr)
+            .ptr::<u32>(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref ptr to return value");
         assert_eq!(self.test_word.len() as u32, given);
@@ -183,7 +184,7 @@ impl MultiStringExercise {
         let host_memory = HostMemory::new();
 
         let write_string = |val: &str, loc: MemArea| {
-            let ptr = host_memory.ptr::<str>((loc.ptr, val.len() as u32));
+            let ptr = host_memory.ptr::<str>((loc.ptr.into(), val.len() as u64));
             for (slot, byte) in ptr.as_bytes().iter().zip(val.bytes()) {
                 slot.expect("should be valid pointer")
                     .write(byte)
@@ -209,7 +210,7 @@ impl MultiStringExercise {
         assert_eq!(res, types::Errno::Ok.into(), "multi string errno");
 
         let given = host_memory
-            .ptr::<u32>(self.return_ptr_loc.ptr)
+            .ptr::<u32>(self.return_ptr_loc.ptr.into())
             .read()
             .expect("deref ptr to return value");
         assert_eq!((self.a.len() + self.b.len() + self.c.len()) as u32, given);
@@ -221,3 +222,186 @@ proptest! {
         e.test()
     }
 }
+
+#[test]
+fn string_bytes_are_not_validated() {
+    let host_memory = HostMemory::new();
+    let bytes = b"not \xff utf-8";
+
+    let ptr = host_memory.ptr::<str>((0, bytes.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(bytes.iter()) {
+        slot.expect("should be valid pointer")
+            .write(*byte)
+            .expect("failed to write");
+    }
+
+    let mut bc = GuestBorrows::new();
+    let raw = ptr.as_raw_bytes(&mut bc).expect("bytes are in bounds");
+    assert_eq!(unsafe { &*raw }, &bytes[..]);
+
+    let mut bc = GuestBorrows::new();
+    let e = ptr.as_raw(&mut bc).expect_err("invalid utf-8");
+    assert_eq!(
+        e.to_string(),
+        "Invalid UTF-8 encountered in string at Region { start: 0, len: 11 }: \
+         invalid utf-8 sequence of 1 bytes from index 4"
+    );
+    match e {
+        GuestError::InvalidUtf8 { region, err } => {
+            assert_eq!(region, Region { start: 0, len: 11 });
+            assert_eq!(err.valid_up_to(), 4);
+            assert_eq!(err.error_len(), Some(1));
+        }
+        e => panic!("expected invalid utf-8 error, got {:?}", e),
+    }
+}
+
+#[test]
+fn call_memory_shares_borrows() {
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let ptr = GuestPtr::<str>::new(&call_memory, (0, 4));
+
+    // Each view is made with its own `GuestBorrows`, but they're both made
+    // through the same call so the second one still conflicts.
+    let mut bc1 = GuestBorrows::new();
+    let _s1 = ptr.as_raw(&mut bc1).expect("first borrow");
+    let mut bc2 = GuestBorrows::new();
+    assert!(matches!(
+        ptr.as_raw(&mut bc2),
+        Err(GuestError::PtrBorrowed(_))
+    ));
+
+    // Borrows outside of a call aren't tracked beyond their `GuestBorrows`.
+    let ptr = host_memory.ptr::<str>((0, 4));
+    let mut bc3 = GuestBorrows::new();
+    ptr.as_raw(&mut bc3).expect("untracked borrow");
+}
+
+#[test]
+fn str_guard() {
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let text = "καλημέρα";
+    let ptr = call_memory.ptr::<str>((0, text.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(text.bytes()) {
+        slot.expect("valid pointer").write(byte).expect("write");
+    }
+
+    {
+        let s = ptr.as_str().expect("valid string");
+        assert_eq!(&*s, text);
+        // Strings are borrowed shared, so can be viewed twice but not
+        // modified.
+        assert_eq!(&*ptr.as_str().expect("borrow twice"), text);
+        assert!(matches!(
+            ptr.as_bytes().as_slice_mut(),
+            Err(GuestError::PtrBorrowed(_))
+        ));
+    }
+    ptr.as_bytes()
+        .as_slice_mut()
+        .expect("borrows were released");
+
+    // Cut the string off in the middle of a character.
+    let ptr = call_memory.ptr::<str>((0, 3));
+    assert!(matches!(ptr.as_str(), Err(GuestError::InvalidUtf8 { .. })));
+    ptr.as_bytes()
+        .as_slice_mut()
+        .expect("failed borrow was released");
+}
+
+/// Caps copies out of memory at 8 bytes.
+struct Capped<'a>(&'a HostMemory);
+
+unsafe impl GuestMemory for Capped<'_> {
+    fn base(&self) -> (*mut u8, u64) {
+        self.0.base()
+    }
+    fn max_copy_len(&self) -> Option<u64> {
+        Some(8)
+    }
+}
+
+#[test]
+fn copy_length_limits() {
+    let host_memory = HostMemory::new();
+    let text = "hello, world";
+    let ptr = host_memory.ptr::<str>((0, text.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(text.bytes()) {
+        slot.expect("valid pointer").write(byte).expect("write");
+    }
+    assert_eq!(ptr.to_string().expect("unlimited"), text);
+    assert_eq!(ptr.to_string_limited(12).expect("at limit"), text);
+    assert_eq!(
+        ptr.to_string_limited(11),
+        Err(GuestError::TooLarge { len: 12, max: 11 })
+    );
+    assert_eq!(
+        ptr.as_bytes().to_vec_limited(4),
+        Err(GuestError::TooLarge { len: 12, max: 4 })
+    );
+
+    // A guest asking for all of memory doesn't get anything allocated.
+    let huge = host_memory.ptr::<[u32]>((0, u64::from(u32::MAX)));
+    assert!(matches!(
+        huge.to_vec_limited(1024),
+        Err(GuestError::TooLarge { .. })
+    ));
+
+    // Memories can cap every copy, in bytes.
+    let capped = Capped(&host_memory);
+    let ptr = GuestPtr::<str>::new(&capped, (0, 5));
+    assert_eq!(ptr.to_string().expect("under the cap"), "hello");
+    let ptr = GuestPtr::<str>::new(&capped, (0, 12));
+    assert_eq!(
+        ptr.to_string(),
+        Err(GuestError::TooLarge { len: 12, max: 8 })
+    );
+    let words = GuestPtr::<[u32]>::new(&capped, (0, 3));
+    assert_eq!(
+        words.to_vec(),
+        Err(GuestError::TooLarge { len: 12, max: 8 })
+    );
+}
+
+#[test]
+fn lossy_strings() {
+    let host_memory = HostMemory::new();
+    let call_memory = CallMemory::new(&host_memory);
+    let bytes = b"C:\\\xff\xfe.txt";
+    let ptr = call_memory.ptr::<str>((0, bytes.len() as u64));
+    for (slot, byte) in ptr.as_bytes().iter().zip(bytes.iter()) {
+        slot.expect("valid pointer").write(*byte).expect("write");
+    }
+
+    assert!(matches!(
+        ptr.to_string(),
+        Err(GuestError::InvalidUtf8 { .. })
+    ));
+    assert_eq!(
+        ptr.to_string_lossy().expect("lossy copy"),
+        "C:\\\u{fffd}\u{fffd}.txt"
+    );
+    {
+        // Invalid strings are copied, so leave the memory unborrowed.
+        let s = ptr.as_str_lossy().expect("lossy view");
+        assert!(!s.is_borrowed());
+        assert_eq!(&*s, "C:\\\u{fffd}\u{fffd}.txt");
+        ptr.as_bytes()
+            .as_slice_mut()
+            .expect("copied strings aren't borrowed");
+    }
+
+    // Valid strings are borrowed in place.
+    let ptr = call_memory.ptr::<str>((0, 3));
+    let s = ptr.as_str_lossy().expect("lossy view");
+    assert!(s.is_borrowed());
+    assert_eq!(&*s, "C:\\");
+    assert!(matches!(
+        ptr.as_bytes().as_slice_mut(),
+        Err(GuestError::PtrBorrowed(_))
+    ));
+    assert_eq!(s.into_owned(), "C:\\");
+    ptr.as_bytes().as_slice_mut().expect("borrow was released");
+}
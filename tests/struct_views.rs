@@ -0,0 +1,106 @@
+use wiggle::{GuestError, GuestMemory, GuestPtr};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/structs.witx"],
+    ctx: WasiCtx,
+    struct_views: true,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> structs::Structs for WasiCtx<'a> {
+    fn sum_of_pair(&self, an_pair: types::PairIntsView<'_>) -> Result<i64, types::Errno> {
+        // Only the second member is read.
+        let second = an_pair
+            .second()
+            .read()
+            .map_err(|_| types::Errno::InvalidArg)?;
+        Ok(second as i64 * 2)
+    }
+
+    fn sum_of_pair_of_ptrs<'b>(
+        &self,
+        an_pair: types::PairIntPtrsView<'b>,
+    ) -> Result<i64, types::Errno> {
+        let pair = an_pair.read().map_err(|_| types::Errno::InvalidArg)?;
+        let first = pair.first.read().map_err(|_| types::Errno::InvalidArg)?;
+        let second = pair.second.read().map_err(|_| types::Errno::InvalidArg)?;
+        Ok(first as i64 + second as i64)
+    }
+
+    fn sum_of_int_and_ptr<'b>(
+        &self,
+        an_pair: types::PairIntAndPtrView<'b>,
+    ) -> Result<i64, types::Errno> {
+        let first = an_pair
+            .first()
+            .read()
+            .map_err(|_| types::Errno::InvalidArg)?;
+        let first = first.read().map_err(|_| types::Errno::InvalidArg)?;
+        let second = an_pair
+            .second()
+            .read()
+            .map_err(|_| types::Errno::InvalidArg)?;
+        Ok(first as i64 + second as i64)
+    }
+
+    fn return_pair_ints(&self) -> Result<types::PairInts, types::Errno> {
+        Ok(types::PairInts {
+            first: 10,
+            second: 20,
+        })
+    }
+
+    fn return_pair_of_ptrs<'b>(
+        &self,
+        first: GuestPtr<'b, i32>,
+        second: GuestPtr<'b, i32>,
+    ) -> Result<types::PairIntPtrs<'b>, types::Errno> {
+        Ok(types::PairIntPtrs { first, second })
+    }
+}
+
+#[test]
+fn view_params() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    host_memory.ptr::<i32>(12).write(21).expect("write second");
+
+    let e = structs::sum_of_pair(&ctx, &host_memory, 8, 16);
+    assert_eq!(e, types::Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<i64>(16).read(), Ok(42));
+}
+
+#[test]
+fn view_members() {
+    let host_memory = HostMemory::new();
+    host_memory.ptr::<i32>(0).write(3).expect("write int");
+    host_memory
+        .ptr::<types::PairIntAndPtr>(8)
+        .write(types::PairIntAndPtr {
+            first: host_memory.ptr(0),
+            second: 4,
+        })
+        .expect("write pair");
+
+    let view = types::PairIntAndPtrView::new(host_memory.ptr(8)).expect("view");
+    assert_eq!(view.first().offset(), 8);
+    assert_eq!(view.second().offset(), 12);
+    assert_eq!(view.second().read(), Ok(4));
+    assert_eq!(view.read().expect("read pair").second, 4);
+
+    let ctx = WasiCtx::new();
+    let e = structs::sum_of_int_and_ptr(&ctx, &host_memory, 8, 16);
+    assert_eq!(e, types::Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<i64>(16).read(), Ok(7));
+}
+
+#[test]
+fn views_of_overflowing_pointers() {
+    let host_memory = HostMemory::new();
+    assert_eq!(
+        types::PairIntsView::new(host_memory.ptr(u64::MAX - 4)).err(),
+        Some(GuestError::PtrOverflow)
+    );
+}
@@ -1,10 +1,11 @@
 use proptest::prelude::*;
-use wiggle_runtime::{GuestError, GuestMemory, GuestPtr};
+use wiggle::{GuestError, GuestMemory, GuestPtr, GuestType};
 use wiggle_test::{impl_errno, HostMemory, MemArea, WasiCtx};
 
 wiggle::from_witx!({
     witx: ["tests/structs.witx"],
     ctx: WasiCtx,
+    proptest: true,
 });
 
 impl_errno!(types::Errno);
@@ -61,18 +62,17 @@ struct SumOfPairExercise {
 impl SumOfPairExercise {
     pub fn strat() -> BoxedStrategy<Self> {
         (
-            prop::num::i32::ANY,
-            prop::num::i32::ANY,
+            any::<types::PairInts>(),
             HostMemory::mem_area_strat(8),
             HostMemory::mem_area_strat(8),
         )
-            .prop_map(|(first, second, input_loc, return_loc)| SumOfPairExercise {
-                input: types::PairInts { first, second },
+            .prop_map(|(input, input_loc, return_loc)| SumOfPairExercise {
+                input,
                 input_loc,
                 return_loc,
             })
             .prop_filter("non-overlapping pointers", |e| {
-                MemArea::non_overlapping_set(&[e.input_loc, e.return_loc])
+                MemArea::non_overlapping_set([e.input_loc, e.return_loc])
             })
             .boxed()
     }
@@ -82,11 +82,11 @@ impl SumOfPairExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input_loc.ptr)
+            .ptr(self.input_loc.ptr.into())
             .write(self.input.first)
             .expect("input ref_mut");
         host_memory
-            .ptr(self.input_loc.ptr + 4)
+            .ptr((self.input_loc.ptr + 4).into())
             .write(self.input.second)
             .expect("input ref_mut");
         let sum_err = structs::sum_of_pair(
@@ -99,7 +99,7 @@ impl SumOfPairExercise {
         assert_eq!(sum_err, types::Errno::Ok.into(), "sum errno");
 
         let return_val: i64 = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -156,7 +156,7 @@ impl SumPairPtrsExercise {
                 },
             )
             .prop_filter("non-overlapping pointers", |e| {
-                MemArea::non_overlapping_set(&[
+                MemArea::non_overlapping_set([
                     e.input_first_loc,
                     e.input_second_loc,
                     e.input_struct_loc,
@@ -170,20 +170,20 @@ impl SumPairPtrsExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input_first_loc.ptr)
+            .ptr(self.input_first_loc.ptr.into())
             .write(self.input_first)
             .expect("input_first ref");
         host_memory
-            .ptr(self.input_second_loc.ptr)
+            .ptr(self.input_second_loc.ptr.into())
             .write(self.input_second)
             .expect("input_second ref");
 
         host_memory
-            .ptr(self.input_struct_loc.ptr)
+            .ptr(self.input_struct_loc.ptr.into())
             .write(self.input_first_loc.ptr)
             .expect("input_struct ref");
         host_memory
-            .ptr(self.input_struct_loc.ptr + 4)
+            .ptr((self.input_struct_loc.ptr + 4).into())
             .write(self.input_second_loc.ptr)
             .expect("input_struct ref");
 
@@ -197,7 +197,7 @@ impl SumPairPtrsExercise {
         assert_eq!(res, types::Errno::Ok.into(), "sum of pair of ptrs errno");
 
         let doubled: i64 = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -245,7 +245,7 @@ impl SumIntAndPtrExercise {
                 },
             )
             .prop_filter("non-overlapping pointers", |e| {
-                MemArea::non_overlapping_set(&[e.input_first_loc, e.input_struct_loc, e.return_loc])
+                MemArea::non_overlapping_set([e.input_first_loc, e.input_struct_loc, e.return_loc])
             })
             .boxed()
     }
@@ -254,15 +254,15 @@ impl SumIntAndPtrExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input_first_loc.ptr)
+            .ptr(self.input_first_loc.ptr.into())
             .write(self.input_first)
             .expect("input_first ref");
         host_memory
-            .ptr(self.input_struct_loc.ptr)
+            .ptr(self.input_struct_loc.ptr.into())
             .write(self.input_first_loc.ptr)
             .expect("input_struct ref");
         host_memory
-            .ptr(self.input_struct_loc.ptr + 4)
+            .ptr((self.input_struct_loc.ptr + 4).into())
             .write(self.input_second)
             .expect("input_struct ref");
 
@@ -276,7 +276,7 @@ impl SumIntAndPtrExercise {
         assert_eq!(res, types::Errno::Ok.into(), "sum of int and ptr errno");
 
         let doubled: i64 = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -315,7 +315,7 @@ impl ReturnPairInts {
         assert_eq!(err, types::Errno::Ok.into(), "return struct errno");
 
         let return_struct: types::PairInts = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
@@ -367,7 +367,7 @@ impl ReturnPairPtrsExercise {
                 },
             )
             .prop_filter("non-overlapping pointers", |e| {
-                MemArea::non_overlapping_set(&[e.input_first_loc, e.input_second_loc, e.return_loc])
+                MemArea::non_overlapping_set([e.input_first_loc, e.input_second_loc, e.return_loc])
             })
             .boxed()
     }
@@ -376,11 +376,11 @@ impl ReturnPairPtrsExercise {
         let host_memory = HostMemory::new();
 
         host_memory
-            .ptr(self.input_first_loc.ptr)
+            .ptr(self.input_first_loc.ptr.into())
             .write(self.input_first)
             .expect("input_first ref");
         host_memory
-            .ptr(self.input_second_loc.ptr)
+            .ptr(self.input_second_loc.ptr.into())
             .write(self.input_second)
             .expect("input_second ref");
 
@@ -395,7 +395,7 @@ impl ReturnPairPtrsExercise {
         assert_eq!(res, types::Errno::Ok.into(), "return pair of ptrs errno");
 
         let ptr_pair_int_ptrs: types::PairIntPtrs<'_> = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("failed to read return location");
         let ret_first_ptr = ptr_pair_int_ptrs.first;
@@ -420,3 +420,143 @@ proptest! {
         e.test()
     }
 }
+
+#[test]
+fn tuples_are_laid_out_like_structs() {
+    assert_eq!(<(i32, i32)>::guest_size(), types::PairInts::guest_size());
+    assert_eq!(<(u8, u64, u16)>::guest_size(), 24);
+    assert_eq!(<(u8, u64, u16)>::guest_align(), 8);
+    assert_eq!(<(u8,)>::guest_size(), 1);
+
+    let host_memory = HostMemory::new();
+    host_memory
+        .ptr(8)
+        .write(types::PairInts {
+            first: 1,
+            second: -2,
+        })
+        .expect("write pair");
+    assert_eq!(host_memory.ptr::<(i32, i32)>(8).read(), Ok((1, -2)));
+
+    let scratch = host_memory.ptr::<(u8, u64, u16)>(16);
+    scratch.write((7, u64::MAX, 300)).expect("write tuple");
+    assert_eq!(host_memory.ptr::<u64>(24).read(), Ok(u64::MAX));
+    assert_eq!(host_memory.ptr::<u16>(32).read(), Ok(300));
+    assert_eq!(scratch.read(), Ok((7, u64::MAX, 300)));
+}
+
+#[test]
+fn member_errors_point_at_witx() {
+    let host_memory = HostMemory::new();
+    let e = host_memory
+        .ptr::<types::PairInts>(2)
+        .read()
+        .expect_err("misaligned pair");
+    assert_eq!(
+        e.to_string(),
+        "In data pair_ints.first (at tests/structs.witx:6:12):"
+    );
+    match e {
+        GuestError::InDataField { err, .. } => {
+            assert!(matches!(*err, GuestError::PtrNotAligned(_, 4)))
+        }
+        e => panic!("expected InDataField, got {:?}", e),
+    }
+}
+
+#[test]
+fn builders() {
+    let pair = types::PairInts::builder().second(2).build().unwrap();
+    assert_eq!(
+        pair,
+        types::PairInts {
+            first: 0,
+            second: 2
+        }
+    );
+
+    let host_memory = HostMemory::new();
+    let first = host_memory.ptr::<i32>(0);
+    let pair = types::PairIntAndPtr::builder()
+        .first(first)
+        .build()
+        .unwrap();
+    assert_eq!(pair.first.offset(), 0);
+    assert_eq!(pair.second, 0);
+
+    let err = types::PairIntPtrs::builder()
+        .first(first)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        wiggle::MissingFieldError {
+            typename: "PairIntPtrs",
+            field: "second",
+        }
+    );
+    assert_eq!(err.to_string(), "field `second` of PairIntPtrs wasn't set");
+}
+
+#[test]
+fn layout() {
+    assert_eq!(types::PairInts::GUEST_SIZE, 8);
+    assert_eq!(types::PairInts::GUEST_ALIGN, 4);
+    assert_eq!(types::PairInts::OFFSET_FIRST, 0);
+    assert_eq!(types::PairInts::OFFSET_SECOND, 4);
+    assert_eq!(
+        types::PairIntAndPtr::GUEST_SIZE,
+        <types::PairIntAndPtr as GuestType>::guest_size()
+    );
+}
+
+mod padded {
+    wiggle::from_witx!({
+        witx_literal: r#"
+            (typename $tagged (struct (field $tag u8) (field $value u32)))
+        "#,
+        ctx: WasiCtx,
+    });
+}
+
+#[test]
+fn padded_struct_slices_skip_padding() {
+    use padded::types::Tagged;
+    let host_memory = HostMemory::new();
+    host_memory
+        .ptr::<[u8]>((0, 16))
+        .copy_from_slice(&[0xaa; 16])
+        .expect("fill memory");
+    let tagged = host_memory.ptr::<[Tagged]>((0, 2));
+    tagged
+        .copy_from_slice(&[Tagged { tag: 1, value: 2 }, Tagged { tag: 3, value: 4 }])
+        .expect("write structs");
+
+    // Only the members are written, so the guest's padding bytes are left
+    // as they were rather than overwritten with the host's.
+    let bytes = host_memory
+        .ptr::<[u8]>((0, 16))
+        .to_vec()
+        .expect("read bytes");
+    assert_eq!(bytes[1..4], [0xaa; 3]);
+    assert_eq!(bytes[9..12], [0xaa; 3]);
+    let copied = tagged.to_vec().expect("read structs");
+    assert_eq!((copied[0].tag, copied[0].value), (1, 2));
+    assert_eq!((copied[1].tag, copied[1].value), (3, 4));
+
+    // Structs without padding are still copied whole, and read back the same.
+    let pairs = host_memory.ptr::<[types::PairInts]>((16, 2));
+    let vals = [
+        types::PairInts {
+            first: 1,
+            second: -1,
+        },
+        types::PairInts {
+            first: 2,
+            second: -2,
+        },
+    ];
+    pairs.copy_from_slice(&vals).expect("write pairs");
+    let copied = pairs.to_vec().expect("read pairs");
+    assert_eq!((copied[1].first, copied[1].second), (2, -2));
+}
@@ -0,0 +1,122 @@
+use std::time::Duration;
+use wiggle::{GuestError, GuestMemory, GuestPtr};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx_literal: r#"
+        (typename $errno (enum u32 $ok $invalid_arg))
+        (typename $timestamp u64)
+        (typename $clockid (enum u32 $realtime $monotonic))
+        (typename $event
+          (struct
+            (field $clock $clockid)
+            (field $at $timestamp)))
+        (module $clocks
+          (@interface func (export "later")
+            (param $clock $clockid)
+            (param $at $timestamp)
+            (param $by $timestamp)
+            (result $error $errno)
+            (result $later $timestamp))
+          (@interface func (export "event_time")
+            (param $event $event)
+            (result $error $errno)
+            (result $at $timestamp)))
+    "#,
+    ctx: WasiCtx,
+    substitute: { timestamp: crate::Timestamp, clockid: crate::Clock },
+});
+
+impl_errno!(types::Errno);
+
+/// The host's own timestamp type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp(Duration);
+
+impl From<types::TimestampRepr> for Timestamp {
+    fn from(nanos: types::TimestampRepr) -> Timestamp {
+        Timestamp(Duration::from_nanos(nanos))
+    }
+}
+
+impl From<Timestamp> for types::TimestampRepr {
+    fn from(t: Timestamp) -> types::TimestampRepr {
+        t.0.as_nanos() as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clock {
+    Wall,
+    Steady,
+}
+
+impl From<types::ClockidRepr> for Clock {
+    fn from(c: types::ClockidRepr) -> Clock {
+        match c {
+            types::ClockidRepr::Realtime => Clock::Wall,
+            types::ClockidRepr::Monotonic => Clock::Steady,
+        }
+    }
+}
+
+impl From<Clock> for types::ClockidRepr {
+    fn from(c: Clock) -> types::ClockidRepr {
+        match c {
+            Clock::Wall => types::ClockidRepr::Realtime,
+            Clock::Steady => types::ClockidRepr::Monotonic,
+        }
+    }
+}
+
+impl<'a> clocks::Clocks for WasiCtx<'a> {
+    fn later(&self, clock: Clock, at: Timestamp, by: Timestamp) -> Result<Timestamp, types::Errno> {
+        match clock {
+            Clock::Steady => Ok(Timestamp(at.0 + by.0)),
+            Clock::Wall => Err(types::Errno::InvalidArg),
+        }
+    }
+    fn event_time(&self, event: &types::Event) -> Result<Timestamp, types::Errno> {
+        assert_eq!(event.clock, Clock::Steady);
+        Ok(event.at)
+    }
+}
+
+#[test]
+fn substituted_params_and_results() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let e = clocks::later(&ctx, &host_memory, 1, 1_000, 500, 8);
+    assert_eq!(e, types::Errno::Ok.into());
+    let later: GuestPtr<Timestamp> = host_memory.ptr(8);
+    assert_eq!(later.read(), Ok(Timestamp(Duration::from_nanos(1_500))));
+    assert_eq!(host_memory.ptr::<u64>(8).read(), Ok(1_500));
+
+    let e = clocks::later(&ctx, &host_memory, 0, 1_000, 500, 8);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+    // Values which aren't valid for the witx type are still rejected.
+    let e = clocks::later(&ctx, &host_memory, 2, 1_000, 500, 8);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+    assert!(matches!(
+        ctx.guest_errors.borrow().last(),
+        Some(GuestError::InFunc { .. })
+    ));
+}
+
+#[test]
+fn substituted_struct_members() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let event = types::Event {
+        clock: Clock::Steady,
+        at: Timestamp(Duration::from_secs(2)),
+    };
+    host_memory
+        .ptr::<types::Event>(16)
+        .write(event)
+        .expect("write event");
+    assert_eq!(host_memory.ptr::<u32>(16).read(), Ok(1));
+    let e = clocks::event_time(&ctx, &host_memory, 16, 8);
+    assert_eq!(e, types::Errno::Ok.into());
+    assert_eq!(host_memory.ptr::<u64>(8).read(), Ok(2_000_000_000));
+}
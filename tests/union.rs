@@ -1,5 +1,5 @@
 use proptest::prelude::*;
-use wiggle_runtime::{GuestError, GuestMemory, GuestType};
+use wiggle::{GuestError, GuestMemory, GuestType};
 use wiggle_test::{impl_errno, HostMemory, MemArea, WasiCtx};
 
 wiggle::from_witx!({
@@ -34,11 +34,7 @@ fn mult_zero_nan(a: f32, b: u32) -> f32 {
 impl<'a> union_example::UnionExample for WasiCtx<'a> {
     fn get_tag(&self, u: &types::Reason) -> Result<types::Excuse, types::Errno> {
         println!("GET TAG: {:?}", u);
-        match u {
-            types::Reason::DogAte { .. } => Ok(types::Excuse::DogAte),
-            types::Reason::Traffic { .. } => Ok(types::Excuse::Traffic),
-            types::Reason::Sleeping { .. } => Ok(types::Excuse::Sleeping),
-        }
+        Ok(u.tag())
     }
     fn reason_mult(&self, u: &types::ReasonMut<'_>, multiply_by: u32) -> Result<(), types::Errno> {
         match u {
@@ -64,21 +60,13 @@ impl<'a> union_example::UnionExample for WasiCtx<'a> {
 
 fn reason_strat() -> impl Strategy<Value = types::Reason> {
     prop_oneof![
-        prop::num::f32::ANY.prop_map(|v| types::Reason::DogAte(v)),
-        prop::num::i32::ANY.prop_map(|v| types::Reason::Traffic(v)),
+        prop::num::f32::ANY.prop_map(types::Reason::DogAte),
+        prop::num::i32::ANY.prop_map(types::Reason::Traffic),
         Just(types::Reason::Sleeping),
     ]
     .boxed()
 }
 
-fn reason_tag(r: &types::Reason) -> types::Excuse {
-    match r {
-        types::Reason::DogAte { .. } => types::Excuse::DogAte,
-        types::Reason::Traffic { .. } => types::Excuse::Traffic,
-        types::Reason::Sleeping { .. } => types::Excuse::Sleeping,
-    }
-}
-
 #[derive(Debug)]
 struct GetTagExercise {
     pub input: types::Reason,
@@ -99,7 +87,7 @@ impl GetTagExercise {
                 return_loc,
             })
             .prop_filter("non-overlapping pointers", |e| {
-                MemArea::non_overlapping_set(&[e.input_loc, e.return_loc])
+                MemArea::non_overlapping_set([e.input_loc, e.return_loc])
             })
             .boxed()
     }
@@ -108,20 +96,20 @@ impl GetTagExercise {
         let ctx = WasiCtx::new();
         let host_memory = HostMemory::new();
 
-        let discriminant: u8 = reason_tag(&self.input).into();
+        let discriminant: u8 = self.input.tag().into();
         host_memory
-            .ptr(self.input_loc.ptr)
+            .ptr(self.input_loc.ptr.into())
             .write(discriminant)
             .expect("input discriminant ptr");
         match self.input {
             types::Reason::DogAte(f) => {
                 host_memory
-                    .ptr(self.input_loc.ptr + 4)
+                    .ptr((self.input_loc.ptr + 4).into())
                     .write(f)
                     .expect("input contents ref_mut");
             }
             types::Reason::Traffic(v) => host_memory
-                .ptr(self.input_loc.ptr + 4)
+                .ptr((self.input_loc.ptr + 4).into())
                 .write(v)
                 .expect("input contents ref_mut"),
             types::Reason::Sleeping => {} // Do nothing
@@ -136,11 +124,11 @@ impl GetTagExercise {
         assert_eq!(e, types::Errno::Ok.into(), "get_tag errno");
 
         let return_val: types::Excuse = host_memory
-            .ptr(self.return_loc.ptr)
+            .ptr(self.return_loc.ptr.into())
             .read()
             .expect("return ref");
 
-        assert_eq!(return_val, reason_tag(&self.input), "get_tag return value");
+        assert_eq!(return_val, self.input.tag(), "get_tag return value");
     }
 }
 
@@ -176,7 +164,7 @@ impl ReasonMultExercise {
                 },
             )
             .prop_filter("non-overlapping pointers", |e| {
-                MemArea::non_overlapping_set(&[e.input_loc, e.input_pointee_loc])
+                MemArea::non_overlapping_set([e.input_loc, e.input_pointee_loc])
             })
             .boxed()
     }
@@ -185,26 +173,26 @@ impl ReasonMultExercise {
         let ctx = WasiCtx::new();
         let host_memory = HostMemory::new();
 
-        let discriminant: u8 = reason_tag(&self.input).into();
+        let discriminant: u8 = self.input.tag().into();
         host_memory
-            .ptr(self.input_loc.ptr)
+            .ptr(self.input_loc.ptr.into())
             .write(discriminant)
             .expect("input discriminant ref_mut");
         host_memory
-            .ptr(self.input_loc.ptr + 4)
+            .ptr((self.input_loc.ptr + 4).into())
             .write(self.input_pointee_loc.ptr)
             .expect("input pointer ref_mut");
 
         match self.input {
             types::Reason::DogAte(f) => {
                 host_memory
-                    .ptr(self.input_pointee_loc.ptr)
+                    .ptr(self.input_pointee_loc.ptr.into())
                     .write(f)
                     .expect("input contents ref_mut");
             }
             types::Reason::Traffic(v) => {
                 host_memory
-                    .ptr(self.input_pointee_loc.ptr)
+                    .ptr(self.input_pointee_loc.ptr.into())
                     .write(v)
                     .expect("input contents ref_mut");
             }
@@ -222,7 +210,7 @@ impl ReasonMultExercise {
         match self.input {
             types::Reason::DogAte(f) => {
                 let f_result: f32 = host_memory
-                    .ptr(self.input_pointee_loc.ptr)
+                    .ptr(self.input_pointee_loc.ptr.into())
                     .read()
                     .expect("input contents ref_mut");
                 assert_eq!(
@@ -233,7 +221,7 @@ impl ReasonMultExercise {
             }
             types::Reason::Traffic(v) => {
                 let v_result: i32 = host_memory
-                    .ptr(self.input_pointee_loc.ptr)
+                    .ptr(self.input_pointee_loc.ptr.into())
                     .read()
                     .expect("input contents ref_mut");
                 assert_eq!(
@@ -253,3 +241,30 @@ proptest! {
         e.test();
     }
 }
+
+#[test]
+fn invalid_tag() {
+    let host_memory = HostMemory::new();
+    host_memory.ptr::<u8>(0).write(3).expect("write tag");
+    assert_eq!(
+        host_memory.ptr::<types::Reason>(0).read(),
+        Err(GuestError::InvalidEnumValue("Excuse"))
+    );
+}
+
+#[test]
+fn accessors() {
+    let reason = types::Reason::Traffic(3);
+    assert_eq!(reason.tag(), types::Excuse::Traffic);
+    assert_eq!(reason.as_traffic(), Some(&3));
+    assert_eq!(reason.as_dog_ate(), None);
+    assert_eq!(types::Reason::Sleeping.tag(), types::Excuse::Sleeping);
+    assert_eq!(types::Reason::Sleeping.as_traffic(), None);
+}
+
+#[test]
+fn layout() {
+    assert_eq!(types::Reason::GUEST_SIZE, 8);
+    assert_eq!(types::Reason::GUEST_ALIGN, 4);
+    assert_eq!(types::Reason::OFFSET_CONTENTS, 4);
+}
@@ -0,0 +1,57 @@
+use wiggle::GuestError;
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx: ["tests/handles.witx"],
+    ctx: WasiCtx,
+    errors: { errno: crate::FdError },
+    mock: true,
+});
+
+impl_errno!(types::Errno);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FdError {
+    Exhausted,
+    Unknown(types::Fd),
+}
+
+impl From<FdError> for types::Errno {
+    fn from(e: FdError) -> types::Errno {
+        match e {
+            FdError::Exhausted => types::Errno::PhysicallyUnable,
+            FdError::Unknown(_) => types::Errno::InvalidArg,
+        }
+    }
+}
+
+impl<'a> handle_examples::HandleExamples for WasiCtx<'a> {
+    fn fd_create(&self) -> Result<types::Fd, FdError> {
+        Err(FdError::Exhausted)
+    }
+    fn fd_consume(&self, fd: types::Fd) -> Result<(), FdError> {
+        Err(FdError::Unknown(fd))
+    }
+}
+
+#[test]
+fn user_errors_are_converted() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    assert_eq!(
+        handle_examples::fd_create(&ctx, &host_memory, 0),
+        types::Errno::PhysicallyUnable as i32
+    );
+    assert_eq!(
+        handle_examples::fd_consume(&ctx, &host_memory, 3),
+        types::Errno::InvalidArg as i32
+    );
+}
+
+#[test]
+fn mocks_return_user_errors() {
+    use handle_examples::HandleExamples;
+
+    let mock = handle_examples::mock::MockHandleExamples::new(FdError::Exhausted);
+    assert_eq!(mock.fd_create(), Err(FdError::Exhausted));
+}
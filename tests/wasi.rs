@@ -1,4 +1,4 @@
-use wiggle_runtime::{GuestBorrows, GuestError, GuestErrorType, GuestPtr};
+use wiggle::{GuestBorrows, GuestError, GuestErrorType, GuestPtr};
 use wiggle_test::WasiCtx;
 
 wiggle::from_witx!({
@@ -131,14 +131,14 @@ impl<'a> crate::wasi_snapshot_preview1::WasiSnapshotPreview1 for WasiCtx<'a> {
         let mut slices: Vec<&'_ mut [u8]> = Vec::new();
         // Mark the iov elements as borrowed, to ensure that they does not
         // overlap with any of the as_raw regions.
-        bc.borrow_slice(&iovs).expect("borrow iovec array");
+        bc.borrow_slice(iovs).expect("borrow iovec array");
         for iov_ptr in iovs.iter() {
             let iov_ptr = iov_ptr.expect("iovec element pointer is valid");
 
             let iov: types::Iovec = iov_ptr.read().expect("read iovec element");
             let base: GuestPtr<u8> = iov.buf;
             let len: u32 = iov.buf_len;
-            let buf: GuestPtr<[u8]> = base.as_array(len);
+            let buf: GuestPtr<[u8]> = base.as_array(len.into());
             let slice = buf.as_raw(&mut bc).expect("borrow slice from iovec");
             slices.push(unsafe { &mut *slice });
         }
@@ -340,3 +340,55 @@ impl<'a> crate::wasi_snapshot_preview1::WasiSnapshotPreview1 for WasiCtx<'a> {
         unimplemented!("sock_shutdown")
     }
 }
+
+#[test]
+fn vectored_io() {
+    use std::io::{Read, Write};
+    use wiggle::{CallMemory, GuestMemory};
+    use wiggle_test::HostMemory;
+
+    let host_memory = HostMemory::new();
+    let mem = CallMemory::new(&host_memory);
+    for (i, (buf, buf_len)) in [(64, 3), (80, 4)].iter().enumerate() {
+        mem.ptr(i as u64 * 8)
+            .write(types::Iovec {
+                buf: mem.ptr(*buf),
+                buf_len: *buf_len,
+            })
+            .expect("write iovec");
+    }
+
+    let iovs: types::IovecArray = mem.ptr((0, 2));
+    let mut slices = iovs.io_slices_mut().expect("borrow iovecs");
+    let n = (&b"abcdefgh"[..])
+        .read_vectored(&mut slices)
+        .expect("read into iovecs");
+    assert_eq!(n, 7);
+    assert!(matches!(
+        mem.ptr::<[u8]>((80, 1)).as_slice(),
+        Err(GuestError::PtrBorrowed(_))
+    ));
+    drop(slices);
+
+    // The same buffers, as ciovecs to write out of.
+    let ciovs: types::CiovecArray = mem.ptr((0, 2));
+    let slices = ciovs.io_slices().expect("borrow ciovecs");
+    let mut out = Vec::new();
+    let n = out.write_vectored(&slices).expect("write from ciovecs");
+    assert_eq!(n, 7);
+    assert_eq!(out, b"abcdefg");
+    drop(slices);
+
+    // Buffers read into can't overlap.
+    mem.ptr(8)
+        .write(types::Iovec {
+            buf: mem.ptr(65),
+            buf_len: 1,
+        })
+        .expect("write overlapping iovec");
+    assert!(matches!(
+        iovs.io_slices_mut(),
+        Err(GuestError::PtrBorrowed(_))
+    ));
+    iovs.io_slices().expect("overlapping buffers can be read");
+}
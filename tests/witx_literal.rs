@@ -0,0 +1,35 @@
+use wiggle::{GuestError, GuestMemory};
+use wiggle_test::{impl_errno, HostMemory, WasiCtx};
+
+wiggle::from_witx!({
+    witx_literal: r#"
+        (typename $errno (enum u32 $ok $invalid_arg))
+        (module $calc
+          (@interface func (export "double")
+            (param $x u32)
+            (result $error $errno)
+            (result $doubled u32)))
+    "#,
+    ctx: WasiCtx,
+});
+
+impl_errno!(types::Errno);
+
+impl<'a> calc::Calc for WasiCtx<'a> {
+    fn double(&self, x: u32) -> Result<u32, types::Errno> {
+        x.checked_mul(2).ok_or(types::Errno::InvalidArg)
+    }
+}
+
+#[test]
+fn generates_from_a_literal() {
+    let ctx = WasiCtx::new();
+    let host_memory = HostMemory::new();
+    let e = calc::double(&ctx, &host_memory, 21, 8);
+    assert_eq!(e, types::Errno::Ok.into());
+    let doubled = host_memory.ptr::<u32>(8).read().expect("read result");
+    assert_eq!(doubled, 42);
+
+    let e = calc::double(&ctx, &host_memory, u32::MAX as i32, 8);
+    assert_eq!(e, types::Errno::InvalidArg.into());
+}
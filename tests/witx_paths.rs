@@ -0,0 +1,34 @@
+use wiggle::GuestError;
+use wiggle_test::{impl_errno, WasiCtx};
+
+// Environment variables are interpolated into paths, in either form.
+mod interpolated {
+    use super::*;
+
+    wiggle::from_witx!({
+        witx: ["${CARGO_MANIFEST_DIR}/tests/atoms.witx"],
+        ctx: WasiCtx,
+        include_functions: [int_float_args],
+    });
+
+    impl_errno!(types::Errno);
+
+    impl<'a> atoms::Atoms for WasiCtx<'a> {
+        fn int_float_args(&self, _an_int: u32, _an_float: f32) -> Result<(), types::Errno> {
+            Ok(())
+        }
+    }
+}
+
+mod absolute {
+    wiggle::from_witx!({
+        witx: ["$CARGO_MANIFEST_DIR/tests/errno.witx"],
+        ctx: WasiCtx,
+    });
+}
+
+#[test]
+fn interpolated_paths() {
+    assert!(interpolated::atoms::INTERFACE.is_implemented("int_float_args"));
+    assert_eq!(absolute::types::Errno::PicketLine as u32, 4);
+}